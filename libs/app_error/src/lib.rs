@@ -67,6 +67,9 @@ pub enum AppError {
   #[error("Payload too large:{0}")]
   PayloadTooLarge(String),
 
+  #[error("Plan limit exceeded:{0}")]
+  PlanLimitExceeded(String),
+
   #[error(transparent)]
   UuidError(#[from] uuid::Error),
 
@@ -127,6 +130,7 @@ impl AppError {
       AppError::S3Error(_) => ErrorCode::S3Error,
       AppError::StorageSpaceNotEnough => ErrorCode::StorageSpaceNotEnough,
       AppError::PayloadTooLarge(_) => ErrorCode::PayloadTooLarge,
+      AppError::PlanLimitExceeded(_) => ErrorCode::PlanLimitExceeded,
       AppError::Internal(_) => ErrorCode::Internal,
       AppError::UuidError(_) => ErrorCode::UuidError,
       AppError::IOError(_) => ErrorCode::IOError,
@@ -235,6 +239,7 @@ pub enum ErrorCode {
   S3ResponseError = 1021,
   SerdeError = 1022,
   NetworkError = 1023,
+  PlanLimitExceeded = 1024,
 }
 
 impl ErrorCode {
@@ -6,10 +6,21 @@ use prost::Message as ProstMessage;
 use app_error::AppError;
 use bytes::Bytes;
 use database_entity::dto::{
-  AFBlobMetadata, AFBlobRecord, AFCollabMember, AFCollabMembers, AFUserProfile,
-  AFUserWorkspaceInfo, AFWorkspace, AFWorkspaceMember, AFWorkspaces, BatchQueryCollabParams,
-  BatchQueryCollabResult, CollabMemberIdentify, DeleteCollabParams, InsertCollabMemberParams,
-  InsertCollabParams, QueryCollabMembers, QueryCollabParams, UpdateCollabMemberParams,
+  AFAuditLog, AFBlobGCReport, AFBlobMetadata, AFBlobRecord, AFCollabComment, AFCollabMember,
+  AFCollabMembers, AFCollabSearchResult, AFCollabSearchResults, AFCollabShareLink,
+  AFCollabSnapshots, AFDatabaseField, AFDatabaseRow, AFDatabaseRows, AFDocumentFormat,
+  AFExportedDocument, AFFolderView, AFImportedDocument, AFPresignedUrl, AFSharedCollab,
+  AFSnapshotDiff, AFTrashedCollab, AFUpload, AFUploadPart, AFUploadParts, AFUserProfile,
+  AFUserWorkspaceInfo, AFWorkspace, AFWorkspaceInvitation, AFWorkspaceMember, AFWorkspaceRole,
+  AFWorkspaces, BatchQueryCollabParams, BatchQueryCollabResult, CollabMemberIdentify,
+  CreateCollabCommentParams, CreateCollabShareLinkParams, CreateFolderViewParams,
+  CreatePresignedUploadParams, CreateUploadParams, DeleteCollabParams, ExportDocumentParams,
+  ImportDocumentParams, InsertCollabMemberParams, InsertCollabParams, InsertDatabaseRowParams,
+  InsertDocumentBlocksParams, MoveFolderViewParams, QueryCollabMembers, QueryCollabParams,
+  QueryDatabaseRowsParams, QueryObjectSnapshotParams, QuerySnapshotDiffParams, QuerySnapshotParams,
+  RawData, ResolveCollabCommentParams, RestoreSnapshotParams, RunBlobGCParams,
+  UpdateCollabCommentParams, UpdateCollabMemberParams, UpdateDatabaseRowParams,
+  UpdateFolderViewParams,
 };
 use futures_util::StreamExt;
 use gotrue::grant::Grant;
@@ -28,20 +39,24 @@ use reqwest::RequestBuilder;
 use shared_entity::dto::auth_dto::SignInTokenResponse;
 use shared_entity::dto::auth_dto::UpdateUserParams;
 use shared_entity::dto::workspace_dto::{
-  CreateWorkspaceMembers, WorkspaceBlobMetadata, WorkspaceMemberChangeset, WorkspaceMembers,
-  WorkspaceSpaceUsage,
+  AssignWorkspaceRoleParams, CreateWorkspaceMembers, CreateWorkspaceParams,
+  CreateWorkspaceRoleParams, ImportWorkspaceParams, PatchWorkspaceParams, QueryAuditLogParams,
+  TransferWorkspaceOwnershipParams, UpdateWorkspacePlanParams, UpdateWorkspaceRoleParams,
+  WorkspaceBlobMetadata, WorkspaceMemberChangeset, WorkspaceMemberInvitations, WorkspaceMembers,
+  WorkspacePlans, WorkspaceSpaceUsage, SHARE_LINK_PASSWORD_HEADER, WORKSPACE_ARCHIVE_CONTENT_TYPE,
 };
 use shared_entity::response::{AppResponse, AppResponseError};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 use tokio::fs::File;
-use tokio::io::AsyncReadExt;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
 use tokio_retry::strategy::FixedInterval;
-use tokio_retry::RetryIf;
+use tokio_retry::{Retry, RetryIf};
 use tokio_tungstenite::tungstenite::Message;
 use tracing::{event, instrument, trace};
 use url::Url;
+use uuid::Uuid;
 
 use crate::retry::{RefreshTokenAction, RefreshTokenRetryCondition};
 use crate::ws::{WSClientHttpSender, WSError};
@@ -77,6 +92,8 @@ type RefreshTokenSender = tokio::sync::oneshot::Sender<Result<(), AppResponseErr
 
 /// Hardcoded schema in the frontend application. Do not change this value.
 const DESKTOP_CALLBACK_URL: &str = "appflowy-flutter://login-callback";
+/// The size of each part when uploading a blob with [Client::put_blob_multipart].
+const UPLOAD_PART_SIZE: u64 = 8 * 1024 * 1024;
 
 impl Client {
   /// Constructs a new `Client` instance.
@@ -486,6 +503,136 @@ impl Client {
       .into_data()
   }
 
+  /// Creates a workspace owned by the current user. The name defaults to `My Workspace`.
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn create_workspace(
+    &self,
+    workspace_name: Option<String>,
+  ) -> Result<AFWorkspace, AppResponseError> {
+    let url = format!("{}/api/workspace", self.base_url);
+    let resp = self
+      .http_client_with_auth(Method::POST, &url)
+      .await?
+      .json(&CreateWorkspaceParams { workspace_name })
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<AFWorkspace>::from_response(resp)
+      .await?
+      .into_data()
+  }
+
+  /// Returns the archive of the workspace, which has every collab, snapshot, member and blob of
+  /// the workspace. Only the owner of the workspace can export it.
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn export_workspace(&self, workspace_id: &str) -> Result<Bytes, AppResponseError> {
+    let url = format!("{}/api/workspace/{}/archive", self.base_url, workspace_id);
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .send()
+      .await?;
+    log_request_id(&resp);
+
+    let is_archive = resp
+      .headers()
+      .get(header::CONTENT_TYPE)
+      .map(|content_type| content_type == WORKSPACE_ARCHIVE_CONTENT_TYPE)
+      .unwrap_or(false);
+    if !is_archive {
+      // The errors are returned as the JSON response
+      AppResponse::<()>::from_response(resp).await?.into_error()?;
+      return Err(AppResponseError::from(AppError::Unhandled(
+        "the response is not a workspace archive".to_string(),
+      )));
+    }
+    Ok(resp.bytes().await?)
+  }
+
+  /// Creates a new workspace owned by the current user from the archive returned by
+  /// [Client::export_workspace]. The workspace and its collabs get new ids.
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn import_workspace<T: Into<Bytes>>(
+    &self,
+    archive: T,
+    params: ImportWorkspaceParams,
+  ) -> Result<AFWorkspace, AppResponseError> {
+    let url = format!("{}/api/workspace/archive", self.base_url);
+    let resp = self
+      .http_client_with_auth(Method::POST, &url)
+      .await?
+      .query(&params)
+      .header(header::CONTENT_TYPE, WORKSPACE_ARCHIVE_CONTENT_TYPE)
+      .body(archive.into())
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<AFWorkspace>::from_response(resp)
+      .await?
+      .into_data()
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn rename_workspace<W: AsRef<str>>(
+    &self,
+    workspace_id: W,
+    workspace_name: &str,
+  ) -> Result<(), AppResponseError> {
+    let url = format!("{}/api/workspace/{}", self.base_url, workspace_id.as_ref());
+    let resp = self
+      .http_client_with_auth(Method::PUT, &url)
+      .await?
+      .json(&PatchWorkspaceParams {
+        workspace_name: workspace_name.to_string(),
+      })
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<()>::from_response(resp).await?.into_error()
+  }
+
+  /// Deletes the workspace. Only the owner can delete the workspace, and the last workspace of the
+  /// owner can't be deleted.
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn delete_workspace<W: AsRef<str>>(
+    &self,
+    workspace_id: W,
+  ) -> Result<(), AppResponseError> {
+    let url = format!("{}/api/workspace/{}", self.base_url, workspace_id.as_ref());
+    let resp = self
+      .http_client_with_auth(Method::DELETE, &url)
+      .await?
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<()>::from_response(resp).await?.into_error()
+  }
+
+  /// Transfers the ownership of the workspace to the member with the given email. The current
+  /// owner becomes a member of the workspace.
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn transfer_workspace_ownership<W: AsRef<str>>(
+    &self,
+    workspace_id: W,
+    new_owner_email: &str,
+  ) -> Result<(), AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/owner",
+      self.base_url,
+      workspace_id.as_ref()
+    );
+    let resp = self
+      .http_client_with_auth(Method::PUT, &url)
+      .await?
+      .json(&TransferWorkspaceOwnershipParams {
+        new_owner_email: new_owner_email.to_string(),
+      })
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<()>::from_response(resp).await?.into_error()
+  }
+
   #[instrument(level = "debug", skip_all, err)]
   pub async fn open_workspace(&self, workspace_id: &str) -> Result<AFWorkspace, AppResponseError> {
     let url = format!("{}/api/workspace/{}/open", self.base_url, workspace_id);
@@ -521,6 +668,35 @@ impl Client {
       .into_data()
   }
 
+  /// Searches the documents and database rows of the workspace that the user can read. The
+  /// `query` uses the web search syntax, e.g. `"exact phrase" -excluded`.
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn search_collab<W: AsRef<str>>(
+    &self,
+    workspace_id: W,
+    query: &str,
+    limit: Option<u32>,
+  ) -> Result<Vec<AFCollabSearchResult>, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/search",
+      self.base_url,
+      workspace_id.as_ref()
+    );
+    let mut request = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .query(&[("q", query)]);
+    if let Some(limit) = limit {
+      request = request.query(&[("limit", limit)]);
+    }
+    let resp = request.send().await?;
+    log_request_id(&resp);
+    AppResponse::<AFCollabSearchResults>::from_response(resp)
+      .await?
+      .into_data()
+      .map(|results| results.0)
+  }
+
   #[instrument(level = "debug", skip_all, err)]
   pub async fn add_workspace_members<T: Into<CreateWorkspaceMembers>, W: AsRef<str>>(
     &self,
@@ -589,146 +765,67 @@ impl Client {
     Ok(())
   }
 
-  #[instrument(skip_all, err)]
-  pub async fn sign_in_password(
-    &self,
-    email: &str,
-    password: &str,
-  ) -> Result<bool, AppResponseError> {
-    let access_token_resp = self
-      .gotrue_client
-      .token(&Grant::Password(PasswordGrant {
-        email: email.to_owned(),
-        password: password.to_owned(),
-      }))
-      .await?;
-    let is_new = self
-      .verify_token_cloud(&access_token_resp.access_token)
-      .await?;
-    self.token.write().set(access_token_resp);
-    Ok(is_new)
-  }
-
-  /// Refreshes the access token using the stored refresh token.
-  ///
-  /// This function attempts to refresh the access token by sending a request to the authentication server
-  /// using the stored refresh token. If successful, it updates the stored access token with the new one
-  /// received from the server.
-  #[instrument(level = "debug", skip_all, err)]
-  pub async fn refresh_token(&self) -> Result<RefreshTokenRet, AppResponseError> {
-    let (tx, rx) = tokio::sync::oneshot::channel();
-    self.refresh_ret_txs.write().push(tx);
-
-    if !self.is_refreshing_token.load(Ordering::SeqCst) {
-      self.is_refreshing_token.store(true, Ordering::SeqCst);
-      let txs = std::mem::take(&mut *self.refresh_ret_txs.write());
-      let result = self.inner_refresh_token().await;
-      for tx in txs {
-        let _ = tx.send(result.clone());
-      }
-      self.is_refreshing_token.store(false, Ordering::SeqCst);
-    }
-    Ok(rx)
-  }
-
-  async fn inner_refresh_token(&self) -> Result<(), AppResponseError> {
-    let retry_strategy = FixedInterval::new(Duration::from_secs(2)).take(4);
-    let action = RefreshTokenAction::new(self.token.clone(), self.gotrue_client.clone());
-    match RetryIf::spawn(retry_strategy, action, RefreshTokenRetryCondition).await {
-      Ok(_) => {
-        event!(tracing::Level::INFO, "refresh token success");
-        Ok(())
-      },
-      Err(err) => {
-        let err = AppError::from(err);
-        event!(tracing::Level::ERROR, "refresh token failed: {}", err);
-
-        // If the error is an OAuth error, unset the token.
-        if err.is_oauth_error() {
-          self.token.write().unset();
-        }
-        Err(err.into())
-      },
-    }
-  }
-
-  #[instrument(level = "debug", skip_all, err)]
-  pub async fn sign_up(&self, email: &str, password: &str) -> Result<(), AppResponseError> {
-    match self.gotrue_client.sign_up(email, password).await? {
-      Authenticated(access_token_resp) => {
-        self.token.write().set(access_token_resp);
-        Ok(())
-      },
-      NotAuthenticated(user) => {
-        tracing::info!("sign_up but not authenticated: {}", user.email);
-        Ok(())
-      },
-    }
-  }
-
-  #[instrument(level = "debug", skip_all, err)]
-  pub async fn sign_out(&self) -> Result<(), AppResponseError> {
-    if self.token.read().is_empty() {
-      return Ok(());
-    }
-
-    self.gotrue_client.logout(&self.access_token()?).await?;
-    self.token.write().unset();
-    Ok(())
-  }
-
+  /// Invites the emails to the workspace. The invitees that don't have an account join the
+  /// workspace when they sign up, others need to accept the invitation.
   #[instrument(level = "debug", skip_all, err)]
-  pub async fn update_user(&self, params: UpdateUserParams) -> Result<(), AppResponseError> {
-    let gotrue_params = UpdateGotrueUserParams::new()
-      .with_opt_email(params.email.clone())
-      .with_opt_password(params.password.clone());
-
-    let updated_user = self
-      .gotrue_client
-      .update_user(&self.access_token()?, &gotrue_params)
-      .await?;
-
-    if let Some(token) = self.token.write().as_mut() {
-      token.user = updated_user;
-    }
-
-    let url = format!("{}/api/user/update", self.base_url);
+  pub async fn invite_workspace_members<T: Into<WorkspaceMemberInvitations>, W: AsRef<str>>(
+    &self,
+    workspace_id: W,
+    invitations: T,
+  ) -> Result<(), AppResponseError> {
+    let invitations = invitations.into();
+    let url = format!(
+      "{}/api/workspace/{}/invite",
+      self.base_url,
+      workspace_id.as_ref()
+    );
     let resp = self
       .http_client_with_auth(Method::POST, &url)
       .await?
-      .json(&params)
+      .json(&invitations)
       .send()
       .await?;
     log_request_id(&resp);
     AppResponse::<()>::from_response(resp).await?.into_error()
   }
 
+  /// Returns the pending invitations of the workspace.
   #[instrument(level = "debug", skip_all, err)]
-  pub async fn create_collab(&self, params: InsertCollabParams) -> Result<(), AppResponseError> {
+  pub async fn get_workspace_invitations<W: AsRef<str>>(
+    &self,
+    workspace_id: W,
+  ) -> Result<Vec<AFWorkspaceInvitation>, AppResponseError> {
     let url = format!(
-      "{}/api/workspace/{}/collab/{}",
-      self.base_url, params.workspace_id, &params.object_id
+      "{}/api/workspace/{}/invite",
+      self.base_url,
+      workspace_id.as_ref()
     );
     let resp = self
-      .http_client_with_auth(Method::POST, &url)
+      .http_client_with_auth(Method::GET, &url)
       .await?
-      .json(&params)
       .send()
       .await?;
     log_request_id(&resp);
-    AppResponse::<()>::from_response(resp).await?.into_error()
+    AppResponse::<Vec<AFWorkspaceInvitation>>::from_response(resp)
+      .await?
+      .into_data()
   }
 
   #[instrument(level = "debug", skip_all, err)]
-  pub async fn update_collab(&self, params: InsertCollabParams) -> Result<(), AppResponseError> {
+  pub async fn revoke_workspace_invitation<W: AsRef<str>>(
+    &self,
+    workspace_id: W,
+    invite_id: &Uuid,
+  ) -> Result<(), AppResponseError> {
     let url = format!(
-      "{}/api/workspace/{}/collab/{}",
-      self.base_url, &params.workspace_id, &params.object_id
+      "{}/api/workspace/{}/invite/{}",
+      self.base_url,
+      workspace_id.as_ref(),
+      invite_id
     );
     let resp = self
-      .http_client_with_auth(Method::PUT, &url)
+      .http_client_with_auth(Method::DELETE, &url)
       .await?
-      .json(&params)
       .send()
       .await?;
     log_request_id(&resp);
@@ -736,196 +833,1513 @@ impl Client {
   }
 
   #[instrument(level = "debug", skip_all, err)]
-  pub async fn get_collab(
+  pub async fn get_workspace_roles<W: AsRef<str>>(
     &self,
-    params: QueryCollabParams,
-  ) -> Result<EncodedCollabV1, AppResponseError> {
+    workspace_id: W,
+  ) -> Result<Vec<AFWorkspaceRole>, AppResponseError> {
     let url = format!(
-      "{}/api/workspace/{}/collab/{}",
-      self.base_url, &params.workspace_id, &params.object_id
+      "{}/api/workspace/{}/role",
+      self.base_url,
+      workspace_id.as_ref()
     );
     let resp = self
       .http_client_with_auth(Method::GET, &url)
       .await?
-      .json(&params)
       .send()
       .await?;
     log_request_id(&resp);
-    AppResponse::<EncodedCollabV1>::from_response(resp)
+    AppResponse::<Vec<AFWorkspaceRole>>::from_response(resp)
       .await?
       .into_data()
   }
 
+  /// Returns the audit log of the workspace, from the newest entry to the oldest. Only the owner
+  /// of the workspace is allowed to read it.
   #[instrument(level = "debug", skip_all, err)]
-  pub async fn batch_get_collab(
+  pub async fn get_workspace_audit_logs(
     &self,
     workspace_id: &str,
-    params: BatchQueryCollabParams,
-  ) -> Result<BatchQueryCollabResult, AppResponseError> {
-    let url = format!(
-      "{}/api/workspace/{}/collab_list",
-      self.base_url, workspace_id
-    );
+    params: QueryAuditLogParams,
+  ) -> Result<Vec<AFAuditLog>, AppResponseError> {
+    let url = format!("{}/api/workspace/{}/audit", self.base_url, workspace_id);
     let resp = self
       .http_client_with_auth(Method::GET, &url)
       .await?
-      .json(&params)
+      .query(&params)
       .send()
       .await?;
     log_request_id(&resp);
-    AppResponse::<BatchQueryCollabResult>::from_response(resp)
+    AppResponse::<Vec<AFAuditLog>>::from_response(resp)
       .await?
       .into_data()
   }
 
   #[instrument(level = "debug", skip_all, err)]
-  pub async fn delete_collab(&self, params: DeleteCollabParams) -> Result<(), AppResponseError> {
+  pub async fn create_workspace_role<W: AsRef<str>>(
+    &self,
+    workspace_id: W,
+    params: CreateWorkspaceRoleParams,
+  ) -> Result<AFWorkspaceRole, AppResponseError> {
     let url = format!(
-      "{}/api/workspace/{}/collab/{}",
-      self.base_url, &params.workspace_id, &params.object_id
+      "{}/api/workspace/{}/role",
+      self.base_url,
+      workspace_id.as_ref()
     );
     let resp = self
-      .http_client_with_auth(Method::DELETE, &url)
+      .http_client_with_auth(Method::POST, &url)
+      .await?
+      .json(&params)
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<AFWorkspaceRole>::from_response(resp)
+      .await?
+      .into_data()
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn update_workspace_role<W: AsRef<str>>(
+    &self,
+    workspace_id: W,
+    role_id: i32,
+    params: UpdateWorkspaceRoleParams,
+  ) -> Result<AFWorkspaceRole, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/role/{}",
+      self.base_url,
+      workspace_id.as_ref(),
+      role_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::PUT, &url)
+      .await?
+      .json(&params)
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<AFWorkspaceRole>::from_response(resp)
+      .await?
+      .into_data()
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn delete_workspace_role<W: AsRef<str>>(
+    &self,
+    workspace_id: W,
+    role_id: i32,
+  ) -> Result<(), AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/role/{}",
+      self.base_url,
+      workspace_id.as_ref(),
+      role_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::DELETE, &url)
+      .await?
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<()>::from_response(resp).await?.into_error()
+  }
+
+  /// Assigns the custom role to the member of the workspace. The custom role of the member is
+  /// removed if the `role_id` is None.
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn assign_workspace_role<W: AsRef<str>>(
+    &self,
+    workspace_id: W,
+    email: &str,
+    role_id: Option<i32>,
+  ) -> Result<(), AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/member/role",
+      self.base_url,
+      workspace_id.as_ref()
+    );
+    let resp = self
+      .http_client_with_auth(Method::PUT, &url)
+      .await?
+      .json(&AssignWorkspaceRoleParams {
+        email: email.to_string(),
+        role_id,
+      })
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<()>::from_response(resp).await?.into_error()
+  }
+
+  /// Returns the pending invitations sent to the current user.
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn get_user_invitations(&self) -> Result<Vec<AFWorkspaceInvitation>, AppResponseError> {
+    let url = format!("{}/api/workspace/invite", self.base_url);
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<Vec<AFWorkspaceInvitation>>::from_response(resp)
+      .await?
+      .into_data()
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn accept_workspace_invitation(
+    &self,
+    invite_id: &Uuid,
+  ) -> Result<(), AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/invite/{}/accept",
+      self.base_url, invite_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::POST, &url)
+      .await?
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<()>::from_response(resp).await?.into_error()
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn decline_workspace_invitation(
+    &self,
+    invite_id: &Uuid,
+  ) -> Result<(), AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/invite/{}/decline",
+      self.base_url, invite_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::POST, &url)
+      .await?
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<()>::from_response(resp).await?.into_error()
+  }
+
+  #[instrument(skip_all, err)]
+  pub async fn sign_in_password(
+    &self,
+    email: &str,
+    password: &str,
+  ) -> Result<bool, AppResponseError> {
+    let access_token_resp = self
+      .gotrue_client
+      .token(&Grant::Password(PasswordGrant {
+        email: email.to_owned(),
+        password: password.to_owned(),
+      }))
+      .await?;
+    let is_new = self
+      .verify_token_cloud(&access_token_resp.access_token)
+      .await?;
+    self.token.write().set(access_token_resp);
+    Ok(is_new)
+  }
+
+  /// Refreshes the access token using the stored refresh token.
+  ///
+  /// This function attempts to refresh the access token by sending a request to the authentication server
+  /// using the stored refresh token. If successful, it updates the stored access token with the new one
+  /// received from the server.
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn refresh_token(&self) -> Result<RefreshTokenRet, AppResponseError> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    self.refresh_ret_txs.write().push(tx);
+
+    if !self.is_refreshing_token.load(Ordering::SeqCst) {
+      self.is_refreshing_token.store(true, Ordering::SeqCst);
+      let txs = std::mem::take(&mut *self.refresh_ret_txs.write());
+      let result = self.inner_refresh_token().await;
+      for tx in txs {
+        let _ = tx.send(result.clone());
+      }
+      self.is_refreshing_token.store(false, Ordering::SeqCst);
+    }
+    Ok(rx)
+  }
+
+  async fn inner_refresh_token(&self) -> Result<(), AppResponseError> {
+    let retry_strategy = FixedInterval::new(Duration::from_secs(2)).take(4);
+    let action = RefreshTokenAction::new(self.token.clone(), self.gotrue_client.clone());
+    match RetryIf::spawn(retry_strategy, action, RefreshTokenRetryCondition).await {
+      Ok(_) => {
+        event!(tracing::Level::INFO, "refresh token success");
+        Ok(())
+      },
+      Err(err) => {
+        let err = AppError::from(err);
+        event!(tracing::Level::ERROR, "refresh token failed: {}", err);
+
+        // If the error is an OAuth error, unset the token.
+        if err.is_oauth_error() {
+          self.token.write().unset();
+        }
+        Err(err.into())
+      },
+    }
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn sign_up(&self, email: &str, password: &str) -> Result<(), AppResponseError> {
+    match self.gotrue_client.sign_up(email, password).await? {
+      Authenticated(access_token_resp) => {
+        self.token.write().set(access_token_resp);
+        Ok(())
+      },
+      NotAuthenticated(user) => {
+        tracing::info!("sign_up but not authenticated: {}", user.email);
+        Ok(())
+      },
+    }
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn sign_out(&self) -> Result<(), AppResponseError> {
+    if self.token.read().is_empty() {
+      return Ok(());
+    }
+
+    self.gotrue_client.logout(&self.access_token()?).await?;
+    self.token.write().unset();
+    Ok(())
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn update_user(&self, params: UpdateUserParams) -> Result<(), AppResponseError> {
+    let gotrue_params = UpdateGotrueUserParams::new()
+      .with_opt_email(params.email.clone())
+      .with_opt_password(params.password.clone());
+
+    let updated_user = self
+      .gotrue_client
+      .update_user(&self.access_token()?, &gotrue_params)
+      .await?;
+
+    if let Some(token) = self.token.write().as_mut() {
+      token.user = updated_user;
+    }
+
+    let url = format!("{}/api/user/update", self.base_url);
+    let resp = self
+      .http_client_with_auth(Method::POST, &url)
+      .await?
+      .json(&params)
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<()>::from_response(resp).await?.into_error()
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn create_collab(&self, params: InsertCollabParams) -> Result<(), AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/collab/{}",
+      self.base_url, params.workspace_id, &params.object_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::POST, &url)
+      .await?
+      .json(&params)
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<()>::from_response(resp).await?.into_error()
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn update_collab(&self, params: InsertCollabParams) -> Result<(), AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/collab/{}",
+      self.base_url, &params.workspace_id, &params.object_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::PUT, &url)
+      .await?
+      .json(&params)
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<()>::from_response(resp).await?.into_error()
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn get_collab(
+    &self,
+    params: QueryCollabParams,
+  ) -> Result<EncodedCollabV1, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/collab/{}",
+      self.base_url, &params.workspace_id, &params.object_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .json(&params)
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<EncodedCollabV1>::from_response(resp)
+      .await?
+      .into_data()
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn batch_get_collab(
+    &self,
+    workspace_id: &str,
+    params: BatchQueryCollabParams,
+  ) -> Result<BatchQueryCollabResult, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/collab_list",
+      self.base_url, workspace_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .json(&params)
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<BatchQueryCollabResult>::from_response(resp)
+      .await?
+      .into_data()
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn delete_collab(&self, params: DeleteCollabParams) -> Result<(), AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/collab/{}",
+      self.base_url, &params.workspace_id, &params.object_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::DELETE, &url)
+      .await?
+      .json(&params)
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<()>::from_response(resp).await?.into_error()
+  }
+
+  /// Returns the deleted collabs of the workspace, the most recently deleted first.
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn get_workspace_trash(
+    &self,
+    workspace_id: &str,
+  ) -> Result<Vec<AFTrashedCollab>, AppResponseError> {
+    let url = format!("{}/api/workspace/{}/trash", self.base_url, workspace_id);
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<Vec<AFTrashedCollab>>::from_response(resp)
+      .await?
+      .into_data()
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn restore_collab_from_trash(
+    &self,
+    workspace_id: &str,
+    object_id: &str,
+  ) -> Result<(), AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/trash/{}/restore",
+      self.base_url, workspace_id, object_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::POST, &url)
+      .await?
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<()>::from_response(resp).await?.into_error()
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn get_database_fields(
+    &self,
+    workspace_id: &str,
+    database_id: &str,
+  ) -> Result<Vec<AFDatabaseField>, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/database/{}/field",
+      self.base_url, workspace_id, database_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<Vec<AFDatabaseField>>::from_response(resp)
+      .await?
+      .into_data()
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn query_database_rows(
+    &self,
+    workspace_id: &str,
+    database_id: &str,
+    params: QueryDatabaseRowsParams,
+  ) -> Result<AFDatabaseRows, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/database/{}/row/query",
+      self.base_url, workspace_id, database_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::POST, &url)
+      .await?
+      .json(&params)
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<AFDatabaseRows>::from_response(resp)
+      .await?
+      .into_data()
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn insert_database_row(
+    &self,
+    workspace_id: &str,
+    database_id: &str,
+    params: InsertDatabaseRowParams,
+  ) -> Result<AFDatabaseRow, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/database/{}/row",
+      self.base_url, workspace_id, database_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::POST, &url)
+      .await?
+      .json(&params)
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<AFDatabaseRow>::from_response(resp)
+      .await?
+      .into_data()
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn update_database_row(
+    &self,
+    workspace_id: &str,
+    database_id: &str,
+    row_id: &str,
+    params: UpdateDatabaseRowParams,
+  ) -> Result<AFDatabaseRow, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/database/{}/row/{}",
+      self.base_url, workspace_id, database_id, row_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::PUT, &url)
+      .await?
+      .json(&params)
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<AFDatabaseRow>::from_response(resp)
+      .await?
+      .into_data()
+  }
+
+  #[cfg(feature = "collab-sync")]
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn get_document(
+    &self,
+    workspace_id: &str,
+    object_id: &str,
+  ) -> Result<collab_document::blocks::DocumentData, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/collab/{}/document",
+      self.base_url, workspace_id, object_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<collab_document::blocks::DocumentData>::from_response(resp)
+      .await?
+      .into_data()
+  }
+
+  /// Inserts the blocks into the document and returns the ids of the inserted blocks.
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn insert_document_blocks(
+    &self,
+    workspace_id: &str,
+    object_id: &str,
+    params: InsertDocumentBlocksParams,
+  ) -> Result<Vec<String>, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/collab/{}/document/blocks",
+      self.base_url, workspace_id, object_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::POST, &url)
+      .await?
+      .json(&params)
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<Vec<String>>::from_response(resp)
+      .await?
+      .into_data()
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn import_document(
+    &self,
+    workspace_id: &str,
+    params: ImportDocumentParams,
+  ) -> Result<AFImportedDocument, AppResponseError> {
+    let url = format!("{}/api/workspace/{}/import", self.base_url, workspace_id);
+    let resp = self
+      .http_client_with_auth(Method::POST, &url)
+      .await?
+      .json(&params)
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<AFImportedDocument>::from_response(resp)
+      .await?
+      .into_data()
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn export_document(
+    &self,
+    workspace_id: &str,
+    object_id: &str,
+    format: AFDocumentFormat,
+  ) -> Result<AFExportedDocument, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/collab/{}/export",
+      self.base_url, workspace_id, object_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .query(&ExportDocumentParams { format })
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<AFExportedDocument>::from_response(resp)
+      .await?
+      .into_data()
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn get_folder_views(
+    &self,
+    workspace_id: &str,
+  ) -> Result<Vec<AFFolderView>, AppResponseError> {
+    let url = format!("{}/api/workspace/{}/folder", self.base_url, workspace_id);
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<Vec<AFFolderView>>::from_response(resp)
+      .await?
+      .into_data()
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn create_folder_view(
+    &self,
+    workspace_id: &str,
+    params: CreateFolderViewParams,
+  ) -> Result<AFFolderView, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/folder/view",
+      self.base_url, workspace_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::POST, &url)
+      .await?
+      .json(&params)
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<AFFolderView>::from_response(resp)
+      .await?
+      .into_data()
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn update_folder_view(
+    &self,
+    workspace_id: &str,
+    view_id: &str,
+    params: UpdateFolderViewParams,
+  ) -> Result<(), AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/folder/view/{}",
+      self.base_url, workspace_id, view_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::PUT, &url)
+      .await?
+      .json(&params)
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<()>::from_response(resp).await?.into_error()
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn move_folder_view(
+    &self,
+    workspace_id: &str,
+    view_id: &str,
+    params: MoveFolderViewParams,
+  ) -> Result<(), AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/folder/view/{}/move",
+      self.base_url, workspace_id, view_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::PUT, &url)
+      .await?
+      .json(&params)
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<()>::from_response(resp).await?.into_error()
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn delete_folder_view(
+    &self,
+    workspace_id: &str,
+    view_id: &str,
+  ) -> Result<(), AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/folder/view/{}",
+      self.base_url, workspace_id, view_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::DELETE, &url)
+      .await?
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<()>::from_response(resp).await?.into_error()
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn add_collab_member(
+    &self,
+    params: InsertCollabMemberParams,
+  ) -> Result<(), AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/collab/{}/member",
+      self.base_url, params.workspace_id, &params.object_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::POST, &url)
+      .await?
+      .json(&params)
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<()>::from_response(resp).await?.into_error()
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn get_collab_member(
+    &self,
+    params: CollabMemberIdentify,
+  ) -> Result<AFCollabMember, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/collab/{}/member",
+      self.base_url, params.workspace_id, &params.object_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .json(&params)
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<AFCollabMember>::from_response(resp)
+      .await?
+      .into_data()
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn update_collab_member(
+    &self,
+    params: UpdateCollabMemberParams,
+  ) -> Result<(), AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/collab/{}/member",
+      self.base_url, params.workspace_id, &params.object_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::PUT, &url)
+      .await?
+      .json(&params)
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<()>::from_response(resp).await?.into_error()
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn remove_collab_member(
+    &self,
+    params: CollabMemberIdentify,
+  ) -> Result<(), AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/collab/{}/member",
+      self.base_url, params.workspace_id, &params.object_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::DELETE, &url)
+      .await?
+      .json(&params)
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<()>::from_response(resp).await?.into_error()
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn get_collab_members(
+    &self,
+    params: QueryCollabMembers,
+  ) -> Result<AFCollabMembers, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/collab/{}/member/list",
+      self.base_url, params.workspace_id, &params.object_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .json(&params)
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<AFCollabMembers>::from_response(resp)
+      .await?
+      .into_data()
+  }
+
+  /// Returns the snapshots of the collab object.
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn get_snapshot_list(
+    &self,
+    object_id: &str,
+  ) -> Result<AFCollabSnapshots, AppResponseError> {
+    let url = format!("{}/api/workspace/snapshots", self.base_url);
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .json(&QueryObjectSnapshotParams {
+        object_id: object_id.to_string(),
+      })
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<AFCollabSnapshots>::from_response(resp)
+      .await?
+      .into_data()
+  }
+
+  /// Returns the data of the snapshot, which is an encoded [EncodedCollabV1].
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn get_snapshot(&self, snapshot_id: i64) -> Result<RawData, AppResponseError> {
+    let url = format!("{}/api/workspace/snapshot", self.base_url);
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .json(&QuerySnapshotParams { snapshot_id })
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<RawData>::from_response(resp)
+      .await?
+      .into_data()
+  }
+
+  /// Restores the collab object to the content of the snapshot. The connected clients receive
+  /// the restored content as a regular update.
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn restore_snapshot(
+    &self,
+    workspace_id: &str,
+    object_id: &str,
+    snapshot_id: i64,
+    params: RestoreSnapshotParams,
+  ) -> Result<(), AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/collab/{}/snapshot/{}/restore",
+      self.base_url, workspace_id, object_id, snapshot_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::POST, &url)
+      .await?
+      .json(&params)
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<()>::from_response(resp).await?.into_error()
+  }
+
+  /// Returns the changes between two snapshots of the collab object, or between a snapshot and
+  /// the current state when `to_snapshot_id` is None.
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn get_snapshot_diff(
+    &self,
+    workspace_id: &str,
+    object_id: &str,
+    params: QuerySnapshotDiffParams,
+  ) -> Result<AFSnapshotDiff, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/collab/{}/snapshot/diff",
+      self.base_url, workspace_id, object_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .query(&params)
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<AFSnapshotDiff>::from_response(resp)
+      .await?
+      .into_data()
+  }
+
+  pub fn ws_url(&self, device_id: &str) -> Result<String, AppResponseError> {
+    let access_token = self.access_token()?;
+    Ok(format!("{}/{}/{}", self.ws_addr, access_token, device_id))
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn create_collab_share_link(
+    &self,
+    workspace_id: &str,
+    object_id: &str,
+    params: CreateCollabShareLinkParams,
+  ) -> Result<AFCollabShareLink, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/collab/{}/share",
+      self.base_url, workspace_id, object_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::POST, &url)
+      .await?
+      .json(&params)
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<AFCollabShareLink>::from_response(resp)
+      .await?
+      .into_data()
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn get_collab_share_links(
+    &self,
+    workspace_id: &str,
+    object_id: &str,
+  ) -> Result<Vec<AFCollabShareLink>, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/collab/{}/share",
+      self.base_url, workspace_id, object_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<Vec<AFCollabShareLink>>::from_response(resp)
+      .await?
+      .into_data()
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn delete_collab_share_link(
+    &self,
+    workspace_id: &str,
+    object_id: &str,
+    share_id: &Uuid,
+  ) -> Result<(), AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/collab/{}/share/{}",
+      self.base_url, workspace_id, object_id, share_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::DELETE, &url)
+      .await?
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<()>::from_response(resp).await?.into_error()
+  }
+
+  /// Returns the collab of the share link. It doesn't require the user to sign in.
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn get_shared_collab(
+    &self,
+    token: &str,
+    password: Option<&str>,
+  ) -> Result<AFSharedCollab, AppResponseError> {
+    let url = format!("{}/api/share/{}", self.base_url, token);
+    let mut request = self.cloud_client.get(&url);
+    if let Some(password) = password {
+      request = request.header(SHARE_LINK_PASSWORD_HEADER, password);
+    }
+    let resp = request.send().await?;
+    log_request_id(&resp);
+    AppResponse::<AFSharedCollab>::from_response(resp)
+      .await?
+      .into_data()
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn get_collab_comments(
+    &self,
+    workspace_id: &str,
+    object_id: &str,
+  ) -> Result<Vec<AFCollabComment>, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/collab/{}/comment",
+      self.base_url, workspace_id, object_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<Vec<AFCollabComment>>::from_response(resp)
+      .await?
+      .into_data()
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn create_collab_comment(
+    &self,
+    workspace_id: &str,
+    object_id: &str,
+    params: CreateCollabCommentParams,
+  ) -> Result<AFCollabComment, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/collab/{}/comment",
+      self.base_url, workspace_id, object_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::POST, &url)
+      .await?
+      .json(&params)
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<AFCollabComment>::from_response(resp)
+      .await?
+      .into_data()
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn update_collab_comment(
+    &self,
+    workspace_id: &str,
+    object_id: &str,
+    comment_id: &Uuid,
+    params: UpdateCollabCommentParams,
+  ) -> Result<AFCollabComment, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/collab/{}/comment/{}",
+      self.base_url, workspace_id, object_id, comment_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::PUT, &url)
+      .await?
+      .json(&params)
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<AFCollabComment>::from_response(resp)
+      .await?
+      .into_data()
+  }
+
+  /// Resolves the thread of the root comment, or reopens it if `resolved` is false.
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn resolve_collab_comment(
+    &self,
+    workspace_id: &str,
+    object_id: &str,
+    comment_id: &Uuid,
+    resolved: bool,
+  ) -> Result<AFCollabComment, AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/collab/{}/comment/{}/resolve",
+      self.base_url, workspace_id, object_id, comment_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::PUT, &url)
+      .await?
+      .json(&ResolveCollabCommentParams { resolved })
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<AFCollabComment>::from_response(resp)
+      .await?
+      .into_data()
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn delete_collab_comment(
+    &self,
+    workspace_id: &str,
+    object_id: &str,
+    comment_id: &Uuid,
+  ) -> Result<(), AppResponseError> {
+    let url = format!(
+      "{}/api/workspace/{}/collab/{}/comment/{}",
+      self.base_url, workspace_id, object_id, comment_id
+    );
+    let resp = self
+      .http_client_with_auth(Method::DELETE, &url)
+      .await?
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<()>::from_response(resp).await?.into_error()
+  }
+
+  pub async fn put_blob<T: Into<Bytes>, M: ToString>(
+    &self,
+    workspace_id: &str,
+    data: T,
+    mime: M,
+  ) -> Result<String, AppResponseError> {
+    let url = format!("{}/api/file_storage/{}/blob", self.base_url, workspace_id);
+    let data = data.into();
+    let content_length = data.len();
+    let resp = self
+      .http_client_with_auth(Method::PUT, &url)
+      .await?
+      .header(header::CONTENT_TYPE, mime.to_string())
+      .header(header::CONTENT_LENGTH, content_length)
+      .body(data)
+      .send()
+      .await?;
+    log_request_id(&resp);
+    let record = AppResponse::<AFBlobRecord>::from_response(resp)
+      .await?
+      .into_data()?;
+    Ok(format!(
+      "{}/api/file_storage/{}/blob/{}",
+      self.base_url, workspace_id, record.file_id
+    ))
+  }
+
+  pub async fn put_blob_with_path(
+    &self,
+    workspace_id: &str,
+    file_path: &str,
+  ) -> Result<String, AppResponseError> {
+    if file_path.is_empty() {
+      return Err(AppError::InvalidRequest("path is empty".to_owned()).into());
+    }
+
+    let mut file = File::open(&file_path).await?;
+    let mime = mime_guess::from_path(file_path)
+      .first_or_octet_stream()
+      .to_string();
+
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer).await?;
+    self.put_blob(workspace_id, buffer, mime).await
+  }
+
+  /// Only expose this method for testing
+  #[cfg(debug_assertions)]
+  pub async fn put_blob_with_content_length<T: Into<Bytes>>(
+    &self,
+    workspace_id: &str,
+    data: T,
+    mime: &Mime,
+    content_length: usize,
+  ) -> Result<AFBlobRecord, AppResponseError> {
+    let url = format!("{}/api/file_storage/{}/blob", self.base_url, workspace_id);
+    let resp = self
+      .http_client_with_auth(Method::PUT, &url)
+      .await?
+      .header(header::CONTENT_TYPE, mime.to_string())
+      .header(header::CONTENT_LENGTH, content_length)
+      .body(data.into())
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<AFBlobRecord>::from_response(resp)
+      .await?
+      .into_data()
+  }
+
+  /// Get the file with the given url. The url should be in the format of
+  /// `https://appflowy.io/api/file_storage/<workspace_id>/<file_id>`.
+  pub async fn get_blob<T: AsRef<str>>(&self, url: T) -> Result<Bytes, AppResponseError> {
+    Url::parse(url.as_ref())?;
+    let resp = self
+      .http_client_with_auth(Method::GET, url.as_ref())
+      .await?
+      .send()
+      .await?;
+    log_request_id(&resp);
+
+    match resp.status() {
+      reqwest::StatusCode::OK => {
+        let mut stream = resp.bytes_stream();
+        let mut acc: Vec<u8> = Vec::new();
+        while let Some(raw_bytes) = stream.next().await {
+          acc.extend_from_slice(&raw_bytes?);
+        }
+        Ok(Bytes::from(acc))
+      },
+      reqwest::StatusCode::NOT_FOUND => Err(AppResponseError::from(AppError::RecordNotFound(
+        url.as_ref().to_owned(),
+      ))),
+      c => Err(AppResponseError::from(AppError::Unhandled(format!(
+        "status code: {}, message: {}",
+        c,
+        resp.text().await?
+      )))),
+    }
+  }
+
+  /// Get the thumbnail of the image with the given url that is at least `width` pixels wide. The
+  /// original image is returned if there is no such thumbnail.
+  pub async fn get_blob_thumbnail<T: AsRef<str>>(
+    &self,
+    url: T,
+    width: u32,
+  ) -> Result<Bytes, AppResponseError> {
+    Url::parse(url.as_ref())?;
+    let resp = self
+      .http_client_with_auth(Method::GET, url.as_ref())
+      .await?
+      .query(&[("w", width)])
+      .send()
+      .await?;
+    log_request_id(&resp);
+
+    match resp.status() {
+      reqwest::StatusCode::OK => Ok(resp.bytes().await?),
+      reqwest::StatusCode::NOT_FOUND => Err(AppResponseError::from(AppError::RecordNotFound(
+        url.as_ref().to_owned(),
+      ))),
+      c => Err(AppResponseError::from(AppError::Unhandled(format!(
+        "status code: {}, message: {}",
+        c,
+        resp.text().await?
+      )))),
+    }
+  }
+
+  /// Get the bytes from `start` to `end` (both inclusive) of the file with the given url. The
+  /// returned bytes are shorter than requested if the range exceeds the end of the file.
+  pub async fn get_blob_range<T: AsRef<str>>(
+    &self,
+    url: T,
+    start: u64,
+    end: u64,
+  ) -> Result<Bytes, AppResponseError> {
+    Url::parse(url.as_ref())?;
+    let resp = self
+      .http_client_with_auth(Method::GET, url.as_ref())
+      .await?
+      .header(header::RANGE, format!("bytes={}-{}", start, end))
+      .send()
+      .await?;
+    log_request_id(&resp);
+
+    match resp.status() {
+      reqwest::StatusCode::PARTIAL_CONTENT => Ok(resp.bytes().await?),
+      reqwest::StatusCode::NOT_FOUND => Err(AppResponseError::from(AppError::RecordNotFound(
+        url.as_ref().to_owned(),
+      ))),
+      reqwest::StatusCode::RANGE_NOT_SATISFIABLE => Err(AppResponseError::from(
+        AppError::InvalidRequest(format!("range not satisfiable: {}-{}", start, end)),
+      )),
+      c => Err(AppResponseError::from(AppError::Unhandled(format!(
+        "status code: {}, message: {}",
+        c,
+        resp.text().await?
+      )))),
+    }
+  }
+
+  pub async fn get_blob_metadata<T: AsRef<str>>(
+    &self,
+    url: T,
+  ) -> Result<AFBlobMetadata, AppResponseError> {
+    let resp = self
+      .http_client_with_auth(Method::GET, url.as_ref())
+      .await?
+      .send()
+      .await?;
+
+    log_request_id(&resp);
+    AppResponse::<AFBlobMetadata>::from_response(resp)
+      .await?
+      .into_data()
+  }
+
+  pub async fn delete_blob(&self, url: &str) -> Result<(), AppResponseError> {
+    let resp = self
+      .http_client_with_auth(Method::DELETE, url)
+      .await?
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<()>::from_response(resp).await?.into_error()
+  }
+
+  /// Returns the plans of the workspaces. Only the admin user can call this method.
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn get_workspace_plans(&self) -> Result<WorkspacePlans, AppResponseError> {
+    let url = format!("{}/api/admin/plan", self.base_url);
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
+      .await?
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<WorkspacePlans>::from_response(resp)
+      .await?
+      .into_data()
+  }
+
+  /// Changes the plan of the workspace. Only the admin user can call this method.
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn update_workspace_plan(
+    &self,
+    workspace_id: &str,
+    workspace_type: i32,
+  ) -> Result<(), AppResponseError> {
+    let workspace_id = Uuid::parse_str(workspace_id).map_err(AppError::from)?;
+    let url = format!("{}/api/admin/workspace/plan", self.base_url);
+    let resp = self
+      .http_client_with_auth(Method::PUT, &url)
+      .await?
+      .json(&UpdateWorkspacePlanParams {
+        workspace_id,
+        workspace_type,
+      })
+      .send()
+      .await?;
+    log_request_id(&resp);
+    AppResponse::<()>::from_response(resp).await?.into_error()
+  }
+
+  /// Returns the report of the last blob garbage collection. Only the admin user can call this
+  /// method.
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn get_blob_gc_report(&self) -> Result<AFBlobGCReport, AppResponseError> {
+    let url = format!("{}/api/admin/blob/gc", self.base_url);
+    let resp = self
+      .http_client_with_auth(Method::GET, &url)
       .await?
-      .json(&params)
       .send()
       .await?;
     log_request_id(&resp);
-    AppResponse::<()>::from_response(resp).await?.into_error()
+    AppResponse::<AFBlobGCReport>::from_response(resp)
+      .await?
+      .into_data()
   }
 
+  /// Runs the blob garbage collection now. Nothing is removed if `dry_run` is true. Only the
+  /// admin user can call this method.
   #[instrument(level = "debug", skip_all, err)]
-  pub async fn add_collab_member(
-    &self,
-    params: InsertCollabMemberParams,
-  ) -> Result<(), AppResponseError> {
-    let url = format!(
-      "{}/api/workspace/{}/collab/{}/member",
-      self.base_url, params.workspace_id, &params.object_id
-    );
+  pub async fn run_blob_gc(&self, dry_run: bool) -> Result<AFBlobGCReport, AppResponseError> {
+    let url = format!("{}/api/admin/blob/gc", self.base_url);
     let resp = self
       .http_client_with_auth(Method::POST, &url)
       .await?
-      .json(&params)
+      .json(&RunBlobGCParams { dry_run })
       .send()
       .await?;
     log_request_id(&resp);
-    AppResponse::<()>::from_response(resp).await?.into_error()
+    AppResponse::<AFBlobGCReport>::from_response(resp)
+      .await?
+      .into_data()
   }
 
-  #[instrument(level = "debug", skip_all, err)]
-  pub async fn get_collab_member(
+  pub async fn get_workspace_usage(
     &self,
-    params: CollabMemberIdentify,
-  ) -> Result<AFCollabMember, AppResponseError> {
-    let url = format!(
-      "{}/api/workspace/{}/collab/{}/member",
-      self.base_url, params.workspace_id, &params.object_id
-    );
+    workspace_id: &str,
+  ) -> Result<WorkspaceSpaceUsage, AppResponseError> {
+    let url = format!("{}/api/file_storage/{}/usage", self.base_url, workspace_id);
     let resp = self
       .http_client_with_auth(Method::GET, &url)
       .await?
-      .json(&params)
       .send()
       .await?;
     log_request_id(&resp);
-    AppResponse::<AFCollabMember>::from_response(resp)
+    AppResponse::<WorkspaceSpaceUsage>::from_response(resp)
       .await?
       .into_data()
   }
 
-  #[instrument(level = "debug", skip_all, err)]
-  pub async fn update_collab_member(
+  pub async fn get_workspace_all_blob_metadata(
     &self,
-    params: UpdateCollabMemberParams,
-  ) -> Result<(), AppResponseError> {
-    let url = format!(
-      "{}/api/workspace/{}/collab/{}/member",
-      self.base_url, params.workspace_id, &params.object_id
-    );
+    workspace_id: &str,
+  ) -> Result<WorkspaceBlobMetadata, AppResponseError> {
+    let url = format!("{}/api/file_storage/{}/blobs", self.base_url, workspace_id);
     let resp = self
-      .http_client_with_auth(Method::PUT, &url)
+      .http_client_with_auth(Method::GET, &url)
       .await?
-      .json(&params)
       .send()
       .await?;
     log_request_id(&resp);
-    AppResponse::<()>::from_response(resp).await?.into_error()
+    AppResponse::<WorkspaceBlobMetadata>::from_response(resp)
+      .await?
+      .into_data()
   }
 
+  /// Uploads the blob to the bucket directly with a presigned url, instead of sending it through
+  /// the server. Returns the url of the blob.
   #[instrument(level = "debug", skip_all, err)]
-  pub async fn remove_collab_member(
+  pub async fn put_blob_presigned<T: Into<Bytes>, M: ToString>(
     &self,
-    params: CollabMemberIdentify,
-  ) -> Result<(), AppResponseError> {
+    workspace_id: &str,
+    data: T,
+    mime: M,
+  ) -> Result<String, AppResponseError> {
+    let data = data.into();
+    let file_type = mime.to_string();
+    let presigned_url = self
+      .create_presigned_upload(
+        workspace_id,
+        CreatePresignedUploadParams {
+          file_type: file_type.clone(),
+          file_size: data.len() as i64,
+        },
+      )
+      .await?;
+
+    // The presigned url carries the credentials, so the request doesn't need the access token
+    let resp = self
+      .cloud_client
+      .put(&presigned_url.url)
+      .header(header::CONTENT_TYPE, file_type)
+      .header(header::CONTENT_LENGTH, data.len())
+      .body(data)
+      .send()
+      .await?;
+    if !resp.status().is_success() {
+      return Err(AppResponseError::from(AppError::S3ResponseError(format!(
+        "status code: {}, message: {}",
+        resp.status(),
+        resp.text().await?
+      ))));
+    }
+
+    let record = self
+      .complete_presigned_upload(workspace_id, &presigned_url.file_id)
+      .await?;
+    Ok(format!(
+      "{}/api/file_storage/{}/blob/{}",
+      self.base_url, workspace_id, record.file_id
+    ))
+  }
+
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn create_presigned_upload(
+    &self,
+    workspace_id: &str,
+    params: CreatePresignedUploadParams,
+  ) -> Result<AFPresignedUrl, AppResponseError> {
     let url = format!(
-      "{}/api/workspace/{}/collab/{}/member",
-      self.base_url, params.workspace_id, &params.object_id
+      "{}/api/file_storage/{}/presigned/upload",
+      self.base_url, workspace_id
     );
     let resp = self
-      .http_client_with_auth(Method::DELETE, &url)
+      .http_client_with_auth(Method::POST, &url)
       .await?
       .json(&params)
       .send()
       .await?;
     log_request_id(&resp);
-    AppResponse::<()>::from_response(resp).await?.into_error()
+    AppResponse::<AFPresignedUrl>::from_response(resp)
+      .await?
+      .into_data()
   }
 
+  /// Records the blob after it was uploaded with the url returned by
+  /// [Client::create_presigned_upload].
   #[instrument(level = "debug", skip_all, err)]
-  pub async fn get_collab_members(
+  pub async fn complete_presigned_upload(
     &self,
-    params: QueryCollabMembers,
-  ) -> Result<AFCollabMembers, AppResponseError> {
+    workspace_id: &str,
+    file_id: &str,
+  ) -> Result<AFBlobRecord, AppResponseError> {
     let url = format!(
-      "{}/api/workspace/{}/collab/{}/member/list",
-      self.base_url, params.workspace_id, &params.object_id
+      "{}/api/file_storage/{}/presigned/upload/{}/complete",
+      self.base_url, workspace_id, file_id
     );
     let resp = self
-      .http_client_with_auth(Method::GET, &url)
+      .http_client_with_auth(Method::POST, &url)
       .await?
-      .json(&params)
       .send()
       .await?;
     log_request_id(&resp);
-    AppResponse::<AFCollabMembers>::from_response(resp)
+    AppResponse::<AFBlobRecord>::from_response(resp)
       .await?
       .into_data()
   }
 
-  pub fn ws_url(&self, device_id: &str) -> Result<String, AppResponseError> {
-    let access_token = self.access_token()?;
-    Ok(format!("{}/{}/{}", self.ws_addr, access_token, device_id))
-  }
-
-  pub async fn put_blob<T: Into<Bytes>, M: ToString>(
+  /// Returns a time-limited url to download the blob from the bucket directly.
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn get_presigned_download_url(
     &self,
     workspace_id: &str,
-    data: T,
-    mime: M,
-  ) -> Result<String, AppResponseError> {
-    let url = format!("{}/api/file_storage/{}/blob", self.base_url, workspace_id);
-    let data = data.into();
-    let content_length = data.len();
+    file_id: &str,
+  ) -> Result<AFPresignedUrl, AppResponseError> {
+    let url = format!(
+      "{}/api/file_storage/{}/presigned/download/{}",
+      self.base_url, workspace_id, file_id
+    );
     let resp = self
-      .http_client_with_auth(Method::PUT, &url)
+      .http_client_with_auth(Method::GET, &url)
       .await?
-      .header(header::CONTENT_TYPE, mime.to_string())
-      .header(header::CONTENT_LENGTH, content_length)
-      .body(data)
       .send()
       .await?;
     log_request_id(&resp);
-    let record = AppResponse::<AFBlobRecord>::from_response(resp)
+    AppResponse::<AFPresignedUrl>::from_response(resp)
       .await?
-      .into_data()?;
-    Ok(format!(
-      "{}/api/file_storage/{}/blob/{}",
-      self.base_url, workspace_id, record.file_id
-    ))
+      .into_data()
   }
 
-  pub async fn put_blob_with_path(
+  /// Uploads the file in parts, which is required for the files larger than the limit of
+  /// [Client::put_blob]. Returns the url of the blob.
+  ///
+  /// If the upload is interrupted, call [Client::resume_blob_multipart] with the same upload to
+  /// upload the remaining parts.
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn put_blob_multipart(
     &self,
     workspace_id: &str,
     file_path: &str,
@@ -933,128 +2347,179 @@ impl Client {
     if file_path.is_empty() {
       return Err(AppError::InvalidRequest("path is empty".to_owned()).into());
     }
-
-    let mut file = File::open(&file_path).await?;
-    let mime = mime_guess::from_path(file_path)
+    let file_size = tokio::fs::metadata(file_path).await?.len();
+    let file_type = mime_guess::from_path(file_path)
       .first_or_octet_stream()
       .to_string();
-
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer).await?;
-    self.put_blob(workspace_id, buffer, mime).await
+    let upload = self
+      .create_upload(
+        workspace_id,
+        CreateUploadParams {
+          file_type,
+          file_size: file_size as i64,
+        },
+      )
+      .await?;
+    self
+      .resume_blob_multipart(workspace_id, file_path, &upload)
+      .await
   }
 
-  /// Only expose this method for testing
-  #[cfg(debug_assertions)]
-  pub async fn put_blob_with_content_length<T: Into<Bytes>>(
+  /// Uploads the parts of the file that are not uploaded yet and completes the upload. Each part
+  /// is retried a few times before giving up.
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn resume_blob_multipart(
     &self,
     workspace_id: &str,
-    data: T,
-    mime: &Mime,
-    content_length: usize,
-  ) -> Result<AFBlobRecord, AppResponseError> {
-    let url = format!("{}/api/file_storage/{}/blob", self.base_url, workspace_id);
-    let resp = self
-      .http_client_with_auth(Method::PUT, &url)
+    file_path: &str,
+    upload: &AFUpload,
+  ) -> Result<String, AppResponseError> {
+    let file_size = tokio::fs::metadata(file_path).await?.len();
+    let uploaded_parts = self
+      .get_upload_parts(workspace_id, &upload.upload_id)
       .await?
-      .header(header::CONTENT_TYPE, mime.to_string())
-      .header(header::CONTENT_LENGTH, content_length)
-      .body(data.into())
-      .send()
+      .0;
+
+    let mut file = File::open(file_path).await?;
+    let part_count = (file_size + UPLOAD_PART_SIZE - 1) / UPLOAD_PART_SIZE;
+    for index in 0..part_count {
+      let part_number = index as i32 + 1;
+      let offset = index * UPLOAD_PART_SIZE;
+      let part_size = UPLOAD_PART_SIZE.min(file_size - offset);
+      let is_uploaded = uploaded_parts
+        .iter()
+        .any(|part| part.part_number == part_number && part.part_size as u64 == part_size);
+      if is_uploaded {
+        trace!("skip uploaded part: {}", part_number);
+        continue;
+      }
+
+      file.seek(std::io::SeekFrom::Start(offset)).await?;
+      let mut data = vec![0; part_size as usize];
+      file.read_exact(&mut data).await?;
+      let data = Bytes::from(data);
+      let retry_strategy = FixedInterval::from_millis(1000).take(3);
+      Retry::spawn(retry_strategy, || {
+        self.put_upload_part(workspace_id, &upload.upload_id, part_number, data.clone())
+      })
       .await?;
-    log_request_id(&resp);
-    AppResponse::<AFBlobRecord>::from_response(resp)
-      .await?
-      .into_data()
+    }
+
+    let record = self
+      .complete_upload(workspace_id, &upload.upload_id)
+      .await?;
+    Ok(format!(
+      "{}/api/file_storage/{}/blob/{}",
+      self.base_url, workspace_id, record.file_id
+    ))
   }
 
-  /// Get the file with the given url. The url should be in the format of
-  /// `https://appflowy.io/api/file_storage/<workspace_id>/<file_id>`.
-  pub async fn get_blob<T: AsRef<str>>(&self, url: T) -> Result<Bytes, AppResponseError> {
-    Url::parse(url.as_ref())?;
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn create_upload(
+    &self,
+    workspace_id: &str,
+    params: CreateUploadParams,
+  ) -> Result<AFUpload, AppResponseError> {
+    let url = format!("{}/api/file_storage/{}/upload", self.base_url, workspace_id);
     let resp = self
-      .http_client_with_auth(Method::GET, url.as_ref())
+      .http_client_with_auth(Method::POST, &url)
       .await?
+      .json(&params)
       .send()
       .await?;
     log_request_id(&resp);
-
-    match resp.status() {
-      reqwest::StatusCode::OK => {
-        let mut stream = resp.bytes_stream();
-        let mut acc: Vec<u8> = Vec::new();
-        while let Some(raw_bytes) = stream.next().await {
-          acc.extend_from_slice(&raw_bytes?);
-        }
-        Ok(Bytes::from(acc))
-      },
-      reqwest::StatusCode::NOT_FOUND => Err(AppResponseError::from(AppError::RecordNotFound(
-        url.as_ref().to_owned(),
-      ))),
-      c => Err(AppResponseError::from(AppError::Unhandled(format!(
-        "status code: {}, message: {}",
-        c,
-        resp.text().await?
-      )))),
-    }
+    AppResponse::<AFUpload>::from_response(resp)
+      .await?
+      .into_data()
   }
 
-  pub async fn get_blob_metadata<T: AsRef<str>>(
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn get_upload_parts(
     &self,
-    url: T,
-  ) -> Result<AFBlobMetadata, AppResponseError> {
+    workspace_id: &str,
+    upload_id: &str,
+  ) -> Result<AFUploadParts, AppResponseError> {
+    let url = format!(
+      "{}/api/file_storage/{}/upload/{}",
+      self.base_url, workspace_id, upload_id
+    );
     let resp = self
-      .http_client_with_auth(Method::GET, url.as_ref())
+      .http_client_with_auth(Method::GET, &url)
       .await?
       .send()
       .await?;
-
     log_request_id(&resp);
-    AppResponse::<AFBlobMetadata>::from_response(resp)
+    AppResponse::<AFUploadParts>::from_response(resp)
       .await?
       .into_data()
   }
 
-  pub async fn delete_blob(&self, url: &str) -> Result<(), AppResponseError> {
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn put_upload_part<T: Into<Bytes>>(
+    &self,
+    workspace_id: &str,
+    upload_id: &str,
+    part_number: i32,
+    data: T,
+  ) -> Result<AFUploadPart, AppResponseError> {
+    let url = format!(
+      "{}/api/file_storage/{}/upload/{}/part/{}",
+      self.base_url, workspace_id, upload_id, part_number
+    );
+    let data = data.into();
+    let content_length = data.len();
     let resp = self
-      .http_client_with_auth(Method::DELETE, url)
+      .http_client_with_auth(Method::PUT, &url)
       .await?
+      .header(header::CONTENT_TYPE, "application/octet-stream")
+      .header(header::CONTENT_LENGTH, content_length)
+      .body(data)
       .send()
       .await?;
     log_request_id(&resp);
-    AppResponse::<()>::from_response(resp).await?.into_error()
+    AppResponse::<AFUploadPart>::from_response(resp)
+      .await?
+      .into_data()
   }
 
-  pub async fn get_workspace_usage(
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn complete_upload(
     &self,
     workspace_id: &str,
-  ) -> Result<WorkspaceSpaceUsage, AppResponseError> {
-    let url = format!("{}/api/file_storage/{}/usage", self.base_url, workspace_id);
+    upload_id: &str,
+  ) -> Result<AFBlobRecord, AppResponseError> {
+    let url = format!(
+      "{}/api/file_storage/{}/upload/{}/complete",
+      self.base_url, workspace_id, upload_id
+    );
     let resp = self
-      .http_client_with_auth(Method::GET, &url)
+      .http_client_with_auth(Method::POST, &url)
       .await?
       .send()
       .await?;
     log_request_id(&resp);
-    AppResponse::<WorkspaceSpaceUsage>::from_response(resp)
+    AppResponse::<AFBlobRecord>::from_response(resp)
       .await?
       .into_data()
   }
 
-  pub async fn get_workspace_all_blob_metadata(
+  #[instrument(level = "debug", skip_all, err)]
+  pub async fn abort_upload(
     &self,
     workspace_id: &str,
-  ) -> Result<WorkspaceBlobMetadata, AppResponseError> {
-    let url = format!("{}/api/file_storage/{}/blobs", self.base_url, workspace_id);
+    upload_id: &str,
+  ) -> Result<(), AppResponseError> {
+    let url = format!(
+      "{}/api/file_storage/{}/upload/{}",
+      self.base_url, workspace_id, upload_id
+    );
     let resp = self
-      .http_client_with_auth(Method::GET, &url)
+      .http_client_with_auth(Method::DELETE, &url)
       .await?
       .send()
       .await?;
     log_request_id(&resp);
-    AppResponse::<WorkspaceBlobMetadata>::from_response(resp)
-      .await?
-      .into_data()
+    AppResponse::<()>::from_response(resp).await?.into_error()
   }
 
   #[instrument(level = "debug", skip_all, err)]
@@ -14,6 +14,7 @@ use crate::ws::{WSError, WebSocketChannel};
 use tokio::sync::broadcast::{channel, Receiver, Sender};
 
 use realtime_entity::collab_msg::CollabMessage;
+use realtime_entity::comment::CommentMessage;
 use realtime_entity::message::RealtimeMessage;
 use realtime_entity::user::UserMessage;
 use tokio::sync::{oneshot, Mutex};
@@ -62,6 +63,7 @@ pub struct WSClient {
   sender: Sender<Message>,
   http_sender: Arc<dyn WSClientHttpSender>,
   user_channel: Arc<Sender<UserMessage>>,
+  comment_channel: Arc<Sender<CommentMessage>>,
   collab_channels: Arc<RwLock<ChannelByObjectId>>,
   ping: Arc<Mutex<Option<ServerFixIntervalPing>>>,
   stop_tx: Mutex<Option<oneshot::Sender<()>>>,
@@ -78,6 +80,7 @@ impl WSClient {
     let ping = Arc::new(Mutex::new(None));
     let http_sender = Arc::new(http_sender);
     let (user_channel, _) = channel(1);
+    let (comment_channel, _) = channel(100);
     WSClient {
       addr: Arc::new(parking_lot::Mutex::new(None)),
       config,
@@ -85,6 +88,7 @@ impl WSClient {
       sender,
       http_sender,
       user_channel: Arc::new(user_channel),
+      comment_channel: Arc::new(comment_channel),
       collab_channels,
       ping,
       stop_tx: Mutex::new(None),
@@ -157,6 +161,7 @@ impl WSClient {
     *self.ping.lock().await = Some(ping);
 
     let user_message_tx = self.user_channel.as_ref().clone();
+    let comment_message_tx = self.comment_channel.as_ref().clone();
     // Receive messages from the websocket, and send them to the channels.
     tokio::spawn(async move {
       while let Some(Ok(ws_msg)) = stream.next().await {
@@ -192,6 +197,9 @@ impl WSClient {
                   RealtimeMessage::User(user_message) => {
                     let _ = user_message_tx.send(user_message);
                   },
+                  RealtimeMessage::Comment(comment_message) => {
+                    let _ = comment_message_tx.send(comment_message);
+                  },
                   RealtimeMessage::ServerKickedOff => {},
                 }
               },
@@ -278,6 +286,11 @@ impl WSClient {
     self.user_channel.subscribe()
   }
 
+  /// Subscribes to the comment changes of the collabs that are opened by this client.
+  pub fn subscribe_comment_changed(&self) -> Receiver<CommentMessage> {
+    self.comment_channel.subscribe()
+  }
+
   pub fn subscribe_connect_state(&self) -> WSConnectStateReceiver {
     self.state_notify.lock().subscribe()
   }
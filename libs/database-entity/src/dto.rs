@@ -1,4 +1,7 @@
-use crate::pg_row::{AFBlobMetadataRow, AFUserProfileRow, AFWorkspaceRow};
+use crate::pg_row::{
+  AFAuditLogRow, AFBlobMetadataRow, AFCollabCommentRow, AFCollabShareLinkRow, AFTrashedCollabRow,
+  AFUserProfileRow, AFWorkspaceInvitationRow, AFWorkspaceRoleRow, AFWorkspaceRow,
+};
 use anyhow::anyhow;
 use app_error::AppError;
 use chrono::{DateTime, Utc};
@@ -54,6 +57,19 @@ impl InsertCollabParams {
   }
 }
 
+/// A single yrs update of a collab object. The update is appended to the update log of the
+/// collab and merged into the collab later.
+#[derive(Debug, Clone, Validate, Serialize, Deserialize)]
+pub struct InsertCollabUpdateParams {
+  #[validate(custom = "validate_not_empty_str")]
+  pub object_id: String,
+  #[validate(custom = "validate_not_empty_payload")]
+  pub update: Vec<u8>,
+  #[validate(custom = "validate_not_empty_str")]
+  pub workspace_id: String,
+  pub collab_type: CollabType,
+}
+
 #[derive(Debug, Clone, Validate, Serialize, Deserialize)]
 pub struct DeleteCollabParams {
   #[validate(custom = "validate_not_empty_str")]
@@ -128,16 +144,185 @@ pub struct AFCollabSnapshot {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AFCollabSnapshots(pub Vec<AFCollabSnapshot>);
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QuerySnapshotParams {
   pub snapshot_id: i64,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QueryObjectSnapshotParams {
   pub object_id: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoreSnapshotParams {
+  pub collab_type: CollabType,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuerySnapshotDiffParams {
+  pub collab_type: CollabType,
+  /// The snapshot that the changes are computed from.
+  pub from_snapshot_id: i64,
+  /// The snapshot that the changes are computed to. If it's None, the changes are computed to
+  /// the current state of the collab.
+  pub to_snapshot_id: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AFSnapshotDiff {
+  /// The yrs update (v1 encoding) that contains the changes between the two states.
+  pub update: Vec<u8>,
+  /// The block-level summary of the changes. Only available for [CollabType::Document].
+  pub document_diff: Option<AFDocumentDiff>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AFDocumentDiff {
+  pub inserted_blocks: Vec<String>,
+  pub deleted_blocks: Vec<String>,
+  pub modified_blocks: Vec<String>,
+}
+
+/// A document or database row that matches the search query.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AFCollabSearchResult {
+  pub object_id: String,
+  pub collab_type: CollabType,
+  /// The higher the rank, the better the collab matches the query.
+  pub rank: f32,
+  /// A fragment of the content of the collab, in which the matched words are wrapped in `<b>`
+  /// and `</b>`.
+  pub snippet: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AFCollabSearchResults(pub Vec<AFCollabSearchResult>);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateCollabShareLinkParams {
+  pub collab_type: CollabType,
+  /// Either [AFAccessLevel::ReadOnly] or [AFAccessLevel::ReadAndComment].
+  pub access_level: AFAccessLevel,
+  pub expires_at: Option<DateTime<Utc>>,
+  pub password: Option<String>,
+}
+
+/// A link that publishes the collab to the people outside the workspace. The password of the link
+/// is never returned.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AFCollabShareLink {
+  pub share_id: Uuid,
+  pub token: String,
+  pub workspace_id: Uuid,
+  pub object_id: String,
+  pub collab_type: CollabType,
+  pub access_level: AFAccessLevel,
+  pub expires_at: Option<DateTime<Utc>>,
+  pub has_password: bool,
+  pub created_at: DateTime<Utc>,
+}
+
+impl From<AFCollabShareLinkRow> for AFCollabShareLink {
+  fn from(value: AFCollabShareLinkRow) -> Self {
+    Self {
+      share_id: value.id,
+      token: value.token,
+      workspace_id: value.workspace_id,
+      object_id: value.oid,
+      collab_type: CollabType::from(value.collab_type),
+      access_level: AFAccessLevel::from(value.access_level),
+      expires_at: value.expires_at,
+      has_password: value.password_hash.is_some(),
+      created_at: value.created_at,
+    }
+  }
+}
+
+/// A deleted collab that can be restored until it's purged from the trash.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AFTrashedCollab {
+  pub object_id: String,
+  pub collab_type: CollabType,
+  pub owner_uid: i64,
+  pub deleted_at: DateTime<Utc>,
+}
+
+impl From<AFTrashedCollabRow> for AFTrashedCollab {
+  fn from(value: AFTrashedCollabRow) -> Self {
+    Self {
+      object_id: value.oid,
+      collab_type: CollabType::from(value.partition_key),
+      owner_uid: value.owner_uid,
+      deleted_at: value.deleted_at,
+    }
+  }
+}
+
+/// The collab returned for a share link.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AFSharedCollab {
+  pub object_id: String,
+  pub workspace_id: Uuid,
+  pub collab_type: CollabType,
+  pub access_level: AFAccessLevel,
+  pub encoded_collab_v1: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateCollabCommentParams {
+  pub content: String,
+  /// The id of the block that the comment is anchored to. None if the comment is about the whole
+  /// collab.
+  pub block_id: Option<String>,
+  /// The root comment of the thread that the comment replies to.
+  pub reply_to: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateCollabCommentParams {
+  pub content: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolveCollabCommentParams {
+  /// Set to false to reopen the thread.
+  pub resolved: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AFCollabComment {
+  pub comment_id: Uuid,
+  pub workspace_id: Uuid,
+  pub object_id: String,
+  pub block_id: Option<String>,
+  pub reply_to: Option<Uuid>,
+  pub content: String,
+  pub created_by: i64,
+  pub created_at: DateTime<Utc>,
+  pub updated_at: DateTime<Utc>,
+  pub resolved_at: Option<DateTime<Utc>>,
+  pub resolved_by: Option<i64>,
+}
+
+impl From<AFCollabCommentRow> for AFCollabComment {
+  fn from(value: AFCollabCommentRow) -> Self {
+    Self {
+      comment_id: value.id,
+      workspace_id: value.workspace_id,
+      object_id: value.oid,
+      block_id: value.block_id,
+      reply_to: value.reply_to,
+      content: value.content,
+      created_by: value.created_by,
+      created_at: value.created_at,
+      updated_at: value.updated_at,
+      resolved_at: value.resolved_at,
+      resolved_by: value.resolved_by,
+    }
+  }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct AFBlobRecord {
   pub file_id: String,
@@ -149,6 +334,72 @@ impl AFBlobRecord {
   }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateUploadParams {
+  pub file_type: String,
+  /// The size of the whole file in bytes.
+  pub file_size: i64,
+}
+
+/// A multipart upload of a blob. The `file_id` identifies the blob after the upload is completed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AFUpload {
+  pub upload_id: String,
+  pub file_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AFUploadPart {
+  /// Starts from 1.
+  pub part_number: i32,
+  pub e_tag: String,
+  pub part_size: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AFUploadParts(pub Vec<AFUploadPart>);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatePresignedUploadParams {
+  pub file_type: String,
+  /// The size of the file in bytes. The uploaded blob must not be larger than this size.
+  pub file_size: i64,
+}
+
+/// A time-limited url to access the blob in the bucket directly.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AFPresignedUrl {
+  pub file_id: String,
+  pub url: String,
+  pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunBlobGCParams {
+  /// Only reports the garbage without removing it.
+  pub dry_run: bool,
+}
+
+/// The result of a garbage collection of the blobs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AFBlobGCReport {
+  pub dry_run: bool,
+  pub started_at: DateTime<Utc>,
+  pub finished_at: DateTime<Utc>,
+  /// The objects in the bucket that are not referenced by any blob metadata or upload.
+  pub orphan_blobs: Vec<String>,
+  /// The blob metadata whose object doesn't exist in the bucket.
+  pub missing_blobs: Vec<AFMissingBlob>,
+  /// The file ids of the multipart and presigned uploads that were not completed in time.
+  pub expired_uploads: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AFMissingBlob {
+  pub workspace_id: Uuid,
+  pub file_id: String,
+}
+
 #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
 pub enum QueryCollabResult {
   Success { encode_collab_v1: Vec<u8> },
@@ -204,7 +455,129 @@ pub enum AFRole {
 impl AFRole {
   /// The user can create a [Collab] if the user is [AFRole::Owner] or [AFRole::Member] of the workspace.
   pub fn can_create_collab(&self) -> bool {
-    matches!(self, AFRole::Owner | AFRole::Member)
+    self
+      .default_permissions()
+      .contains(AFWorkspacePermission::CreateCollab)
+  }
+
+  /// The permissions of the member who doesn't have a custom role.
+  pub fn default_permissions(&self) -> AFWorkspacePermissions {
+    match self {
+      AFRole::Owner => AFWorkspacePermissions::all(),
+      AFRole::Member => AFWorkspacePermissions::from(
+        [
+          AFWorkspacePermission::CreateCollab,
+          AFWorkspacePermission::EditCollab,
+          AFWorkspacePermission::Comment,
+        ]
+        .as_slice(),
+      ),
+      AFRole::Guest => AFWorkspacePermissions::from([AFWorkspacePermission::Comment].as_slice()),
+    }
+  }
+}
+
+/// The permissions that can be granted by a custom role of the workspace.
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum AFWorkspacePermission {
+  CreateCollab,
+  EditCollab,
+  DeleteCollab,
+  Comment,
+  InviteMember,
+  ManageBlob,
+}
+
+impl AFWorkspacePermission {
+  pub const ALL: [AFWorkspacePermission; 6] = [
+    AFWorkspacePermission::CreateCollab,
+    AFWorkspacePermission::EditCollab,
+    AFWorkspacePermission::DeleteCollab,
+    AFWorkspacePermission::Comment,
+    AFWorkspacePermission::InviteMember,
+    AFWorkspacePermission::ManageBlob,
+  ];
+
+  // Can't modify the bit of the permission, it's stored in the af_workspace_role table
+  fn bit(&self) -> i64 {
+    match self {
+      AFWorkspacePermission::CreateCollab => 1,
+      AFWorkspacePermission::EditCollab => 1 << 1,
+      AFWorkspacePermission::DeleteCollab => 1 << 2,
+      AFWorkspacePermission::Comment => 1 << 3,
+      AFWorkspacePermission::InviteMember => 1 << 4,
+      AFWorkspacePermission::ManageBlob => 1 << 5,
+    }
+  }
+}
+
+/// A set of [AFWorkspacePermission], kept as the bit flags in the database.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default)]
+pub struct AFWorkspacePermissions(pub i64);
+
+impl AFWorkspacePermissions {
+  pub fn all() -> Self {
+    Self::from(AFWorkspacePermission::ALL.as_slice())
+  }
+
+  pub fn contains(&self, permission: AFWorkspacePermission) -> bool {
+    self.0 & permission.bit() != 0
+  }
+
+  pub fn to_vec(&self) -> Vec<AFWorkspacePermission> {
+    AFWorkspacePermission::ALL
+      .into_iter()
+      .filter(|permission| self.contains(*permission))
+      .collect()
+  }
+
+  /// Returns the highest access level that the permissions allow in the collabs of the workspace.
+  pub fn max_access_level(&self) -> AFAccessLevel {
+    if !self.contains(AFWorkspacePermission::EditCollab) {
+      if self.contains(AFWorkspacePermission::Comment) {
+        AFAccessLevel::ReadAndComment
+      } else {
+        AFAccessLevel::ReadOnly
+      }
+    } else if !self.contains(AFWorkspacePermission::DeleteCollab) {
+      AFAccessLevel::ReadAndWrite
+    } else {
+      AFAccessLevel::FullAccess
+    }
+  }
+}
+
+impl From<&[AFWorkspacePermission]> for AFWorkspacePermissions {
+  fn from(permissions: &[AFWorkspacePermission]) -> Self {
+    Self(
+      permissions
+        .iter()
+        .fold(0, |bits, permission| bits | permission.bit()),
+    )
+  }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AFWorkspaceRole {
+  pub role_id: i32,
+  pub workspace_id: Uuid,
+  pub name: String,
+  pub permissions: Vec<AFWorkspacePermission>,
+  pub created_at: DateTime<Utc>,
+  pub updated_at: DateTime<Utc>,
+}
+
+impl From<AFWorkspaceRoleRow> for AFWorkspaceRole {
+  fn from(value: AFWorkspaceRoleRow) -> Self {
+    Self {
+      role_id: value.id,
+      workspace_id: value.workspace_id,
+      name: value.name,
+      permissions: AFWorkspacePermissions(value.permissions).to_vec(),
+      created_at: value.created_at,
+      updated_at: value.updated_at,
+    }
   }
 }
 
@@ -273,6 +646,15 @@ impl AFAccessLevel {
     }
   }
 
+  pub fn can_comment(&self) -> bool {
+    match self {
+      AFAccessLevel::ReadOnly => false,
+      AFAccessLevel::ReadAndComment | AFAccessLevel::ReadAndWrite | AFAccessLevel::FullAccess => {
+        true
+      },
+    }
+  }
+
   pub fn can_delete(&self) -> bool {
     match self {
       AFAccessLevel::ReadOnly | AFAccessLevel::ReadAndComment | AFAccessLevel::ReadAndWrite => {
@@ -350,7 +732,7 @@ impl TryFrom<AFUserProfileRow> for AFUserProfile {
   }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AFWorkspace {
   pub workspace_id: Uuid,
   pub database_storage_id: Uuid,
@@ -403,6 +785,327 @@ pub struct AFWorkspaceMember {
   pub avatar_url: Option<String>,
 }
 
+#[derive(Serialize_repr, Deserialize_repr, Eq, PartialEq, Debug, Clone, Copy)]
+#[repr(i16)]
+pub enum AFWorkspaceInvitationStatus {
+  Pending = 0,
+  Accepted = 1,
+  Declined = 2,
+}
+
+impl From<i16> for AFWorkspaceInvitationStatus {
+  fn from(value: i16) -> Self {
+    match value {
+      1 => AFWorkspaceInvitationStatus::Accepted,
+      2 => AFWorkspaceInvitationStatus::Declined,
+      _ => AFWorkspaceInvitationStatus::Pending,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AFWorkspaceInvitation {
+  pub invite_id: Uuid,
+  pub workspace_id: Uuid,
+  pub workspace_name: String,
+  pub inviter_name: String,
+  pub invitee_email: String,
+  pub role: AFRole,
+  pub status: AFWorkspaceInvitationStatus,
+  pub created_at: DateTime<Utc>,
+}
+
+impl From<AFWorkspaceInvitationRow> for AFWorkspaceInvitation {
+  fn from(value: AFWorkspaceInvitationRow) -> Self {
+    Self {
+      invite_id: value.invite_id,
+      workspace_id: value.workspace_id,
+      workspace_name: value.workspace_name.unwrap_or_default(),
+      inviter_name: value.inviter_name,
+      invitee_email: value.invitee_email,
+      role: AFRole::from(value.role_id),
+      status: AFWorkspaceInvitationStatus::from(value.status),
+      created_at: value.created_at,
+    }
+  }
+}
+
+/// A field of a database collab. The `field_type` is the `FieldType` of `collab_database`, e.g.
+/// 0 for rich text and 1 for number.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AFDatabaseField {
+  pub field_id: String,
+  pub name: String,
+  pub field_type: i64,
+  pub is_primary: bool,
+}
+
+/// A row of a database collab. The cells are keyed by the field id, and the value of each cell is
+/// the raw cell data stored by `collab_database`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AFDatabaseRow {
+  pub row_id: String,
+  pub cells: HashMap<String, String>,
+  pub created_at: i64,
+  pub last_modified: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AFDatabaseRows {
+  pub rows: Vec<AFDatabaseRow>,
+  /// The number of the rows that match the filters.
+  pub total: usize,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum DatabaseRowFilterCondition {
+  Is,
+  IsNot,
+  Contains,
+  DoesNotContain,
+  IsEmpty,
+  IsNotEmpty,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseRowFilter {
+  pub field_id: String,
+  pub condition: DatabaseRowFilterCondition,
+  /// Ignored by [DatabaseRowFilterCondition::IsEmpty] and [DatabaseRowFilterCondition::IsNotEmpty].
+  #[serde(default)]
+  pub value: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseRowSort {
+  pub field_id: String,
+  #[serde(default)]
+  pub descending: bool,
+}
+
+/// Queries the rows of a database. The rows are returned in the order of the inline view of the
+/// database unless sorts are given. A row must match all the filters, and the sorts are applied
+/// in the given order.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QueryDatabaseRowsParams {
+  #[serde(default)]
+  pub filters: Vec<DatabaseRowFilter>,
+  #[serde(default)]
+  pub sorts: Vec<DatabaseRowSort>,
+  pub offset: Option<usize>,
+  pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InsertDatabaseRowParams {
+  pub cells: HashMap<String, String>,
+}
+
+/// Only the given cells are updated, the other cells of the row are kept.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateDatabaseRowParams {
+  pub cells: HashMap<String, String>,
+}
+
+/// A block of a document with its children. The text of the block is the `delta` in its data.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AFDocumentBlock {
+  #[serde(rename = "type")]
+  pub ty: String,
+  #[serde(default)]
+  pub data: HashMap<String, serde_json::Value>,
+  #[serde(default)]
+  pub children: Vec<AFDocumentBlock>,
+}
+
+#[derive(Debug, Clone, Validate, Serialize, Deserialize)]
+pub struct InsertDocumentBlocksParams {
+  /// The id of the block that the blocks are inserted under. Defaults to the page block.
+  #[serde(default)]
+  pub parent_id: Option<String>,
+  /// The id of the child of the parent that the blocks are inserted after. Defaults to the last
+  /// child of the parent.
+  #[serde(default)]
+  pub prev_id: Option<String>,
+  #[validate(length(min = 1))]
+  pub blocks: Vec<AFDocumentBlock>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AFDocumentFormat {
+  Markdown,
+  Html,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportDocumentParams {
+  pub format: AFDocumentFormat,
+  /// The image sources that are the file ids of the blobs of the workspace are linked to the
+  /// blobs.
+  pub content: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AFImportedDocument {
+  pub object_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportDocumentParams {
+  pub format: AFDocumentFormat,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AFExportedDocument {
+  pub format: AFDocumentFormat,
+  pub content: String,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AFViewLayout {
+  Document,
+  Grid,
+  Board,
+  Calendar,
+}
+
+/// A view of the folder of the workspace with its child views. The top level views are the
+/// children of the workspace, whose id is their `parent_view_id`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AFFolderView {
+  pub view_id: String,
+  pub parent_view_id: String,
+  pub name: String,
+  pub layout: AFViewLayout,
+  pub created_at: i64,
+  pub last_edited_time: i64,
+  pub children: Vec<AFFolderView>,
+}
+
+#[derive(Debug, Clone, Validate, Serialize, Deserialize)]
+pub struct CreateFolderViewParams {
+  /// The id of the view that the view is created under. Defaults to the workspace.
+  #[serde(default)]
+  pub parent_view_id: Option<String>,
+  /// The id of the view that the view is placed after. Defaults to the last child of the parent.
+  #[serde(default)]
+  pub prev_view_id: Option<String>,
+  #[validate(custom = "validate_not_empty_str")]
+  pub name: String,
+  pub layout: AFViewLayout,
+  /// The id of the existing document or database view that the view opens. When it's empty, an
+  /// empty document is created for the view, which is only allowed for the document layout.
+  #[serde(default)]
+  pub object_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Validate, Serialize, Deserialize)]
+pub struct UpdateFolderViewParams {
+  #[validate(custom = "validate_not_empty_str")]
+  pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MoveFolderViewParams {
+  /// The id of the view that the view is moved under. Defaults to the workspace.
+  #[serde(default)]
+  pub parent_view_id: Option<String>,
+  /// The id of the view that the view is placed after. Defaults to the last child of the parent.
+  #[serde(default)]
+  pub prev_view_id: Option<String>,
+}
+
+/// The security-relevant actions that are recorded in the audit log.
+#[derive(Serialize_repr, Deserialize_repr, Eq, PartialEq, Debug, Clone, Copy)]
+#[repr(i16)]
+pub enum AFAuditAction {
+  Unknown = 0,
+  WorkspaceRename = 1,
+  WorkspaceDelete = 2,
+  WorkspaceOwnershipTransfer = 3,
+  WorkspaceMemberAdd = 4,
+  WorkspaceMemberRemove = 5,
+  WorkspaceMemberRoleUpdate = 6,
+  WorkspaceRoleCreate = 7,
+  WorkspaceRoleUpdate = 8,
+  WorkspaceRoleDelete = 9,
+  WorkspaceRoleAssign = 10,
+  WorkspaceExport = 11,
+  WorkspaceImport = 12,
+  CollabDelete = 20,
+  CollabMemberAdd = 21,
+  CollabMemberUpdate = 22,
+  CollabMemberRemove = 23,
+  CollabSnapshotRestore = 24,
+  CollabShareLinkCreate = 25,
+  CollabShareLinkDelete = 26,
+  CollabRestore = 27,
+  BlobDelete = 40,
+  UserUpdate = 60,
+}
+
+impl From<i16> for AFAuditAction {
+  fn from(value: i16) -> Self {
+    match value {
+      1 => AFAuditAction::WorkspaceRename,
+      2 => AFAuditAction::WorkspaceDelete,
+      3 => AFAuditAction::WorkspaceOwnershipTransfer,
+      4 => AFAuditAction::WorkspaceMemberAdd,
+      5 => AFAuditAction::WorkspaceMemberRemove,
+      6 => AFAuditAction::WorkspaceMemberRoleUpdate,
+      7 => AFAuditAction::WorkspaceRoleCreate,
+      8 => AFAuditAction::WorkspaceRoleUpdate,
+      9 => AFAuditAction::WorkspaceRoleDelete,
+      10 => AFAuditAction::WorkspaceRoleAssign,
+      11 => AFAuditAction::WorkspaceExport,
+      12 => AFAuditAction::WorkspaceImport,
+      20 => AFAuditAction::CollabDelete,
+      21 => AFAuditAction::CollabMemberAdd,
+      22 => AFAuditAction::CollabMemberUpdate,
+      23 => AFAuditAction::CollabMemberRemove,
+      24 => AFAuditAction::CollabSnapshotRestore,
+      25 => AFAuditAction::CollabShareLinkCreate,
+      26 => AFAuditAction::CollabShareLinkDelete,
+      27 => AFAuditAction::CollabRestore,
+      40 => AFAuditAction::BlobDelete,
+      60 => AFAuditAction::UserUpdate,
+      _ => {
+        error!("Invalid audit action: {}", value);
+        AFAuditAction::Unknown
+      },
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AFAuditLog {
+  pub log_id: i64,
+  pub actor_uid: i64,
+  pub workspace_id: Option<Uuid>,
+  pub action: AFAuditAction,
+  pub target: String,
+  pub metadata: Option<serde_json::Value>,
+  pub request_id: Option<String>,
+  pub created_at: DateTime<Utc>,
+}
+
+impl From<AFAuditLogRow> for AFAuditLog {
+  fn from(value: AFAuditLogRow) -> Self {
+    Self {
+      log_id: value.id,
+      actor_uid: value.actor_uid,
+      workspace_id: value.workspace_id,
+      action: AFAuditAction::from(value.action),
+      target: value.target,
+      metadata: value.metadata,
+      request_id: value.request_id,
+      created_at: value.created_at,
+    }
+  }
+}
+
 /// ***************************************************************
 /// Make alias for the database entity. Hiding the Sqlx Rows type.
 pub type AFBlobMetadata = AFBlobMetadataRow;
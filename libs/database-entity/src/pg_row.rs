@@ -16,6 +16,26 @@ pub struct AFWorkspaceRow {
   pub workspace_name: Option<String>,
 }
 
+/// Represent the row of the af_workspace_plan table, which defines the limits of a workspace type.
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct AFWorkspacePlanRow {
+  pub workspace_type: i32,
+  pub name: String,
+  pub storage_limit: i64,
+  pub member_limit: i64,
+  pub blob_size_limit: i64,
+}
+
+/// A collab that matches the full-text search query, with its rank and a snippet of the matched
+/// content.
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct AFCollabSearchRow {
+  pub oid: String,
+  pub collab_type: i32,
+  pub rank: f32,
+  pub snippet: String,
+}
+
 /// Represent the row of the af_user table
 #[derive(Debug, FromRow, Deserialize, Serialize, Clone)]
 pub struct AFUserRow {
@@ -54,6 +74,93 @@ pub struct AFWorkspaceMemberRow {
   pub role: AFRole,
 }
 
+/// Represent the row of the af_workspace_invitation table, with the name of the workspace and the
+/// name of the inviter.
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct AFWorkspaceInvitationRow {
+  pub invite_id: Uuid,
+  pub workspace_id: Uuid,
+  pub workspace_name: Option<String>,
+  pub inviter_name: String,
+  pub invitee_email: String,
+  pub role_id: i32,
+  pub status: i16,
+  pub created_at: DateTime<Utc>,
+}
+
+/// Represent the row of the af_collab_share_link table
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct AFCollabShareLinkRow {
+  pub id: Uuid,
+  pub token: String,
+  pub workspace_id: Uuid,
+  pub oid: String,
+  pub collab_type: i32,
+  pub created_by: i64,
+  pub access_level: i32,
+  pub expires_at: Option<DateTime<Utc>>,
+  pub password_hash: Option<String>,
+  pub created_at: DateTime<Utc>,
+}
+
+/// Represent the row of the af_audit_log table
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct AFAuditLogRow {
+  pub id: i64,
+  pub actor_uid: i64,
+  pub workspace_id: Option<Uuid>,
+  pub action: i16,
+  pub target: String,
+  pub metadata: Option<serde_json::Value>,
+  pub request_id: Option<String>,
+  pub created_at: DateTime<Utc>,
+}
+
+/// Represent the row of the af_collab_comment table
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct AFCollabCommentRow {
+  pub id: Uuid,
+  pub workspace_id: Uuid,
+  pub oid: String,
+  pub block_id: Option<String>,
+  pub reply_to: Option<Uuid>,
+  pub content: String,
+  pub created_by: i64,
+  pub created_at: DateTime<Utc>,
+  pub updated_at: DateTime<Utc>,
+  pub resolved_at: Option<DateTime<Utc>>,
+  pub resolved_by: Option<i64>,
+}
+
+/// Represent a collab in the trash. The columns are selected from the af_collab table.
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct AFTrashedCollabRow {
+  pub oid: String,
+  pub partition_key: i32,
+  pub owner_uid: i64,
+  pub deleted_at: DateTime<Utc>,
+}
+
+/// Represent the row of the af_workspace_role table
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct AFWorkspaceRoleRow {
+  pub id: i32,
+  pub workspace_id: Uuid,
+  pub name: String,
+  pub permissions: i64,
+  pub created_at: DateTime<Utc>,
+  pub updated_at: DateTime<Utc>,
+}
+
+/// The built-in role of the workspace member, with the permissions of the custom role if the member
+/// has one.
+#[derive(Debug, Clone, FromRow)]
+pub struct AFWorkspaceMemberPermissionRow {
+  pub role_id: i32,
+  pub custom_role_id: Option<i32>,
+  pub permissions: Option<i64>,
+}
+
 #[derive(FromRow, Clone, Debug, Serialize, Deserialize)]
 pub struct AFCollabMemberRow {
   pub uid: i64,
@@ -61,6 +168,35 @@ pub struct AFCollabMemberRow {
   pub permission_id: i64,
 }
 
+/// Represent the row of the af_collab_update table
+#[derive(Debug, FromRow, Clone)]
+pub struct AFCollabUpdateRow {
+  pub id: i64,
+  pub oid: String,
+  pub blob: Vec<u8>,
+}
+
+/// Represent the row of the af_blob_upload table
+#[derive(Debug, FromRow, Clone)]
+pub struct AFBlobUploadRow {
+  pub upload_id: String,
+  pub workspace_id: Uuid,
+  pub file_id: String,
+  pub file_type: String,
+  pub file_size: i64,
+  pub created_at: DateTime<Utc>,
+}
+
+/// Represent the row of the af_blob_presigned_upload table
+#[derive(Debug, FromRow, Clone)]
+pub struct AFBlobPresignedUploadRow {
+  pub file_id: String,
+  pub workspace_id: Uuid,
+  pub file_type: String,
+  pub file_size: i64,
+  pub created_at: DateTime<Utc>,
+}
+
 #[derive(FromRow, Serialize, Deserialize)]
 pub struct AFBlobMetadataRow {
   pub workspace_id: Uuid,
@@ -68,6 +204,16 @@ pub struct AFBlobMetadataRow {
   pub file_type: String,
   pub file_size: i64,
   pub modified_at: DateTime<Utc>,
+  /// The number of times the blob was uploaded to the workspace.
+  #[serde(default = "default_ref_count")]
+  pub ref_count: i64,
+  /// The entity tag of the blob content. None if the file id is the content hash of the blob.
+  #[serde(default)]
+  pub e_tag: Option<String>,
+}
+
+fn default_ref_count() -> i64 {
+  1
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -0,0 +1,76 @@
+use app_error::AppError;
+use database_entity::pg_row::AFAuditLogRow;
+use sqlx::{Executor, PgPool, Postgres};
+use tracing::instrument;
+use uuid::Uuid;
+
+/// Inserts the audit log of the action performed by the user with the given uuid.
+#[allow(clippy::too_many_arguments)]
+#[instrument(level = "trace", skip(executor, metadata), err)]
+pub async fn insert_audit_log<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  actor_uuid: &Uuid,
+  workspace_id: Option<&Uuid>,
+  action: i16,
+  target: &str,
+  metadata: Option<serde_json::Value>,
+  request_id: &str,
+) -> Result<(), AppError> {
+  let res = sqlx::query!(
+    r#"
+        INSERT INTO af_audit_log (actor_uid, workspace_id, action, target, metadata, request_id)
+        SELECT uid, $2, $3, $4, $5, $6 FROM af_user WHERE uuid = $1
+        "#,
+    actor_uuid,
+    workspace_id,
+    action,
+    target,
+    metadata,
+    request_id
+  )
+  .execute(executor)
+  .await?;
+
+  if res.rows_affected() == 0 {
+    return Err(AppError::RecordNotFound(format!(
+      "user:{} doesn't exist",
+      actor_uuid
+    )));
+  }
+  Ok(())
+}
+
+/// Returns the audit logs of the workspace from the newest to the oldest. The filters are ignored
+/// when they are None.
+#[allow(clippy::too_many_arguments)]
+pub async fn select_audit_logs(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  action: Option<i16>,
+  actor_uid: Option<i64>,
+  target: Option<&str>,
+  offset: i64,
+  limit: i64,
+) -> Result<Vec<AFAuditLogRow>, AppError> {
+  let rows = sqlx::query_as!(
+    AFAuditLogRow,
+    r#"
+        SELECT * FROM af_audit_log
+        WHERE workspace_id = $1
+          AND ($2::SMALLINT IS NULL OR action = $2)
+          AND ($3::BIGINT IS NULL OR actor_uid = $3)
+          AND ($4::TEXT IS NULL OR target = $4)
+        ORDER BY created_at DESC, id DESC
+        OFFSET $5 LIMIT $6
+        "#,
+    workspace_id,
+    action,
+    actor_uid,
+    target,
+    offset,
+    limit
+  )
+  .fetch_all(pg_pool)
+  .await?;
+  Ok(rows)
+}
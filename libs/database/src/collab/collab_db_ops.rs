@@ -2,10 +2,12 @@ use anyhow::{anyhow, Context};
 use collab_entity::CollabType;
 use database_entity::dto::{
   AFAccessLevel, AFCollabMember, AFCollabSnapshot, AFCollabSnapshots, AFPermission,
-  BatchQueryCollab, InsertCollabParams, QueryCollabResult, RawData,
+  BatchQueryCollab, InsertCollabParams, InsertCollabUpdateParams, QueryCollabResult, RawData,
 };
+use database_entity::pg_row::AFCollabUpdateRow;
 
 use app_error::AppError;
+use chrono::{DateTime, Utc};
 use sqlx::postgres::PgRow;
 use sqlx::{Error, Executor, PgPool, Postgres, Row, Transaction};
 use std::collections::HashMap;
@@ -245,7 +247,10 @@ struct QueryCollabData {
 }
 
 #[inline]
-pub async fn delete_collab(pg_pool: &PgPool, object_id: &str) -> Result<(), sqlx::Error> {
+pub async fn delete_collab<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  object_id: &str,
+) -> Result<(), sqlx::Error> {
   sqlx::query!(
     r#"
         UPDATE af_collab
@@ -255,7 +260,174 @@ pub async fn delete_collab(pg_pool: &PgPool, object_id: &str) -> Result<(), sqlx
     object_id,
     chrono::Utc::now()
   )
-  .execute(pg_pool)
+  .execute(executor)
+  .await?;
+  Ok(())
+}
+
+/// Appends the update to the `af_collab_update` table. The update will be merged into the
+/// `af_collab` row by [crate::collab::CollabStoragePgImpl::compact_collab_updates].
+#[inline]
+#[instrument(level = "trace", skip(executor, params), fields(oid=%params.object_id), err)]
+pub async fn insert_into_af_collab_update<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  params: &InsertCollabUpdateParams,
+) -> Result<(), AppError> {
+  let workspace_id = Uuid::from_str(&params.workspace_id)?;
+  sqlx::query!(
+    r#"
+      INSERT INTO af_collab_update (oid, partition_key, blob, len, workspace_id)
+      VALUES ($1, $2, $3, $4, $5)
+    "#,
+    params.object_id,
+    params.collab_type.value(),
+    params.update,
+    params.update.len() as i32,
+    workspace_id
+  )
+  .execute(executor)
+  .await
+  .context(format!(
+    "Insert af_collab_update failed: {}:{}",
+    params.object_id, params.collab_type
+  ))?;
+  Ok(())
+}
+
+/// Returns the updates of the collab that have not been merged into the `af_collab` row yet.
+/// The updates are ordered by the insertion order.
+#[inline]
+pub async fn select_collab_updates<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  object_id: &str,
+) -> Result<Vec<AFCollabUpdateRow>, sqlx::Error> {
+  sqlx::query_as!(
+    AFCollabUpdateRow,
+    r#"
+      SELECT id, oid, blob
+      FROM af_collab_update
+      WHERE oid = $1
+      ORDER BY id;
+    "#,
+    object_id
+  )
+  .fetch_all(executor)
+  .await
+}
+
+#[inline]
+pub async fn batch_select_collab_updates(
+  pg_pool: &PgPool,
+  object_ids: &[String],
+) -> Result<HashMap<String, Vec<RawData>>, sqlx::Error> {
+  let rows = sqlx::query_as!(
+    AFCollabUpdateRow,
+    r#"
+      SELECT id, oid, blob
+      FROM af_collab_update
+      WHERE oid = ANY($1)
+      ORDER BY id;
+    "#,
+    object_ids
+  )
+  .fetch_all(pg_pool)
+  .await?;
+
+  let mut updates_by_oid: HashMap<String, Vec<RawData>> = HashMap::new();
+  for row in rows {
+    updates_by_oid.entry(row.oid).or_default().push(row.blob);
+  }
+  Ok(updates_by_oid)
+}
+
+#[inline]
+pub async fn delete_collab_updates<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  update_ids: &[i64],
+) -> Result<(), sqlx::Error> {
+  sqlx::query!(
+    "DELETE FROM af_collab_update WHERE id = ANY($1)",
+    update_ids
+  )
+  .execute(executor)
+  .await?;
+  Ok(())
+}
+
+/// Returns the object id and partition key of the collabs whose update log should be compacted.
+/// A collab's update log is compacted when it has at least `min_update_count` pending updates or
+/// its oldest pending update is older than `max_pending_secs` seconds.
+///
+/// The updates of the collabs that don't exist or are in the trash can't be compacted, so they
+/// are left out. The collabs with the oldest pending updates come first.
+pub async fn select_collabs_to_compact(
+  pg_pool: &PgPool,
+  min_update_count: i64,
+  max_pending_secs: i64,
+  limit: i64,
+) -> Result<Vec<(String, i32)>, sqlx::Error> {
+  let rows = sqlx::query!(
+    r#"
+      SELECT u.oid, MIN(u.partition_key) AS "partition_key!"
+      FROM af_collab_update u
+      JOIN af_collab c
+        ON c.oid = u.oid AND c.partition_key = u.partition_key AND c.deleted_at IS NULL
+      GROUP BY u.oid
+      HAVING COUNT(*) >= $1
+        OR MIN(u.created_at) <= NOW() - make_interval(secs => $2)
+      ORDER BY MIN(u.created_at)
+      LIMIT $3;
+    "#,
+    min_update_count,
+    max_pending_secs as f64,
+    limit
+  )
+  .fetch_all(pg_pool)
+  .await?;
+  Ok(
+    rows
+      .into_iter()
+      .map(|row| (row.oid, row.partition_key))
+      .collect(),
+  )
+}
+
+/// Selects the blob of the collab and locks the row until the end of the transaction.
+#[inline]
+pub async fn select_blob_from_af_collab_for_update(
+  tx: &mut Transaction<'_, sqlx::Postgres>,
+  partition_key: i32,
+  object_id: &str,
+) -> Result<Option<RawData>, sqlx::Error> {
+  sqlx::query_scalar!(
+    r#"
+      SELECT blob
+      FROM af_collab
+      WHERE oid = $1 AND partition_key = $2 AND deleted_at IS NULL
+      FOR UPDATE;
+    "#,
+    object_id,
+    partition_key
+  )
+  .fetch_optional(tx.deref_mut())
+  .await
+}
+
+#[inline]
+pub async fn update_af_collab_blob(
+  tx: &mut Transaction<'_, sqlx::Postgres>,
+  partition_key: i32,
+  object_id: &str,
+  blob: &[u8],
+) -> Result<(), sqlx::Error> {
+  sqlx::query!(
+    "UPDATE af_collab SET blob = $3, len = $4 WHERE oid = $1 AND partition_key = $2",
+    object_id,
+    partition_key,
+    blob,
+    blob.len() as i32
+  )
+  .execute(tx.deref_mut())
   .await?;
   Ok(())
 }
@@ -284,6 +456,31 @@ pub async fn create_snapshot(
   Ok(())
 }
 
+/// Inserts the snapshot with its original creation time, e.g. when the snapshot is imported from
+/// an archive.
+pub async fn insert_snapshot_with_txn(
+  tx: &mut Transaction<'_, sqlx::Postgres>,
+  object_id: &str,
+  blob: &[u8],
+  workspace_id: &Uuid,
+  created_at: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+  sqlx::query!(
+    r#"
+        INSERT INTO af_collab_snapshot (oid, blob, len, encrypt, workspace_id, created_at)
+        VALUES ($1, $2, $3, 0, $4, $5)
+        "#,
+    object_id,
+    blob,
+    blob.len() as i32,
+    workspace_id,
+    created_at
+  )
+  .execute(tx.deref_mut())
+  .await?;
+  Ok(())
+}
+
 #[inline]
 pub async fn get_snapshot_blob(pg_pool: &PgPool, snapshot_id: i64) -> Result<Vec<u8>, sqlx::Error> {
   let blob = sqlx::query!(
@@ -300,6 +497,73 @@ pub async fn get_snapshot_blob(pg_pool: &PgPool, snapshot_id: i64) -> Result<Vec
   Ok(blob)
 }
 
+/// Returns the object id and the blob of the snapshot.
+pub async fn get_snapshot_object_id_and_blob(
+  pg_pool: &PgPool,
+  snapshot_id: i64,
+) -> Result<(String, Vec<u8>), sqlx::Error> {
+  let row = sqlx::query!(
+    r#"
+        SELECT oid, blob
+        FROM af_collab_snapshot
+        WHERE sid = $1 AND deleted_at IS NULL;
+        "#,
+    snapshot_id
+  )
+  .fetch_one(pg_pool)
+  .await?;
+  Ok((row.oid, row.blob))
+}
+
+/// Returns the id, the object id and the creation time of the snapshots in the workspace.
+pub async fn select_workspace_snapshots<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  workspace_id: &Uuid,
+) -> Result<Vec<(i64, String, DateTime<Utc>)>, sqlx::Error> {
+  let rows = sqlx::query!(
+    r#"
+        SELECT sid, oid, created_at
+        FROM af_collab_snapshot
+        WHERE workspace_id = $1 AND deleted_at IS NULL
+        ORDER BY sid;
+        "#,
+    workspace_id
+  )
+  .fetch_all(executor)
+  .await?;
+  Ok(
+    rows
+      .into_iter()
+      .map(|row| (row.sid, row.oid, row.created_at))
+      .collect(),
+  )
+}
+
+/// Deletes the snapshots of the collab except the latest `keep_count` ones.
+/// Returns the number of deleted snapshots.
+pub async fn delete_outdated_snapshots(
+  pg_pool: &PgPool,
+  object_id: &str,
+  keep_count: i64,
+) -> Result<u64, sqlx::Error> {
+  let result = sqlx::query!(
+    r#"
+        DELETE FROM af_collab_snapshot
+        WHERE oid = $1 AND sid NOT IN (
+          SELECT sid FROM af_collab_snapshot
+          WHERE oid = $1 AND deleted_at IS NULL
+          ORDER BY created_at DESC, sid DESC
+          LIMIT $2
+        );
+        "#,
+    object_id,
+    keep_count
+  )
+  .execute(pg_pool)
+  .await?;
+  Ok(result.rows_affected())
+}
+
 pub async fn get_all_snapshots(
   pg_pool: &PgPool,
   object_id: &str,
@@ -373,11 +637,15 @@ pub async fn insert_collab_member(
   Ok(())
 }
 
-pub async fn delete_collab_member(uid: i64, oid: &str, pg_pool: &PgPool) -> Result<(), AppError> {
+pub async fn delete_collab_member<'a, E: Executor<'a, Database = Postgres>>(
+  uid: i64,
+  oid: &str,
+  executor: E,
+) -> Result<(), AppError> {
   sqlx::query("DELETE FROM af_collab_member WHERE uid = $1 AND oid = $2")
     .bind(uid)
     .bind(oid)
-    .execute(pg_pool)
+    .execute(executor)
     .await?;
   Ok(())
 }
@@ -459,6 +727,30 @@ pub async fn is_collab_member_exists<'a, E: Executor<'a, Database = Postgres>>(
   transform_record_not_found_error(result)
 }
 
+/// Returns the object id and the type of the collabs in the workspace that are not deleted.
+pub async fn select_workspace_collabs<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  workspace_id: &Uuid,
+) -> Result<Vec<(String, CollabType)>, sqlx::Error> {
+  let rows = sqlx::query!(
+    r#"
+        SELECT oid, partition_key
+        FROM af_collab
+        WHERE workspace_id = $1 AND deleted_at IS NULL
+        ORDER BY created_at, oid;
+        "#,
+    workspace_id
+  )
+  .fetch_all(executor)
+  .await?;
+  Ok(
+    rows
+      .into_iter()
+      .map(|row| (row.oid, CollabType::from(row.partition_key)))
+      .collect(),
+  )
+}
+
 #[inline]
 fn transform_record_not_found_error(
   result: Result<Option<bool>, sqlx::Error>,
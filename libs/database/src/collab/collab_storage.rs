@@ -1,20 +1,26 @@
 use crate::collab::{collab_db_ops, is_collab_exists};
+use crate::trash::is_collab_trashed;
 use anyhow::{anyhow, Context};
 use app_error::AppError;
 use async_trait::async_trait;
-use collab::core::collab::MutexCollab;
+use collab::core::collab::{MutexCollab, TransactionMutExt};
 use collab::core::collab_plugin::EncodedCollabV1;
 
 use database_entity::dto::{
-  AFAccessLevel, AFCollabSnapshots, AFRole, BatchQueryCollab, InsertCollabParams,
-  InsertSnapshotParams, QueryCollabParams, QueryCollabResult, QueryObjectSnapshotParams,
-  QuerySnapshotParams, RawData,
+  AFAccessLevel, AFCollabSnapshots, AFRole, AFWorkspacePermissions, BatchQueryCollab,
+  InsertCollabParams, InsertCollabUpdateParams, InsertSnapshotParams, QueryCollabParams,
+  QueryCollabResult, QueryObjectSnapshotParams, QuerySnapshotParams, RawData,
 };
 use sqlx::types::Uuid;
 use sqlx::PgPool;
 use std::collections::HashMap;
+use std::ops::DerefMut;
 use std::sync::{Arc, Weak};
+use tracing::{error, trace};
 use validator::Validate;
+use yrs::updates::decoder::Decode;
+use yrs::updates::encoder::Encode;
+use yrs::{Doc, ReadTxn, StateVector, Transact, Update};
 
 pub type DatabaseResult<T, E = AppError> = core::result::Result<T, E>;
 
@@ -35,6 +41,13 @@ pub trait CollabStorageAccessControl: Send + Sync + 'static {
 
   /// Returns the role of the user in the workspace.
   async fn get_user_role(&self, uid: &i64, workspace_id: &str) -> Result<AFRole, AppError>;
+
+  /// Returns the permissions of the user in the workspace.
+  async fn get_user_permissions(
+    &self,
+    uid: &i64,
+    workspace_id: &str,
+  ) -> Result<AFWorkspacePermissions, AppError>;
 }
 
 /// Represents a storage mechanism for collaborations.
@@ -59,6 +72,9 @@ pub trait CollabStorage: Send + Sync + 'static {
 
   async fn is_collab_exist(&self, oid: &str) -> DatabaseResult<bool>;
 
+  /// Returns true if the collab was deleted and is waiting in the trash to be restored or purged.
+  async fn is_collab_trashed(&self, oid: &str) -> DatabaseResult<bool>;
+
   /// Creates a new collaboration in the storage.
   ///
   /// # Arguments
@@ -70,6 +86,14 @@ pub trait CollabStorage: Send + Sync + 'static {
   /// * `Result<()>` - Returns `Ok(())` if the collaboration was created successfully, `Err` otherwise.
   async fn insert_collab(&self, uid: &i64, params: InsertCollabParams) -> DatabaseResult<()>;
 
+  /// Appends a single update to the update log of the collaboration. The pending updates are
+  /// replayed by [CollabStorage::get_collab_encoded_v1] until they are merged into the collaboration.
+  async fn insert_collab_update(
+    &self,
+    uid: &i64,
+    params: InsertCollabUpdateParams,
+  ) -> DatabaseResult<()>;
+
   /// Retrieves a collaboration from the storage.
   ///
   /// # Arguments
@@ -133,10 +157,22 @@ where
     self.as_ref().is_collab_exist(oid).await
   }
 
+  async fn is_collab_trashed(&self, oid: &str) -> DatabaseResult<bool> {
+    self.as_ref().is_collab_trashed(oid).await
+  }
+
   async fn insert_collab(&self, uid: &i64, params: InsertCollabParams) -> DatabaseResult<()> {
     self.as_ref().insert_collab(uid, params).await
   }
 
+  async fn insert_collab_update(
+    &self,
+    uid: &i64,
+    params: InsertCollabUpdateParams,
+  ) -> DatabaseResult<()> {
+    self.as_ref().insert_collab_update(uid, params).await
+  }
+
   async fn get_collab_encoded_v1(
     &self,
     uid: &i64,
@@ -173,10 +209,21 @@ where
   }
 }
 
+/// Controls when the update log of a collab is merged into the collab.
+/// The updates are merged when the number of pending updates reaches `flush_per_update` or
+/// the oldest pending update is older than `flush_per_seconds`.
+///
+/// It also controls the snapshot policy of the realtime collab. A snapshot is created after
+/// every `snapshot_per_update` edits, or when the collab was edited and the latest snapshot is
+/// older than `snapshot_per_seconds`. Only the latest `max_snapshot_count` snapshots of a collab
+/// are kept.
 #[derive(Debug, Clone)]
 pub struct WriteConfig {
   pub flush_per_update: u32,
   pub flush_per_seconds: u32,
+  pub snapshot_per_update: u32,
+  pub snapshot_per_seconds: u32,
+  pub max_snapshot_count: u32,
 }
 
 impl Default for WriteConfig {
@@ -184,6 +231,9 @@ impl Default for WriteConfig {
     Self {
       flush_per_update: 100,
       flush_per_seconds: 3 * 60,
+      snapshot_per_update: 1000,
+      snapshot_per_seconds: 30 * 60,
+      max_snapshot_count: 20,
     }
   }
 }
@@ -199,6 +249,116 @@ impl CollabStoragePgImpl {
     let config = WriteConfig::default();
     Self { pg_pool, config }
   }
+
+  /// Merges the pending updates in the `af_collab_update` table into the `af_collab` rows.
+  /// Returns the number of collabs that were compacted.
+  pub async fn compact_collab_updates(&self) -> DatabaseResult<usize> {
+    let collabs = collab_db_ops::select_collabs_to_compact(
+      &self.pg_pool,
+      self.config.flush_per_update as i64,
+      self.config.flush_per_seconds as i64,
+      100,
+    )
+    .await?;
+
+    let mut count = 0;
+    for (object_id, partition_key) in collabs {
+      match self.compact_collab(&object_id, partition_key).await {
+        Ok(true) => count += 1,
+        Ok(false) => {},
+        Err(err) => error!("fail to compact updates of collab:{}: {:?}", object_id, err),
+      }
+    }
+    Ok(count)
+  }
+
+  /// Merges the pending updates into the collab. Returns false if there is nothing to compact, e.g.
+  /// the collab was deleted after it was selected.
+  async fn compact_collab(&self, object_id: &str, partition_key: i32) -> DatabaseResult<bool> {
+    let mut transaction = self
+      .pg_pool
+      .begin()
+      .await
+      .context("Failed to acquire a Postgres transaction to compact collab updates")?;
+
+    let blob = match collab_db_ops::select_blob_from_af_collab_for_update(
+      &mut transaction,
+      partition_key,
+      object_id,
+    )
+    .await?
+    {
+      None => {
+        trace!(
+          "skip compacting collab:{}, the collab doesn't exist",
+          object_id
+        );
+        return Ok(false);
+      },
+      Some(blob) => blob,
+    };
+
+    let rows = collab_db_ops::select_collab_updates(transaction.deref_mut(), object_id).await?;
+    if rows.is_empty() {
+      return Ok(false);
+    }
+
+    let (update_ids, updates): (Vec<_>, Vec<_>) =
+      rows.into_iter().map(|row| (row.id, row.blob)).unzip();
+    let encoded_collab = decode_encoded_collab(&blob)?;
+    let encoded_collab = merge_collab_updates(encoded_collab, &updates)?;
+    let blob = encoded_collab
+      .encode_to_bytes()
+      .map_err(|err| AppError::Internal(anyhow!("fail to encode EncodedCollabV1: {:?}", err)))?;
+
+    collab_db_ops::update_af_collab_blob(&mut transaction, partition_key, object_id, &blob).await?;
+    collab_db_ops::delete_collab_updates(transaction.deref_mut(), &update_ids).await?;
+    transaction
+      .commit()
+      .await
+      .context("Failed to commit transaction to compact collab updates")?;
+
+    trace!(
+      "did compact {} updates of collab:{}",
+      update_ids.len(),
+      object_id
+    );
+    Ok(true)
+  }
+}
+
+#[inline]
+fn decode_encoded_collab(data: &[u8]) -> DatabaseResult<EncodedCollabV1> {
+  EncodedCollabV1::decode_from_bytes(data)
+    .map_err(|err| AppError::Internal(anyhow!("fail to decode data to EncodedDocV1: {:?}", err)))
+}
+
+/// Applies the updates on top of the encoded collab and returns the merged collab.
+fn merge_collab_updates(
+  encoded_collab: EncodedCollabV1,
+  updates: &[RawData],
+) -> DatabaseResult<EncodedCollabV1> {
+  let doc = Doc::new();
+  {
+    let mut txn = doc.transact_mut();
+    let doc_state = encoded_collab.doc_state.as_ref();
+    for update in std::iter::once(doc_state).chain(updates.iter().map(|update| update.as_slice())) {
+      if update.is_empty() {
+        continue;
+      }
+      let update = Update::decode_v1(update)
+        .map_err(|err| AppError::Internal(anyhow!("fail to decode collab update: {:?}", err)))?;
+      txn
+        .try_apply_update(update)
+        .map_err(|err| AppError::Internal(anyhow!("fail to apply collab update: {:?}", err)))?;
+    }
+  }
+
+  let txn = doc.transact();
+  Ok(EncodedCollabV1::new(
+    txn.state_vector().encode_v1(),
+    txn.encode_state_as_update_v1(&StateVector::default()),
+  ))
 }
 
 #[async_trait]
@@ -220,6 +380,11 @@ impl CollabStorage for CollabStoragePgImpl {
     Ok(is_exist)
   }
 
+  async fn is_collab_trashed(&self, oid: &str) -> DatabaseResult<bool> {
+    let is_trashed = is_collab_trashed(oid, &self.pg_pool).await?;
+    Ok(is_trashed)
+  }
+
   async fn insert_collab(&self, uid: &i64, params: InsertCollabParams) -> DatabaseResult<()> {
     let mut transaction = self
       .pg_pool
@@ -234,21 +399,28 @@ impl CollabStorage for CollabStoragePgImpl {
     Ok(())
   }
 
+  async fn insert_collab_update(
+    &self,
+    _uid: &i64,
+    params: InsertCollabUpdateParams,
+  ) -> DatabaseResult<()> {
+    params.validate()?;
+    collab_db_ops::insert_into_af_collab_update(&self.pg_pool, &params).await
+  }
+
   async fn get_collab_encoded_v1(
     &self,
     _uid: &i64,
     params: QueryCollabParams,
   ) -> DatabaseResult<EncodedCollabV1> {
-    match collab_db_ops::select_blob_from_af_collab(
+    let encoded_collab = match collab_db_ops::select_blob_from_af_collab(
       &self.pg_pool,
       &params.collab_type,
       &params.object_id,
     )
     .await
     {
-      Ok(data) => EncodedCollabV1::decode_from_bytes(&data).map_err(|err| {
-        AppError::Internal(anyhow!("fail to decode data to EncodedDocV1: {:?}", err))
-      }),
+      Ok(data) => decode_encoded_collab(&data),
       Err(e) => match e {
         sqlx::Error::RowNotFound => {
           let msg = format!("Can't find the row for query: {:?}", params);
@@ -256,6 +428,18 @@ impl CollabStorage for CollabStoragePgImpl {
         },
         _ => Err(e.into()),
       },
+    }?;
+
+    // Replay the updates that haven't been merged into the collab yet
+    let updates = collab_db_ops::select_collab_updates(&self.pg_pool, &params.object_id)
+      .await?
+      .into_iter()
+      .map(|row| row.blob)
+      .collect::<Vec<_>>();
+    if updates.is_empty() {
+      Ok(encoded_collab)
+    } else {
+      merge_collab_updates(encoded_collab, &updates)
     }
   }
 
@@ -264,7 +448,39 @@ impl CollabStorage for CollabStoragePgImpl {
     _uid: &i64,
     queries: Vec<BatchQueryCollab>,
   ) -> HashMap<String, QueryCollabResult> {
-    collab_db_ops::batch_select_collab_blob(&self.pg_pool, queries).await
+    let mut results = collab_db_ops::batch_select_collab_blob(&self.pg_pool, queries).await;
+    let object_ids = results
+      .iter()
+      .filter(|(_, result)| matches!(result, QueryCollabResult::Success { .. }))
+      .map(|(object_id, _)| object_id.clone())
+      .collect::<Vec<_>>();
+    if object_ids.is_empty() {
+      return results;
+    }
+
+    // Replay the updates that haven't been merged into the collabs yet
+    match collab_db_ops::batch_select_collab_updates(&self.pg_pool, &object_ids).await {
+      Ok(updates_by_oid) => {
+        for (object_id, updates) in updates_by_oid {
+          if let Some(QueryCollabResult::Success { encode_collab_v1 }) = results.get_mut(&object_id)
+          {
+            let merged = decode_encoded_collab(encode_collab_v1)
+              .and_then(|encoded_collab| merge_collab_updates(encoded_collab, &updates))
+              .and_then(|encoded_collab| {
+                encoded_collab.encode_to_bytes().map_err(|err| {
+                  AppError::Internal(anyhow!("fail to encode EncodedCollabV1: {:?}", err))
+                })
+              });
+            match merged {
+              Ok(bytes) => *encode_collab_v1 = bytes,
+              Err(err) => error!("fail to replay updates of collab:{}: {:?}", object_id, err),
+            }
+          }
+        }
+      },
+      Err(err) => error!("Batch get collab updates errors: {}", err),
+    }
+    results
   }
 
   async fn delete_collab(&self, _uid: &i64, object_id: &str) -> DatabaseResult<()> {
@@ -282,6 +498,20 @@ impl CollabStorage for CollabStoragePgImpl {
       &params.workspace_id.parse::<Uuid>()?,
     )
     .await?;
+
+    let count = collab_db_ops::delete_outdated_snapshots(
+      &self.pg_pool,
+      &params.object_id,
+      self.config.max_snapshot_count as i64,
+    )
+    .await?;
+    if count > 0 {
+      trace!(
+        "did delete {} outdated snapshots of collab:{}",
+        count,
+        params.object_id
+      );
+    }
     Ok(())
   }
 
@@ -298,3 +528,67 @@ impl CollabStorage for CollabStoragePgImpl {
     Ok(s)
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use yrs::{Map, MapRef};
+
+  fn set_value(doc: &Doc, map: &MapRef, key: &str, value: &str) -> Vec<u8> {
+    let mut txn = doc.transact_mut();
+    map.insert(&mut txn, key, value);
+    txn.encode_update_v1()
+  }
+
+  fn get_value(encoded_collab: &EncodedCollabV1, key: &str) -> Option<String> {
+    let doc = Doc::new();
+    let map = doc.get_or_insert_map("data");
+    let mut txn = doc.transact_mut();
+    txn.apply_update(Update::decode_v1(&encoded_collab.doc_state).unwrap());
+    map.get(&txn, key).map(|value| value.to_string(&txn))
+  }
+
+  #[test]
+  fn merge_updates_into_collab_test() {
+    let doc = Doc::new();
+    let map = doc.get_or_insert_map("data");
+    set_value(&doc, &map, "name", "hello");
+    let encoded_collab = {
+      let txn = doc.transact();
+      EncodedCollabV1::new(
+        txn.state_vector().encode_v1(),
+        txn.encode_state_as_update_v1(&StateVector::default()),
+      )
+    };
+    let updates = vec![
+      set_value(&doc, &map, "name", "world"),
+      vec![],
+      set_value(&doc, &map, "title", "compaction"),
+    ];
+
+    let merged = merge_collab_updates(encoded_collab, &updates).unwrap();
+    assert_eq!(get_value(&merged, "name").unwrap(), "world");
+    assert_eq!(get_value(&merged, "title").unwrap(), "compaction");
+
+    // The log may contain updates that are already in the collab, e.g. after a full flush
+    let merged = merge_collab_updates(merged, &updates).unwrap();
+    assert_eq!(get_value(&merged, "name").unwrap(), "world");
+    assert_eq!(get_value(&merged, "title").unwrap(), "compaction");
+  }
+
+  #[test]
+  fn merge_updates_into_empty_collab_test() {
+    let doc = Doc::new();
+    let map = doc.get_or_insert_map("data");
+    let updates = vec![set_value(&doc, &map, "name", "hello")];
+
+    let merged = merge_collab_updates(EncodedCollabV1::new(vec![], vec![]), &updates).unwrap();
+    assert_eq!(get_value(&merged, "name").unwrap(), "hello");
+  }
+
+  #[test]
+  fn merge_invalid_update_test() {
+    let result = merge_collab_updates(EncodedCollabV1::new(vec![], vec![]), &[vec![1, 2, 3, 4, 5]]);
+    assert!(result.is_err());
+  }
+}
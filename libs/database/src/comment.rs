@@ -0,0 +1,137 @@
+use app_error::AppError;
+use database_entity::pg_row::AFCollabCommentRow;
+use sqlx::PgPool;
+use tracing::instrument;
+use uuid::Uuid;
+
+#[instrument(level = "trace", skip(pg_pool, content), err)]
+pub async fn insert_collab_comment(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  oid: &str,
+  block_id: Option<String>,
+  reply_to: Option<Uuid>,
+  content: &str,
+  created_by: i64,
+) -> Result<AFCollabCommentRow, AppError> {
+  let row = sqlx::query_as!(
+    AFCollabCommentRow,
+    r#"
+        INSERT INTO af_collab_comment (workspace_id, oid, block_id, reply_to, content, created_by)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING *
+        "#,
+    workspace_id,
+    oid,
+    block_id,
+    reply_to,
+    content,
+    created_by
+  )
+  .fetch_one(pg_pool)
+  .await?;
+  Ok(row)
+}
+
+/// Returns the comments of the collab, including the resolved ones, in the order they were created.
+pub async fn select_collab_comments(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  oid: &str,
+) -> Result<Vec<AFCollabCommentRow>, AppError> {
+  let rows = sqlx::query_as!(
+    AFCollabCommentRow,
+    r#"
+        SELECT * FROM af_collab_comment
+        WHERE workspace_id = $1 AND oid = $2
+        ORDER BY created_at ASC
+        "#,
+    workspace_id,
+    oid
+  )
+  .fetch_all(pg_pool)
+  .await?;
+  Ok(rows)
+}
+
+pub async fn select_collab_comment(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  oid: &str,
+  comment_id: &Uuid,
+) -> Result<AFCollabCommentRow, AppError> {
+  sqlx::query_as!(
+    AFCollabCommentRow,
+    r#"
+        SELECT * FROM af_collab_comment
+        WHERE id = $1 AND workspace_id = $2 AND oid = $3
+        "#,
+    comment_id,
+    workspace_id,
+    oid
+  )
+  .fetch_optional(pg_pool)
+  .await?
+  .ok_or_else(|| AppError::RecordNotFound(format!("comment:{} doesn't exist", comment_id)))
+}
+
+#[instrument(level = "trace", skip(pg_pool, content), err)]
+pub async fn update_collab_comment_content(
+  pg_pool: &PgPool,
+  comment_id: &Uuid,
+  content: &str,
+) -> Result<AFCollabCommentRow, AppError> {
+  let row = sqlx::query_as!(
+    AFCollabCommentRow,
+    r#"
+        UPDATE af_collab_comment
+        SET content = $2, updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1
+        RETURNING *
+        "#,
+    comment_id,
+    content
+  )
+  .fetch_one(pg_pool)
+  .await?;
+  Ok(row)
+}
+
+/// Resolves the comment by the given user, or reopens it if `resolved_by` is None.
+#[instrument(level = "trace", skip(pg_pool), err)]
+pub async fn update_collab_comment_resolved(
+  pg_pool: &PgPool,
+  comment_id: &Uuid,
+  resolved_by: Option<i64>,
+) -> Result<AFCollabCommentRow, AppError> {
+  let row = sqlx::query_as!(
+    AFCollabCommentRow,
+    r#"
+        UPDATE af_collab_comment
+        SET resolved_by = $2::BIGINT,
+            resolved_at = CASE WHEN $2::BIGINT IS NULL THEN NULL ELSE CURRENT_TIMESTAMP END
+        WHERE id = $1
+        RETURNING *
+        "#,
+    comment_id,
+    resolved_by
+  )
+  .fetch_one(pg_pool)
+  .await?;
+  Ok(row)
+}
+
+/// Deletes the comment. The replies are deleted along with the root comment of the thread.
+#[instrument(level = "trace", skip(pg_pool), err)]
+pub async fn delete_collab_comment(pg_pool: &PgPool, comment_id: &Uuid) -> Result<(), AppError> {
+  sqlx::query!(
+    r#"
+        DELETE FROM af_collab_comment
+        WHERE id = $1
+        "#,
+    comment_id
+  )
+  .execute(pg_pool)
+  .await?;
+  Ok(())
+}
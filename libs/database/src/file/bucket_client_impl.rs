@@ -0,0 +1,170 @@
+use crate::file::bucket_fs_impl::BucketClientFsImpl;
+use crate::file::bucket_memory_impl::BucketClientMemoryImpl;
+use crate::file::bucket_s3_impl::BucketClientS3Impl;
+use crate::file::{BlobHead, BlobObject, BucketClient, BucketStorage, ResponseBlob};
+use app_error::AppError;
+use async_trait::async_trait;
+use database_entity::dto::AFUploadPart;
+use tokio::io::AsyncRead;
+
+pub type BucketStorageImpl = BucketStorage<BucketClientImpl>;
+
+/// A [BucketClient] that dispatches to the backend selected by the configuration.
+pub enum BucketClientImpl {
+  S3(BucketClientS3Impl),
+  FileSystem(BucketClientFsImpl),
+  Memory(BucketClientMemoryImpl),
+}
+
+#[async_trait]
+impl BucketClient for BucketClientImpl {
+  type ResponseData = Vec<u8>;
+
+  async fn put_blob<P>(&self, id: P, blob: Vec<u8>) -> Result<(), AppError>
+  where
+    P: AsRef<str> + Send,
+  {
+    match self {
+      BucketClientImpl::S3(client) => client.put_blob(id, blob).await,
+      BucketClientImpl::FileSystem(client) => client.put_blob(id, blob).await,
+      BucketClientImpl::Memory(client) => client.put_blob(id, blob).await,
+    }
+  }
+
+  async fn delete_blob<P>(&self, id: P) -> Result<Self::ResponseData, AppError>
+  where
+    P: AsRef<str> + Send,
+  {
+    match self {
+      BucketClientImpl::S3(client) => Ok(client.delete_blob(id).await?.to_blob()),
+      BucketClientImpl::FileSystem(client) => client.delete_blob(id).await,
+      BucketClientImpl::Memory(client) => client.delete_blob(id).await,
+    }
+  }
+
+  async fn get_blob<P>(&self, id: P) -> Result<Self::ResponseData, AppError>
+  where
+    P: AsRef<str> + Send,
+  {
+    match self {
+      BucketClientImpl::S3(client) => Ok(client.get_blob(id).await?.to_blob()),
+      BucketClientImpl::FileSystem(client) => client.get_blob(id).await,
+      BucketClientImpl::Memory(client) => client.get_blob(id).await,
+    }
+  }
+
+  async fn get_blob_range<P>(
+    &self,
+    id: P,
+    start: u64,
+    end: u64,
+  ) -> Result<Self::ResponseData, AppError>
+  where
+    P: AsRef<str> + Send,
+  {
+    match self {
+      BucketClientImpl::S3(client) => Ok(client.get_blob_range(id, start, end).await?.to_blob()),
+      BucketClientImpl::FileSystem(client) => client.get_blob_range(id, start, end).await,
+      BucketClientImpl::Memory(client) => client.get_blob_range(id, start, end).await,
+    }
+  }
+
+  async fn list_blobs(&self) -> Result<Vec<BlobObject>, AppError> {
+    match self {
+      BucketClientImpl::S3(client) => client.list_blobs().await,
+      BucketClientImpl::FileSystem(client) => client.list_blobs().await,
+      BucketClientImpl::Memory(client) => client.list_blobs().await,
+    }
+  }
+
+  async fn head_blob(&self, id: &str) -> Result<BlobHead, AppError> {
+    match self {
+      BucketClientImpl::S3(client) => client.head_blob(id).await,
+      BucketClientImpl::FileSystem(client) => client.head_blob(id).await,
+      BucketClientImpl::Memory(client) => client.head_blob(id).await,
+    }
+  }
+
+  async fn copy_blob(&self, from: &str, to: &str) -> Result<(), AppError> {
+    match self {
+      BucketClientImpl::S3(client) => client.copy_blob(from, to).await,
+      BucketClientImpl::FileSystem(client) => client.copy_blob(from, to).await,
+      BucketClientImpl::Memory(client) => client.copy_blob(from, to).await,
+    }
+  }
+
+  fn presign_put(&self, id: &str, expires_in_secs: u32) -> Result<String, AppError> {
+    match self {
+      BucketClientImpl::S3(client) => client.presign_put(id, expires_in_secs),
+      BucketClientImpl::FileSystem(client) => client.presign_put(id, expires_in_secs),
+      BucketClientImpl::Memory(client) => client.presign_put(id, expires_in_secs),
+    }
+  }
+
+  fn presign_get(&self, id: &str, expires_in_secs: u32) -> Result<String, AppError> {
+    match self {
+      BucketClientImpl::S3(client) => client.presign_get(id, expires_in_secs),
+      BucketClientImpl::FileSystem(client) => client.presign_get(id, expires_in_secs),
+      BucketClientImpl::Memory(client) => client.presign_get(id, expires_in_secs),
+    }
+  }
+
+  async fn create_upload(&self, id: &str, content_type: &str) -> Result<String, AppError> {
+    match self {
+      BucketClientImpl::S3(client) => client.create_upload(id, content_type).await,
+      BucketClientImpl::FileSystem(client) => client.create_upload(id, content_type).await,
+      BucketClientImpl::Memory(client) => client.create_upload(id, content_type).await,
+    }
+  }
+
+  async fn upload_part<R>(
+    &self,
+    id: &str,
+    upload_id: &str,
+    part_number: i32,
+    part_size: u64,
+    part_stream: R,
+  ) -> Result<String, AppError>
+  where
+    R: AsyncRead + Unpin + Send,
+  {
+    match self {
+      BucketClientImpl::S3(client) => {
+        client
+          .upload_part(id, upload_id, part_number, part_size, part_stream)
+          .await
+      },
+      BucketClientImpl::FileSystem(client) => {
+        client
+          .upload_part(id, upload_id, part_number, part_size, part_stream)
+          .await
+      },
+      BucketClientImpl::Memory(client) => {
+        client
+          .upload_part(id, upload_id, part_number, part_size, part_stream)
+          .await
+      },
+    }
+  }
+
+  async fn complete_upload(
+    &self,
+    id: &str,
+    upload_id: &str,
+    parts: &[AFUploadPart],
+  ) -> Result<(), AppError> {
+    match self {
+      BucketClientImpl::S3(client) => client.complete_upload(id, upload_id, parts).await,
+      BucketClientImpl::FileSystem(client) => client.complete_upload(id, upload_id, parts).await,
+      BucketClientImpl::Memory(client) => client.complete_upload(id, upload_id, parts).await,
+    }
+  }
+
+  async fn abort_upload(&self, id: &str, upload_id: &str) -> Result<(), AppError> {
+    match self {
+      BucketClientImpl::S3(client) => client.abort_upload(id, upload_id).await,
+      BucketClientImpl::FileSystem(client) => client.abort_upload(id, upload_id).await,
+      BucketClientImpl::Memory(client) => client.abort_upload(id, upload_id).await,
+    }
+  }
+}
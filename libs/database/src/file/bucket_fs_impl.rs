@@ -0,0 +1,527 @@
+use crate::file::utils::{check_part_size, BlobStreamReader};
+use crate::file::{BlobHead, BlobObject, BucketClient, BucketStorage};
+use app_error::AppError;
+use async_trait::async_trait;
+use database_entity::dto::AFUploadPart;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tracing::{error, trace};
+use uuid::Uuid;
+
+pub type FsBucketStorage = BucketStorage<BucketClientFsImpl>;
+
+/// The directory under the root that holds the blobs being written.
+const TMP_DIR: &str = "tmp";
+/// The directory under the root that holds the parts of the multipart uploads. Unlike the
+/// temporary files, the parts are kept across restarts so the uploads can be resumed.
+const UPLOAD_DIR: &str = "uploads";
+
+/// A [BucketClient] that stores the blobs in the local file system, rooted at the given directory.
+///
+/// The blobs are sharded into `{root}/{id[0..2]}/{id[2..4]}/{id}` to keep the directories small.
+/// Each blob is written to a temporary file and synced to disk before it's renamed to its final
+/// path, so a crash never leaves a half-written blob behind.
+pub struct BucketClientFsImpl {
+  root: PathBuf,
+}
+
+impl BucketClientFsImpl {
+  pub async fn new<P: Into<PathBuf>>(root: P) -> Result<Self, AppError> {
+    let root = root.into();
+    let tmp_dir = root.join(TMP_DIR);
+
+    // The temporary files left by a crash are never renamed, so it's safe to remove them
+    if fs::try_exists(&tmp_dir).await? {
+      fs::remove_dir_all(&tmp_dir).await?;
+    }
+    fs::create_dir_all(&tmp_dir).await?;
+    fs::create_dir_all(root.join(UPLOAD_DIR)).await?;
+    Ok(Self { root })
+  }
+
+  fn tmp_path(&self, name: &str) -> PathBuf {
+    self
+      .root
+      .join(TMP_DIR)
+      .join(format!("{}.{}", name, Uuid::new_v4()))
+  }
+
+  fn upload_dir(&self, upload_id: &str) -> Result<PathBuf, AppError> {
+    let upload_id = Uuid::parse_str(upload_id)
+      .map_err(|_| AppError::InvalidRequest(format!("invalid upload id: {}", upload_id)))?;
+    Ok(self.root.join(UPLOAD_DIR).join(upload_id.to_string()))
+  }
+
+  fn part_path(&self, upload_id: &str, part_number: i32) -> Result<PathBuf, AppError> {
+    Ok(self.upload_dir(upload_id)?.join(part_number.to_string()))
+  }
+
+  fn blob_path(&self, id: &str) -> Result<PathBuf, AppError> {
+    let is_valid = !id.is_empty()
+      && !id.starts_with('.')
+      && id
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '=' | '.'));
+    if !is_valid {
+      return Err(AppError::InvalidRequest(format!("invalid blob id: {}", id)));
+    }
+
+    let shard = format!("{:_<4}", &id[..id.len().min(4)]);
+    Ok(self.root.join(&shard[..2]).join(&shard[2..4]).join(id))
+  }
+}
+
+#[async_trait]
+impl BucketClient for BucketClientFsImpl {
+  type ResponseData = Vec<u8>;
+
+  async fn put_blob<P>(&self, id: P, blob: Vec<u8>) -> Result<(), AppError>
+  where
+    P: AsRef<str> + Send,
+  {
+    let id = id.as_ref();
+    let path = self.blob_path(id)?;
+    let tmp_path = self.tmp_path(id);
+    if let Err(err) = write_and_sync(&tmp_path, &blob).await {
+      remove_tmp_file(&tmp_path).await;
+      return Err(err.into());
+    }
+    persist(&tmp_path, &path).await?;
+    trace!("did write blob: {:?}", path);
+    Ok(())
+  }
+
+  async fn delete_blob<P>(&self, id: P) -> Result<Self::ResponseData, AppError>
+  where
+    P: AsRef<str> + Send,
+  {
+    let path = self.blob_path(id.as_ref())?;
+    match fs::remove_file(&path).await {
+      Ok(_) => Ok(vec![]),
+      Err(err) if err.kind() == ErrorKind::NotFound => Ok(vec![]),
+      Err(err) => Err(err.into()),
+    }
+  }
+
+  async fn get_blob<P>(&self, id: P) -> Result<Self::ResponseData, AppError>
+  where
+    P: AsRef<str> + Send,
+  {
+    let path = self.blob_path(id.as_ref())?;
+    match fs::read(&path).await {
+      Ok(blob) => Ok(blob),
+      Err(err) if err.kind() == ErrorKind::NotFound => Err(AppError::RecordNotFound(format!(
+        "blob:{} not found",
+        id.as_ref()
+      ))),
+      Err(err) => Err(err.into()),
+    }
+  }
+
+  async fn get_blob_range<P>(
+    &self,
+    id: P,
+    start: u64,
+    end: u64,
+  ) -> Result<Self::ResponseData, AppError>
+  where
+    P: AsRef<str> + Send,
+  {
+    let path = self.blob_path(id.as_ref())?;
+    let mut file = match fs::File::open(&path).await {
+      Ok(file) => file,
+      Err(err) if err.kind() == ErrorKind::NotFound => {
+        return Err(AppError::RecordNotFound(format!(
+          "blob:{} not found",
+          id.as_ref()
+        )))
+      },
+      Err(err) => return Err(err.into()),
+    };
+    file.seek(std::io::SeekFrom::Start(start)).await?;
+    let mut data = vec![0; (end - start + 1) as usize];
+    file.read_exact(&mut data).await.map_err(|err| {
+      if err.kind() == ErrorKind::UnexpectedEof {
+        AppError::InvalidRequest(format!("invalid range: {}-{}", start, end))
+      } else {
+        err.into()
+      }
+    })?;
+    Ok(data)
+  }
+
+  async fn list_blobs(&self) -> Result<Vec<BlobObject>, AppError> {
+    let mut blobs = vec![];
+    let mut dirs = vec![(self.root.clone(), 0)];
+    while let Some((dir, depth)) = dirs.pop() {
+      let mut entries = fs::read_dir(&dir).await?;
+      while let Some(entry) = entries.next_entry().await? {
+        let file_type = entry.file_type().await?;
+        if depth < 2 {
+          // The blobs are stored in the two levels of shard directories
+          let name = entry.file_name();
+          if file_type.is_dir() && name != TMP_DIR && name != UPLOAD_DIR {
+            dirs.push((entry.path(), depth + 1));
+          }
+        } else if file_type.is_file() {
+          let modified_at = entry.metadata().await?.modified()?;
+          blobs.push(BlobObject {
+            id: entry.file_name().to_string_lossy().to_string(),
+            modified_at: modified_at.into(),
+          });
+        }
+      }
+    }
+    Ok(blobs)
+  }
+
+  /// The file system doesn't keep a hash of the content, so the blob is read to compute its
+  /// e_tag.
+  async fn head_blob(&self, id: &str) -> Result<BlobHead, AppError> {
+    let path = self.blob_path(id)?;
+    let file = match fs::File::open(&path).await {
+      Ok(file) => file,
+      Err(err) if err.kind() == ErrorKind::NotFound => {
+        return Err(AppError::RecordNotFound(format!("blob:{} not found", id)))
+      },
+      Err(err) => return Err(err.into()),
+    };
+    let mut reader = BlobStreamReader::new(file);
+    let size = tokio::io::copy(&mut reader, &mut tokio::io::sink()).await?;
+    Ok(BlobHead {
+      size,
+      e_tag: reader.into_hash(),
+    })
+  }
+
+  async fn copy_blob(&self, from: &str, to: &str) -> Result<(), AppError> {
+    let from_path = self.blob_path(from)?;
+    let path = self.blob_path(to)?;
+    let tmp_path = self.tmp_path(to);
+    if let Err(err) = concat_and_sync(&tmp_path, &[from_path]).await {
+      remove_tmp_file(&tmp_path).await;
+      return match err.kind() {
+        ErrorKind::NotFound => Err(AppError::RecordNotFound(format!("blob:{} not found", from))),
+        _ => Err(err.into()),
+      };
+    }
+    persist(&tmp_path, &path).await?;
+    Ok(())
+  }
+
+  fn presign_put(&self, _id: &str, _expires_in_secs: u32) -> Result<String, AppError> {
+    Err(AppError::InvalidRequest(
+      "presigned url is not supported by the file system bucket".to_string(),
+    ))
+  }
+
+  fn presign_get(&self, _id: &str, _expires_in_secs: u32) -> Result<String, AppError> {
+    Err(AppError::InvalidRequest(
+      "presigned url is not supported by the file system bucket".to_string(),
+    ))
+  }
+
+  async fn create_upload(&self, id: &str, _content_type: &str) -> Result<String, AppError> {
+    self.blob_path(id)?;
+    let upload_id = Uuid::new_v4().to_string();
+    fs::create_dir_all(self.upload_dir(&upload_id)?).await?;
+    Ok(upload_id)
+  }
+
+  async fn upload_part<R>(
+    &self,
+    _id: &str,
+    upload_id: &str,
+    part_number: i32,
+    part_size: u64,
+    part_stream: R,
+  ) -> Result<String, AppError>
+  where
+    R: AsyncRead + Unpin + Send,
+  {
+    let path = self.part_path(upload_id, part_number)?;
+    if !fs::try_exists(self.upload_dir(upload_id)?).await? {
+      return Err(AppError::RecordNotFound(format!(
+        "upload:{} not found",
+        upload_id
+      )));
+    }
+
+    let mut reader = BlobStreamReader::new(part_stream);
+    let tmp_path = self.tmp_path(upload_id);
+    let result = match copy_and_sync(&tmp_path, &mut reader).await {
+      Ok(read_size) => check_part_size(read_size, part_size),
+      Err(err) => Err(err.into()),
+    };
+    if let Err(err) = result {
+      remove_tmp_file(&tmp_path).await;
+      return Err(err);
+    }
+    persist(&tmp_path, &path).await?;
+    Ok(reader.into_hash())
+  }
+
+  async fn complete_upload(
+    &self,
+    id: &str,
+    upload_id: &str,
+    parts: &[AFUploadPart],
+  ) -> Result<(), AppError> {
+    let path = self.blob_path(id)?;
+    let tmp_path = self.tmp_path(id);
+    let mut part_paths = Vec::with_capacity(parts.len());
+    for part in parts {
+      part_paths.push(self.part_path(upload_id, part.part_number)?);
+    }
+    if let Err(err) = concat_and_sync(&tmp_path, &part_paths).await {
+      remove_tmp_file(&tmp_path).await;
+      return Err(err.into());
+    }
+    persist(&tmp_path, &path).await?;
+    fs::remove_dir_all(self.upload_dir(upload_id)?).await?;
+    trace!("did complete upload: {} of blob: {:?}", upload_id, path);
+    Ok(())
+  }
+
+  async fn abort_upload(&self, _id: &str, upload_id: &str) -> Result<(), AppError> {
+    match fs::remove_dir_all(self.upload_dir(upload_id)?).await {
+      Ok(_) => Ok(()),
+      Err(err) if err.kind() == ErrorKind::NotFound => Ok(()),
+      Err(err) => Err(err.into()),
+    }
+  }
+}
+
+/// Moves the written temporary file to its final path.
+async fn persist(tmp_path: &Path, path: &Path) -> Result<(), AppError> {
+  let dir = path
+    .parent()
+    .ok_or_else(|| AppError::Internal(anyhow::anyhow!("invalid blob path: {:?}", path)))?;
+  fs::create_dir_all(dir).await?;
+  fs::rename(tmp_path, path).await?;
+  sync_dir(dir).await?;
+  Ok(())
+}
+
+async fn remove_tmp_file(path: &Path) {
+  if let Err(err) = fs::remove_file(path).await {
+    error!("fail to remove temporary blob file: {:?}", err);
+  }
+}
+
+async fn concat_and_sync(path: &Path, part_paths: &[PathBuf]) -> std::io::Result<()> {
+  let mut file = fs::File::create(path).await?;
+  for part_path in part_paths {
+    let mut part = fs::File::open(part_path).await?;
+    tokio::io::copy(&mut part, &mut file).await?;
+  }
+  file.sync_all().await
+}
+
+/// Copies the stream to the file and returns the number of bytes copied.
+async fn copy_and_sync<R>(path: &Path, reader: &mut R) -> std::io::Result<u64>
+where
+  R: AsyncRead + Unpin,
+{
+  let mut file = fs::File::create(path).await?;
+  let size = tokio::io::copy(reader, &mut file).await?;
+  file.sync_all().await?;
+  Ok(size)
+}
+
+async fn write_and_sync(path: &Path, data: &[u8]) -> std::io::Result<()> {
+  let mut file = fs::File::create(path).await?;
+  file.write_all(data).await?;
+  file.sync_all().await
+}
+
+/// Syncs the directory entries, which persists the rename of a blob file.
+#[cfg(unix)]
+async fn sync_dir(dir: &Path) -> std::io::Result<()> {
+  fs::File::open(dir).await?.sync_all().await
+}
+
+#[cfg(not(unix))]
+async fn sync_dir(_dir: &Path) -> std::io::Result<()> {
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::file::utils::blob_hash;
+  use tempfile::TempDir;
+
+  async fn test_client() -> (TempDir, BucketClientFsImpl) {
+    let dir = TempDir::new().unwrap();
+    let client = BucketClientFsImpl::new(dir.path()).await.unwrap();
+    (dir, client)
+  }
+
+  #[tokio::test]
+  async fn put_get_and_delete_blob_test() {
+    let (dir, client) = test_client().await;
+    client
+      .put_blob("blob_1", b"hello world".to_vec())
+      .await
+      .unwrap();
+    assert!(dir.path().join("bl").join("ob").join("blob_1").is_file());
+    assert_eq!(client.get_blob("blob_1").await.unwrap(), b"hello world");
+    let head = client.head_blob("blob_1").await.unwrap();
+    assert_eq!(head.size, 11);
+    assert_eq!(head.e_tag, blob_hash(b"hello world"));
+    assert_eq!(
+      client.get_blob_range("blob_1", 6, 10).await.unwrap(),
+      b"world"
+    );
+    assert!(matches!(
+      client.get_blob_range("blob_1", 6, 11).await,
+      Err(AppError::InvalidRequest(_))
+    ));
+
+    // The ids shorter than the shard are padded
+    client.put_blob("a", b"short".to_vec()).await.unwrap();
+    assert_ne!(client.head_blob("a").await.unwrap().e_tag, head.e_tag);
+    let mut ids = client
+      .list_blobs()
+      .await
+      .unwrap()
+      .into_iter()
+      .map(|blob| blob.id)
+      .collect::<Vec<_>>();
+    ids.sort();
+    assert_eq!(ids, vec!["a", "blob_1"]);
+
+    client.delete_blob("blob_1").await.unwrap();
+    assert!(matches!(
+      client.get_blob("blob_1").await,
+      Err(AppError::RecordNotFound(_))
+    ));
+    // Deleting a blob that doesn't exist is not an error
+    client.delete_blob("blob_1").await.unwrap();
+  }
+
+  #[tokio::test]
+  async fn reject_invalid_blob_id_test() {
+    let (_dir, client) = test_client().await;
+    for id in ["", "../blob", ".hidden", "a/b"] {
+      assert!(matches!(
+        client.put_blob(id, vec![1]).await,
+        Err(AppError::InvalidRequest(_))
+      ));
+    }
+  }
+
+  #[tokio::test]
+  async fn remove_temporary_files_on_start_test() {
+    let (dir, client) = test_client().await;
+    fs::write(client.tmp_path("blob_1"), b"partial")
+      .await
+      .unwrap();
+    drop(client);
+
+    let client = BucketClientFsImpl::new(dir.path()).await.unwrap();
+    let mut entries = fs::read_dir(dir.path().join(TMP_DIR)).await.unwrap();
+    assert!(entries.next_entry().await.unwrap().is_none());
+    assert!(client.list_blobs().await.unwrap().is_empty());
+  }
+
+  #[tokio::test]
+  async fn copy_blob_test() {
+    let (_dir, client) = test_client().await;
+    client
+      .put_blob("blob_1", b"hello world".to_vec())
+      .await
+      .unwrap();
+    client.copy_blob("blob_1", "blob_2").await.unwrap();
+    assert_eq!(client.get_blob("blob_2").await.unwrap(), b"hello world");
+
+    // The copy is not changed by the later writes to the source
+    client.put_blob("blob_1", b"hello".to_vec()).await.unwrap();
+    assert_eq!(client.get_blob("blob_2").await.unwrap(), b"hello world");
+    assert!(matches!(
+      client.copy_blob("blob_3", "blob_2").await,
+      Err(AppError::RecordNotFound(_))
+    ));
+  }
+
+  #[tokio::test]
+  async fn presign_is_not_supported_test() {
+    let (_dir, client) = test_client().await;
+    assert!(matches!(
+      client.presign_put("blob_1", 60),
+      Err(AppError::InvalidRequest(_))
+    ));
+    assert!(matches!(
+      client.presign_get("blob_1", 60),
+      Err(AppError::InvalidRequest(_))
+    ));
+  }
+
+  #[tokio::test]
+  async fn multipart_upload_test() {
+    let (dir, client) = test_client().await;
+    let upload_id = client.create_upload("blob_1", "text/plain").await.unwrap();
+    // Uploading a part again replaces the previous one
+    client
+      .upload_part("blob_1", &upload_id, 2, 5, &b"stale"[..])
+      .await
+      .unwrap();
+    let e_tag_2 = client
+      .upload_part("blob_1", &upload_id, 2, 5, &b"world"[..])
+      .await
+      .unwrap();
+    let e_tag_1 = client
+      .upload_part("blob_1", &upload_id, 1, 6, &b"hello "[..])
+      .await
+      .unwrap();
+    // The part is rejected if the stream ends before the content length
+    assert!(matches!(
+      client
+        .upload_part("blob_1", &upload_id, 3, 10, &b"short"[..])
+        .await,
+      Err(AppError::InvalidRequest(_))
+    ));
+    let parts = vec![
+      AFUploadPart {
+        part_number: 1,
+        e_tag: e_tag_1,
+        part_size: 6,
+      },
+      AFUploadPart {
+        part_number: 2,
+        e_tag: e_tag_2,
+        part_size: 5,
+      },
+    ];
+    client
+      .complete_upload("blob_1", &upload_id, &parts)
+      .await
+      .unwrap();
+    assert_eq!(client.get_blob("blob_1").await.unwrap(), b"hello world");
+    assert!(!dir.path().join(UPLOAD_DIR).join(&upload_id).exists());
+    assert!(matches!(
+      client
+        .upload_part("blob_1", &upload_id, 3, 1, &b"!"[..])
+        .await,
+      Err(AppError::RecordNotFound(_))
+    ));
+  }
+
+  #[tokio::test]
+  async fn abort_upload_test() {
+    let (dir, client) = test_client().await;
+    let upload_id = client.create_upload("blob_1", "text/plain").await.unwrap();
+    client
+      .upload_part("blob_1", &upload_id, 1, 5, &b"hello"[..])
+      .await
+      .unwrap();
+    client.abort_upload("blob_1", &upload_id).await.unwrap();
+    assert!(!dir.path().join(UPLOAD_DIR).join(&upload_id).exists());
+    assert!(client.list_blobs().await.unwrap().is_empty());
+    // Aborting an upload twice is not an error
+    client.abort_upload("blob_1", &upload_id).await.unwrap();
+  }
+}
@@ -0,0 +1,362 @@
+use crate::file::utils::{blob_hash, check_part_size, BlobStreamReader};
+use crate::file::{BlobHead, BlobObject, BucketClient, BucketStorage};
+use app_error::AppError;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use database_entity::dto::AFUploadPart;
+use std::collections::{BTreeMap, HashMap};
+use tokio::io::AsyncRead;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+pub type MemoryBucketStorage = BucketStorage<BucketClientMemoryImpl>;
+
+/// A [BucketClient] that keeps the blobs in memory. The blobs are lost when the server stops, so
+/// it's only suitable for tests and local development.
+#[derive(Default)]
+pub struct BucketClientMemoryImpl {
+  blob_by_id: RwLock<HashMap<String, MemoryBlob>>,
+  parts_by_upload_id: RwLock<HashMap<String, BTreeMap<i32, Vec<u8>>>>,
+}
+
+struct MemoryBlob {
+  data: Vec<u8>,
+  modified_at: DateTime<Utc>,
+}
+
+impl MemoryBlob {
+  fn new(data: Vec<u8>) -> Self {
+    Self {
+      data,
+      modified_at: Utc::now(),
+    }
+  }
+}
+
+impl BucketClientMemoryImpl {
+  pub fn new() -> Self {
+    Self::default()
+  }
+}
+
+#[async_trait]
+impl BucketClient for BucketClientMemoryImpl {
+  type ResponseData = Vec<u8>;
+
+  async fn put_blob<P>(&self, id: P, blob: Vec<u8>) -> Result<(), AppError>
+  where
+    P: AsRef<str> + Send,
+  {
+    self
+      .blob_by_id
+      .write()
+      .await
+      .insert(id.as_ref().to_string(), MemoryBlob::new(blob));
+    Ok(())
+  }
+
+  async fn delete_blob<P>(&self, id: P) -> Result<Self::ResponseData, AppError>
+  where
+    P: AsRef<str> + Send,
+  {
+    self.blob_by_id.write().await.remove(id.as_ref());
+    Ok(vec![])
+  }
+
+  async fn get_blob<P>(&self, id: P) -> Result<Self::ResponseData, AppError>
+  where
+    P: AsRef<str> + Send,
+  {
+    self
+      .blob_by_id
+      .read()
+      .await
+      .get(id.as_ref())
+      .map(|blob| blob.data.clone())
+      .ok_or_else(|| AppError::RecordNotFound(format!("blob:{} not found", id.as_ref())))
+  }
+
+  async fn get_blob_range<P>(
+    &self,
+    id: P,
+    start: u64,
+    end: u64,
+  ) -> Result<Self::ResponseData, AppError>
+  where
+    P: AsRef<str> + Send,
+  {
+    let blob_by_id = self.blob_by_id.read().await;
+    let blob = blob_by_id
+      .get(id.as_ref())
+      .ok_or_else(|| AppError::RecordNotFound(format!("blob:{} not found", id.as_ref())))?;
+    blob
+      .data
+      .get(start as usize..=end as usize)
+      .map(|range| range.to_vec())
+      .ok_or_else(|| AppError::InvalidRequest(format!("invalid range: {}-{}", start, end)))
+  }
+
+  async fn list_blobs(&self) -> Result<Vec<BlobObject>, AppError> {
+    let blobs = self
+      .blob_by_id
+      .read()
+      .await
+      .iter()
+      .map(|(id, blob)| BlobObject {
+        id: id.clone(),
+        modified_at: blob.modified_at,
+      })
+      .collect();
+    Ok(blobs)
+  }
+
+  async fn head_blob(&self, id: &str) -> Result<BlobHead, AppError> {
+    self
+      .blob_by_id
+      .read()
+      .await
+      .get(id)
+      .map(|blob| BlobHead {
+        size: blob.data.len() as u64,
+        e_tag: blob_hash(&blob.data),
+      })
+      .ok_or_else(|| AppError::RecordNotFound(format!("blob:{} not found", id)))
+  }
+
+  async fn copy_blob(&self, from: &str, to: &str) -> Result<(), AppError> {
+    let mut blob_by_id = self.blob_by_id.write().await;
+    let data = blob_by_id
+      .get(from)
+      .map(|blob| blob.data.clone())
+      .ok_or_else(|| AppError::RecordNotFound(format!("blob:{} not found", from)))?;
+    blob_by_id.insert(to.to_string(), MemoryBlob::new(data));
+    Ok(())
+  }
+
+  fn presign_put(&self, _id: &str, _expires_in_secs: u32) -> Result<String, AppError> {
+    Err(AppError::InvalidRequest(
+      "presigned url is not supported by the memory bucket".to_string(),
+    ))
+  }
+
+  fn presign_get(&self, _id: &str, _expires_in_secs: u32) -> Result<String, AppError> {
+    Err(AppError::InvalidRequest(
+      "presigned url is not supported by the memory bucket".to_string(),
+    ))
+  }
+
+  async fn create_upload(&self, _id: &str, _content_type: &str) -> Result<String, AppError> {
+    let upload_id = Uuid::new_v4().to_string();
+    self
+      .parts_by_upload_id
+      .write()
+      .await
+      .insert(upload_id.clone(), BTreeMap::new());
+    Ok(upload_id)
+  }
+
+  async fn upload_part<R>(
+    &self,
+    _id: &str,
+    upload_id: &str,
+    part_number: i32,
+    part_size: u64,
+    part_stream: R,
+  ) -> Result<String, AppError>
+  where
+    R: AsyncRead + Unpin + Send,
+  {
+    let (data, e_tag) = BlobStreamReader::new(part_stream).finish().await?;
+    check_part_size(data.len() as u64, part_size)?;
+    self
+      .parts_by_upload_id
+      .write()
+      .await
+      .get_mut(upload_id)
+      .ok_or_else(|| AppError::RecordNotFound(format!("upload:{} not found", upload_id)))?
+      .insert(part_number, data);
+    Ok(e_tag)
+  }
+
+  async fn complete_upload(
+    &self,
+    id: &str,
+    upload_id: &str,
+    parts: &[AFUploadPart],
+  ) -> Result<(), AppError> {
+    let mut parts_by_upload_id = self.parts_by_upload_id.write().await;
+    let uploaded_parts = parts_by_upload_id
+      .get(upload_id)
+      .ok_or_else(|| AppError::RecordNotFound(format!("upload:{} not found", upload_id)))?;
+    let mut blob = vec![];
+    for part in parts {
+      let data = uploaded_parts.get(&part.part_number).ok_or_else(|| {
+        AppError::InvalidRequest(format!("part:{} is not uploaded", part.part_number))
+      })?;
+      blob.extend_from_slice(data);
+    }
+    parts_by_upload_id.remove(upload_id);
+    self
+      .blob_by_id
+      .write()
+      .await
+      .insert(id.to_string(), MemoryBlob::new(blob));
+    Ok(())
+  }
+
+  async fn abort_upload(&self, _id: &str, upload_id: &str) -> Result<(), AppError> {
+    self.parts_by_upload_id.write().await.remove(upload_id);
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[tokio::test]
+  async fn put_get_and_delete_blob_test() {
+    let client = BucketClientMemoryImpl::new();
+    client
+      .put_blob("blob_1", b"hello world".to_vec())
+      .await
+      .unwrap();
+    assert_eq!(client.get_blob("blob_1").await.unwrap(), b"hello world");
+    let head = client.head_blob("blob_1").await.unwrap();
+    assert_eq!(head.size, 11);
+
+    // The e_tag changes with the content
+    client
+      .put_blob("blob_2", b"hello world".to_vec())
+      .await
+      .unwrap();
+    assert_eq!(client.head_blob("blob_2").await.unwrap(), head);
+    client.put_blob("blob_2", b"hello".to_vec()).await.unwrap();
+    assert_ne!(client.head_blob("blob_2").await.unwrap().e_tag, head.e_tag);
+    client.delete_blob("blob_2").await.unwrap();
+    assert_eq!(
+      client.get_blob_range("blob_1", 6, 10).await.unwrap(),
+      b"world"
+    );
+    assert!(matches!(
+      client.get_blob_range("blob_1", 6, 11).await,
+      Err(AppError::InvalidRequest(_))
+    ));
+
+    let ids = client
+      .list_blobs()
+      .await
+      .unwrap()
+      .into_iter()
+      .map(|blob| blob.id)
+      .collect::<Vec<_>>();
+    assert_eq!(ids, vec!["blob_1"]);
+
+    client.delete_blob("blob_1").await.unwrap();
+    assert!(matches!(
+      client.get_blob("blob_1").await,
+      Err(AppError::RecordNotFound(_))
+    ));
+    // Deleting a blob that doesn't exist is not an error
+    client.delete_blob("blob_1").await.unwrap();
+  }
+
+  #[tokio::test]
+  async fn copy_blob_test() {
+    let client = BucketClientMemoryImpl::new();
+    client
+      .put_blob("blob_1", b"hello world".to_vec())
+      .await
+      .unwrap();
+    client.copy_blob("blob_1", "blob_2").await.unwrap();
+    assert_eq!(client.get_blob("blob_2").await.unwrap(), b"hello world");
+
+    // The copy is not changed by the later writes to the source
+    client.put_blob("blob_1", b"hello".to_vec()).await.unwrap();
+    assert_eq!(client.get_blob("blob_2").await.unwrap(), b"hello world");
+    assert!(matches!(
+      client.copy_blob("blob_3", "blob_2").await,
+      Err(AppError::RecordNotFound(_))
+    ));
+  }
+
+  #[tokio::test]
+  async fn presign_is_not_supported_test() {
+    let client = BucketClientMemoryImpl::new();
+    assert!(matches!(
+      client.presign_put("blob_1", 60),
+      Err(AppError::InvalidRequest(_))
+    ));
+    assert!(matches!(
+      client.presign_get("blob_1", 60),
+      Err(AppError::InvalidRequest(_))
+    ));
+  }
+
+  #[tokio::test]
+  async fn multipart_upload_test() {
+    let client = BucketClientMemoryImpl::new();
+    let upload_id = client.create_upload("blob_1", "text/plain").await.unwrap();
+    // Uploading a part again replaces the previous one
+    client
+      .upload_part("blob_1", &upload_id, 2, 5, &b"stale"[..])
+      .await
+      .unwrap();
+    let e_tag_2 = client
+      .upload_part("blob_1", &upload_id, 2, 5, &b"world"[..])
+      .await
+      .unwrap();
+    let e_tag_1 = client
+      .upload_part("blob_1", &upload_id, 1, 6, &b"hello "[..])
+      .await
+      .unwrap();
+    // The part is rejected if the stream ends before the content length
+    assert!(matches!(
+      client
+        .upload_part("blob_1", &upload_id, 3, 10, &b"short"[..])
+        .await,
+      Err(AppError::InvalidRequest(_))
+    ));
+    let parts = vec![
+      AFUploadPart {
+        part_number: 1,
+        e_tag: e_tag_1,
+        part_size: 6,
+      },
+      AFUploadPart {
+        part_number: 2,
+        e_tag: e_tag_2,
+        part_size: 5,
+      },
+    ];
+    client
+      .complete_upload("blob_1", &upload_id, &parts)
+      .await
+      .unwrap();
+    assert_eq!(client.get_blob("blob_1").await.unwrap(), b"hello world");
+
+    // The upload is removed once it's completed
+    assert!(matches!(
+      client
+        .upload_part("blob_1", &upload_id, 3, 1, &b"!"[..])
+        .await,
+      Err(AppError::RecordNotFound(_))
+    ));
+  }
+
+  #[tokio::test]
+  async fn abort_upload_test() {
+    let client = BucketClientMemoryImpl::new();
+    let upload_id = client.create_upload("blob_1", "text/plain").await.unwrap();
+    client
+      .upload_part("blob_1", &upload_id, 1, 5, &b"hello"[..])
+      .await
+      .unwrap();
+    client.abort_upload("blob_1", &upload_id).await.unwrap();
+    assert!(matches!(
+      client.complete_upload("blob_1", &upload_id, &[]).await,
+      Err(AppError::RecordNotFound(_))
+    ));
+    assert!(client.list_blobs().await.unwrap().is_empty());
+  }
+}
@@ -1,6 +1,11 @@
-use crate::file::{BucketClient, BucketStorage, ResponseBlob};
+use crate::file::utils::check_part_size;
+use crate::file::{BlobHead, BlobObject, BucketClient, BucketStorage, ResponseBlob};
 use app_error::AppError;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use database_entity::dto::AFUploadPart;
+use s3::serde_types::Part;
+use tokio::io::{AsyncRead, AsyncReadExt};
 
 pub type S3BucketStorage = BucketStorage<BucketClientS3Impl>;
 
@@ -12,6 +17,12 @@ impl S3BucketStorage {
 
 pub struct BucketClientS3Impl(s3::Bucket);
 
+impl BucketClientS3Impl {
+  pub fn new(bucket: s3::Bucket) -> Self {
+    Self(bucket)
+  }
+}
+
 #[async_trait]
 impl BucketClient for BucketClientS3Impl {
   type ResponseData = S3ResponseData;
@@ -41,6 +52,138 @@ impl BucketClient for BucketClientS3Impl {
     let response = self.0.get_object(id).await?;
     Ok(S3ResponseData(response))
   }
+
+  async fn get_blob_range<P>(
+    &self,
+    id: P,
+    start: u64,
+    end: u64,
+  ) -> Result<Self::ResponseData, AppError>
+  where
+    P: AsRef<str> + Send,
+  {
+    let response = self.0.get_object_range(id, start, Some(end)).await?;
+    check_s3_response_data(&response)?;
+    Ok(S3ResponseData(response))
+  }
+
+  async fn list_blobs(&self) -> Result<Vec<BlobObject>, AppError> {
+    let results = self.0.list(String::new(), None).await?;
+    let blobs = results
+      .into_iter()
+      .flat_map(|result| result.contents)
+      .map(|object| BlobObject {
+        // Treat the object as just modified if the time can't be parsed, so it's not collected
+        modified_at: DateTime::parse_from_rfc3339(&object.last_modified)
+          .map(|time| time.with_timezone(&Utc))
+          .unwrap_or_else(|_| Utc::now()),
+        id: object.key,
+      })
+      .collect();
+    Ok(blobs)
+  }
+
+  async fn head_blob(&self, id: &str) -> Result<BlobHead, AppError> {
+    let (head, code) = match self.0.head_object(id).await {
+      Ok(result) => result,
+      Err(s3::error::S3Error::Http(404, _)) => {
+        return Err(AppError::RecordNotFound(format!("blob:{} not found", id)))
+      },
+      Err(err) => return Err(err.into()),
+    };
+    check_s3_status_code(code)?;
+    let size = head
+      .content_length
+      .map(|len| len as u64)
+      .ok_or_else(|| AppError::S3ResponseError(format!("missing content length of blob:{}", id)))?;
+    // The e_tag of the object is quoted in the response header
+    let e_tag = head
+      .e_tag
+      .map(|e_tag| e_tag.trim_matches('"').to_string())
+      .ok_or_else(|| AppError::S3ResponseError(format!("missing e_tag of blob:{}", id)))?;
+    Ok(BlobHead { size, e_tag })
+  }
+
+  async fn copy_blob(&self, from: &str, to: &str) -> Result<(), AppError> {
+    let code = match self.0.copy_object_internal(from, to).await {
+      Ok(code) => code,
+      Err(s3::error::S3Error::Http(404, _)) => {
+        return Err(AppError::RecordNotFound(format!("blob:{} not found", from)))
+      },
+      Err(err) => return Err(err.into()),
+    };
+    check_s3_status_code(code)
+  }
+
+  fn presign_put(&self, id: &str, expires_in_secs: u32) -> Result<String, AppError> {
+    let url = self.0.presign_put(id, expires_in_secs, None)?;
+    Ok(url)
+  }
+
+  fn presign_get(&self, id: &str, expires_in_secs: u32) -> Result<String, AppError> {
+    let url = self.0.presign_get(id, expires_in_secs, None)?;
+    Ok(url)
+  }
+
+  async fn create_upload(&self, id: &str, content_type: &str) -> Result<String, AppError> {
+    let response = self.0.initiate_multipart_upload(id, content_type).await?;
+    Ok(response.upload_id)
+  }
+
+  async fn upload_part<R>(
+    &self,
+    id: &str,
+    upload_id: &str,
+    part_number: i32,
+    part_size: u64,
+    mut part_stream: R,
+  ) -> Result<String, AppError>
+  where
+    R: AsyncRead + Unpin + Send,
+  {
+    // The part is signed as a whole, so it's read into memory. The caller limits the part size to
+    // MAX_UPLOAD_PART_SIZE.
+    let mut data = Vec::with_capacity(part_size as usize);
+    part_stream.read_to_end(&mut data).await?;
+    check_part_size(data.len() as u64, part_size)?;
+    let part = self
+      .0
+      .put_multipart_chunk(
+        data,
+        id,
+        part_number as u32,
+        upload_id,
+        "application/octet-stream",
+      )
+      .await?;
+    Ok(part.etag)
+  }
+
+  async fn complete_upload(
+    &self,
+    id: &str,
+    upload_id: &str,
+    parts: &[AFUploadPart],
+  ) -> Result<(), AppError> {
+    let parts = parts
+      .iter()
+      .map(|part| Part {
+        etag: part.e_tag.clone(),
+        part_number: part.part_number as u32,
+      })
+      .collect();
+    let response = self
+      .0
+      .complete_multipart_upload(id, upload_id, parts)
+      .await?;
+    check_s3_response_data(&response)?;
+    Ok(())
+  }
+
+  async fn abort_upload(&self, id: &str, upload_id: &str) -> Result<(), AppError> {
+    self.0.abort_upload(id, upload_id).await?;
+    Ok(())
+  }
 }
 
 pub struct S3ResponseData(s3::request::ResponseData);
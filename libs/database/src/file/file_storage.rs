@@ -1,24 +1,63 @@
-use crate::file::utils::BlobStreamReader;
+use crate::file::thumbnail::{
+  gen_thumbnails, thumbnail_id, thumbnail_source_id, thumbnail_width_for, THUMBNAIL_WIDTHS,
+};
+use crate::file::utils::{blob_hash, BlobStreamReader};
 use crate::resource_usage::{
-  delete_blob_metadata, get_blob_metadata, get_workspace_usage_size, insert_blob_metadata,
-  is_blob_metadata_exists,
+  decrease_blob_ref_count, delete_blob_metadata, delete_blob_presigned_upload, delete_blob_upload,
+  get_all_referenced_blob_ids, get_blob_metadata, get_blob_metadata_ids_before,
+  get_blob_presigned_upload, get_blob_presigned_uploads_before, get_blob_upload,
+  get_blob_upload_parts, get_blob_uploads_before, get_workspace_usage_size,
+  increase_blob_ref_count, insert_blob_metadata, insert_blob_presigned_upload, insert_blob_upload,
+  is_blob_referenced, lock_blob, upsert_blob_upload_part,
 };
+use crate::workspace::select_workspace_plan;
 use app_error::AppError;
 use async_trait::async_trait;
-use database_entity::pg_row::AFBlobMetadataRow;
-use sqlx::PgPool;
-use tokio::io::AsyncRead;
-use tracing::{event, instrument};
+use chrono::{DateTime, Duration, Utc};
+use database_entity::dto::{AFBlobGCReport, AFMissingBlob, AFPresignedUrl, AFUpload, AFUploadPart};
+use database_entity::pg_row::{AFBlobMetadataRow, AFBlobUploadRow};
+use sqlx::{PgConnection, PgPool, Postgres, Transaction};
+use std::collections::HashSet;
+use std::ops::DerefMut;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tracing::{error, event, instrument};
 use uuid::Uuid;
 
 /// Maximum size of a blob in bytes.
 pub const MAX_BLOB_SIZE: usize = 6 * 1024 * 1024;
-pub const MAX_USAGE: u64 = 10 * 1024 * 1024 * 1024;
+/// Maximum size of a part of a multipart upload in bytes. The s3 backend reads a whole part into
+/// memory before sending it, so the limit is kept small.
+pub const MAX_UPLOAD_PART_SIZE: usize = 16 * 1024 * 1024;
+/// Part numbers of a multipart upload range from 1 to [MAX_UPLOAD_PART_NUMBER], the same limit
+/// as S3.
+pub const MAX_UPLOAD_PART_NUMBER: i32 = 10000;
+/// How long a presigned url stays valid.
+pub const PRESIGNED_URL_EXPIRES_IN_SECS: u32 = 60 * 60;
 
 pub trait ResponseBlob {
   fn to_blob(self) -> Vec<u8>;
 }
 
+impl ResponseBlob for Vec<u8> {
+  fn to_blob(self) -> Vec<u8> {
+    self
+  }
+}
+
+/// An object stored in the bucket.
+#[derive(Debug, Clone)]
+pub struct BlobObject {
+  pub id: String,
+  pub modified_at: DateTime<Utc>,
+}
+
+/// The size and the entity tag of a blob stored in the bucket.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlobHead {
+  pub size: u64,
+  pub e_tag: String,
+}
+
 #[async_trait]
 pub trait BucketClient {
   type ResponseData: ResponseBlob;
@@ -34,6 +73,61 @@ pub trait BucketClient {
   async fn get_blob<P>(&self, id: P) -> Result<Self::ResponseData, AppError>
   where
     P: AsRef<str> + Send;
+
+  /// Returns the bytes of the blob from `start` to `end`, both inclusive. The caller must make
+  /// sure the range is within the blob.
+  async fn get_blob_range<P>(
+    &self,
+    id: P,
+    start: u64,
+    end: u64,
+  ) -> Result<Self::ResponseData, AppError>
+  where
+    P: AsRef<str> + Send;
+
+  /// Returns all the objects in the bucket, including the ones without blob metadata.
+  async fn list_blobs(&self) -> Result<Vec<BlobObject>, AppError>;
+
+  /// Returns the size and the e_tag of the stored blob. The e_tag changes whenever the content of
+  /// the blob changes.
+  async fn head_blob(&self, id: &str) -> Result<BlobHead, AppError>;
+
+  /// Copies the blob to another id, replacing the blob with that id if it exists.
+  async fn copy_blob(&self, from: &str, to: &str) -> Result<(), AppError>;
+
+  /// Returns a url that allows uploading the blob with a PUT request until it expires.
+  fn presign_put(&self, id: &str, expires_in_secs: u32) -> Result<String, AppError>;
+
+  /// Returns a url that allows downloading the blob with a GET request until it expires.
+  fn presign_get(&self, id: &str, expires_in_secs: u32) -> Result<String, AppError>;
+
+  /// Starts a multipart upload of the blob and returns the id of the upload.
+  async fn create_upload(&self, id: &str, content_type: &str) -> Result<String, AppError>;
+
+  /// Uploads a part of the multipart upload from the stream and returns the e_tag of the part.
+  /// The upload fails if the stream doesn't contain exactly `part_size` bytes. Uploading a part
+  /// with the same part number replaces the previous one.
+  async fn upload_part<R>(
+    &self,
+    id: &str,
+    upload_id: &str,
+    part_number: i32,
+    part_size: u64,
+    part_stream: R,
+  ) -> Result<String, AppError>
+  where
+    R: AsyncRead + Unpin + Send;
+
+  /// Assembles the blob from the given parts, which are ordered by the part number.
+  async fn complete_upload(
+    &self,
+    id: &str,
+    upload_id: &str,
+    parts: &[AFUploadPart],
+  ) -> Result<(), AppError>;
+
+  /// Aborts the multipart upload and discards its uploaded parts.
+  async fn abort_upload(&self, id: &str, upload_id: &str) -> Result<(), AppError>;
 }
 
 pub struct BucketStorage<C> {
@@ -63,59 +157,216 @@ where
   {
     let (blob, file_id) = BlobStreamReader::new(blob_stream).finish().await?;
 
-    // check file is exist or not
-    if is_blob_metadata_exists(&self.pg_pool, &workspace_id, &file_id).await? {
+    // The same file was uploaded to the workspace before
+    if increase_blob_ref_count(&self.pg_pool, &workspace_id, &file_id).await? {
       event!(tracing::Level::TRACE, "file:{} is already exist", file_id);
       return Ok(file_id);
     }
 
-    // query the storage space of the workspace
-    let usage = get_workspace_usage_size(&self.pg_pool, &workspace_id).await?;
     event!(
       tracing::Level::TRACE,
-      "workspace consumed space: {}, new file:{} with size: {}",
-      usage,
+      "new file:{} with size: {}",
       file_id,
       file_size
     );
-    if usage > MAX_USAGE {
-      return Err(AppError::StorageSpaceNotEnough);
-    }
+    self
+      .check_workspace_limit(&workspace_id, blob.len() as i64)
+      .await?;
 
-    self.client.put_blob(&file_id, blob).await?;
+    // The blob is identified by its content, so the blob uploaded by other workspaces is shared.
+    // The blob is uploaded before the lock is taken, so the lock isn't held during the upload. If
+    // the metadata isn't saved, the uploaded blob is left without a reference and it's removed by
+    // [BucketStorage::gc_blobs].
+    let is_stored = is_blob_referenced(&self.pg_pool, &file_id).await?;
+    if !is_stored {
+      self.client.put_blob(&file_id, blob.clone()).await?;
+    }
 
-    // save the metadata
-    if let Err(err) = insert_blob_metadata(
-      &self.pg_pool,
+    let mut txn = self.pg_pool.begin().await?;
+    lock_blob(&mut txn, &file_id).await?;
+    // The last reference of the blob may be removed before the lock is taken, and the blob is
+    // removed from the bucket along with it
+    if !is_blob_referenced(txn.deref_mut(), &file_id).await? {
+      match self.client.head_blob(&file_id).await {
+        Ok(_) => {},
+        Err(err) if err.is_record_not_found() => {
+          self.client.put_blob(&file_id, blob.clone()).await?;
+        },
+        Err(err) => return Err(err),
+      }
+    }
+    insert_blob_metadata(
+      txn.deref_mut(),
       &file_id,
       &workspace_id,
       &file_type,
       file_size,
+      None,
     )
-    .await
-    {
-      event!(
-        tracing::Level::ERROR,
-        "failed to save metadata, file_id: {}, err: {}",
-        file_id,
-        err
-      );
-      // if the metadata is not saved, delete the blob.
-      self.client.delete_blob(&file_id).await?;
-      return Err(err);
+    .await?;
+    txn.commit().await?;
+
+    // The thumbnails are optional, the original blob is served if they are missing
+    if !is_stored && file_type.starts_with("image/") {
+      if let Err(err) = self.put_thumbnails(&file_id, blob).await {
+        event!(
+          tracing::Level::DEBUG,
+          "skip thumbnails of file:{}, err: {}",
+          file_id,
+          err
+        );
+      }
     }
 
     Ok(file_id)
   }
 
+  /// Generates the thumbnails of the image and stores them beside the blob. The thumbnails are
+  /// not recorded in the blob metadata, so they don't count against the storage of the workspace.
+  async fn put_thumbnails(&self, file_id: &str, blob: Vec<u8>) -> Result<(), AppError> {
+    let thumbnails = tokio::task::spawn_blocking(move || gen_thumbnails(&blob))
+      .await
+      .map_err(|err| AppError::Internal(err.into()))??;
+    for (width, thumbnail) in thumbnails {
+      self
+        .client
+        .put_blob(thumbnail_id(file_id, width), thumbnail)
+        .await?;
+    }
+    Ok(())
+  }
+
+  /// Returns the thumbnail that best fits the requested width, or None if the requested width is
+  /// larger than all the thumbnails or the thumbnail doesn't exist.
+  pub async fn get_thumbnail(
+    &self,
+    file_id: &str,
+    width: u32,
+  ) -> Result<Option<(u32, Vec<u8>)>, AppError> {
+    let width = match thumbnail_width_for(width) {
+      Some(width) => width,
+      None => return Ok(None),
+    };
+    match self.client.get_blob(thumbnail_id(file_id, width)).await {
+      Ok(thumbnail) => Ok(Some((width, thumbnail.to_blob()))),
+      Err(err) if err.is_record_not_found() => Ok(None),
+      Err(err) => Err(err),
+    }
+  }
+
+  /// Removes the blob and its thumbnails from the bucket.
+  async fn remove_blob_object(&self, file_id: &str) -> Result<(), AppError> {
+    self.client.delete_blob(file_id).await?;
+    for width in THUMBNAIL_WIDTHS {
+      if let Err(err) = self.client.delete_blob(thumbnail_id(file_id, width)).await {
+        error!("fail to delete thumbnail of file:{}, err: {}", file_id, err);
+      }
+    }
+    Ok(())
+  }
+
+  /// Stores the blob under the given id, e.g. a blob imported from a workspace archive. Unlike
+  /// [BucketStorage::put_blob], the id isn't derived from the content, so the blobs that were
+  /// uploaded with random ids keep their ids and the links to them stay valid.
+  ///
+  /// The metadata is inserted in the given transaction, which must be committed by the caller. If
+  /// the transaction is rolled back, the stored blob is left without a reference and it's removed
+  /// by [BucketStorage::gc_blobs].
+  #[instrument(skip(self, txn, blob), err)]
+  pub async fn put_blob_with_id(
+    &self,
+    txn: &mut Transaction<'_, Postgres>,
+    workspace_id: &Uuid,
+    file_id: &str,
+    file_type: &str,
+    blob: Vec<u8>,
+  ) -> Result<(), AppError> {
+    // The ids of the blobs are either the hash of the content or a UUID, the other ids could
+    // overwrite the thumbnails or the staging objects of the uploads. A hash id must match the
+    // content, otherwise any content could be stored under the hash of a known file.
+    let is_hash_id = Uuid::parse_str(file_id).is_err();
+    if !is_valid_file_id(file_id) || (is_hash_id && blob_hash(&blob) != file_id) {
+      return Err(AppError::InvalidRequest(format!(
+        "invalid file id: {}",
+        file_id
+      )));
+    }
+    let file_size = blob.len() as i64;
+    check_workspace_limit(txn.deref_mut(), workspace_id, file_size).await?;
+    lock_blob(txn, file_id).await?;
+
+    // The blob is shared if it's referenced by other workspaces, e.g. the workspace that the
+    // archive was exported from. The content of a blob with a UUID id is compared, so a blob of
+    // another workspace can't be claimed by its id alone.
+    let is_stored = is_blob_referenced(txn.deref_mut(), file_id).await?;
+    let mut thumbnail_source = None;
+    if is_stored {
+      if !is_hash_id && self.get_blob(file_id).await? != blob {
+        return Err(AppError::InvalidRequest(format!(
+          "the content of the file:{} doesn't match the stored file",
+          file_id
+        )));
+      }
+    } else {
+      if file_type.starts_with("image/") {
+        thumbnail_source = Some(blob.clone());
+      }
+      self.client.put_blob(file_id, blob).await?;
+    }
+    insert_blob_metadata(
+      txn.deref_mut(),
+      file_id,
+      workspace_id,
+      file_type,
+      file_size,
+      None,
+    )
+    .await?;
+
+    if let Some(blob) = thumbnail_source {
+      if let Err(err) = self.put_thumbnails(file_id, blob).await {
+        event!(
+          tracing::Level::DEBUG,
+          "skip thumbnails of file:{}, err: {}",
+          file_id,
+          err
+        );
+      }
+    }
+    Ok(())
+  }
+
+  /// Removes a reference of the blob from the workspace. Returns true if it was the last
+  /// reference of all the workspaces.
+  ///
+  /// The metadata is updated in the given transaction, which must be committed by the caller. The
+  /// blob is kept in the bucket until the transaction is committed, so the caller removes it with
+  /// [BucketStorage::remove_unreferenced_blob] after the commit if true is returned.
   pub async fn delete_blob(
     &self,
+    txn: &mut Transaction<'_, Postgres>,
     workspace_id: &Uuid,
     file_id: &str,
-  ) -> Result<AFBlobMetadataRow, AppError> {
-    self.client.delete_blob(file_id).await?;
-    let resp = delete_blob_metadata(&self.pg_pool, workspace_id, file_id).await?;
-    Ok(resp)
+  ) -> Result<bool, AppError> {
+    lock_blob(txn, file_id).await?;
+    let metadata = decrease_blob_ref_count(txn, workspace_id, file_id).await?;
+    let is_unreferenced =
+      metadata.ref_count <= 0 && !is_blob_referenced(txn.deref_mut(), file_id).await?;
+    Ok(is_unreferenced)
+  }
+
+  /// Removes the blob and its thumbnails from the bucket if the blob isn't referenced by any
+  /// workspace. If it fails, the blob is left in the bucket and it's removed by
+  /// [BucketStorage::gc_blobs].
+  pub async fn remove_unreferenced_blob(&self, file_id: &str) -> Result<(), AppError> {
+    let mut txn = self.pg_pool.begin().await?;
+    lock_blob(&mut txn, file_id).await?;
+    // The blob may be uploaded again after its last reference was removed
+    if !is_blob_referenced(txn.deref_mut(), file_id).await? {
+      self.remove_blob_object(file_id).await?;
+    }
+    txn.commit().await?;
+    Ok(())
   }
 
   pub async fn get_blob_metadata(
@@ -131,4 +382,508 @@ where
     let blob = self.client.get_blob(file_id).await?.to_blob();
     Ok(blob)
   }
+
+  /// Returns the bytes of the blob from `start` to `end`, both inclusive.
+  pub async fn get_blob_range(
+    &self,
+    file_id: &str,
+    start: u64,
+    end: u64,
+  ) -> Result<Vec<u8>, AppError> {
+    if start > end {
+      return Err(AppError::InvalidRequest(format!(
+        "invalid range: {}-{}",
+        start, end
+      )));
+    }
+    let blob = self
+      .client
+      .get_blob_range(file_id, start, end)
+      .await?
+      .to_blob();
+    Ok(blob)
+  }
+
+  /// Checks that a new blob of the given size fits into the plan of the workspace.
+  async fn check_workspace_limit(
+    &self,
+    workspace_id: &Uuid,
+    file_size: i64,
+  ) -> Result<(), AppError> {
+    let mut conn = self.pg_pool.acquire().await?;
+    check_workspace_limit(&mut conn, workspace_id, file_size).await
+  }
+
+  /// Returns a presigned url for the client to upload the blob to the bucket directly. The
+  /// blob is recorded after the client calls [BucketStorage::complete_presigned_upload].
+  #[instrument(skip(self), err)]
+  pub async fn create_presigned_upload(
+    &self,
+    workspace_id: &Uuid,
+    file_type: &str,
+    file_size: i64,
+  ) -> Result<AFPresignedUrl, AppError> {
+    if file_size <= 0 {
+      return Err(AppError::InvalidRequest(
+        "the size of the file must be greater than 0".to_string(),
+      ));
+    }
+    self.check_workspace_limit(workspace_id, file_size).await?;
+
+    // The content of the blob is unknown, so the blob can't be identified by its hash
+    let file_id = Uuid::new_v4().to_string();
+    let url = self.client.presign_put(
+      &presigned_upload_object_id(&file_id),
+      PRESIGNED_URL_EXPIRES_IN_SECS,
+    )?;
+    insert_blob_presigned_upload(&self.pg_pool, &file_id, workspace_id, file_type, file_size)
+      .await?;
+    Ok(AFPresignedUrl {
+      file_id,
+      url,
+      expires_at: presigned_url_expires_at(),
+    })
+  }
+
+  /// Records the blob that was uploaded with a presigned url. The uploaded blob is deleted if it
+  /// exceeds the declared size or the quota of the workspace.
+  ///
+  /// The staging object is copied to the blob before it's checked, so the size and the e_tag
+  /// that are checked and recorded are the ones of the blob that is served.
+  #[instrument(skip(self), err)]
+  pub async fn complete_presigned_upload(
+    &self,
+    workspace_id: &Uuid,
+    file_id: &str,
+  ) -> Result<(), AppError> {
+    let upload = get_blob_presigned_upload(&self.pg_pool, workspace_id, file_id).await?;
+    let staging_id = presigned_upload_object_id(file_id);
+    self.client.copy_blob(&staging_id, file_id).await?;
+    self.client.delete_blob(&staging_id).await?;
+    let head = self.client.head_blob(file_id).await?;
+    let file_size = head.size as i64;
+    if file_size > upload.file_size {
+      self.client.delete_blob(file_id).await?;
+      delete_blob_presigned_upload(&self.pg_pool, file_id).await?;
+      return Err(AppError::PayloadTooLarge(format!(
+        "the uploaded size: {} exceeds the declared file size: {}",
+        file_size, upload.file_size
+      )));
+    }
+
+    if let Err(err) = self.check_workspace_limit(workspace_id, file_size).await {
+      self.client.delete_blob(file_id).await?;
+      delete_blob_presigned_upload(&self.pg_pool, file_id).await?;
+      return Err(err);
+    }
+
+    insert_blob_metadata(
+      &self.pg_pool,
+      file_id,
+      workspace_id,
+      &upload.file_type,
+      file_size,
+      Some(&head.e_tag),
+    )
+    .await?;
+    delete_blob_presigned_upload(&self.pg_pool, file_id).await?;
+    Ok(())
+  }
+
+  /// Returns a presigned url for the client to download the blob from the bucket directly.
+  pub async fn create_presigned_download(
+    &self,
+    workspace_id: &Uuid,
+    file_id: &str,
+  ) -> Result<AFPresignedUrl, AppError> {
+    // Make sure the blob belongs to the workspace
+    get_blob_metadata(&self.pg_pool, workspace_id, file_id).await?;
+    let url = self
+      .client
+      .presign_get(file_id, PRESIGNED_URL_EXPIRES_IN_SECS)?;
+    Ok(AFPresignedUrl {
+      file_id: file_id.to_string(),
+      url,
+      expires_at: presigned_url_expires_at(),
+    })
+  }
+
+  /// Starts a multipart upload of a blob with the given size. The upload is rejected if the
+  /// workspace doesn't have enough space left for the blob.
+  ///
+  /// The blob is uploaded in parts, so its content hash is unknown until it's completed. Unlike
+  /// [BucketStorage::put_blob], the file id of the blob is a random id.
+  #[instrument(skip(self), err)]
+  pub async fn create_upload(
+    &self,
+    workspace_id: &Uuid,
+    file_type: &str,
+    file_size: i64,
+  ) -> Result<AFUpload, AppError> {
+    if file_size <= 0 {
+      return Err(AppError::InvalidRequest(
+        "the size of the file must be greater than 0".to_string(),
+      ));
+    }
+    self.check_workspace_limit(workspace_id, file_size).await?;
+
+    let file_id = Uuid::new_v4().to_string();
+    let upload_id = self.client.create_upload(&file_id, file_type).await?;
+    insert_blob_upload(
+      &self.pg_pool,
+      &upload_id,
+      workspace_id,
+      &file_id,
+      file_type,
+      file_size,
+    )
+    .await?;
+    Ok(AFUpload { upload_id, file_id })
+  }
+
+  /// Streams a part of the multipart upload to the bucket. The `part_size` is the content length
+  /// of the request, which is checked against the declared file size before anything is written.
+  #[instrument(skip(self, part_stream), err)]
+  pub async fn put_upload_part<R>(
+    &self,
+    workspace_id: &Uuid,
+    upload_id: &str,
+    part_number: i32,
+    part_size: u64,
+    part_stream: R,
+  ) -> Result<AFUploadPart, AppError>
+  where
+    R: AsyncRead + Unpin + Send,
+  {
+    if !(1..=MAX_UPLOAD_PART_NUMBER).contains(&part_number) {
+      return Err(AppError::InvalidRequest(format!(
+        "part number must be between 1 and {}",
+        MAX_UPLOAD_PART_NUMBER
+      )));
+    }
+    if part_size > MAX_UPLOAD_PART_SIZE as u64 {
+      return Err(AppError::PayloadTooLarge(
+        "The uploading part is too large".to_string(),
+      ));
+    }
+    if part_size == 0 {
+      return Err(AppError::InvalidRequest("the part is empty".to_string()));
+    }
+    let upload = get_blob_upload(&self.pg_pool, workspace_id, upload_id).await?;
+
+    let uploaded_size: i64 = get_blob_upload_parts(&self.pg_pool, upload_id)
+      .await?
+      .iter()
+      .filter(|part| part.part_number != part_number)
+      .map(|part| part.part_size)
+      .sum();
+    let part_size = part_size as i64;
+    if uploaded_size + part_size > upload.file_size {
+      return Err(AppError::InvalidRequest(format!(
+        "the uploaded parts exceed the declared file size: {}",
+        upload.file_size
+      )));
+    }
+
+    let e_tag = self
+      .client
+      .upload_part(
+        &upload.file_id,
+        upload_id,
+        part_number,
+        part_size as u64,
+        part_stream.take(part_size as u64),
+      )
+      .await?;
+    let part = AFUploadPart {
+      part_number,
+      e_tag,
+      part_size,
+    };
+    upsert_blob_upload_part(&self.pg_pool, upload_id, &part).await?;
+    Ok(part)
+  }
+
+  /// Returns the parts that were uploaded, which is used to resume an interrupted upload.
+  pub async fn get_upload_parts(
+    &self,
+    workspace_id: &Uuid,
+    upload_id: &str,
+  ) -> Result<Vec<AFUploadPart>, AppError> {
+    get_blob_upload(&self.pg_pool, workspace_id, upload_id).await?;
+    let parts = get_blob_upload_parts(&self.pg_pool, upload_id).await?;
+    Ok(parts)
+  }
+
+  /// Assembles the uploaded parts into the blob and returns the file id of the blob.
+  #[instrument(skip(self), err)]
+  pub async fn complete_upload(
+    &self,
+    workspace_id: &Uuid,
+    upload_id: &str,
+  ) -> Result<String, AppError> {
+    let AFBlobUploadRow {
+      file_id,
+      file_type,
+      file_size,
+      ..
+    } = get_blob_upload(&self.pg_pool, workspace_id, upload_id).await?;
+    let parts = get_blob_upload_parts(&self.pg_pool, upload_id).await?;
+    if parts.is_empty() {
+      return Err(AppError::InvalidRequest(
+        "the upload doesn't have any part".to_string(),
+      ));
+    }
+    if let Some((index, part)) = parts
+      .iter()
+      .enumerate()
+      .find(|(index, part)| part.part_number != *index as i32 + 1)
+    {
+      return Err(AppError::InvalidRequest(format!(
+        "missing part: {}, found part: {}",
+        index + 1,
+        part.part_number
+      )));
+    }
+    let uploaded_size: i64 = parts.iter().map(|part| part.part_size).sum();
+    if uploaded_size != file_size {
+      return Err(AppError::InvalidRequest(format!(
+        "the uploaded size: {} doesn't match the declared file size: {}",
+        uploaded_size, file_size
+      )));
+    }
+    // The quota was checked when the upload was created, but the workspace may have used up its
+    // space since then. The upload is kept, so it can be completed after some space is freed.
+    self.check_workspace_limit(workspace_id, file_size).await?;
+
+    self
+      .client
+      .complete_upload(&file_id, upload_id, &parts)
+      .await?;
+    let head = self.client.head_blob(&file_id).await?;
+    if let Err(err) = insert_blob_metadata(
+      &self.pg_pool,
+      &file_id,
+      workspace_id,
+      &file_type,
+      uploaded_size,
+      Some(&head.e_tag),
+    )
+    .await
+    {
+      event!(
+        tracing::Level::ERROR,
+        "failed to save metadata, file_id: {}, err: {}",
+        file_id,
+        err
+      );
+      self.client.delete_blob(&file_id).await?;
+      return Err(err);
+    }
+    delete_blob_upload(&self.pg_pool, upload_id).await?;
+    Ok(file_id)
+  }
+
+  /// Collects the garbage of the bucket:
+  /// - the orphan blobs, the objects that are not referenced by any blob metadata or upload. Only
+  ///   the objects older than the `grace_period` are collected, because the metadata of a blob is
+  ///   saved after its object is written.
+  /// - the missing blobs, the blob metadata whose object doesn't exist in the bucket.
+  /// - the expired uploads, the multipart and presigned uploads created before the
+  ///   `upload_expiration`. They are discarded first, so the objects they wrote become orphans.
+  ///
+  /// Nothing is removed if `dry_run` is true.
+  #[instrument(skip(self), err)]
+  pub async fn gc_blobs(
+    &self,
+    dry_run: bool,
+    grace_period: Duration,
+    upload_expiration: Duration,
+  ) -> Result<AFBlobGCReport, AppError> {
+    let started_at = Utc::now();
+    let expired_uploads = self
+      .gc_expired_uploads(dry_run, started_at - upload_expiration)
+      .await?;
+    // List the objects before querying the references, so an object that is written in the
+    // meantime is either referenced or within the grace period.
+    let objects = self.client.list_blobs().await?;
+    let referenced_ids = get_all_referenced_blob_ids(&self.pg_pool)
+      .await?
+      .into_iter()
+      .collect::<HashSet<_>>();
+    let presigned_upload_ids = get_blob_presigned_uploads_before(&self.pg_pool, started_at)
+      .await?
+      .into_iter()
+      .map(|upload| upload.file_id)
+      .collect::<HashSet<_>>();
+
+    let collect_before = started_at - grace_period;
+    let mut orphan_blobs = objects
+      .iter()
+      .filter(|object| {
+        let is_referenced = match presigned_upload_source_id(&object.id) {
+          // The staging object is only needed until its upload is completed
+          Some(file_id) => presigned_upload_ids.contains(file_id),
+          // A thumbnail is referenced as long as its original blob is referenced
+          None => referenced_ids.contains(thumbnail_source_id(&object.id).unwrap_or(&object.id)),
+        };
+        object.modified_at < collect_before && !is_referenced
+      })
+      .map(|object| object.id.clone())
+      .collect::<Vec<_>>();
+    orphan_blobs.sort();
+
+    let object_ids = objects
+      .into_iter()
+      .map(|object| object.id)
+      .collect::<HashSet<_>>();
+    let mut missing_blobs = get_blob_metadata_ids_before(&self.pg_pool, started_at)
+      .await?
+      .into_iter()
+      .filter(|(_, file_id)| !object_ids.contains(file_id))
+      .map(|(workspace_id, file_id)| AFMissingBlob {
+        workspace_id,
+        file_id,
+      })
+      .collect::<Vec<_>>();
+    missing_blobs.sort_by(|a, b| a.file_id.cmp(&b.file_id));
+
+    if !dry_run {
+      for file_id in &orphan_blobs {
+        let mut txn = self.pg_pool.begin().await?;
+        lock_blob(&mut txn, file_id).await?;
+        // The blob may be uploaded again after the references were queried
+        if !is_blob_referenced(txn.deref_mut(), file_id).await? {
+          self.client.delete_blob(file_id).await?;
+        }
+        txn.commit().await?;
+      }
+      for missing_blob in &missing_blobs {
+        let result = delete_blob_metadata(
+          &self.pg_pool,
+          &missing_blob.workspace_id,
+          &missing_blob.file_id,
+        )
+        .await;
+        match result {
+          Ok(_) => {},
+          // The metadata was deleted by the workspace in the meantime
+          Err(err) if err.is_record_not_found() => {},
+          Err(err) => return Err(err),
+        }
+      }
+    }
+
+    event!(
+      tracing::Level::INFO,
+      "blob gc found {} orphan blobs, {} missing blobs and {} expired uploads, dry run: {}",
+      orphan_blobs.len(),
+      missing_blobs.len(),
+      expired_uploads.len(),
+      dry_run
+    );
+    Ok(AFBlobGCReport {
+      dry_run,
+      started_at,
+      finished_at: Utc::now(),
+      orphan_blobs,
+      missing_blobs,
+      expired_uploads,
+    })
+  }
+
+  /// Discards the uploads that were created before `expire_before` and returns their file ids.
+  async fn gc_expired_uploads(
+    &self,
+    dry_run: bool,
+    expire_before: DateTime<Utc>,
+  ) -> Result<Vec<String>, AppError> {
+    let uploads = get_blob_uploads_before(&self.pg_pool, expire_before).await?;
+    let presigned_uploads = get_blob_presigned_uploads_before(&self.pg_pool, expire_before).await?;
+    let mut file_ids = uploads
+      .iter()
+      .map(|upload| upload.file_id.clone())
+      .chain(
+        presigned_uploads
+          .iter()
+          .map(|upload| upload.file_id.clone()),
+      )
+      .collect::<Vec<_>>();
+    file_ids.sort();
+
+    if !dry_run {
+      for upload in &uploads {
+        self
+          .client
+          .abort_upload(&upload.file_id, &upload.upload_id)
+          .await?;
+        delete_blob_upload(&self.pg_pool, &upload.upload_id).await?;
+      }
+      // The object written with the presigned url is collected as an orphan blob
+      for upload in &presigned_uploads {
+        delete_blob_presigned_upload(&self.pg_pool, &upload.file_id).await?;
+      }
+    }
+    Ok(file_ids)
+  }
+
+  #[instrument(skip(self), err)]
+  pub async fn abort_upload(&self, workspace_id: &Uuid, upload_id: &str) -> Result<(), AppError> {
+    let upload = get_blob_upload(&self.pg_pool, workspace_id, upload_id).await?;
+    self.client.abort_upload(&upload.file_id, upload_id).await?;
+    delete_blob_upload(&self.pg_pool, upload_id).await?;
+    Ok(())
+  }
+}
+
+fn is_valid_file_id(file_id: &str) -> bool {
+  !file_id.is_empty()
+    && file_id.len() <= 64
+    && file_id
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '='))
+}
+
+/// Checks that a new blob of the given size fits into the plan of the workspace.
+async fn check_workspace_limit(
+  conn: &mut PgConnection,
+  workspace_id: &Uuid,
+  file_size: i64,
+) -> Result<(), AppError> {
+  let plan = select_workspace_plan(&mut *conn, workspace_id).await?;
+  if file_size > plan.blob_size_limit {
+    return Err(AppError::PayloadTooLarge(format!(
+      "the file size: {} exceeds the limit of the {} plan: {}",
+      file_size, plan.name, plan.blob_size_limit
+    )));
+  }
+
+  let usage = get_workspace_usage_size(&mut *conn, workspace_id).await?;
+  event!(
+    tracing::Level::TRACE,
+    "workspace consumed space: {}, limit: {}",
+    usage,
+    plan.storage_limit
+  );
+  if usage + file_size as u64 > plan.storage_limit as u64 {
+    return Err(AppError::StorageSpaceNotEnough);
+  }
+  Ok(())
+}
+
+/// The blob uploaded with a presigned url is written to a staging object, which is copied to the
+/// blob when the upload is completed. The url stays valid until it expires, so the writes after
+/// the completion only reach the staging object and never change the completed blob.
+fn presigned_upload_object_id(file_id: &str) -> String {
+  format!("{}.upload", file_id)
+}
+
+/// Returns the file id of the presigned upload if the id is the id of its staging object.
+fn presigned_upload_source_id(id: &str) -> Option<&str> {
+  id.strip_suffix(".upload")
+}
+
+#[inline]
+fn presigned_url_expires_at() -> DateTime<Utc> {
+  Utc::now() + Duration::seconds(PRESIGNED_URL_EXPIRES_IN_SECS as i64)
 }
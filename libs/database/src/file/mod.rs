@@ -1,5 +1,9 @@
+pub mod bucket_client_impl;
+pub mod bucket_fs_impl;
+pub mod bucket_memory_impl;
 pub mod bucket_s3_impl;
 mod file_storage;
+pub mod thumbnail;
 mod utils;
 
 pub use file_storage::*;
@@ -0,0 +1,87 @@
+use anyhow::anyhow;
+use app_error::AppError;
+use image::imageops::FilterType;
+use image::io::Reader as ImageReader;
+use image::{DynamicImage, ImageFormat, ImageOutputFormat};
+use std::io::Cursor;
+
+/// The widths of the thumbnails generated for an uploaded image, in ascending order.
+pub const THUMBNAIL_WIDTHS: [u32; 3] = [256, 512, 1024];
+
+/// Images with more pixels than this are not decoded, which protects the server from
+/// decompression bombs.
+const MAX_SOURCE_PIXELS: u64 = 64 * 1024 * 1024;
+
+/// A thumbnail is stored beside the original blob, with the width as the suffix of its id.
+pub fn thumbnail_id(file_id: &str, width: u32) -> String {
+  format!("{}.w{}", file_id, width)
+}
+
+/// Returns the id of the original blob if the id is the id of a thumbnail.
+pub fn thumbnail_source_id(id: &str) -> Option<&str> {
+  let (file_id, width) = id.rsplit_once(".w")?;
+  let width = width.parse::<u32>().ok()?;
+  THUMBNAIL_WIDTHS.contains(&width).then_some(file_id)
+}
+
+/// Returns the smallest thumbnail width that is not narrower than the requested width, or None if
+/// the requested width is larger than all the thumbnails.
+pub fn thumbnail_width_for(width: u32) -> Option<u32> {
+  THUMBNAIL_WIDTHS.iter().copied().find(|w| *w >= width)
+}
+
+/// Returns the mime type of the thumbnail.
+pub fn thumbnail_mime(thumbnail: &[u8]) -> &'static str {
+  match image::guess_format(thumbnail) {
+    Ok(ImageFormat::Jpeg) => "image/jpeg",
+    _ => "image/png",
+  }
+}
+
+/// Resizes the image to each of the [THUMBNAIL_WIDTHS] that is narrower than the image, keeping
+/// the aspect ratio. The thumbnails of a JPEG image are encoded as JPEG, others are encoded as PNG
+/// to keep the transparency.
+///
+/// It's CPU bound, call it from a blocking thread.
+pub fn gen_thumbnails(blob: &[u8]) -> Result<Vec<(u32, Vec<u8>)>, AppError> {
+  let reader = ImageReader::new(Cursor::new(blob))
+    .with_guessed_format()
+    .map_err(|err| AppError::InvalidRequest(format!("unknown image format: {}", err)))?;
+  let format = reader
+    .format()
+    .ok_or_else(|| AppError::InvalidRequest("unknown image format".to_string()))?;
+  let (width, height) = ImageReader::with_format(Cursor::new(blob), format)
+    .into_dimensions()
+    .map_err(|err| AppError::InvalidRequest(format!("invalid image: {}", err)))?;
+  if width as u64 * height as u64 > MAX_SOURCE_PIXELS {
+    return Err(AppError::InvalidRequest(format!(
+      "image is too large to generate thumbnails: {}x{}",
+      width, height
+    )));
+  }
+
+  let image = reader
+    .decode()
+    .map_err(|err| AppError::InvalidRequest(format!("invalid image: {}", err)))?;
+  let mut thumbnails = vec![];
+  for thumbnail_width in THUMBNAIL_WIDTHS {
+    if thumbnail_width >= width {
+      break;
+    }
+    let thumbnail = image.resize(thumbnail_width, u32::MAX, FilterType::Triangle);
+    thumbnails.push((thumbnail_width, encode_thumbnail(thumbnail, format)?));
+  }
+  Ok(thumbnails)
+}
+
+fn encode_thumbnail(thumbnail: DynamicImage, format: ImageFormat) -> Result<Vec<u8>, AppError> {
+  let mut data = vec![];
+  let result = match format {
+    ImageFormat::Jpeg => {
+      DynamicImage::ImageRgb8(thumbnail.to_rgb8()).write_to(&mut data, ImageOutputFormat::Jpeg(85))
+    },
+    _ => thumbnail.write_to(&mut data, ImageOutputFormat::Png),
+  };
+  result.map_err(|err| AppError::Internal(anyhow!("fail to encode thumbnail: {}", err)))?;
+  Ok(data)
+}
@@ -1,3 +1,4 @@
+use app_error::AppError;
 use base64::alphabet::URL_SAFE;
 use base64::engine::general_purpose::PAD;
 use base64::engine::GeneralPurpose;
@@ -8,6 +9,24 @@ use std::task::{Context, Poll};
 use tokio::io::{self, AsyncRead, AsyncReadExt, ReadBuf};
 
 pub const URL_SAFE_ENGINE: GeneralPurpose = GeneralPurpose::new(&URL_SAFE, PAD);
+
+/// Returns the url safe base64 encoded sha256 hash of the data.
+pub(crate) fn blob_hash(data: &[u8]) -> String {
+  URL_SAFE_ENGINE.encode(Sha256::digest(data))
+}
+
+/// Checks that the whole part was read from the stream, which ends early if the client
+/// disconnects in the middle of the upload.
+pub(crate) fn check_part_size(read_size: u64, part_size: u64) -> Result<(), AppError> {
+  if read_size != part_size {
+    return Err(AppError::InvalidRequest(format!(
+      "the size of the part: {} doesn't match the content length: {}",
+      read_size, part_size
+    )));
+  }
+  Ok(())
+}
+
 pub struct BlobStreamReader<R> {
   reader: R,
   hasher: Sha256,
@@ -49,6 +68,11 @@ where
     let hash = URL_SAFE_ENGINE.encode(self.hasher.finalize());
     Ok((buffer, hash))
   }
+
+  /// Returns the hash of the data that was read so far.
+  pub fn into_hash(self) -> String {
+    URL_SAFE_ENGINE.encode(self.hasher.finalize())
+  }
 }
 
 impl<R> AsRef<R> for BlobStreamReader<R>
@@ -1,5 +1,11 @@
+pub mod audit;
 pub mod collab;
+pub mod comment;
 pub mod file;
 pub mod resource_usage;
+pub mod search;
+pub mod share_link;
+pub mod trash;
 pub mod user;
 pub mod workspace;
+pub mod workspace_role;
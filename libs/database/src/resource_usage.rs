@@ -1,8 +1,11 @@
 use app_error::AppError;
-use database_entity::pg_row::AFBlobMetadataRow;
+use chrono::{DateTime, Utc};
+use database_entity::dto::AFUploadPart;
+use database_entity::pg_row::{AFBlobMetadataRow, AFBlobPresignedUploadRow, AFBlobUploadRow};
 use rust_decimal::prelude::ToPrimitive;
 use sqlx::types::Decimal;
-use sqlx::PgPool;
+use sqlx::{Executor, PgPool, Postgres, Transaction};
+use std::ops::DerefMut;
 use tracing::instrument;
 use uuid::Uuid;
 
@@ -31,30 +34,34 @@ pub async fn is_blob_metadata_exists(
 }
 
 #[instrument(level = "trace", skip_all, err)]
-pub async fn insert_blob_metadata(
-  pg_pool: &PgPool,
+pub async fn insert_blob_metadata<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
   file_id: &str,
   workspace_id: &Uuid,
   file_type: &str,
   file_size: i64,
+  e_tag: Option<&str>,
 ) -> Result<AFBlobMetadataRow, AppError> {
   let metadata = sqlx::query_as!(
     AFBlobMetadataRow,
     r#"
         INSERT INTO af_blob_metadata
-        (workspace_id, file_id, file_type, file_size)
-        VALUES ($1, $2, $3, $4)
+        (workspace_id, file_id, file_type, file_size, e_tag)
+        VALUES ($1, $2, $3, $4, $5)
         ON CONFLICT (workspace_id, file_id) DO UPDATE SET
             file_type = $3,
-            file_size = $4
+            file_size = $4,
+            e_tag = $5,
+            ref_count = af_blob_metadata.ref_count + 1
         RETURNING *
         "#,
     workspace_id,
     file_id,
     file_type,
-    file_size
+    file_size,
+    e_tag
   )
-  .fetch_one(pg_pool)
+  .fetch_one(executor)
   .await?;
   Ok(metadata)
 }
@@ -74,13 +81,135 @@ pub async fn delete_blob_metadata(
         RETURNING *
         "#,
     workspace_id,
-    file_id,
+    file_id
   )
   .fetch_one(pg_pool)
   .await?;
   Ok(metadata)
 }
 
+/// Increases the reference count of the blob in the workspace. Returns false if the workspace
+/// doesn't have the blob.
+#[instrument(level = "trace", skip_all, err)]
+pub async fn increase_blob_ref_count<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  workspace_id: &Uuid,
+  file_id: &str,
+) -> Result<bool, AppError> {
+  let result = sqlx::query!(
+    r#"
+        UPDATE af_blob_metadata
+        SET ref_count = ref_count + 1, modified_at = CURRENT_TIMESTAMP
+        WHERE workspace_id = $1 AND file_id = $2
+        "#,
+    workspace_id,
+    file_id
+  )
+  .execute(executor)
+  .await?;
+  Ok(result.rows_affected() > 0)
+}
+
+/// Decreases the reference count of the blob in the workspace, and removes the metadata when the
+/// blob is no longer referenced. Returns the metadata with the decreased reference count.
+#[instrument(level = "trace", skip_all, err)]
+pub async fn decrease_blob_ref_count(
+  txn: &mut Transaction<'_, Postgres>,
+  workspace_id: &Uuid,
+  file_id: &str,
+) -> Result<AFBlobMetadataRow, AppError> {
+  let metadata = sqlx::query_as!(
+    AFBlobMetadataRow,
+    r#"
+        UPDATE af_blob_metadata
+        SET ref_count = ref_count - 1
+        WHERE workspace_id = $1 AND file_id = $2
+        RETURNING *
+        "#,
+    workspace_id,
+    file_id
+  )
+  .fetch_one(txn.deref_mut())
+  .await?;
+
+  if metadata.ref_count <= 0 {
+    sqlx::query!(
+      r#"DELETE FROM af_blob_metadata WHERE workspace_id = $1 AND file_id = $2"#,
+      workspace_id,
+      file_id
+    )
+    .execute(txn.deref_mut())
+    .await?;
+  }
+  Ok(metadata)
+}
+
+/// Returns true if any workspace references the blob.
+#[instrument(level = "trace", skip_all, err)]
+pub async fn is_blob_referenced<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  file_id: &str,
+) -> Result<bool, AppError> {
+  let exists = sqlx::query_scalar!(
+    r#"SELECT EXISTS (SELECT 1 FROM af_blob_metadata WHERE file_id = $1)"#,
+    file_id
+  )
+  .fetch_one(executor)
+  .await?;
+  Ok(exists.unwrap_or(false))
+}
+
+/// Serializes the operations on the same blob across the server instances until the transaction
+/// ends. The blobs are shared by the workspaces, so uploading and deleting the same blob must not
+/// interleave.
+#[instrument(level = "trace", skip_all, err)]
+pub async fn lock_blob(txn: &mut Transaction<'_, Postgres>, file_id: &str) -> Result<(), AppError> {
+  sqlx::query!(r#"SELECT pg_advisory_xact_lock(hashtext($1))"#, file_id)
+    .execute(txn.deref_mut())
+    .await?;
+  Ok(())
+}
+
+/// Returns the file ids of the blobs that are referenced by the metadata or the uploads in
+/// progress.
+#[instrument(level = "trace", skip_all, err)]
+pub async fn get_all_referenced_blob_ids(pg_pool: &PgPool) -> Result<Vec<String>, AppError> {
+  let file_ids = sqlx::query_scalar!(
+    r#"
+        SELECT file_id AS "file_id!" FROM af_blob_metadata
+        UNION
+        SELECT file_id FROM af_blob_upload
+        UNION
+        SELECT file_id FROM af_blob_presigned_upload
+        "#
+  )
+  .fetch_all(pg_pool)
+  .await?;
+  Ok(file_ids)
+}
+
+/// Returns the workspace id and file id of the blob metadata that was modified before the given
+/// time.
+#[instrument(level = "trace", skip_all, err)]
+pub async fn get_blob_metadata_ids_before(
+  pg_pool: &PgPool,
+  before: DateTime<Utc>,
+) -> Result<Vec<(Uuid, String)>, AppError> {
+  let ids = sqlx::query!(
+    r#"
+        SELECT workspace_id, file_id FROM af_blob_metadata
+        WHERE modified_at < $1
+        "#,
+    before
+  )
+  .fetch_all(pg_pool)
+  .await?
+  .into_iter()
+  .map(|row| (row.workspace_id, row.file_id))
+  .collect();
+  Ok(ids)
+}
+
 #[instrument(level = "trace", skip_all, err)]
 pub async fn get_blob_metadata(
   pg_pool: &PgPool,
@@ -94,7 +223,7 @@ pub async fn get_blob_metadata(
         WHERE workspace_id = $1 AND file_id = $2
         "#,
     workspace_id,
-    file_id,
+    file_id
   )
   .fetch_one(pg_pool)
   .await?;
@@ -104,19 +233,19 @@ pub async fn get_blob_metadata(
 /// Return all blob metadata of a workspace
 #[instrument(level = "trace", skip_all, err)]
 #[inline]
-pub async fn get_all_workspace_blob_metadata(
-  pg_pool: &PgPool,
+pub async fn get_all_workspace_blob_metadata<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
   workspace_id: &Uuid,
 ) -> Result<Vec<AFBlobMetadataRow>, AppError> {
   let all_metadata = sqlx::query_as!(
     AFBlobMetadataRow,
     r#"
         SELECT * FROM af_blob_metadata
-        WHERE workspace_id = $1 
+        WHERE workspace_id = $1
         "#,
-    workspace_id,
+    workspace_id
   )
-  .fetch_all(pg_pool)
+  .fetch_all(executor)
   .await?;
   Ok(all_metadata)
 }
@@ -146,14 +275,219 @@ pub async fn get_all_workspace_blob_ids(
 /// Return the total size of a workspace in bytes
 #[instrument(level = "trace", skip_all, err)]
 #[inline]
-pub async fn get_workspace_usage_size(pool: &PgPool, workspace_id: &Uuid) -> Result<u64, AppError> {
+pub async fn get_workspace_usage_size<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  workspace_id: &Uuid,
+) -> Result<u64, AppError> {
   let row: (Option<Decimal>,) =
     sqlx::query_as(r#"SELECT SUM(file_size) FROM af_blob_metadata WHERE workspace_id = $1;"#)
       .bind(workspace_id)
-      .fetch_one(pool)
+      .fetch_one(executor)
       .await?;
   match row.0 {
     Some(decimal) => Ok(decimal.to_u64().unwrap_or(0)),
     None => Ok(0),
   }
 }
+
+#[instrument(level = "trace", skip_all, err)]
+pub async fn insert_blob_upload(
+  pg_pool: &PgPool,
+  upload_id: &str,
+  workspace_id: &Uuid,
+  file_id: &str,
+  file_type: &str,
+  file_size: i64,
+) -> Result<(), AppError> {
+  sqlx::query!(
+    r#"
+        INSERT INTO af_blob_upload
+        (upload_id, workspace_id, file_id, file_type, file_size)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+    upload_id,
+    workspace_id,
+    file_id,
+    file_type,
+    file_size
+  )
+  .execute(pg_pool)
+  .await?;
+  Ok(())
+}
+
+#[instrument(level = "trace", skip_all, err)]
+pub async fn get_blob_upload(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  upload_id: &str,
+) -> Result<AFBlobUploadRow, AppError> {
+  let upload = sqlx::query_as!(
+    AFBlobUploadRow,
+    r#"
+        SELECT * FROM af_blob_upload
+        WHERE workspace_id = $1 AND upload_id = $2
+        "#,
+    workspace_id,
+    upload_id
+  )
+  .fetch_one(pg_pool)
+  .await?;
+  Ok(upload)
+}
+
+/// Returns the multipart uploads that were created before the given time.
+#[instrument(level = "trace", skip_all, err)]
+pub async fn get_blob_uploads_before(
+  pg_pool: &PgPool,
+  before: DateTime<Utc>,
+) -> Result<Vec<AFBlobUploadRow>, AppError> {
+  let uploads = sqlx::query_as!(
+    AFBlobUploadRow,
+    r#"
+        SELECT * FROM af_blob_upload
+        WHERE created_at < $1
+        "#,
+    before
+  )
+  .fetch_all(pg_pool)
+  .await?;
+  Ok(uploads)
+}
+
+#[instrument(level = "trace", skip_all, err)]
+pub async fn delete_blob_upload(pg_pool: &PgPool, upload_id: &str) -> Result<(), AppError> {
+  sqlx::query!(
+    r#"DELETE FROM af_blob_upload WHERE upload_id = $1"#,
+    upload_id
+  )
+  .execute(pg_pool)
+  .await?;
+  Ok(())
+}
+
+/// Inserts the part of the upload. The part with the same part number is replaced.
+#[instrument(level = "trace", skip_all, err)]
+pub async fn upsert_blob_upload_part(
+  pg_pool: &PgPool,
+  upload_id: &str,
+  part: &AFUploadPart,
+) -> Result<(), AppError> {
+  sqlx::query!(
+    r#"
+        INSERT INTO af_blob_upload_part
+        (upload_id, part_number, e_tag, part_size)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (upload_id, part_number) DO UPDATE SET
+            e_tag = $3,
+            part_size = $4
+        "#,
+    upload_id,
+    part.part_number,
+    &part.e_tag,
+    part.part_size
+  )
+  .execute(pg_pool)
+  .await?;
+  Ok(())
+}
+
+/// Return the uploaded parts of the upload, ordered by the part number
+#[instrument(level = "trace", skip_all, err)]
+pub async fn get_blob_upload_parts(
+  pg_pool: &PgPool,
+  upload_id: &str,
+) -> Result<Vec<AFUploadPart>, AppError> {
+  let parts = sqlx::query!(
+    r#"
+        SELECT part_number, e_tag, part_size FROM af_blob_upload_part
+        WHERE upload_id = $1
+        ORDER BY part_number
+        "#,
+    upload_id
+  )
+  .fetch_all(pg_pool)
+  .await?
+  .into_iter()
+  .map(|row| AFUploadPart {
+    part_number: row.part_number,
+    e_tag: row.e_tag,
+    part_size: row.part_size,
+  })
+  .collect();
+  Ok(parts)
+}
+
+#[instrument(level = "trace", skip_all, err)]
+pub async fn insert_blob_presigned_upload(
+  pg_pool: &PgPool,
+  file_id: &str,
+  workspace_id: &Uuid,
+  file_type: &str,
+  file_size: i64,
+) -> Result<(), AppError> {
+  sqlx::query!(
+    r#"
+        INSERT INTO af_blob_presigned_upload
+        (file_id, workspace_id, file_type, file_size)
+        VALUES ($1, $2, $3, $4)
+        "#,
+    file_id,
+    workspace_id,
+    file_type,
+    file_size
+  )
+  .execute(pg_pool)
+  .await?;
+  Ok(())
+}
+
+#[instrument(level = "trace", skip_all, err)]
+pub async fn get_blob_presigned_upload(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  file_id: &str,
+) -> Result<AFBlobPresignedUploadRow, AppError> {
+  let upload = sqlx::query_as!(
+    AFBlobPresignedUploadRow,
+    r#"
+        SELECT * FROM af_blob_presigned_upload
+        WHERE workspace_id = $1 AND file_id = $2
+        "#,
+    workspace_id,
+    file_id
+  )
+  .fetch_one(pg_pool)
+  .await?;
+  Ok(upload)
+}
+
+/// Returns the presigned uploads that were created before the given time.
+#[instrument(level = "trace", skip_all, err)]
+pub async fn get_blob_presigned_uploads_before(
+  pg_pool: &PgPool,
+  before: DateTime<Utc>,
+) -> Result<Vec<AFBlobPresignedUploadRow>, AppError> {
+  let uploads = sqlx::query_as!(
+    AFBlobPresignedUploadRow,
+    r#"
+        SELECT * FROM af_blob_presigned_upload
+        WHERE created_at < $1
+        "#,
+    before
+  )
+  .fetch_all(pg_pool)
+  .await?;
+  Ok(uploads)
+}
+
+#[instrument(level = "trace", skip_all, err)]
+pub async fn delete_blob_presigned_upload(pg_pool: &PgPool, file_id: &str) -> Result<(), AppError> {
+  sqlx::query!(
+    r#"DELETE FROM af_blob_presigned_upload WHERE file_id = $1"#,
+    file_id
+  )
+  .execute(pg_pool)
+  .await?;
+  Ok(())
+}
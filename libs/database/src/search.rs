@@ -0,0 +1,74 @@
+use app_error::AppError;
+use database_entity::pg_row::AFCollabSearchRow;
+use sqlx::PgPool;
+use tracing::instrument;
+use uuid::Uuid;
+
+/// Replaces the searchable content of the collab.
+#[instrument(level = "trace", skip(pg_pool, content), err)]
+pub async fn upsert_collab_search_content(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  oid: &str,
+  collab_type: i32,
+  content: &str,
+) -> Result<(), AppError> {
+  sqlx::query!(
+    r#"
+        INSERT INTO af_collab_search (workspace_id, oid, collab_type, content)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (workspace_id, oid) DO UPDATE
+        SET collab_type = EXCLUDED.collab_type,
+            content = EXCLUDED.content,
+            updated_at = CURRENT_TIMESTAMP
+        "#,
+    workspace_id,
+    oid,
+    collab_type,
+    content
+  )
+  .execute(pg_pool)
+  .await?;
+  Ok(())
+}
+
+#[instrument(level = "trace", skip(pg_pool), err)]
+pub async fn delete_collab_search_content(pg_pool: &PgPool, oid: &str) -> Result<(), AppError> {
+  sqlx::query!("DELETE FROM af_collab_search WHERE oid = $1", oid)
+    .execute(pg_pool)
+    .await?;
+  Ok(())
+}
+
+/// Returns the collabs of the workspace that match the query, ordered by rank. The query uses the
+/// web search syntax, e.g. `"exact phrase" -excluded or`. The matched words in the snippet are
+/// wrapped in `<b>` and `</b>`. The collabs that are deleted or in the trash are left out.
+#[instrument(level = "trace", skip(pg_pool), err)]
+pub async fn search_collab(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  query: &str,
+  limit: i64,
+) -> Result<Vec<AFCollabSearchRow>, AppError> {
+  let rows = sqlx::query_as!(
+    AFCollabSearchRow,
+    r#"
+        SELECT s.oid, s.collab_type,
+               ts_rank(s.content_tsv, q) AS "rank!",
+               ts_headline('simple', s.content, q, 'MaxFragments=1, MaxWords=30, MinWords=10') AS "snippet!"
+        FROM af_collab_search s
+        JOIN af_collab c
+          ON c.oid = s.oid AND c.partition_key = s.collab_type AND c.deleted_at IS NULL,
+        websearch_to_tsquery('simple', $2) q
+        WHERE s.workspace_id = $1 AND s.content_tsv @@ q
+        ORDER BY ts_rank(s.content_tsv, q) DESC, s.updated_at DESC
+        LIMIT $3
+        "#,
+    workspace_id,
+    query,
+    limit
+  )
+  .fetch_all(pg_pool)
+  .await?;
+  Ok(rows)
+}
@@ -0,0 +1,104 @@
+use app_error::AppError;
+use chrono::{DateTime, Utc};
+use database_entity::pg_row::AFCollabShareLinkRow;
+use sqlx::PgPool;
+use tracing::instrument;
+use uuid::Uuid;
+
+#[allow(clippy::too_many_arguments)]
+#[instrument(level = "trace", skip(pg_pool, token, password_hash), err)]
+pub async fn insert_collab_share_link(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  oid: &str,
+  collab_type: i32,
+  created_by: i64,
+  token: &str,
+  access_level: i32,
+  expires_at: Option<DateTime<Utc>>,
+  password_hash: Option<String>,
+) -> Result<AFCollabShareLinkRow, AppError> {
+  let row = sqlx::query_as!(
+    AFCollabShareLinkRow,
+    r#"
+        INSERT INTO af_collab_share_link
+          (workspace_id, oid, collab_type, created_by, token, access_level, expires_at, password_hash)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING *
+        "#,
+    workspace_id,
+    oid,
+    collab_type,
+    created_by,
+    token,
+    access_level,
+    expires_at,
+    password_hash
+  )
+  .fetch_one(pg_pool)
+  .await?;
+  Ok(row)
+}
+
+/// Returns the share links of the collab, including the expired ones.
+pub async fn select_collab_share_links(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  oid: &str,
+) -> Result<Vec<AFCollabShareLinkRow>, AppError> {
+  let rows = sqlx::query_as!(
+    AFCollabShareLinkRow,
+    r#"
+        SELECT * FROM af_collab_share_link
+        WHERE workspace_id = $1 AND oid = $2
+        ORDER BY created_at ASC
+        "#,
+    workspace_id,
+    oid
+  )
+  .fetch_all(pg_pool)
+  .await?;
+  Ok(rows)
+}
+
+/// Returns the share link of the token. The links of the deleted workspaces are excluded, the
+/// expiry is checked by the caller.
+pub async fn select_collab_share_link_by_token(
+  pg_pool: &PgPool,
+  token: &str,
+) -> Result<Option<AFCollabShareLinkRow>, AppError> {
+  let row = sqlx::query_as!(
+    AFCollabShareLinkRow,
+    r#"
+        SELECT l.* FROM af_collab_share_link l
+          JOIN af_workspace w ON l.workspace_id = w.workspace_id
+        WHERE l.token = $1 AND w.deleted_at IS NULL
+        "#,
+    token
+  )
+  .fetch_optional(pg_pool)
+  .await?;
+  Ok(row)
+}
+
+/// Deletes the share link of the collab and returns its token.
+pub async fn delete_collab_share_link(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  oid: &str,
+  share_id: &Uuid,
+) -> Result<String, AppError> {
+  sqlx::query_scalar!(
+    r#"
+        DELETE FROM af_collab_share_link
+        WHERE id = $1 AND workspace_id = $2 AND oid = $3
+        RETURNING token
+        "#,
+    share_id,
+    workspace_id,
+    oid
+  )
+  .fetch_optional(pg_pool)
+  .await?
+  .ok_or_else(|| AppError::RecordNotFound(format!("share link:{} doesn't exist", share_id)))
+}
@@ -0,0 +1,102 @@
+use app_error::AppError;
+use chrono::{DateTime, Utc};
+use database_entity::pg_row::AFTrashedCollabRow;
+use sqlx::{Executor, PgPool, Postgres};
+use std::ops::DerefMut;
+use tracing::instrument;
+use uuid::Uuid;
+
+/// Returns the deleted collabs of the workspace, the most recently deleted first.
+pub async fn select_trashed_collabs(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+) -> Result<Vec<AFTrashedCollabRow>, AppError> {
+  let rows = sqlx::query_as!(
+    AFTrashedCollabRow,
+    r#"
+        SELECT oid, partition_key, owner_uid, deleted_at AS "deleted_at!" FROM af_collab
+        WHERE workspace_id = $1 AND deleted_at IS NOT NULL
+        ORDER BY deleted_at DESC
+        "#,
+    workspace_id
+  )
+  .fetch_all(pg_pool)
+  .await?;
+  Ok(rows)
+}
+
+#[inline]
+pub async fn is_collab_trashed<'a, E: Executor<'a, Database = Postgres>>(
+  oid: &str,
+  executor: E,
+) -> Result<bool, sqlx::Error> {
+  sqlx::query_scalar!(
+    "SELECT EXISTS (SELECT 1 FROM af_collab WHERE oid = $1 AND deleted_at IS NOT NULL)",
+    oid
+  )
+  .fetch_one(executor)
+  .await
+  .map(|exists| exists.unwrap_or(false))
+}
+
+/// Moves the collab out of the trash and returns its partition key. Returns
+/// [AppError::RecordNotFound] if the collab is not in the trash of the workspace.
+#[instrument(level = "trace", skip(executor), err)]
+pub async fn restore_trashed_collab<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  workspace_id: &Uuid,
+  oid: &str,
+) -> Result<i32, AppError> {
+  let partition_key = sqlx::query_scalar!(
+    r#"
+        UPDATE af_collab SET deleted_at = NULL
+        WHERE workspace_id = $1 AND oid = $2 AND deleted_at IS NOT NULL
+        RETURNING partition_key
+        "#,
+    workspace_id,
+    oid
+  )
+  .fetch_optional(executor)
+  .await?;
+  partition_key.ok_or_else(|| {
+    AppError::RecordNotFound(format!(
+      "collab:{} is not in the trash of workspace:{}",
+      oid, workspace_id
+    ))
+  })
+}
+
+/// Permanently deletes the collabs that were moved to the trash before `deleted_before`, along
+/// with their members, updates, snapshots, share links, comments and search content. Returns the
+/// object ids of the deleted collabs.
+#[instrument(level = "debug", skip(pg_pool), err)]
+pub async fn delete_expired_trashed_collabs(
+  pg_pool: &PgPool,
+  deleted_before: DateTime<Utc>,
+) -> Result<Vec<String>, AppError> {
+  let mut txn = pg_pool.begin().await?;
+  let oids = sqlx::query_scalar!(
+    "DELETE FROM af_collab WHERE deleted_at IS NOT NULL AND deleted_at < $1 RETURNING oid",
+    deleted_before
+  )
+  .fetch_all(txn.deref_mut())
+  .await?;
+
+  if !oids.is_empty() {
+    for table in [
+      "af_collab_member",
+      "af_collab_update",
+      "af_collab_snapshot",
+      "af_collab_share_link",
+      "af_collab_comment",
+      "af_collab_search",
+    ] {
+      sqlx::query(&format!("DELETE FROM {} WHERE oid = ANY($1)", table))
+        .bind(&oids)
+        .execute(txn.deref_mut())
+        .await?;
+    }
+  }
+  txn.commit().await?;
+  Ok(oids)
+}
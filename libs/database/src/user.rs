@@ -1,7 +1,7 @@
 use app_error::AppError;
 use sqlx::postgres::PgArguments;
 use sqlx::types::JsonValue;
-use sqlx::{Arguments, Executor, PgPool, Postgres};
+use sqlx::{Arguments, Executor, Postgres};
 use tracing::{instrument, warn};
 use uuid::Uuid;
 
@@ -21,8 +21,8 @@ use uuid::Uuid;
 ///
 #[instrument(skip_all, err)]
 #[inline]
-pub async fn update_user(
-  pool: &PgPool,
+pub async fn update_user<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
   user_uuid: &uuid::Uuid,
   name: Option<String>,
   email: Option<String>,
@@ -65,7 +65,7 @@ pub async fn update_user(
   );
   args.add(user_uuid);
 
-  sqlx::query_with(&query, args).execute(pool).await?;
+  sqlx::query_with(&query, args).execute(executor).await?;
   Ok(())
 }
 
@@ -165,6 +165,22 @@ pub async fn select_uid_from_email<'a, E: Executor<'a, Database = Postgres>>(
   Ok(uid)
 }
 
+#[inline]
+pub async fn select_email_from_uuid<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  user_uuid: &Uuid,
+) -> Result<String, AppError> {
+  let email = sqlx::query_scalar!(
+    r#"
+      SELECT email FROM af_user WHERE uuid = $1
+    "#,
+    user_uuid
+  )
+  .fetch_one(executor)
+  .await?;
+  Ok(email)
+}
+
 #[inline]
 pub async fn is_user_exist<'a, E: Executor<'a, Database = Postgres>>(
   executor: E,
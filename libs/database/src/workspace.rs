@@ -8,7 +8,10 @@ use tracing::{event, instrument};
 
 use crate::user::select_uid_from_email;
 use app_error::AppError;
-use database_entity::pg_row::{AFUserProfileRow, AFWorkspaceMemberRow, AFWorkspaceRow};
+use database_entity::pg_row::{
+  AFUserProfileRow, AFWorkspaceInvitationRow, AFWorkspaceMemberRow, AFWorkspacePlanRow,
+  AFWorkspaceRow,
+};
 
 /// Checks whether a user, identified by a UUID, is an 'Owner' of a workspace, identified by its
 /// workspace_id.
@@ -142,9 +145,9 @@ pub async fn insert_workspace_member_with_txn(
 }
 
 #[inline]
-#[instrument(level = "trace", skip(pool, email, role), err)]
+#[instrument(level = "trace", skip(txn, email, role), err)]
 pub async fn upsert_workspace_member(
-  pool: &PgPool,
+  txn: &mut Transaction<'_, sqlx::Postgres>,
   workspace_id: &Uuid,
   email: &str,
   role: Option<AFRole>,
@@ -157,7 +160,7 @@ pub async fn upsert_workspace_member(
     tracing::Level::TRACE,
     "update workspace member: workspace_id:{}, uid {:?}, role:{:?}",
     workspace_id,
-    select_uid_from_email(pool, email).await,
+    select_uid_from_email(txn.deref_mut(), email).await,
     role
   );
 
@@ -175,7 +178,7 @@ pub async fn upsert_workspace_member(
     workspace_id,
     email
   )
-  .execute(pool)
+  .execute(txn.deref_mut())
   .await?;
 
   Ok(())
@@ -239,8 +242,8 @@ pub async fn delete_workspace_members(
 
 /// returns a list of workspace members, sorted by their creation time.
 #[inline]
-pub async fn select_workspace_member_list(
-  pg_pool: &PgPool,
+pub async fn select_workspace_member_list<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
   workspace_id: &uuid::Uuid,
 ) -> Result<Vec<AFWorkspaceMemberRow>, AppError> {
   let members = sqlx::query_as!(
@@ -255,7 +258,7 @@ pub async fn select_workspace_member_list(
     "#,
     workspace_id
   )
-  .fetch_all(pg_pool)
+  .fetch_all(executor)
   .await?;
   Ok(members)
 }
@@ -309,7 +312,7 @@ pub async fn select_workspace<'a, E: Executor<'a, Database = Postgres>>(
   let workspace = sqlx::query_as!(
     AFWorkspaceRow,
     r#"
-       SELECT * FROM public.af_workspace WHERE workspace_id = $1
+       SELECT * FROM public.af_workspace WHERE workspace_id = $1 AND deleted_at IS NULL
     "#,
     workspace_id
   )
@@ -337,7 +340,7 @@ pub async fn update_updated_at_of_workspace<'a, E: Executor<'a, Database = Postg
   Ok(())
 }
 
-/// Returns a list of workspaces that the user is a member of.
+/// Returns a list of workspaces that the user is a member of. The deleted workspaces are excluded.
 #[inline]
 pub async fn select_user_workspace<'a, E: Executor<'a, Database = Postgres>>(
   executor: E,
@@ -346,12 +349,14 @@ pub async fn select_user_workspace<'a, E: Executor<'a, Database = Postgres>>(
   let workspaces = sqlx::query_as!(
     AFWorkspaceRow,
     r#"
-      SELECT w.* 
+      SELECT w.*
       FROM af_workspace w
       JOIN af_workspace_member wm ON w.workspace_id = wm.workspace_id
       WHERE wm.uid = (
          SELECT uid FROM public.af_user WHERE uuid = $1
-      );
+      )
+      AND w.deleted_at IS NULL
+      ORDER BY w.created_at ASC;
     "#,
     user_uuid
   )
@@ -371,6 +376,8 @@ pub async fn select_all_user_workspaces(
       SELECT * FROM public.af_workspace WHERE owner_uid = (
         SELECT uid FROM public.af_user WHERE uuid = $1
       )
+      AND deleted_at IS NULL
+      ORDER BY created_at ASC
     "#,
     owner_uuid
   )
@@ -378,3 +385,461 @@ pub async fn select_all_user_workspaces(
   .await?;
   Ok(workspaces)
 }
+
+/// Creates a workspace owned by the user. The owner is added as the member of the workspace and
+/// the member of the workspace's collab, the same as the default workspace created in
+/// [crate::user::create_user].
+#[inline]
+pub async fn insert_user_workspace(
+  txn: &mut Transaction<'_, sqlx::Postgres>,
+  owner_uid: &i64,
+  workspace_name: &str,
+) -> Result<AFWorkspaceRow, AppError> {
+  let workspace = sqlx::query_as!(
+    AFWorkspaceRow,
+    r#"
+    WITH owner_role AS (
+        SELECT id FROM af_roles WHERE name = 'Owner'
+    ),
+    ins_workspace AS (
+        INSERT INTO af_workspace (owner_uid, workspace_name)
+        VALUES ($1, $2)
+        RETURNING *
+    ),
+    ins_collab_member AS (
+        INSERT INTO af_collab_member (uid, oid, permission_id)
+        SELECT ins_workspace.owner_uid,
+               ins_workspace.workspace_id::TEXT,
+               (SELECT permission_id FROM af_role_permissions WHERE role_id = owner_role.id)
+        FROM ins_workspace, owner_role
+    ),
+    ins_workspace_member AS (
+        INSERT INTO af_workspace_member (uid, role_id, workspace_id)
+        SELECT ins_workspace.owner_uid, owner_role.id, ins_workspace.workspace_id
+        FROM ins_workspace, owner_role
+    )
+    SELECT * FROM ins_workspace;
+    "#,
+    owner_uid,
+    workspace_name
+  )
+  .fetch_one(txn.deref_mut())
+  .await?;
+  Ok(workspace)
+}
+
+#[inline]
+pub async fn update_workspace_name<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  workspace_id: &Uuid,
+  workspace_name: &str,
+) -> Result<(), AppError> {
+  let result = sqlx::query!(
+    r#"
+      UPDATE af_workspace SET workspace_name = $2
+      WHERE workspace_id = $1 AND deleted_at IS NULL
+    "#,
+    workspace_id,
+    workspace_name
+  )
+  .execute(executor)
+  .await?;
+  if result.rows_affected() == 0 {
+    return Err(AppError::RecordNotFound(format!(
+      "workspace:{} doesn't exist",
+      workspace_id
+    )));
+  }
+  Ok(())
+}
+
+/// Marks the workspace as deleted. The workspace and its collabs are kept in the database, but the
+/// workspace is no longer listed or opened.
+#[inline]
+pub async fn soft_delete_workspace<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  workspace_id: &Uuid,
+) -> Result<(), AppError> {
+  let result = sqlx::query!(
+    r#"
+      UPDATE af_workspace SET deleted_at = CURRENT_TIMESTAMP
+      WHERE workspace_id = $1 AND deleted_at IS NULL
+    "#,
+    workspace_id
+  )
+  .execute(executor)
+  .await?;
+  if result.rows_affected() == 0 {
+    return Err(AppError::RecordNotFound(format!(
+      "workspace:{} doesn't exist",
+      workspace_id
+    )));
+  }
+  Ok(())
+}
+
+/// Returns the uids of the members whose only workspace that is not deleted is the given one.
+#[inline]
+pub async fn select_members_without_other_workspace<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  workspace_id: &Uuid,
+) -> Result<Vec<i64>, AppError> {
+  let uids = sqlx::query_scalar!(
+    r#"
+      SELECT wm.uid FROM af_workspace_member wm
+      WHERE wm.workspace_id = $1
+        AND NOT EXISTS (
+          SELECT 1 FROM af_workspace_member other
+            JOIN af_workspace w ON other.workspace_id = w.workspace_id
+          WHERE other.uid = wm.uid AND other.workspace_id <> $1 AND w.deleted_at IS NULL
+        )
+    "#,
+    workspace_id
+  )
+  .fetch_all(executor)
+  .await?;
+  Ok(uids)
+}
+
+/// Removes all the members of the workspace, and the members of the workspace's collabs. The
+/// access control caches drop the access of the removed members when they are notified of the
+/// deleted rows.
+#[inline]
+pub async fn delete_all_workspace_members(
+  txn: &mut Transaction<'_, sqlx::Postgres>,
+  workspace_id: &Uuid,
+) -> Result<(), AppError> {
+  sqlx::query!(
+    r#"
+      DELETE FROM af_collab_member
+      WHERE oid = $1::UUID::TEXT
+        OR oid IN (SELECT oid FROM af_collab WHERE workspace_id = $1)
+    "#,
+    workspace_id
+  )
+  .execute(txn.deref_mut())
+  .await?;
+  sqlx::query!(
+    r#"
+      DELETE FROM af_workspace_member WHERE workspace_id = $1
+    "#,
+    workspace_id
+  )
+  .execute(txn.deref_mut())
+  .await?;
+  Ok(())
+}
+
+/// Makes the member the owner of the workspace, and demotes the current owner to a member.
+/// Returns the uid of the previous owner.
+#[inline]
+pub async fn update_workspace_owner(
+  txn: &mut Transaction<'_, sqlx::Postgres>,
+  workspace_id: &Uuid,
+  new_owner_uid: &i64,
+) -> Result<i64, AppError> {
+  let old_owner_uid = sqlx::query_scalar!(
+    r#"
+      SELECT owner_uid FROM af_workspace
+      WHERE workspace_id = $1 AND deleted_at IS NULL
+      FOR UPDATE
+    "#,
+    workspace_id
+  )
+  .fetch_optional(txn.deref_mut())
+  .await?
+  .ok_or_else(|| AppError::RecordNotFound(format!("workspace:{} doesn't exist", workspace_id)))?;
+
+  sqlx::query!(
+    r#"
+      UPDATE af_workspace SET owner_uid = $2 WHERE workspace_id = $1
+    "#,
+    workspace_id,
+    new_owner_uid
+  )
+  .execute(txn.deref_mut())
+  .await?;
+
+  sqlx::query!(
+    r#"
+      UPDATE af_workspace_member SET role_id = $3
+      WHERE workspace_id = $1 AND uid = $2
+    "#,
+    workspace_id,
+    new_owner_uid,
+    i32::from(AFRole::Owner)
+  )
+  .execute(txn.deref_mut())
+  .await?;
+  sqlx::query!(
+    r#"
+      UPDATE af_workspace_member SET role_id = $3
+      WHERE workspace_id = $1 AND uid = $2
+    "#,
+    workspace_id,
+    old_owner_uid,
+    i32::from(AFRole::Member)
+  )
+  .execute(txn.deref_mut())
+  .await?;
+  Ok(old_owner_uid)
+}
+
+/// Returns the plan of the workspace, which is determined by the workspace type.
+#[inline]
+pub async fn select_workspace_plan<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  workspace_id: &Uuid,
+) -> Result<AFWorkspacePlanRow, AppError> {
+  let plan = sqlx::query_as!(
+    AFWorkspacePlanRow,
+    r#"
+      SELECT p.*
+      FROM af_workspace_plan p
+      JOIN af_workspace w ON w.workspace_type = p.workspace_type
+      WHERE w.workspace_id = $1
+    "#,
+    workspace_id
+  )
+  .fetch_one(executor)
+  .await?;
+  Ok(plan)
+}
+
+#[inline]
+pub async fn select_workspace_plans(pool: &PgPool) -> Result<Vec<AFWorkspacePlanRow>, AppError> {
+  let plans = sqlx::query_as!(
+    AFWorkspacePlanRow,
+    r#"
+      SELECT * FROM af_workspace_plan ORDER BY workspace_type
+    "#
+  )
+  .fetch_all(pool)
+  .await?;
+  Ok(plans)
+}
+
+#[inline]
+pub async fn update_workspace_type(
+  pool: &PgPool,
+  workspace_id: &Uuid,
+  workspace_type: i32,
+) -> Result<(), AppError> {
+  let plan_exists = sqlx::query_scalar!(
+    r#"
+      SELECT EXISTS (SELECT 1 FROM af_workspace_plan WHERE workspace_type = $1)
+    "#,
+    workspace_type
+  )
+  .fetch_one(pool)
+  .await?
+  .unwrap_or(false);
+  if !plan_exists {
+    return Err(AppError::RecordNotFound(format!(
+      "workspace type:{} doesn't exist",
+      workspace_type
+    )));
+  }
+
+  let result = sqlx::query!(
+    r#"
+      UPDATE af_workspace SET workspace_type = $2 WHERE workspace_id = $1
+    "#,
+    workspace_id,
+    workspace_type
+  )
+  .execute(pool)
+  .await?;
+  if result.rows_affected() == 0 {
+    return Err(AppError::RecordNotFound(format!(
+      "workspace:{} doesn't exist",
+      workspace_id
+    )));
+  }
+  Ok(())
+}
+
+#[inline]
+pub async fn select_workspace_member_count<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  workspace_id: &Uuid,
+) -> Result<i64, AppError> {
+  let count = sqlx::query_scalar!(
+    r#"
+      SELECT COUNT(*) FROM af_workspace_member WHERE workspace_id = $1
+    "#,
+    workspace_id
+  )
+  .fetch_one(executor)
+  .await?;
+  Ok(count.unwrap_or(0))
+}
+
+/// Creates a pending invitation for the email, or updates the role and the inviter of the pending
+/// invitation if the email has already been invited to the workspace.
+#[inline]
+pub async fn upsert_workspace_invitation(
+  txn: &mut Transaction<'_, sqlx::Postgres>,
+  workspace_id: &Uuid,
+  inviter_uid: &i64,
+  invitee_email: &str,
+  role: AFRole,
+) -> Result<Uuid, AppError> {
+  let invite_id = sqlx::query_scalar!(
+    r#"
+      INSERT INTO af_workspace_invitation (workspace_id, inviter, invitee_email, role_id)
+      VALUES ($1, $2, $3, $4)
+      ON CONFLICT (workspace_id, invitee_email) WHERE status = 0
+      DO UPDATE SET inviter = EXCLUDED.inviter, role_id = EXCLUDED.role_id,
+        updated_at = CURRENT_TIMESTAMP
+      RETURNING id
+    "#,
+    workspace_id,
+    inviter_uid,
+    invitee_email,
+    i32::from(role)
+  )
+  .fetch_one(txn.deref_mut())
+  .await?;
+  Ok(invite_id)
+}
+
+/// Returns the pending invitations of the workspace, sorted by their creation time.
+#[inline]
+pub async fn select_workspace_invitations(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+) -> Result<Vec<AFWorkspaceInvitationRow>, AppError> {
+  let invitations = sqlx::query_as!(
+    AFWorkspaceInvitationRow,
+    r#"
+      SELECT i.id AS invite_id, i.workspace_id, w.workspace_name, u.name AS inviter_name,
+             i.invitee_email, i.role_id, i.status, i.created_at
+      FROM af_workspace_invitation i
+        JOIN af_workspace w ON i.workspace_id = w.workspace_id
+        JOIN af_user u ON i.inviter = u.uid
+      WHERE i.workspace_id = $1 AND i.status = 0
+      ORDER BY i.created_at ASC
+    "#,
+    workspace_id
+  )
+  .fetch_all(pg_pool)
+  .await?;
+  Ok(invitations)
+}
+
+/// Returns the pending invitations sent to the email. The invitations of the deleted workspaces
+/// are excluded.
+#[inline]
+pub async fn select_invitations_for_email<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  invitee_email: &str,
+) -> Result<Vec<AFWorkspaceInvitationRow>, AppError> {
+  let invitations = sqlx::query_as!(
+    AFWorkspaceInvitationRow,
+    r#"
+      SELECT i.id AS invite_id, i.workspace_id, w.workspace_name, u.name AS inviter_name,
+             i.invitee_email, i.role_id, i.status, i.created_at
+      FROM af_workspace_invitation i
+        JOIN af_workspace w ON i.workspace_id = w.workspace_id
+        JOIN af_user u ON i.inviter = u.uid
+      WHERE i.invitee_email = lower($1) AND i.status = 0 AND w.deleted_at IS NULL
+      ORDER BY i.created_at ASC
+    "#,
+    invitee_email
+  )
+  .fetch_all(executor)
+  .await?;
+  Ok(invitations)
+}
+
+/// Returns the pending invitation and locks it until the end of the transaction.
+#[inline]
+pub async fn select_pending_invitation_for_update(
+  txn: &mut Transaction<'_, sqlx::Postgres>,
+  invite_id: &Uuid,
+) -> Result<AFWorkspaceInvitationRow, AppError> {
+  sqlx::query_as!(
+    AFWorkspaceInvitationRow,
+    r#"
+      SELECT i.id AS invite_id, i.workspace_id, w.workspace_name, u.name AS inviter_name,
+             i.invitee_email, i.role_id, i.status, i.created_at
+      FROM af_workspace_invitation i
+        JOIN af_workspace w ON i.workspace_id = w.workspace_id
+        JOIN af_user u ON i.inviter = u.uid
+      WHERE i.id = $1 AND i.status = 0 AND w.deleted_at IS NULL
+      FOR UPDATE OF i
+    "#,
+    invite_id
+  )
+  .fetch_optional(txn.deref_mut())
+  .await?
+  .ok_or_else(|| AppError::RecordNotFound(format!("invitation:{} doesn't exist", invite_id)))
+}
+
+#[inline]
+pub async fn update_workspace_invitation_status(
+  txn: &mut Transaction<'_, sqlx::Postgres>,
+  invite_id: &Uuid,
+  status: i16,
+) -> Result<(), AppError> {
+  sqlx::query!(
+    r#"
+      UPDATE af_workspace_invitation SET status = $2, updated_at = CURRENT_TIMESTAMP
+      WHERE id = $1
+    "#,
+    invite_id,
+    status
+  )
+  .execute(txn.deref_mut())
+  .await?;
+  Ok(())
+}
+
+/// Deletes the pending invitation of the workspace.
+#[inline]
+pub async fn delete_workspace_invitation(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  invite_id: &Uuid,
+) -> Result<(), AppError> {
+  let result = sqlx::query!(
+    r#"
+      DELETE FROM af_workspace_invitation
+      WHERE id = $1 AND workspace_id = $2 AND status = 0
+    "#,
+    invite_id,
+    workspace_id
+  )
+  .execute(pg_pool)
+  .await?;
+  if result.rows_affected() == 0 {
+    return Err(AppError::RecordNotFound(format!(
+      "invitation:{} doesn't exist",
+      invite_id
+    )));
+  }
+  Ok(())
+}
+
+#[inline]
+pub async fn is_workspace_member_email<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  workspace_id: &Uuid,
+  email: &str,
+) -> Result<bool, AppError> {
+  let exists = sqlx::query_scalar!(
+    r#"
+      SELECT EXISTS(
+        SELECT 1 FROM af_workspace_member wm
+          JOIN af_user u ON wm.uid = u.uid
+        WHERE wm.workspace_id = $1 AND lower(u.email) = lower($2)
+      )
+    "#,
+    workspace_id,
+    email
+  )
+  .fetch_one(executor)
+  .await?;
+  Ok(exists.unwrap_or(false))
+}
@@ -0,0 +1,203 @@
+use app_error::AppError;
+use database_entity::pg_row::{AFWorkspaceMemberPermissionRow, AFWorkspaceRoleRow};
+use sqlx::PgPool;
+use tracing::instrument;
+use uuid::Uuid;
+
+/// Inserts the custom role into the workspace. Returns None if the workspace already has a role
+/// with the same name.
+#[instrument(level = "trace", skip(pg_pool), err)]
+pub async fn insert_workspace_role(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  name: &str,
+  permissions: i64,
+) -> Result<Option<AFWorkspaceRoleRow>, AppError> {
+  let row = sqlx::query_as!(
+    AFWorkspaceRoleRow,
+    r#"
+        INSERT INTO af_workspace_role (workspace_id, name, permissions)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (workspace_id, name) DO NOTHING
+        RETURNING *
+        "#,
+    workspace_id,
+    name,
+    permissions
+  )
+  .fetch_optional(pg_pool)
+  .await?;
+  Ok(row)
+}
+
+pub async fn select_workspace_roles(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+) -> Result<Vec<AFWorkspaceRoleRow>, AppError> {
+  let rows = sqlx::query_as!(
+    AFWorkspaceRoleRow,
+    r#"
+        SELECT * FROM af_workspace_role
+        WHERE workspace_id = $1
+        ORDER BY created_at ASC
+        "#,
+    workspace_id
+  )
+  .fetch_all(pg_pool)
+  .await?;
+  Ok(rows)
+}
+
+pub async fn select_workspace_role(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  role_id: i32,
+) -> Result<AFWorkspaceRoleRow, AppError> {
+  sqlx::query_as!(
+    AFWorkspaceRoleRow,
+    r#"
+        SELECT * FROM af_workspace_role
+        WHERE id = $1 AND workspace_id = $2
+        "#,
+    role_id,
+    workspace_id
+  )
+  .fetch_optional(pg_pool)
+  .await?
+  .ok_or_else(|| AppError::RecordNotFound(format!("role:{} doesn't exist", role_id)))
+}
+
+/// Updates the name and the permissions of the custom role. The fields that are None are kept.
+pub async fn update_workspace_role(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  role_id: i32,
+  name: Option<&str>,
+  permissions: Option<i64>,
+) -> Result<AFWorkspaceRoleRow, AppError> {
+  sqlx::query_as!(
+    AFWorkspaceRoleRow,
+    r#"
+        UPDATE af_workspace_role
+        SET name = COALESCE($3, name),
+            permissions = COALESCE($4, permissions),
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1 AND workspace_id = $2
+        RETURNING *
+        "#,
+    role_id,
+    workspace_id,
+    name,
+    permissions
+  )
+  .fetch_optional(pg_pool)
+  .await?
+  .ok_or_else(|| AppError::RecordNotFound(format!("role:{} doesn't exist", role_id)))
+}
+
+/// Deletes the custom role. The members of the role fall back to the permissions of their
+/// built-in role.
+pub async fn delete_workspace_role(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  role_id: i32,
+) -> Result<(), AppError> {
+  let result = sqlx::query!(
+    r#"
+        DELETE FROM af_workspace_role
+        WHERE id = $1 AND workspace_id = $2
+        "#,
+    role_id,
+    workspace_id
+  )
+  .execute(pg_pool)
+  .await?;
+  if result.rows_affected() == 0 {
+    return Err(AppError::RecordNotFound(format!(
+      "role:{} doesn't exist",
+      role_id
+    )));
+  }
+  Ok(())
+}
+
+/// Assigns the custom role to the member, or removes the custom role of the member if the role id
+/// is None.
+pub async fn update_workspace_member_custom_role(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  uid: i64,
+  role_id: Option<i32>,
+) -> Result<(), AppError> {
+  let result = sqlx::query!(
+    r#"
+        UPDATE af_workspace_member
+        SET custom_role_id = $3, updated_at = CURRENT_TIMESTAMP
+        WHERE workspace_id = $1 AND uid = $2
+        "#,
+    workspace_id,
+    uid,
+    role_id
+  )
+  .execute(pg_pool)
+  .await?;
+  if result.rows_affected() == 0 {
+    return Err(AppError::RecordNotFound(format!(
+      "user:{} is not a member of workspace:{}",
+      uid, workspace_id
+    )));
+  }
+  Ok(())
+}
+
+/// Returns the role of the member and the permissions of the custom role of the member.
+pub async fn select_workspace_member_permissions(
+  pg_pool: &PgPool,
+  uid: &i64,
+  workspace_id: &Uuid,
+) -> Result<AFWorkspaceMemberPermissionRow, AppError> {
+  sqlx::query_as!(
+    AFWorkspaceMemberPermissionRow,
+    r#"
+        SELECT m.role_id, m.custom_role_id, r.permissions
+        FROM af_workspace_member m
+          LEFT JOIN af_workspace_role r ON m.custom_role_id = r.id
+        WHERE m.workspace_id = $1 AND m.uid = $2
+        "#,
+    workspace_id,
+    uid
+  )
+  .fetch_optional(pg_pool)
+  .await?
+  .ok_or_else(|| {
+    AppError::RecordNotFound(format!(
+      "user:{} is not a member of workspace:{}",
+      uid, workspace_id
+    ))
+  })
+}
+
+/// Returns the permissions of the custom role that the user has in the workspace of the collab,
+/// or None if the user doesn't have a custom role in the workspace. The custom role of an owner,
+/// whose role_id is 1, is ignored.
+pub async fn select_collab_custom_role_permissions(
+  pg_pool: &PgPool,
+  uid: &i64,
+  oid: &str,
+) -> Result<Option<i64>, AppError> {
+  let permissions = sqlx::query_scalar!(
+    r#"
+        SELECT r.permissions
+        FROM af_collab c
+          JOIN af_workspace_member m ON m.workspace_id = c.workspace_id AND m.uid = $1
+          JOIN af_workspace_role r ON m.custom_role_id = r.id
+        WHERE c.oid = $2 AND m.role_id <> 1
+        LIMIT 1
+        "#,
+    uid,
+    oid
+  )
+  .fetch_optional(pg_pool)
+  .await?;
+  Ok(permissions)
+}
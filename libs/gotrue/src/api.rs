@@ -1,7 +1,7 @@
 use super::grant::Grant;
 use crate::params::{
   AdminDeleteUserParams, AdminUserParams, CreateSSOProviderParams, GenerateLinkParams,
-  GenerateLinkResponse, MagicLinkParams,
+  GenerateLinkResponse, InviteUserParams, MagicLinkParams,
 };
 use anyhow::Context;
 use gotrue_entity::dto::{
@@ -223,6 +223,21 @@ impl Client {
     to_gotrue_result(resp).await
   }
 
+  pub async fn admin_invite_user(
+    &self,
+    access_token: &str,
+    invite_user_params: &InviteUserParams,
+  ) -> Result<User, GoTrueError> {
+    let resp = self
+      .client
+      .post(format!("{}/invite", self.base_url))
+      .header("Authorization", format!("Bearer {}", access_token))
+      .json(&invite_user_params)
+      .send()
+      .await?;
+    to_gotrue_result(resp).await
+  }
+
   pub async fn magic_link(
     &self,
     access_token: &str,
@@ -3,6 +3,14 @@ use std::collections::btree_map::BTreeMap;
 use gotrue_entity::dto::{Factor, Identity};
 use serde::{Deserialize, Serialize};
 
+/// Invites a user by email. Gotrue creates the user in the invited state and sends the invite
+/// email, the data is kept in the user metadata of the invited user.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct InviteUserParams {
+  pub email: String,
+  pub data: BTreeMap<String, serde_json::Value>,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct AdminDeleteUserParams {
   pub should_soft_delete: bool,
@@ -0,0 +1,18 @@
+use database_entity::dto::AFCollabComment;
+use serde::{Deserialize, Serialize};
+
+/// Pushed to the users who opened the collab when a comment of the collab is changed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommentMessage {
+  pub object_id: String,
+  pub event: CommentEvent,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum CommentEvent {
+  Created(AFCollabComment),
+  /// The content of the comment is edited, or the thread is resolved or reopened.
+  Updated(AFCollabComment),
+  /// The comment is deleted. The replies are deleted along with the root comment of the thread.
+  Deleted(AFCollabComment),
+}
@@ -1,4 +1,5 @@
 pub mod collab_msg;
+pub mod comment;
 pub mod message;
 pub mod user;
 
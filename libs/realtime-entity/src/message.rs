@@ -1,4 +1,5 @@
 use crate::collab_msg::CollabMessage;
+use crate::comment::CommentMessage;
 use bytes::Bytes;
 use serde::{Deserialize, Serialize};
 use std::fmt::Display;
@@ -13,6 +14,7 @@ pub enum RealtimeMessage {
   Collab(CollabMessage),
   User(UserMessage),
   ServerKickedOff,
+  Comment(CommentMessage),
 }
 
 impl Display for RealtimeMessage {
@@ -21,6 +23,7 @@ impl Display for RealtimeMessage {
       RealtimeMessage::Collab(_msg) => f.write_fmt(format_args!("Collab")),
       RealtimeMessage::ServerKickedOff => f.write_fmt(format_args!("ServerKickedOff")),
       RealtimeMessage::User(_) => f.write_fmt(format_args!("User")),
+      RealtimeMessage::Comment(_) => f.write_fmt(format_args!("Comment")),
     }
   }
 }
@@ -1,23 +1,26 @@
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
+use crate::collaborate::fanout::{CollabFanout, CollabFanoutMessage, CollabFanoutPayload};
 use crate::collaborate::sync_protocol::ServerSyncProtocol;
-use collab::core::collab::MutexCollab;
+use collab::core::collab::{MutexCollab, TransactionMutExt};
 use collab::core::origin::CollabOrigin;
 use collab::sync_protocol::awareness::{Awareness, AwarenessUpdate};
 use collab::sync_protocol::message::{Message, MessageReader, MSG_SYNC, MSG_SYNC_UPDATE};
 use collab::sync_protocol::{awareness, handle_msg};
 use futures_util::{SinkExt, StreamExt};
 use tokio::select;
-use tokio::sync::broadcast::error::SendError;
-use tokio::sync::broadcast::{channel, Sender};
-use tokio::sync::Mutex;
-use yrs::updates::decoder::DecoderV1;
+use tokio::sync::broadcast::error::{RecvError, SendError};
+use tokio::sync::broadcast::{channel, Receiver, Sender};
+use tokio::sync::{oneshot, Mutex};
+use yrs::updates::decoder::{Decode, DecoderV1};
 use yrs::updates::encoder::{Encode, Encoder, EncoderV1};
-use yrs::UpdateSubscription;
+use yrs::{Transact, Update, UpdateSubscription};
 
 use crate::collaborate::retry::SinkCollabMessageAction;
 use crate::error::RealtimeError;
 use realtime_entity::collab_msg::{CollabAck, CollabAwareness, CollabBroadcastData, CollabMessage};
+use realtime_entity::comment::CommentMessage;
 use tracing::{error, trace, warn};
 use yrs::encoding::write::Write;
 
@@ -28,11 +31,16 @@ pub struct CollabBroadcast {
   object_id: String,
   collab: MutexCollab,
   sender: Sender<CollabMessage>,
+  /// True while applying an update published by other nodes
+  is_applying_remote: Arc<AtomicBool>,
 
   #[allow(dead_code)]
   awareness_sub: awareness::UpdateSubscription,
   #[allow(dead_code)]
   doc_sub: UpdateSubscription,
+  /// Stops relaying the messages of other nodes when the broadcast is dropped
+  #[allow(dead_code)]
+  fanout_stop_tx: oneshot::Sender<()>,
 }
 
 impl CollabBroadcast {
@@ -42,15 +50,33 @@ impl CollabBroadcast {
   ///
   /// The overflow of the incoming events that needs to be propagates will be buffered up to a
   /// provided `buffer_capacity` size.
-  pub fn new(object_id: &str, collab: MutexCollab, buffer_capacity: usize) -> Self {
+  ///
+  /// The updates produced by the local subscribers are published to other nodes via the `fanout`,
+  /// and the updates published by other nodes are applied to the `collab`. The comments published
+  /// by other nodes are forwarded to the `comment_sender`.
+  pub fn new(
+    object_id: &str,
+    collab: MutexCollab,
+    buffer_capacity: usize,
+    node_id: &str,
+    fanout: Arc<dyn CollabFanout>,
+    comment_sender: Sender<CommentMessage>,
+  ) -> Self {
     let object_id = object_id.to_owned();
+    let node_id = node_id.to_owned();
     // broadcast channel
     let (sender, _) = channel(buffer_capacity);
+    // Set to true while applying the update published by other nodes, which prevents the
+    // update from being published again.
+    let is_applying_remote = Arc::new(AtomicBool::new(false));
     let (doc_sub, awareness_sub) = {
       let mut mutex_collab = collab.lock();
 
       // Observer the document's update and broadcast it to all subscribers.
       let cloned_oid = object_id.clone();
+      let cloned_node_id = node_id.clone();
+      let cloned_fanout = fanout.clone();
+      let cloned_is_applying_remote = is_applying_remote.clone();
       let broadcast_sink = sender.clone();
       let doc_sub = mutex_collab
         .get_mut_awareness()
@@ -63,11 +89,21 @@ impl CollabBroadcast {
           if let Err(e) = broadcast_sink.send(msg.into()) {
             error!("broadcast sink fail: {}", e);
           }
+
+          if !cloned_is_applying_remote.load(Ordering::SeqCst) {
+            cloned_fanout.publish(CollabFanoutMessage {
+              node_id: cloned_node_id.clone(),
+              object_id: cloned_oid.clone(),
+              payload: CollabFanoutPayload::Update(event.update.clone()),
+            });
+          }
         })
         .unwrap();
 
       let broadcast_sink = sender.clone();
       let cloned_oid = object_id.clone();
+      let cloned_node_id = node_id.clone();
+      let cloned_fanout = fanout.clone();
 
       // Observer the awareness's update and broadcast it to all subscribers.
       let awareness_sub = mutex_collab
@@ -75,6 +111,12 @@ impl CollabBroadcast {
         .on_update(move |awareness, event| {
           if let Ok(awareness_update) = gen_awareness_update_message(awareness, event) {
             let payload = Message::Awareness(awareness_update).encode_v1();
+            cloned_fanout.publish(CollabFanoutMessage {
+              node_id: cloned_node_id.clone(),
+              object_id: cloned_oid.clone(),
+              payload: CollabFanoutPayload::Awareness(payload.clone()),
+            });
+
             let msg = CollabAwareness::new(cloned_oid.clone(), payload);
             if let Err(_e) = broadcast_sink.send(msg.into()) {
               trace!("Broadcast group is closed");
@@ -83,15 +125,36 @@ impl CollabBroadcast {
         });
       (doc_sub, awareness_sub)
     };
+
+    let (fanout_stop_tx, fanout_stop_rx) = oneshot::channel();
+    spawn_receive_fanout_messages(
+      &node_id,
+      &object_id,
+      collab.clone(),
+      sender.clone(),
+      is_applying_remote.clone(),
+      comment_sender,
+      fanout.subscribe(&object_id),
+      fanout_stop_rx,
+    );
+
     CollabBroadcast {
       object_id,
       collab,
       sender,
+      is_applying_remote,
       awareness_sub,
       doc_sub,
+      fanout_stop_tx,
     }
   }
 
+  /// Returns true if the collab is applying an update published by other nodes. Such an update
+  /// is persisted by the node that received it, so the other nodes must not persist it again.
+  pub fn is_applying_remote_update(&self) -> bool {
+    self.is_applying_remote.load(Ordering::SeqCst)
+  }
+
   /// Returns a reference to an underlying [MutexCollab] instance.
   pub fn collab(&self) -> &MutexCollab {
     &self.collab
@@ -273,6 +336,74 @@ impl Drop for Subscription {
   }
 }
 
+/// Applies the messages that were published by other nodes hosting the same object. The doc
+/// updates are applied to the collab, which will broadcast them to the local subscribers. The
+/// awareness messages are broadcast to the local subscribers directly, and the comments are
+/// forwarded to the `comment_sender`.
+#[allow(clippy::too_many_arguments)]
+fn spawn_receive_fanout_messages(
+  node_id: &str,
+  object_id: &str,
+  collab: MutexCollab,
+  sender: Sender<CollabMessage>,
+  is_applying_remote: Arc<AtomicBool>,
+  comment_sender: Sender<CommentMessage>,
+  mut fanout_rx: Receiver<CollabFanoutMessage>,
+  mut stop_rx: oneshot::Receiver<()>,
+) {
+  let node_id = node_id.to_owned();
+  let object_id = object_id.to_owned();
+  tokio::spawn(async move {
+    loop {
+      select! {
+        _ = &mut stop_rx => break,
+        result = fanout_rx.recv() => {
+          let message = match result {
+            Ok(message) => message,
+            Err(RecvError::Lagged(count)) => {
+              warn!("[realtime]: {} skipped {} fanout messages", object_id, count);
+              continue;
+            },
+            Err(RecvError::Closed) => break,
+          };
+
+          if message.node_id == node_id || message.object_id != object_id {
+            continue;
+          }
+
+          match message.payload {
+            CollabFanoutPayload::Update(update) => {
+              let mut lock = collab.lock();
+              is_applying_remote.store(true, Ordering::SeqCst);
+              let result = Update::decode_v1(&update)
+                .map_err(RealtimeError::from)
+                .and_then(|update| {
+                  let mut txn = lock.get_mut_awareness().doc_mut().transact_mut();
+                  txn.try_apply_update(update).map_err(RealtimeError::from)
+                });
+              is_applying_remote.store(false, Ordering::SeqCst);
+              if let Err(err) = result {
+                error!("[realtime]: fail to apply fanout update of {}: {}", object_id, err);
+              }
+            },
+            CollabFanoutPayload::Awareness(payload) => {
+              let msg = CollabAwareness::new(object_id.clone(), payload);
+              if let Err(_e) = sender.send(msg.into()) {
+                trace!("Broadcast group is closed");
+              }
+            },
+            CollabFanoutPayload::Comment(comment) => {
+              if let Err(_e) = comment_sender.send(comment) {
+                trace!("No receiver of the fanout comments");
+              }
+            },
+          }
+        },
+      }
+    }
+  });
+}
+
 /// Generates a message: Message::Sync::(SyncMessage::Update(update))
 #[inline]
 fn gen_update_message(update: &[u8]) -> Vec<u8> {
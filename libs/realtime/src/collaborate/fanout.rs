@@ -0,0 +1,81 @@
+use parking_lot::Mutex;
+use realtime_entity::comment::CommentMessage;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::sync::broadcast;
+use tracing::trace;
+
+/// A message that is relayed between the nodes hosting the same collab object.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollabFanoutMessage {
+  /// The id of the node that produced the message. A node ignores the messages produced by itself.
+  pub node_id: String,
+  pub object_id: String,
+  pub payload: CollabFanoutPayload,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum CollabFanoutPayload {
+  /// A yrs update (v1 encoding) of the collab object.
+  Update(Vec<u8>),
+  /// An encoded awareness message of the collab object.
+  Awareness(Vec<u8>),
+  /// A comment event of the collab object. It is delivered to the local subscribers of the
+  /// object without touching the collab.
+  Comment(CommentMessage),
+}
+
+/// [CollabFanout] relays the updates, awareness messages and comments of a collab object to every
+/// node that hosts a [CollabGroup](crate::collaborate::CollabGroup) of the same object.
+///
+/// Each [CollabBroadcast](crate::collaborate::CollabBroadcast) publishes the messages produced by
+/// its local subscribers and applies the messages published by other nodes.
+pub trait CollabFanout: Send + Sync + 'static {
+  /// Publishes the message to other nodes. It must not block, the implementation should queue
+  /// the message if it requires network IO.
+  fn publish(&self, message: CollabFanoutMessage);
+
+  /// Subscribes to the messages of the given object. The subscription ends when the returned
+  /// receiver is dropped.
+  fn subscribe(&self, object_id: &str) -> broadcast::Receiver<CollabFanoutMessage>;
+}
+
+/// An in-process [CollabFanout]. The messages are only delivered to the nodes (e.g. multiple
+/// [CollabServer](crate::collaborate::CollabServer)s) that share the same instance. It is the
+/// default fanout when the server runs as a single instance.
+#[derive(Default)]
+pub struct LocalCollabFanout {
+  sender_by_object_id: Mutex<HashMap<String, broadcast::Sender<CollabFanoutMessage>>>,
+}
+
+impl LocalCollabFanout {
+  pub fn new() -> Self {
+    Self::default()
+  }
+}
+
+impl CollabFanout for LocalCollabFanout {
+  fn publish(&self, message: CollabFanoutMessage) {
+    let mut sender_by_object_id = self.sender_by_object_id.lock();
+    if let Some(sender) = sender_by_object_id.get(&message.object_id) {
+      if sender.receiver_count() == 0 {
+        // All the subscribers of the object were dropped
+        sender_by_object_id.remove(&message.object_id);
+        return;
+      }
+
+      if let Err(err) = sender.send(message) {
+        trace!("fail to fanout collab message: {}", err);
+      }
+    }
+  }
+
+  fn subscribe(&self, object_id: &str) -> broadcast::Receiver<CollabFanoutMessage> {
+    self
+      .sender_by_object_id
+      .lock()
+      .entry(object_id.to_string())
+      .or_insert_with(|| broadcast::channel(1000).0)
+      .subscribe()
+  }
+}
@@ -1,23 +1,43 @@
-use crate::collaborate::{CollabAccessControl, CollabBroadcast, CollabStoragePlugin, Subscription};
+use crate::collaborate::{
+  CollabAccessControl, CollabBroadcast, CollabFanout, CollabFanoutMessage, CollabFanoutPayload,
+  CollabRealtimeMetrics, CollabStoragePlugin, Subscription,
+};
 use crate::entities::RealtimeUser;
+use crate::error::RealtimeError;
 use anyhow::Error;
-use collab::core::collab::MutexCollab;
+use collab::core::collab::{MutexCollab, TransactionMutExt};
 use collab::core::origin::CollabOrigin;
 use collab::preclude::Collab;
 use collab_entity::CollabType;
 use database::collab::CollabStorage;
+use realtime_entity::comment::CommentMessage;
 use std::collections::HashMap;
 
+use std::sync::atomic::{AtomicI64, Ordering};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Duration;
+use tokio::sync::{broadcast, RwLock};
 use tokio::task::spawn_blocking;
 
 use tracing::{error, event, trace, warn};
+use yrs::updates::decoder::Decode;
+use yrs::{Transact, Update};
 
 pub struct CollabGroupCache<S, U, AC> {
   group_by_object_id: Arc<RwLock<HashMap<String, Arc<CollabGroup<U>>>>>,
   storage: Arc<S>,
   access_control: Arc<AC>,
+  /// A group that has not been edited for this duration will be evicted in [CollabGroupCache::tick]
+  idle_timeout: Duration,
+  /// The groups that were evicted for inactivity while they still had subscribers. The group is
+  /// opened again by the next message of the subscribers.
+  inactive_groups: RwLock<HashMap<String, InactiveGroup>>,
+  metrics: Arc<CollabRealtimeMetrics>,
+  /// Identifies the current server instance when relaying messages via the [CollabFanout]
+  node_id: String,
+  fanout: Arc<dyn CollabFanout>,
+  /// Receives the comments published by other nodes for the groups opened by this node
+  remote_comment_tx: broadcast::Sender<CommentMessage>,
 }
 
 impl<S, U, AC> CollabGroupCache<S, U, AC>
@@ -26,19 +46,126 @@ where
   U: RealtimeUser,
   AC: CollabAccessControl,
 {
-  pub fn new(storage: Arc<S>, access_control: Arc<AC>) -> Self {
+  pub fn new(
+    storage: Arc<S>,
+    access_control: Arc<AC>,
+    idle_timeout: Duration,
+    metrics: Arc<CollabRealtimeMetrics>,
+    fanout: Arc<dyn CollabFanout>,
+  ) -> Self {
     Self {
       group_by_object_id: Arc::new(RwLock::new(HashMap::new())),
       storage,
       access_control,
+      idle_timeout,
+      inactive_groups: Default::default(),
+      metrics,
+      node_id: uuid::Uuid::new_v4().to_string(),
+      fanout,
+      remote_comment_tx: broadcast::channel(1000).0,
     }
   }
 
   /// Performs a periodic check to remove groups based on the following conditions:
   /// 1. Groups without any subscribers.
-  /// 2. Groups that have been inactive for a specified period of time.
-  pub async fn tick(&self) {
-    // TODO(nathan): Implement this.
+  /// 2. Groups that have been inactive for [CollabGroupCache::idle_timeout].
+  ///
+  /// The collab of each removed group is flushed to the storage before the group is dropped. The
+  /// subscribers of an inactive group are stopped, the client subscribes the group again by
+  /// sending the init sync message, or the group is opened again by the next message of the
+  /// client.
+  ///
+  /// Returns the object ids of the evicted groups that didn't have any subscriber.
+  pub async fn tick(&self) -> Vec<String> {
+    let now = chrono::Utc::now().timestamp();
+    let idle_timeout = self.idle_timeout.as_secs() as i64;
+
+    let mut evicted_groups = vec![];
+    for (object_id, group) in self.group_by_object_id.read().await.iter() {
+      if group.is_empty().await {
+        evicted_groups.push((object_id.clone(), GroupEvictReason::Empty));
+      } else if now - group.modified_at() >= idle_timeout {
+        evicted_groups.push((object_id.clone(), GroupEvictReason::Inactive));
+      }
+    }
+
+    if evicted_groups.is_empty() {
+      return vec![];
+    }
+
+    let evicted_groups = {
+      let mut group_by_object_id = self.group_by_object_id.write().await;
+      let mut groups = vec![];
+      for (object_id, reason) in evicted_groups {
+        // A user might subscribe or edit the group after the check above
+        let should_evict = match group_by_object_id.get(&object_id) {
+          Some(group) => match reason {
+            GroupEvictReason::Empty => group.is_empty().await,
+            GroupEvictReason::Inactive => now - group.modified_at() >= idle_timeout,
+          },
+          None => false,
+        };
+        if should_evict {
+          if let Some(group) = group_by_object_id.remove(&object_id) {
+            groups.push((object_id, group, reason));
+          }
+        }
+      }
+      self
+        .metrics
+        .opening_group_count
+        .set(group_by_object_id.len() as i64);
+      groups
+    };
+
+    let mut empty_object_ids = vec![];
+    for (object_id, group, reason) in evicted_groups {
+      event!(
+        tracing::Level::INFO,
+        "Evict group: {}, reason: {:?}",
+        object_id,
+        reason
+      );
+      group.flush_collab();
+      match reason {
+        GroupEvictReason::Empty => {
+          self.metrics.evicted_empty_group_count.inc();
+          empty_object_ids.push(object_id);
+        },
+        GroupEvictReason::Inactive => {
+          for (_, subscriber) in group.subscribers.write().await.drain() {
+            subscriber.stop().await;
+          }
+          self.inactive_groups.write().await.insert(
+            object_id,
+            InactiveGroup {
+              workspace_id: group.workspace_id.clone(),
+              collab_type: group.collab_type.clone(),
+            },
+          );
+          self.metrics.evicted_inactive_group_count.inc();
+        },
+      }
+    }
+    empty_object_ids
+  }
+
+  /// Returns the group that was evicted for inactivity, so it can be opened again for the
+  /// subscribers that didn't send the init sync message.
+  pub async fn take_inactive_group(&self, object_id: &str) -> Option<InactiveGroup> {
+    self.inactive_groups.write().await.remove(object_id)
+  }
+
+  /// Forgets the inactive groups that are no longer opened by any client.
+  pub async fn retain_inactive_groups<F>(&self, f: F)
+  where
+    F: Fn(&str) -> bool,
+  {
+    self
+      .inactive_groups
+      .write()
+      .await
+      .retain(|object_id, _| f(object_id));
   }
 
   pub async fn contains_user(&self, object_id: &str, user: &U) -> Result<bool, Error> {
@@ -69,36 +196,98 @@ where
     self.group_by_object_id.read().await.get(object_id).cloned()
   }
 
+  /// Applies the update to the group of the given object. The update is broadcast to the
+  /// subscribers of the group and persisted by the [CollabStoragePlugin].
+  ///
+  /// When the group is not opened by this server, the update is only published to other nodes
+  /// and false is returned.
+  pub async fn apply_update(
+    &self,
+    object_id: &str,
+    update: Vec<u8>,
+  ) -> Result<bool, RealtimeError> {
+    match self.get_group(object_id).await {
+      Some(group) => {
+        group.apply_update(&update)?;
+        Ok(true)
+      },
+      None => {
+        self.fanout.publish(CollabFanoutMessage {
+          node_id: self.node_id.clone(),
+          object_id: object_id.to_string(),
+          payload: CollabFanoutPayload::Update(update),
+        });
+        Ok(false)
+      },
+    }
+  }
+
+  /// Publishes the comment to other nodes. The comment is delivered to the subscribers of the
+  /// object on the nodes that opened the group of the object.
+  pub fn publish_comment(&self, message: CommentMessage) {
+    self.fanout.publish(CollabFanoutMessage {
+      node_id: self.node_id.clone(),
+      object_id: message.object_id.clone(),
+      payload: CollabFanoutPayload::Comment(message),
+    });
+  }
+
+  /// Subscribes to the comments published by other nodes for the groups opened by this node.
+  pub fn subscribe_remote_comments(&self) -> broadcast::Receiver<CommentMessage> {
+    self.remote_comment_tx.subscribe()
+  }
+
   pub async fn remove_group(&self, object_id: &str) {
     match self.group_by_object_id.try_write() {
       Ok(mut group_by_object_id) => {
         group_by_object_id.remove(object_id);
+        self
+          .metrics
+          .opening_group_count
+          .set(group_by_object_id.len() as i64);
       },
       Err(err) => error!("Failed to acquire write lock to remove group: {:?}", err),
     }
   }
 
+  /// Creates the group of the object. The objects in the trash can't be opened until they are
+  /// restored.
   pub async fn create_group(
     &self,
     uid: i64,
     workspace_id: &str,
     object_id: &str,
     collab_type: CollabType,
-  ) {
+  ) -> Result<(), RealtimeError> {
+    let is_trashed = self
+      .storage
+      .is_collab_trashed(object_id)
+      .await
+      .map_err(|err| RealtimeError::Internal(err.into()))?;
+    if is_trashed {
+      return Err(RealtimeError::CollabTrashed(object_id.to_string()));
+    }
+    self.inactive_groups.write().await.remove(object_id);
+
     match self.group_by_object_id.try_write() {
       Ok(mut group_by_object_id) => {
         if group_by_object_id.contains_key(object_id) {
           warn!("Group for object_id:{} already exists", object_id);
-          return;
+          return Ok(());
         }
 
         let group = self
           .init_group(uid, workspace_id, object_id, collab_type)
           .await;
         group_by_object_id.insert(object_id.to_string(), group);
+        self
+          .metrics
+          .opening_group_count
+          .set(group_by_object_id.len() as i64);
       },
       Err(err) => error!("Failed to acquire write lock to create group: {:?}", err),
     }
+    Ok(())
   }
 
   #[tracing::instrument(skip(self))]
@@ -115,14 +304,24 @@ where
       object_id
     );
     let collab = MutexCollab::new(CollabOrigin::Server, object_id, vec![]);
-    let broadcast = CollabBroadcast::new(object_id, collab.clone(), 10);
+    let broadcast = CollabBroadcast::new(
+      object_id,
+      collab.clone(),
+      10,
+      &self.node_id,
+      self.fanout.clone(),
+      self.remote_comment_tx.clone(),
+    );
     let collab = Arc::new(collab.clone());
 
     // The lifecycle of the collab is managed by the group.
     let group = Arc::new(CollabGroup {
+      workspace_id: workspace_id.to_string(),
+      collab_type: collab_type.clone(),
       collab: collab.clone(),
       broadcast,
       subscribers: Default::default(),
+      modified_at: AtomicI64::new(chrono::Utc::now().timestamp()),
     });
 
     let plugin = CollabStoragePlugin::new(
@@ -144,8 +343,24 @@ where
   }
 }
 
+/// The group that was evicted by [CollabGroupCache::tick] for inactivity.
+pub struct InactiveGroup {
+  pub workspace_id: String,
+  pub collab_type: CollabType,
+}
+
+#[derive(Debug)]
+enum GroupEvictReason {
+  /// The group doesn't have any subscriber
+  Empty,
+  /// The group has not been edited for the idle timeout
+  Inactive,
+}
+
 /// A group used to manage a single [Collab] object
 pub struct CollabGroup<U> {
+  pub workspace_id: String,
+  pub collab_type: CollabType,
   pub collab: Arc<MutexCollab>,
 
   /// A broadcast used to propagate updates produced by yrs [yrs::Doc] and [Awareness]
@@ -155,6 +370,9 @@ pub struct CollabGroup<U> {
   /// A list of subscribers to this group. Each subscriber will receive updates from the
   /// broadcast.
   pub subscribers: RwLock<HashMap<U, Subscription>>,
+
+  /// The timestamp (in seconds) of the last update applied to the [Collab].
+  modified_at: AtomicI64,
 }
 
 impl<U> CollabGroup<U>
@@ -170,6 +388,28 @@ where
     f(&collab);
   }
 
+  /// Record that the [Collab] was just modified. Called by the [CollabStoragePlugin] when
+  /// receiving an update.
+  pub fn did_modify(&self) {
+    self
+      .modified_at
+      .store(chrono::Utc::now().timestamp(), Ordering::SeqCst);
+  }
+
+  pub fn modified_at(&self) -> i64 {
+    self.modified_at.load(Ordering::SeqCst)
+  }
+
+  /// Applies an update that was not sent by the subscribers. The update is broadcast to all
+  /// the subscribers.
+  pub fn apply_update(&self, update: &[u8]) -> Result<(), RealtimeError> {
+    let update = Update::decode_v1(update)?;
+    let mut lock = self.collab.lock();
+    let mut txn = lock.get_mut_awareness().doc_mut().transact_mut();
+    txn.try_apply_update(update)?;
+    Ok(())
+  }
+
   pub async fn is_empty(&self) -> bool {
     self.subscribers.read().await.is_empty()
   }
@@ -183,3 +423,140 @@ where
     });
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::collaborate::test_util::{
+    collab_value, test_update, wait_until, TestAccessControl, TestCollabStorage, TestUser,
+  };
+  use crate::collaborate::LocalCollabFanout;
+  use futures_util::{sink, stream};
+  use realtime_entity::collab_msg::CollabMessage;
+
+  type TestGroupCache = CollabGroupCache<TestCollabStorage, TestUser, TestAccessControl>;
+
+  fn test_group_cache(
+    idle_timeout: Duration,
+  ) -> (
+    TestGroupCache,
+    Arc<TestCollabStorage>,
+    Arc<CollabRealtimeMetrics>,
+  ) {
+    let storage = Arc::new(TestCollabStorage::default());
+    let metrics = Arc::new(CollabRealtimeMetrics::default());
+    let groups = CollabGroupCache::new(
+      storage.clone(),
+      Arc::new(TestAccessControl),
+      idle_timeout,
+      metrics.clone(),
+      Arc::new(LocalCollabFanout::new()),
+    );
+    (groups, storage, metrics)
+  }
+
+  async fn subscribe(group: &CollabGroup<TestUser>, user: &TestUser) {
+    let subscription = group.broadcast.subscribe(
+      CollabOrigin::Empty,
+      sink::drain(),
+      stream::pending::<Result<CollabMessage, anyhow::Error>>(),
+    );
+    group
+      .subscribers
+      .write()
+      .await
+      .insert(user.clone(), subscription);
+  }
+
+  #[tokio::test]
+  async fn keep_group_with_subscriber_test() {
+    let (groups, _storage, metrics) = test_group_cache(Duration::from_secs(600));
+    let user = TestUser::new(1);
+    groups
+      .create_group(user.uid, "w1", "o1", CollabType::Document)
+      .await
+      .unwrap();
+    let group = groups.get_group("o1").await.unwrap();
+    subscribe(&group, &user).await;
+
+    for _ in 0..3 {
+      assert!(groups.tick().await.is_empty());
+    }
+    assert!(groups.contains_group("o1").await.unwrap());
+    assert!(groups.contains_user("o1", &user).await.unwrap());
+    assert_eq!(metrics.evicted_empty_group_count.get(), 0);
+    assert_eq!(metrics.opening_group_count.get(), 1);
+    groups.remove_user("o1", &user).await;
+  }
+
+  #[tokio::test]
+  async fn evict_group_without_subscriber_test() {
+    let (groups, storage, metrics) = test_group_cache(Duration::from_secs(600));
+    let user = TestUser::new(1);
+    groups
+      .create_group(user.uid, "w1", "o1", CollabType::Document)
+      .await
+      .unwrap();
+    groups
+      .create_group(user.uid, "w1", "o2", CollabType::Document)
+      .await
+      .unwrap();
+    subscribe(&groups.get_group("o2").await.unwrap(), &user).await;
+    groups
+      .apply_update("o1", test_update("name", "hello"))
+      .await
+      .unwrap();
+    groups.remove_user("o1", &user).await;
+    assert_eq!(groups.tick().await, vec!["o1".to_string()]);
+    assert!(!groups.contains_group("o1").await.unwrap());
+    assert!(groups.contains_group("o2").await.unwrap());
+    assert_eq!(metrics.evicted_empty_group_count.get(), 1);
+    assert_eq!(metrics.opening_group_count.get(), 1);
+
+    // The evicted collab is flushed to the storage
+    wait_until(|| {
+      collab_value(&storage.collab("o1").unwrap(), "name") == Some("hello".to_string())
+    })
+    .await;
+
+    // The group is evicted after its last subscriber leaves
+    groups.remove_user("o2", &user).await;
+    assert_eq!(groups.tick().await, vec!["o2".to_string()]);
+    assert_eq!(metrics.evicted_empty_group_count.get(), 2);
+    assert_eq!(metrics.opening_group_count.get(), 0);
+  }
+
+  #[tokio::test]
+  async fn evict_inactive_group_test() {
+    let (groups, storage, metrics) = test_group_cache(Duration::ZERO);
+    let user = TestUser::new(1);
+    groups
+      .create_group(user.uid, "w1", "o1", CollabType::Document)
+      .await
+      .unwrap();
+    let group = groups.get_group("o1").await.unwrap();
+    subscribe(&group, &user).await;
+    groups
+      .apply_update("o1", test_update("name", "hello"))
+      .await
+      .unwrap();
+
+    // The group is evicted even though it has a subscriber, and its subscribers are stopped
+    assert!(groups.tick().await.is_empty());
+    assert!(!groups.contains_group("o1").await.unwrap());
+    assert!(group.is_empty().await);
+    assert_eq!(metrics.evicted_inactive_group_count.get(), 1);
+    assert_eq!(metrics.evicted_empty_group_count.get(), 0);
+    assert_eq!(metrics.opening_group_count.get(), 0);
+    wait_until(|| {
+      collab_value(&storage.collab("o1").unwrap(), "name") == Some("hello".to_string())
+    })
+    .await;
+
+    // The group can be opened again for the stopped subscribers
+    let inactive_group = groups.take_inactive_group("o1").await.unwrap();
+    assert_eq!(inactive_group.workspace_id, "w1");
+    assert_eq!(inactive_group.collab_type, CollabType::Document);
+    assert!(groups.take_inactive_group("o1").await.is_none());
+  }
+}
@@ -0,0 +1,36 @@
+use prometheus_client::metrics::counter::Counter;
+use prometheus_client::metrics::gauge::Gauge;
+use prometheus_client::registry::Registry;
+
+/// Metrics of the realtime collab server. The metrics are registered into the application's
+/// registry and exposed by the `/metrics` endpoint.
+#[derive(Clone, Default)]
+pub struct CollabRealtimeMetrics {
+  /// Number of groups that were removed because they didn't have any subscriber.
+  pub(crate) evicted_empty_group_count: Counter,
+  /// Number of groups that were removed because they were not edited for a while.
+  pub(crate) evicted_inactive_group_count: Counter,
+  /// Number of groups that are currently kept in memory.
+  pub(crate) opening_group_count: Gauge,
+}
+
+impl CollabRealtimeMetrics {
+  pub fn register(&self, registry: &mut Registry) {
+    let realtime_registry = registry.sub_registry_with_prefix("realtime");
+    realtime_registry.register(
+      "evicted_empty_group_count",
+      "number of collab groups evicted because there is no subscriber",
+      self.evicted_empty_group_count.clone(),
+    );
+    realtime_registry.register(
+      "evicted_inactive_group_count",
+      "number of collab groups evicted because there is no edit for a period of time",
+      self.evicted_inactive_group_count.clone(),
+    );
+    realtime_registry.register(
+      "opening_group_count",
+      "number of collab groups kept in memory",
+      self.opening_group_count.clone(),
+    );
+  }
+}
@@ -1,12 +1,18 @@
 mod broadcast;
+mod fanout;
 mod group;
+mod metrics;
 mod permission;
 mod plugin;
 mod retry;
 mod server;
 mod sync_protocol;
+#[cfg(test)]
+mod test_util;
 
 pub use broadcast::*;
+pub use fanout::*;
+pub use metrics::*;
 pub use permission::*;
 pub use plugin::*;
 pub use server::*;
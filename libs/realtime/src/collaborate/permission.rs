@@ -63,6 +63,10 @@ pub trait CollabAccessControl: Sync + Send + 'static {
   ///
   /// The user can recv the message if the user is the member of the collab object
   async fn can_receive_collab_update(&self, uid: &i64, oid: &str) -> Result<bool, AppError>;
+
+  /// Called after the websocket connection of the user is closed, e.g. to end the session of a
+  /// share link.
+  async fn did_disconnect(&self, _uid: &i64) {}
 }
 //
 #[async_trait]
@@ -109,4 +113,8 @@ where
   async fn can_receive_collab_update(&self, uid: &i64, oid: &str) -> Result<bool, AppError> {
     self.as_ref().can_receive_collab_update(uid, oid).await
   }
+
+  async fn did_disconnect(&self, uid: &i64) {
+    self.as_ref().did_disconnect(uid).await
+  }
 }
@@ -11,13 +11,17 @@ use collab::core::origin::CollabOrigin;
 use collab::preclude::{CollabPlugin, Doc, TransactionMut};
 use collab::sync_protocol::awareness::Awareness;
 use collab_entity::CollabType;
-use database::collab::CollabStorage;
-use database_entity::dto::{AFAccessLevel, InsertCollabParams, QueryCollabParams};
+use database::collab::{CollabStorage, WriteConfig};
+use database_entity::dto::{
+  AFAccessLevel, InsertCollabParams, InsertCollabUpdateParams, InsertSnapshotParams,
+  QueryCollabParams,
+};
 use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU32, Ordering};
 use std::sync::{Arc, Weak};
-use std::time::Duration;
-use tokio::time::interval;
-use tracing::{error, info, trace};
+use tokio::sync::mpsc;
+use tokio_retry::strategy::FixedInterval;
+use tokio_retry::Retry;
+use tracing::{error, info, trace, warn};
 use yrs::updates::decoder::Decode;
 use yrs::updates::encoder::Encode;
 use yrs::{ReadTxn, StateVector, Transact, Update};
@@ -30,6 +34,7 @@ pub struct CollabStoragePlugin<S, U, AC> {
   group: Weak<CollabGroup<U>>,
   collab_type: CollabType,
   access_control: Arc<AC>,
+  update_tx: mpsc::UnboundedSender<InsertCollabUpdateParams>,
 }
 
 impl<S, U, AC> CollabStoragePlugin<S, U, AC>
@@ -48,7 +53,8 @@ where
     let storage = Arc::new(storage);
     let workspace_id = workspace_id.to_string();
     let edit_state = Arc::new(CollabEditState::new());
-    let plugin = Self {
+    let update_tx = spawn_save_updates(uid, storage.clone(), group.clone());
+    Self {
       uid,
       workspace_id,
       storage,
@@ -56,46 +62,78 @@ where
       group,
       collab_type,
       access_control,
-    };
-    spawn_period_check(&plugin);
-    plugin
+      update_tx,
+    }
+  }
+
+  /// Saves the encoded collab as a snapshot. The outdated snapshots are pruned by the storage.
+  fn create_snapshot(&self, object_id: &str, encoded_collab: &EncodedCollabV1) {
+    self.edit_state.did_create_snapshot();
+    match encoded_collab.encode_to_bytes() {
+      Ok(raw_data) => {
+        let params = InsertSnapshotParams {
+          object_id: object_id.to_string(),
+          len: raw_data.len() as i32,
+          raw_data,
+          workspace_id: self.workspace_id.clone(),
+        };
+        let storage = self.storage.clone();
+        tokio::spawn(async move {
+          let object_id = params.object_id.clone();
+          match storage.create_snapshot(params).await {
+            Ok(_) => trace!("[realtime] did create snapshot of collab: {}", object_id),
+            Err(err) => error!("create snapshot of collab:{} failed: {:?}", object_id, err),
+          }
+        });
+      },
+      Err(err) => error!("fail to encode EncodedCollabV1 to bytes: {:?}", err),
+    }
   }
 }
 
-/// Spawns an asynchronous task to periodically check and perform flush operations for collaboration groups.
-///
-/// This function sets up a loop that, at regular intervals, checks if a flush operation is needed
-/// based on edit count and time interval. If so, it triggers the flush operation.
+/// Returns the retry strategy used to save the updates and flush the collab.
+#[inline]
+fn save_retry_strategy() -> std::iter::Take<FixedInterval> {
+  FixedInterval::from_millis(500).take(3)
+}
+
+/// Spawns a task that appends the updates of the collab to the update log in the order they
+/// were received. The task stops when the plugin is dropped.
 ///
-fn spawn_period_check<S, U, AC>(plugin: &CollabStoragePlugin<S, U, AC>)
+/// A failed save is retried. If it still fails, the whole collab is flushed instead, the flushed
+/// state contains the update that couldn't be saved.
+fn spawn_save_updates<S, U>(
+  uid: i64,
+  storage: Arc<S>,
+  group: Weak<CollabGroup<U>>,
+) -> mpsc::UnboundedSender<InsertCollabUpdateParams>
 where
   S: CollabStorage,
   U: RealtimeUser,
 {
-  let weak_edit_state = Arc::downgrade(&plugin.edit_state);
-  let weak_storage = Arc::downgrade(&plugin.storage);
-  let weak_group = plugin.group.clone();
+  let (tx, mut rx) = mpsc::unbounded_channel::<InsertCollabUpdateParams>();
   tokio::spawn(async move {
-    let mut interval = interval(Duration::from_secs(30));
-    loop {
-      interval.tick().await;
-      match (
-        weak_group.upgrade(),
-        weak_storage.upgrade(),
-        weak_edit_state.upgrade(),
-      ) {
-        (Some(group), Some(storage), Some(edit_state)) => {
-          let max_edit_count = storage.config().flush_per_update;
-          let max_interval = storage.config().flush_per_seconds as i64;
-          if edit_state.should_flush(max_edit_count, max_interval) {
-            edit_state.flush_edit();
-            group.flush_collab();
-          }
-        },
-        _ => break,
+    while let Some(params) = rx.recv().await {
+      let object_id = params.object_id.clone();
+      let result = Retry::spawn(save_retry_strategy(), || {
+        storage.insert_collab_update(&uid, params.clone())
+      })
+      .await;
+
+      if let Err(err) = result {
+        error!(
+          "save update of collab:{} failed: {:?}, flush the whole collab instead",
+          object_id, err
+        );
+        match group.upgrade() {
+          Some(group) => group.flush_collab(),
+          // The collab was flushed when the group was closed
+          None => warn!("the group of collab:{} was closed", object_id),
+        }
       }
     }
   });
+  tx
 }
 
 async fn init_collab_with_raw_data(
@@ -179,23 +217,53 @@ where
     self.edit_state.set_did_load()
   }
 
-  fn receive_update(&self, _object_id: &str, _txn: &TransactionMut, _update: &[u8]) {
-    let count = self.edit_state.increment_edit_count();
+  fn receive_update(&self, object_id: &str, txn: &TransactionMut, update: &[u8]) {
+    self.edit_state.increment_edit_count();
     if !self.edit_state.did_load() {
       return;
     }
 
-    if count >= self.storage.config().flush_per_update {
-      self.edit_state.flush_edit();
-      trace!("number of updates reach flush_per_update, start flushing");
-      match self.group.upgrade() {
-        None => error!("🔴Group is dropped, skip flush collab"),
-        Some(group) => group.flush_collab(),
+    // The updates relayed from other nodes were already persisted by the node that received them
+    let group = self.group.upgrade();
+    if let Some(group) = &group {
+      group.did_modify();
+    }
+    let is_remote_update = group
+      .map(|group| group.broadcast.is_applying_remote_update())
+      .unwrap_or(false);
+    if !is_remote_update {
+      // Instead of encoding the whole document, only the update is persisted. The updates will
+      // be merged into the collab by the storage.
+      let params = InsertCollabUpdateParams {
+        object_id: object_id.to_string(),
+        update: update.to_vec(),
+        workspace_id: self.workspace_id.clone(),
+        collab_type: self.collab_type.clone(),
+      };
+      if let Err(err) = self.update_tx.send(params) {
+        error!("🔴fail to send collab update: {}", err);
       }
     }
+
+    self.edit_state.increment_snapshot_edit_count();
+    if self
+      .edit_state
+      .should_create_snapshot(self.storage.config())
+    {
+      let encoded_collab = EncodedCollabV1::new(
+        txn.state_vector().encode_v1(),
+        txn.encode_state_as_update_v1(&StateVector::default()),
+      );
+      self.create_snapshot(object_id, &encoded_collab);
+    }
   }
 
   fn flush(&self, object_id: &str, data: &EncodedCollabV1) {
+    // Keep the latest edits in the version history before the collab is closed
+    if self.edit_state.has_snapshot_edit() {
+      self.create_snapshot(object_id, data);
+    }
+
     let storage = self.storage.clone();
     match data.encode_to_bytes() {
       Ok(encoded_collab_v1) => {
@@ -216,9 +284,13 @@ where
         let uid = self.uid;
         tokio::spawn(async move {
           let object_id = params.object_id.clone();
-          match storage.insert_collab(&uid, params).await {
+          let result = Retry::spawn(save_retry_strategy(), || {
+            storage.insert_collab(&uid, params.clone())
+          })
+          .await;
+          match result {
             Ok(_) => info!("[realtime] end flushing collab: {}", object_id),
-            Err(err) => error!("save collab failed: {:?}", err),
+            Err(err) => error!("save collab:{} failed: {:?}", object_id, err),
           }
         });
       },
@@ -231,18 +303,20 @@ where
 
 struct CollabEditState {
   edit_count: AtomicU32,
-  flush_edit_count: AtomicU32,
-  flush_interval: AtomicI64,
   did_load_collab: AtomicBool,
+  /// The number of edits since the latest snapshot
+  snapshot_edit_count: AtomicU32,
+  /// The timestamp (in seconds) of the latest snapshot
+  snapshot_at: AtomicI64,
 }
 
 impl CollabEditState {
   fn new() -> Self {
     Self {
       edit_count: AtomicU32::new(0),
-      flush_edit_count: Default::default(),
-      flush_interval: AtomicI64::new(chrono::Utc::now().timestamp()),
       did_load_collab: AtomicBool::new(false),
+      snapshot_edit_count: AtomicU32::new(0),
+      snapshot_at: AtomicI64::new(chrono::Utc::now().timestamp()),
     }
   }
 
@@ -258,43 +332,94 @@ impl CollabEditState {
     self.edit_count.fetch_add(1, Ordering::SeqCst)
   }
 
-  fn flush_edit(&self) {
-    self
-      .flush_edit_count
-      .store(self.edit_count.load(Ordering::SeqCst), Ordering::SeqCst);
+  fn increment_snapshot_edit_count(&self) {
+    self.snapshot_edit_count.fetch_add(1, Ordering::SeqCst);
+  }
+
+  fn has_snapshot_edit(&self) -> bool {
+    self.snapshot_edit_count.load(Ordering::SeqCst) > 0
+  }
+
+  fn should_create_snapshot(&self, config: &WriteConfig) -> bool {
+    let edit_count = self.snapshot_edit_count.load(Ordering::SeqCst);
+    if edit_count == 0 {
+      return false;
+    }
+    let elapsed_secs = chrono::Utc::now().timestamp() - self.snapshot_at.load(Ordering::SeqCst);
+    edit_count >= config.snapshot_per_update || elapsed_secs >= config.snapshot_per_seconds as i64
+  }
+
+  fn did_create_snapshot(&self) {
+    self.snapshot_edit_count.store(0, Ordering::SeqCst);
     self
-      .flush_interval
+      .snapshot_at
       .store(chrono::Utc::now().timestamp(), Ordering::SeqCst);
   }
+}
 
-  /// Determines whether a flush operation should be performed based on edit count and time interval.
-  ///
-  /// This function checks two conditions to decide if flushing is necessary:
-  /// 1. Time-based: Compares the current time with the last flush time. A flush is needed if the time
-  /// elapsed since the last flush is greater than or equal to `max_interval`.
-  ///
-  /// 2. Edit count-based: Compares the current edit count with the last flush edit count. A flush is
-  /// required if the number of new edits since the last flush is greater than or equal to `max_edit_count`.
-  ///
-  /// # Arguments
-  /// * `max_edit_count` - The maximum number of edits allowed before a flush is triggered.
-  /// * `max_interval` - The maximum time interval (in seconds) allowed before a flush is triggered.
-  fn should_flush(&self, max_edit_count: u32, max_interval: i64) -> bool {
-    // compare current time with last flush time
-    let current = chrono::Utc::now().timestamp();
-    let prev = self.flush_interval.load(Ordering::SeqCst);
-    let current_edit_count = self.edit_count.load(Ordering::SeqCst);
-    let prev_flush_edit_count = self.flush_edit_count.load(Ordering::SeqCst);
-
-    if current > prev && current_edit_count != prev_flush_edit_count {
-      return current - prev >= max_interval;
-    }
+#[cfg(test)]
+mod tests {
+  use crate::collaborate::group::CollabGroupCache;
+  use crate::collaborate::test_util::{
+    collab_value, test_update, wait_until, TestAccessControl, TestCollabStorage, TestUser,
+  };
+  use crate::collaborate::{CollabRealtimeMetrics, LocalCollabFanout};
+  use collab_entity::CollabType;
+  use std::sync::Arc;
+  use std::time::Duration;
 
-    // compare current edit count with last flush edit count
-    if current_edit_count > prev_flush_edit_count {
-      return current_edit_count - prev_flush_edit_count >= max_edit_count;
-    }
+  async fn open_group(
+    storage: Arc<TestCollabStorage>,
+    object_id: &str,
+  ) -> CollabGroupCache<TestCollabStorage, TestUser, TestAccessControl> {
+    let groups = CollabGroupCache::new(
+      storage,
+      Arc::new(TestAccessControl),
+      Duration::from_secs(600),
+      Arc::new(CollabRealtimeMetrics::default()),
+      Arc::new(LocalCollabFanout::new()),
+    );
+    groups
+      .create_group(1, "w1", object_id, CollabType::Document)
+      .await
+      .unwrap();
+    groups
+  }
+
+  #[tokio::test]
+  async fn save_updates_to_update_log_test() {
+    let storage = Arc::new(TestCollabStorage::default());
+    let groups = open_group(storage.clone(), "o1").await;
+    let created_collab = storage.collab("o1").unwrap();
+
+    groups
+      .apply_update("o1", test_update("name", "hello"))
+      .await
+      .unwrap();
+    groups
+      .apply_update("o1", test_update("title", "world"))
+      .await
+      .unwrap();
+    wait_until(|| storage.updates("o1").len() == 2).await;
+
+    // Only the updates are saved, the collab is not rewritten
+    assert_eq!(storage.collab("o1").unwrap(), created_collab);
+  }
+
+  #[tokio::test]
+  async fn flush_collab_when_saving_update_failed_test() {
+    let storage = Arc::new(TestCollabStorage::default());
+    let groups = open_group(storage.clone(), "o1").await;
 
-    false
+    storage.set_fail_updates(true);
+    groups
+      .apply_update("o1", test_update("name", "hello"))
+      .await
+      .unwrap();
+    wait_until(|| {
+      collab_value(&storage.collab("o1").unwrap(), "name") == Some("hello".to_string())
+    })
+    .await;
+    assert!(storage.updates("o1").is_empty());
   }
 }
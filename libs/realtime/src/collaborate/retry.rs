@@ -2,6 +2,7 @@ use crate::collaborate::CollabClientStream;
 
 use anyhow::{anyhow, Error};
 use collab::core::origin::CollabOrigin;
+use collab::sync_protocol::message::{Message, MessageReader, SyncMessage};
 use database::collab::CollabStorage;
 use futures_util::SinkExt;
 use parking_lot::Mutex;
@@ -15,6 +16,7 @@ use std::pin::Pin;
 use std::sync::{Arc, Weak};
 use std::time::Duration;
 use tokio::sync::RwLock;
+use yrs::updates::decoder::DecoderV1;
 
 use crate::entities::{Editing, RealtimeUser};
 use tokio_retry::strategy::FixedInterval;
@@ -80,25 +82,38 @@ where
         // When create a group, the message must be the init sync message.
         match collab_message {
           CollabMessage::ClientInitSync(client_init) => {
-            let uid = client_init
-              .origin
-              .client_user_id()
-              .ok_or(RealtimeError::UnexpectedData("The client user id is empty"))?;
-
+            // The group is created with the uid of the connection instead of the uid in the
+            // origin of the message, which is provided by the client. The uid is used to save
+            // the collab, so it must be the uid of a user.
             self
               .groups
               .create_group(
-                uid,
+                user.storage_uid(),
                 &client_init.workspace_id,
                 object_id,
                 client_init.collab_type.clone(),
               )
-              .await;
+              .await?;
           },
-          _ => {
-            return Err(RealtimeError::UnexpectedData(
-              "The first message must be init sync message",
-            ));
+          // The group was evicted for inactivity while the client kept it open, so it's opened
+          // again without waiting for the init sync message of the client
+          _ => match self.groups.take_inactive_group(object_id).await {
+            Some(inactive_group) => {
+              self
+                .groups
+                .create_group(
+                  user.storage_uid(),
+                  &inactive_group.workspace_id,
+                  object_id,
+                  inactive_group.collab_type,
+                )
+                .await?;
+            },
+            None => {
+              return Err(RealtimeError::UnexpectedData(
+                "The first message must be init sync message",
+              ));
+            },
           },
         }
       }
@@ -196,7 +211,13 @@ where
                     return Box::pin(future::ready(false));
                   }
 
-                  let is_init = msg.is_client_init();
+                  // The init sync message of a client that has local changes carries the
+                  // updates, so it requires the same permission as the update message.
+                  let is_init = msg.is_client_init()
+                    && !msg
+                      .payload()
+                      .map(|payload| carries_update(payload))
+                      .unwrap_or(false);
                   let object_id = object_id.to_string();
                   let cloned_stream_permission_service = stream_permission_service.clone();
 
@@ -260,10 +281,24 @@ where
   }
 }
 
+/// Returns true if the payload contains the updates of the document. A payload that can't be
+/// decoded is treated as an update.
+fn carries_update(payload: &[u8]) -> bool {
+  let mut decoder = DecoderV1::from(payload);
+  MessageReader::new(&mut decoder).any(|msg| {
+    matches!(
+      msg,
+      Ok(Message::Sync(
+        SyncMessage::SyncStep2(_) | SyncMessage::Update(_)
+      )) | Err(_)
+    )
+  })
+}
+
 pub struct SubscribeGroupCondition<U>(pub Weak<RwLock<HashMap<U, CollabClientStream>>>);
 impl<U> Condition<RealtimeError> for SubscribeGroupCondition<U> {
-  fn should_retry(&mut self, _error: &RealtimeError) -> bool {
-    self.0.upgrade().is_some()
+  fn should_retry(&mut self, error: &RealtimeError) -> bool {
+    !matches!(error, RealtimeError::CollabTrashed(_)) && self.0.upgrade().is_some()
   }
 }
 
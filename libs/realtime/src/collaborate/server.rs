@@ -1,4 +1,7 @@
-use crate::entities::{ClientMessage, Connect, Disconnect, Editing, RealtimeMessage, RealtimeUser};
+use crate::entities::{
+  ApplyCollabUpdate, BroadcastComment, ClientMessage, Connect, Disconnect, Editing,
+  RealtimeMessage, RealtimeUser,
+};
 use crate::error::{RealtimeError, StreamError};
 use anyhow::Result;
 
@@ -6,11 +9,13 @@ use actix::{Actor, Context, Handler, ResponseFuture};
 use futures_util::future::BoxFuture;
 use parking_lot::Mutex;
 use realtime_entity::collab_msg::CollabMessage;
+use realtime_entity::comment::CommentMessage;
 use std::collections::{HashMap, HashSet};
 
 use std::sync::Arc;
 use std::time::Duration;
 
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::RwLock;
 use tokio::time::interval;
 
@@ -19,7 +24,9 @@ use tokio_stream::StreamExt;
 use tracing::{error, event, info, instrument, trace};
 
 use crate::client::ClientWSSink;
+use crate::collaborate::fanout::CollabFanout;
 use crate::collaborate::group::CollabGroupCache;
+use crate::collaborate::metrics::CollabRealtimeMetrics;
 use crate::collaborate::permission::CollabAccessControl;
 use crate::collaborate::retry::{CollabUserMessage, SubscribeGroupIfNeed};
 use crate::util::channel_ext::UnboundedSenderSink;
@@ -44,23 +51,84 @@ where
   U: RealtimeUser,
   AC: CollabAccessControl,
 {
-  pub fn new(storage: Arc<S>, access_control: AC) -> Result<Self, RealtimeError> {
+  /// Create a new [CollabServer].
+  ///
+  /// The `group_idle_timeout` is the duration after which a group without any edit is evicted
+  /// from memory. Empty groups are evicted regardless of the timeout.
+  ///
+  /// The `fanout` relays the updates and comments of the groups to other server instances hosting
+  /// the same collab objects. Use [LocalCollabFanout](crate::collaborate::LocalCollabFanout) when
+  /// running a single instance.
+  pub fn new(
+    storage: Arc<S>,
+    access_control: AC,
+    group_idle_timeout: Duration,
+    metrics: Arc<CollabRealtimeMetrics>,
+    fanout: Arc<dyn CollabFanout>,
+  ) -> Result<Self, RealtimeError> {
     let access_control = Arc::new(access_control);
     let groups = Arc::new(CollabGroupCache::new(
       storage.clone(),
       access_control.clone(),
+      group_idle_timeout,
+      metrics,
+      fanout,
     ));
-    let edit_collab_by_user = Arc::new(Mutex::new(HashMap::new()));
+    let edit_collab_by_user: Arc<Mutex<HashMap<U, HashSet<Editing>>>> =
+      Arc::new(Mutex::new(HashMap::new()));
 
     // Periodically check the collab groups
     let weak_group = Arc::downgrade(&groups);
+    let weak_edit_collab_by_user = Arc::downgrade(&edit_collab_by_user);
     tokio::spawn(async move {
       let mut interval = interval(Duration::from_secs(60));
       loop {
         interval.tick().await;
-        match weak_group.upgrade() {
-          Some(groups) => groups.tick().await,
+        let groups = match weak_group.upgrade() {
+          Some(groups) => groups,
+          None => break,
+        };
+        let evicted_object_ids = groups.tick().await;
+        let edit_collab_by_user = match weak_edit_collab_by_user.upgrade() {
+          Some(edit_collab_by_user) => edit_collab_by_user,
           None => break,
+        };
+        if !evicted_object_ids.is_empty() {
+          remove_evicted_editing(&edit_collab_by_user, &evicted_object_ids);
+        }
+        // The inactive groups are only opened again for the users who are still editing them
+        let editing_object_ids = edit_collab_by_user
+          .lock()
+          .values()
+          .flatten()
+          .map(|editing| editing.object_id.clone())
+          .collect::<HashSet<_>>();
+        groups
+          .retain_inactive_groups(|object_id| editing_object_ids.contains(object_id))
+          .await;
+      }
+    });
+
+    // Deliver the comments published by other nodes to the local subscribers
+    let client_stream_by_user: Arc<RwLock<HashMap<U, CollabClientStream>>> = Default::default();
+    let mut remote_comment_rx = groups.subscribe_remote_comments();
+    let weak_group = Arc::downgrade(&groups);
+    let weak_client_stream_by_user = Arc::downgrade(&client_stream_by_user);
+    tokio::spawn(async move {
+      loop {
+        let message = match remote_comment_rx.recv().await {
+          Ok(message) => message,
+          Err(RecvError::Lagged(count)) => {
+            error!("[realtime]: skipped {} fanout comments", count);
+            continue;
+          },
+          Err(RecvError::Closed) => break,
+        };
+        match (weak_group.upgrade(), weak_client_stream_by_user.upgrade()) {
+          (Some(groups), Some(client_stream_by_user)) => {
+            send_comment_to_subscribers(&groups, &client_stream_by_user, &message).await;
+          },
+          _ => break,
         }
       }
     });
@@ -69,12 +137,27 @@ where
       storage,
       groups,
       editing_collab_by_user: edit_collab_by_user,
-      client_stream_by_user: Default::default(),
+      client_stream_by_user,
       access_control,
     })
   }
 }
 
+/// Removes the [Editing] records of the evicted groups. The evicted groups don't have any
+/// subscriber, so the records are stale.
+fn remove_evicted_editing<U>(
+  editing_collab_by_user: &Mutex<HashMap<U, HashSet<Editing>>>,
+  evicted_object_ids: &[String],
+) where
+  U: RealtimeUser,
+{
+  let mut editing_collab_by_user = editing_collab_by_user.lock();
+  editing_collab_by_user.retain(|_, editing_set| {
+    editing_set.retain(|editing| !evicted_object_ids.contains(&editing.object_id));
+    !editing_set.is_empty()
+  });
+}
+
 async fn remove_user<S, U, AC>(
   groups: &Arc<CollabGroupCache<S, U, AC>>,
   editing_collab_by_user: &Arc<Mutex<HashMap<U, HashSet<Editing>>>>,
@@ -145,6 +228,7 @@ where
     let groups = self.groups.clone();
     let client_stream_by_user = self.client_stream_by_user.clone();
     let editing_collab_by_user = self.editing_collab_by_user.clone();
+    let access_control = self.access_control.clone();
     Box::pin(async move {
       remove_user(&groups, &editing_collab_by_user, &msg.user).await;
       if client_stream_by_user
@@ -155,6 +239,7 @@ where
       {
         info!("Remove user stream: {}", &msg.user);
       }
+      access_control.did_disconnect(&msg.user.uid()).await;
       Ok(())
     })
   }
@@ -207,6 +292,71 @@ where
   }
 }
 
+impl<S, U, AC> Handler<ApplyCollabUpdate> for CollabServer<S, U, AC>
+where
+  U: RealtimeUser + Unpin,
+  S: CollabStorage + Unpin,
+  AC: CollabAccessControl + Unpin,
+{
+  type Result = ResponseFuture<Result<bool, RealtimeError>>;
+
+  fn handle(&mut self, msg: ApplyCollabUpdate, _ctx: &mut Context<Self>) -> Self::Result {
+    let groups = self.groups.clone();
+    Box::pin(async move {
+      trace!(
+        "[realtime]: apply update to {} with len: {}",
+        msg.object_id,
+        msg.update.len()
+      );
+      groups.apply_update(&msg.object_id, msg.update).await
+    })
+  }
+}
+
+impl<S, U, AC> Handler<BroadcastComment> for CollabServer<S, U, AC>
+where
+  U: RealtimeUser + Unpin,
+  S: CollabStorage + Unpin,
+  AC: CollabAccessControl + Unpin,
+{
+  type Result = ResponseFuture<Result<(), RealtimeError>>;
+
+  fn handle(&mut self, msg: BroadcastComment, _ctx: &mut Context<Self>) -> Self::Result {
+    let groups = self.groups.clone();
+    let client_stream_by_user = self.client_stream_by_user.clone();
+    Box::pin(async move {
+      send_comment_to_subscribers(&groups, &client_stream_by_user, &msg.message).await;
+      // The users who opened the collab might be connected to other nodes
+      groups.publish_comment(msg.message);
+      Ok(())
+    })
+  }
+}
+
+/// Sends the comment to the users who opened the collab on this node.
+async fn send_comment_to_subscribers<S, U, AC>(
+  groups: &CollabGroupCache<S, U, AC>,
+  client_stream_by_user: &RwLock<HashMap<U, CollabClientStream>>,
+  message: &CommentMessage,
+) where
+  S: CollabStorage,
+  U: RealtimeUser,
+  AC: CollabAccessControl,
+{
+  let object_id = &message.object_id;
+  if let Some(group) = groups.get_group(object_id).await {
+    let client_streams = client_stream_by_user.read().await;
+    for user in group.subscribers.read().await.keys() {
+      if let Some(client_stream) = client_streams.get(user) {
+        trace!("[realtime]: send comment of {} to {}", object_id, user);
+        client_stream
+          .sink
+          .do_send(RealtimeMessage::Comment(message.clone()));
+      }
+    }
+  }
+}
+
 #[inline]
 async fn broadcast_message<U>(
   user: &U,
@@ -356,3 +506,215 @@ impl CollabClientStream {
     self.sink.do_send(RealtimeMessage::ServerKickedOff);
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::collaborate::test_util::{
+    test_update, wait_until, TestAccessControl, TestCollabStorage, TestUser,
+  };
+  use crate::collaborate::LocalCollabFanout;
+  use actix::{Actor, Addr, Context, Handler};
+  use collab::core::origin::{CollabClient, CollabOrigin};
+  use collab::sync_protocol::message::{Message, SyncMessage};
+  use collab_entity::CollabType;
+  use database_entity::dto::AFCollabComment;
+  use realtime_entity::collab_msg::{InitSync, UpdateSync};
+  use realtime_entity::comment::CommentEvent;
+  use yrs::updates::encoder::Encode;
+  use yrs::StateVector;
+
+  type TestServer = CollabServer<TestCollabStorage, TestUser, TestAccessControl>;
+
+  /// Records the messages that the server sends to a websocket client.
+  #[derive(Default)]
+  struct TestClientSink {
+    messages: Arc<Mutex<Vec<RealtimeMessage>>>,
+  }
+
+  impl Actor for TestClientSink {
+    type Context = Context<Self>;
+  }
+
+  impl Handler<RealtimeMessage> for TestClientSink {
+    type Result = ();
+
+    fn handle(&mut self, msg: RealtimeMessage, _ctx: &mut Context<Self>) {
+      self.messages.lock().push(msg);
+    }
+  }
+
+  fn start_server(
+    storage: Arc<TestCollabStorage>,
+    fanout: Arc<LocalCollabFanout>,
+  ) -> Addr<TestServer> {
+    CollabServer::new(
+      storage,
+      TestAccessControl,
+      Duration::from_secs(600),
+      Arc::new(CollabRealtimeMetrics::default()),
+      fanout,
+    )
+    .unwrap()
+    .start()
+  }
+
+  /// Connects the user to the server and opens the collab. Returns the messages received by
+  /// the user.
+  async fn open_collab(
+    server: &Addr<TestServer>,
+    user: &TestUser,
+    object_id: &str,
+  ) -> Arc<Mutex<Vec<RealtimeMessage>>> {
+    let sink = TestClientSink::default();
+    let messages = sink.messages.clone();
+    server
+      .send(Connect {
+        socket: sink.start().recipient(),
+        user: user.clone(),
+      })
+      .await
+      .unwrap()
+      .unwrap();
+
+    let payload = Message::Sync(SyncMessage::SyncStep1(StateVector::default())).encode_v1();
+    let init_sync = InitSync::new(
+      client_origin(user),
+      object_id.to_string(),
+      CollabType::Document,
+      "w1".to_string(),
+      1,
+      payload,
+    );
+    server
+      .send(ClientMessage {
+        user: user.clone(),
+        message: RealtimeMessage::Collab(init_sync.into()),
+      })
+      .await
+      .unwrap()
+      .unwrap();
+    messages
+  }
+
+  fn client_origin(user: &TestUser) -> CollabOrigin {
+    CollabOrigin::Client(CollabClient::new(user.uid, user.device_id.clone()))
+  }
+
+  #[actix_rt::test]
+  async fn relay_update_between_two_servers_test() {
+    let storage = Arc::new(TestCollabStorage::default());
+    let fanout = Arc::new(LocalCollabFanout::new());
+    let server_1 = start_server(storage.clone(), fanout.clone());
+    let server_2 = start_server(storage.clone(), fanout.clone());
+    let user_1 = TestUser::new(1);
+    let user_2 = TestUser::new(2);
+    open_collab(&server_1, &user_1, "o1").await;
+    let user_2_messages = open_collab(&server_2, &user_2, "o1").await;
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    user_2_messages.lock().clear();
+
+    let payload = Message::Sync(SyncMessage::Update(test_update("name", "hello"))).encode_v1();
+    let update_sync = UpdateSync::new(client_origin(&user_1), "o1".to_string(), payload, 2);
+    server_1
+      .send(ClientMessage {
+        user: user_1.clone(),
+        message: RealtimeMessage::Collab(update_sync.into()),
+      })
+      .await
+      .unwrap()
+      .unwrap();
+
+    // The update is relayed to the user connected to the other server
+    wait_until(|| {
+      user_2_messages.lock().iter().any(|msg| {
+        matches!(
+          msg,
+          RealtimeMessage::Collab(msg @ CollabMessage::ServerBroadcast(_)) if msg.object_id() == "o1"
+        )
+      })
+    })
+    .await;
+
+    // Only the server that received the update persists it
+    wait_until(|| storage.updates("o1").len() == 1).await;
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    assert_eq!(storage.updates("o1").len(), 1);
+  }
+
+  #[actix_rt::test]
+  async fn relay_comment_between_two_servers_test() {
+    let storage = Arc::new(TestCollabStorage::default());
+    let fanout = Arc::new(LocalCollabFanout::new());
+    let server_1 = start_server(storage.clone(), fanout.clone());
+    let server_2 = start_server(storage.clone(), fanout.clone());
+    let user_1 = TestUser::new(1);
+    let user_2 = TestUser::new(2);
+    let user_1_messages = open_collab(&server_1, &user_1, "o1").await;
+    let user_2_messages = open_collab(&server_2, &user_2, "o1").await;
+
+    let now = chrono::Utc::now();
+    let comment = AFCollabComment {
+      comment_id: uuid::Uuid::new_v4(),
+      workspace_id: uuid::Uuid::new_v4(),
+      object_id: "o1".to_string(),
+      block_id: None,
+      reply_to: None,
+      content: "hello".to_string(),
+      created_by: user_1.uid,
+      created_at: now,
+      updated_at: now,
+      resolved_at: None,
+      resolved_by: None,
+    };
+    server_1
+      .send(BroadcastComment {
+        message: CommentMessage {
+          object_id: "o1".to_string(),
+          event: CommentEvent::Created(comment),
+        },
+      })
+      .await
+      .unwrap()
+      .unwrap();
+
+    // The comment is sent to the users connected to both servers
+    for messages in [user_1_messages, user_2_messages] {
+      wait_until(|| {
+        messages
+          .lock()
+          .iter()
+          .any(|msg| matches!(msg, RealtimeMessage::Comment(msg) if msg.object_id == "o1"))
+      })
+      .await;
+    }
+  }
+
+  fn editing(object_id: &str) -> Editing {
+    Editing {
+      object_id: object_id.to_string(),
+      origin: CollabOrigin::Empty,
+    }
+  }
+
+  #[test]
+  fn remove_editing_of_evicted_groups_test() {
+    let user_1 = TestUser::new(1);
+    let user_2 = TestUser::new(2);
+    let editing_collab_by_user = Mutex::new(HashMap::from([
+      (
+        user_1.clone(),
+        HashSet::from([editing("o1"), editing("o2")]),
+      ),
+      (user_2.clone(), HashSet::from([editing("o1")])),
+    ]));
+
+    remove_evicted_editing(&editing_collab_by_user, &["o1".to_string()]);
+    let editing_collab_by_user = editing_collab_by_user.lock();
+    assert_eq!(
+      editing_collab_by_user.get(&user_1).unwrap(),
+      &HashSet::from([editing("o2")])
+    );
+    assert!(!editing_collab_by_user.contains_key(&user_2));
+  }
+}
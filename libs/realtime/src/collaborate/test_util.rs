@@ -0,0 +1,240 @@
+use crate::collaborate::{CollabAccessControl, CollabUserId};
+use crate::entities::RealtimeUser;
+use app_error::AppError;
+use async_trait::async_trait;
+use collab::core::collab::MutexCollab;
+use collab::core::collab_plugin::EncodedCollabV1;
+use database::collab::{CollabStorage, DatabaseResult, WriteConfig};
+use database_entity::dto::{
+  AFAccessLevel, AFCollabSnapshots, BatchQueryCollab, InsertCollabParams, InsertCollabUpdateParams,
+  InsertSnapshotParams, QueryCollabParams, QueryCollabResult, QueryObjectSnapshotParams,
+  QuerySnapshotParams, RawData,
+};
+use parking_lot::Mutex;
+use reqwest::Method;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Weak;
+use std::time::Duration;
+use yrs::updates::decoder::Decode;
+use yrs::{Doc, Map, ReadTxn, StateVector, Transact, Update};
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub(crate) struct TestUser {
+  pub uid: i64,
+  pub device_id: String,
+}
+
+impl TestUser {
+  pub fn new(uid: i64) -> Self {
+    Self {
+      uid,
+      device_id: uuid::Uuid::new_v4().to_string(),
+    }
+  }
+}
+
+impl Display for TestUser {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.write_fmt(format_args!(
+      "uid:{}|device_id:{}",
+      self.uid, self.device_id
+    ))
+  }
+}
+
+impl RealtimeUser for TestUser {
+  fn uid(&self) -> i64 {
+    self.uid
+  }
+}
+
+/// A [CollabAccessControl] that grants full access to every user.
+pub(crate) struct TestAccessControl;
+
+#[async_trait]
+impl CollabAccessControl for TestAccessControl {
+  async fn get_collab_access_level(
+    &self,
+    _user: CollabUserId<'_>,
+    _oid: &str,
+  ) -> Result<AFAccessLevel, AppError> {
+    Ok(AFAccessLevel::FullAccess)
+  }
+
+  async fn cache_collab_access_level(
+    &self,
+    _user: CollabUserId<'_>,
+    _oid: &str,
+    _level: AFAccessLevel,
+  ) -> Result<(), AppError> {
+    Ok(())
+  }
+
+  async fn can_access_http_method(
+    &self,
+    _user: CollabUserId<'_>,
+    _oid: &str,
+    _method: &Method,
+  ) -> Result<bool, AppError> {
+    Ok(true)
+  }
+
+  async fn can_send_collab_update(&self, _uid: &i64, _oid: &str) -> Result<bool, AppError> {
+    Ok(true)
+  }
+
+  async fn can_receive_collab_update(&self, _uid: &i64, _oid: &str) -> Result<bool, AppError> {
+    Ok(true)
+  }
+}
+
+/// An in-memory [CollabStorage] that records the collabs and the updates written by the
+/// realtime server.
+#[derive(Default)]
+pub(crate) struct TestCollabStorage {
+  config: WriteConfig,
+  collabs: Mutex<HashMap<String, Vec<u8>>>,
+  updates: Mutex<HashMap<String, Vec<Vec<u8>>>>,
+  fail_updates: AtomicBool,
+}
+
+impl TestCollabStorage {
+  /// Makes [CollabStorage::insert_collab_update] fail until it is set back to false.
+  pub fn set_fail_updates(&self, fail: bool) {
+    self.fail_updates.store(fail, Ordering::SeqCst);
+  }
+
+  pub fn collab(&self, object_id: &str) -> Option<Vec<u8>> {
+    self.collabs.lock().get(object_id).cloned()
+  }
+
+  pub fn updates(&self, object_id: &str) -> Vec<Vec<u8>> {
+    self
+      .updates
+      .lock()
+      .get(object_id)
+      .cloned()
+      .unwrap_or_default()
+  }
+}
+
+#[async_trait]
+impl CollabStorage for TestCollabStorage {
+  fn config(&self) -> &WriteConfig {
+    &self.config
+  }
+
+  async fn is_exist(&self, object_id: &str) -> bool {
+    self.collabs.lock().contains_key(object_id)
+  }
+
+  async fn cache_collab(&self, _object_id: &str, _collab: Weak<MutexCollab>) {}
+
+  async fn is_collab_exist(&self, oid: &str) -> DatabaseResult<bool> {
+    Ok(self.collabs.lock().contains_key(oid))
+  }
+
+  async fn is_collab_trashed(&self, _oid: &str) -> DatabaseResult<bool> {
+    Ok(false)
+  }
+
+  async fn insert_collab(&self, _uid: &i64, params: InsertCollabParams) -> DatabaseResult<()> {
+    self
+      .collabs
+      .lock()
+      .insert(params.object_id, params.encoded_collab_v1);
+    Ok(())
+  }
+
+  async fn insert_collab_update(
+    &self,
+    _uid: &i64,
+    params: InsertCollabUpdateParams,
+  ) -> DatabaseResult<()> {
+    if self.fail_updates.load(Ordering::SeqCst) {
+      return Err(AppError::Internal(anyhow::anyhow!("fail to save update")));
+    }
+    self
+      .updates
+      .lock()
+      .entry(params.object_id)
+      .or_default()
+      .push(params.update);
+    Ok(())
+  }
+
+  async fn get_collab_encoded_v1(
+    &self,
+    _uid: &i64,
+    params: QueryCollabParams,
+  ) -> DatabaseResult<EncodedCollabV1> {
+    let data = self
+      .collab(&params.object_id)
+      .ok_or_else(|| AppError::RecordNotFound(params.object_id.clone()))?;
+    EncodedCollabV1::decode_from_bytes(&data)
+      .map_err(|err| AppError::Internal(anyhow::anyhow!("{:?}", err)))
+  }
+
+  async fn batch_get_collab(
+    &self,
+    _uid: &i64,
+    _queries: Vec<BatchQueryCollab>,
+  ) -> HashMap<String, QueryCollabResult> {
+    HashMap::new()
+  }
+
+  async fn delete_collab(&self, _uid: &i64, object_id: &str) -> DatabaseResult<()> {
+    self.collabs.lock().remove(object_id);
+    Ok(())
+  }
+
+  async fn create_snapshot(&self, _params: InsertSnapshotParams) -> DatabaseResult<()> {
+    Ok(())
+  }
+
+  async fn get_snapshot_data(&self, params: QuerySnapshotParams) -> DatabaseResult<RawData> {
+    Err(AppError::RecordNotFound(params.snapshot_id.to_string()))
+  }
+
+  async fn get_all_snapshots(
+    &self,
+    _params: QueryObjectSnapshotParams,
+  ) -> DatabaseResult<AFCollabSnapshots> {
+    Ok(AFCollabSnapshots(vec![]))
+  }
+}
+
+/// Returns a yrs update that sets the key of the collab's data map to the value.
+pub(crate) fn test_update(key: &str, value: &str) -> Vec<u8> {
+  let doc = Doc::new();
+  let map = doc.get_or_insert_map("data");
+  map.insert(&mut doc.transact_mut(), key, value);
+  let txn = doc.transact();
+  txn.encode_state_as_update_v1(&StateVector::default())
+}
+
+/// Returns the value of the key in the data map of the encoded collab.
+pub(crate) fn collab_value(encoded_collab_v1: &[u8], key: &str) -> Option<String> {
+  let encoded_collab = EncodedCollabV1::decode_from_bytes(encoded_collab_v1).ok()?;
+  let doc = Doc::new();
+  let map = doc.get_or_insert_map("data");
+  let mut txn = doc.transact_mut();
+  txn.apply_update(Update::decode_v1(&encoded_collab.doc_state).ok()?);
+  map.get(&txn, key).map(|value| value.to_string(&txn))
+}
+
+/// Waits until the condition is true. Panics if the condition is still false after 5 seconds.
+pub(crate) async fn wait_until<F>(mut condition: F)
+where
+  F: FnMut() -> bool,
+{
+  for _ in 0..50 {
+    if condition() {
+      return;
+    }
+    tokio::time::sleep(Duration::from_millis(100)).await;
+  }
+  panic!("the condition is not satisfied in time");
+}
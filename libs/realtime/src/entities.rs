@@ -7,12 +7,19 @@ use std::fmt::{Debug, Display};
 use std::hash::Hash;
 use std::sync::Arc;
 
+use realtime_entity::comment::CommentMessage;
 pub use realtime_entity::message::RealtimeMessage;
 
 pub trait RealtimeUser:
   Clone + Debug + Send + Sync + 'static + Display + Hash + Eq + PartialEq
 {
   fn uid(&self) -> i64;
+
+  /// The uid that the collabs opened by the user are saved with. It's the uid of the user, except
+  /// for the users that act on behalf of another user, e.g. the session of a share link.
+  fn storage_uid(&self) -> i64 {
+    self.uid()
+  }
 }
 
 impl<T> RealtimeUser for Arc<T>
@@ -22,6 +29,10 @@ where
   fn uid(&self) -> i64 {
     self.as_ref().uid()
   }
+
+  fn storage_uid(&self) -> i64 {
+    self.as_ref().storage_uid()
+  }
 }
 
 #[derive(Debug, Message, Clone)]
@@ -53,6 +64,23 @@ pub struct ClientMessage<U> {
   pub message: RealtimeMessage,
 }
 
+/// Applies a yrs update (v1 encoding) that was produced by the server, e.g. restoring a snapshot,
+/// to the collab object. The result is false when the collab is not opened by the server, in
+/// which case the caller is responsible for persisting the update.
+#[derive(Debug, Message, Clone)]
+#[rtype(result = "Result<bool, RealtimeError>")]
+pub struct ApplyCollabUpdate {
+  pub object_id: String,
+  pub update: Vec<u8>,
+}
+
+/// Sends the comment message to the users who opened the collab on this server.
+#[derive(Debug, Message, Clone)]
+#[rtype(result = "Result<(), RealtimeError>")]
+pub struct BroadcastComment {
+  pub message: CommentMessage,
+}
+
 #[derive(Debug, Hash, PartialEq, Eq)]
 pub(crate) struct Editing {
   pub object_id: String,
@@ -32,6 +32,9 @@ pub enum RealtimeError {
   #[error("Client:{0} does not have enough permission to read")]
   NotEnoughPermissionToRead(i64),
 
+  #[error("The collab:{0} is in the trash")]
+  CollabTrashed(String),
+
   #[error("Internal failure: {0}")]
   Internal(#[from] anyhow::Error),
 }
@@ -1,7 +1,15 @@
-use database_entity::dto::AFRole;
-use database_entity::pg_row::AFBlobMetadataRow;
+use database_entity::dto::{AFAuditAction, AFRole, AFWorkspacePermission};
+use database_entity::pg_row::{AFBlobMetadataRow, AFWorkspacePlanRow};
 use serde::{Deserialize, Serialize};
 use std::ops::Deref;
+use uuid::Uuid;
+
+/// The content type of the archive that a workspace is exported to and imported from.
+pub const WORKSPACE_ARCHIVE_CONTENT_TYPE: &str = "application/x-tar";
+
+/// The header that carries the password of a share link. The password is kept out of the url, so
+/// it doesn't end up in the access logs.
+pub const SHARE_LINK_PASSWORD_HEADER: &str = "x-share-password";
 
 #[derive(Deserialize, Serialize)]
 pub struct WorkspaceMembers(pub Vec<WorkspaceMember>);
@@ -20,6 +28,62 @@ impl From<Vec<String>> for WorkspaceMembers {
   }
 }
 
+#[derive(Deserialize, Serialize)]
+pub struct CreateWorkspaceParams {
+  /// The name of the workspace. Defaults to `My Workspace` if it's not provided.
+  pub workspace_name: Option<String>,
+}
+
+/// The options of importing a workspace archive.
+#[derive(Deserialize, Serialize, Default, Debug)]
+pub struct ImportWorkspaceParams {
+  /// Invites the members of the archived workspace to the new workspace. The members join the
+  /// workspace once they accept the invitation.
+  #[serde(default)]
+  pub invite_members: bool,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct PatchWorkspaceParams {
+  pub workspace_name: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct TransferWorkspaceOwnershipParams {
+  /// The email of the member who becomes the owner of the workspace.
+  pub new_owner_email: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct CreateWorkspaceRoleParams {
+  pub name: String,
+  pub permissions: Vec<AFWorkspacePermission>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct UpdateWorkspaceRoleParams {
+  pub name: Option<String>,
+  pub permissions: Option<Vec<AFWorkspacePermission>>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct AssignWorkspaceRoleParams {
+  pub email: String,
+  /// The id of the custom role. None removes the custom role of the member.
+  pub role_id: Option<i32>,
+}
+
+/// The filters of the audit log of the workspace. The logs are returned from the newest to the
+/// oldest.
+#[derive(Deserialize, Serialize, Default, Debug)]
+pub struct QueryAuditLogParams {
+  pub action: Option<AFAuditAction>,
+  pub actor_uid: Option<i64>,
+  pub target: Option<String>,
+  pub offset: Option<i64>,
+  pub limit: Option<i64>,
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct CreateWorkspaceMembers(pub Vec<CreateWorkspaceMember>);
 impl From<Vec<CreateWorkspaceMember>> for CreateWorkspaceMembers {
@@ -34,6 +98,21 @@ pub struct CreateWorkspaceMember {
   pub role: AFRole,
 }
 
+#[derive(Deserialize, Serialize)]
+pub struct WorkspaceMemberInvitations(pub Vec<WorkspaceMemberInvitation>);
+impl From<Vec<WorkspaceMemberInvitation>> for WorkspaceMemberInvitations {
+  fn from(value: Vec<WorkspaceMemberInvitation>) -> Self {
+    Self(value)
+  }
+}
+
+/// Invites the email to the workspace with the role. The email doesn't need to belong to a user.
+#[derive(Deserialize, Serialize)]
+pub struct WorkspaceMemberInvitation {
+  pub email: String,
+  pub role: AFRole,
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct WorkspaceMemberChangeset {
   pub email: String,
@@ -63,7 +142,23 @@ impl WorkspaceMemberChangeset {
 pub struct WorkspaceSpaceUsage {
   pub total_capacity: u64,
   pub consumed_capacity: u64,
+  /// The workspace type that determines the limits of the workspace.
+  pub workspace_type: i32,
+  pub plan_name: String,
+  /// The maximum size of a single blob in bytes.
+  pub max_blob_size: u64,
+  pub member_count: u64,
+  pub max_members: u64,
 }
 
+#[derive(Deserialize, Serialize)]
+pub struct UpdateWorkspacePlanParams {
+  pub workspace_id: Uuid,
+  pub workspace_type: i32,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct WorkspacePlans(pub Vec<AFWorkspacePlanRow>);
+
 #[derive(Serialize, Deserialize)]
 pub struct WorkspaceBlobMetadata(pub Vec<AFBlobMetadataRow>);
@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+
+use collab_document::blocks::{Block, DocumentData};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::document::parser::{JsonToDocumentParser, SerdeBlock};
+
+// The block types of the documents
+pub(crate) const PAGE: &str = "page";
+pub(crate) const PARAGRAPH: &str = "paragraph";
+pub(crate) const HEADING: &str = "heading";
+pub(crate) const BULLETED_LIST: &str = "bulleted_list";
+pub(crate) const NUMBERED_LIST: &str = "numbered_list";
+pub(crate) const TODO_LIST: &str = "todo_list";
+pub(crate) const QUOTE: &str = "quote";
+pub(crate) const CODE: &str = "code";
+pub(crate) const IMAGE: &str = "image";
+pub(crate) const DIVIDER: &str = "divider";
+
+// The keys of the block data
+pub(crate) const DELTA: &str = "delta";
+pub(crate) const LEVEL: &str = "level";
+pub(crate) const CHECKED: &str = "checked";
+pub(crate) const LANGUAGE: &str = "language";
+pub(crate) const URL: &str = "url";
+
+// The attributes of the text
+pub(crate) const BOLD: &str = "bold";
+pub(crate) const ITALIC: &str = "italic";
+pub(crate) const UNDERLINE: &str = "underline";
+pub(crate) const STRIKETHROUGH: &str = "strikethrough";
+pub(crate) const INLINE_CODE: &str = "code";
+pub(crate) const HREF: &str = "href";
+
+/// The blocks that have text.
+const TEXT_BLOCKS: [&str; 7] = [
+  PARAGRAPH,
+  HEADING,
+  BULLETED_LIST,
+  NUMBERED_LIST,
+  TODO_LIST,
+  QUOTE,
+  CODE,
+];
+
+/// The blocks whose first paragraph is their own text, e.g. the paragraph of a list item.
+const PARAGRAPH_CONTAINERS: [&str; 4] = [BULLETED_LIST, NUMBERED_LIST, TODO_LIST, QUOTE];
+
+/// An insert of the delta of a text block.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub(crate) struct TextOp {
+  pub insert: String,
+  #[serde(default)]
+  pub attributes: Map<String, Value>,
+}
+
+impl TextOp {
+  pub fn is_enabled(&self, attribute: &str) -> bool {
+    self.attributes.get(attribute) == Some(&Value::Bool(true))
+  }
+
+  pub fn href(&self) -> Option<&str> {
+    self.attributes.get(HREF).and_then(Value::as_str)
+  }
+
+  fn into_value(self) -> Value {
+    let mut op = Map::new();
+    op.insert("insert".to_string(), Value::String(self.insert));
+    if !self.attributes.is_empty() {
+      op.insert("attributes".to_string(), Value::Object(self.attributes));
+    }
+    Value::Object(op)
+  }
+}
+
+struct OpenBlock {
+  ty: String,
+  data: HashMap<String, Value>,
+  delta: Vec<TextOp>,
+  children: Vec<SerdeBlock>,
+}
+
+impl OpenBlock {
+  fn new(ty: &str, data: HashMap<String, Value>) -> Self {
+    Self {
+      ty: ty.to_string(),
+      data,
+      delta: vec![],
+      children: vec![],
+    }
+  }
+
+  fn into_block(self) -> SerdeBlock {
+    let mut data = self.data;
+    if TEXT_BLOCKS.contains(&self.ty.as_str()) || !self.delta.is_empty() {
+      let delta = self.delta.into_iter().map(TextOp::into_value).collect();
+      data.insert(DELTA.to_string(), Value::Array(delta));
+    }
+    SerdeBlock {
+      ty: self.ty,
+      data,
+      children: self.children,
+    }
+  }
+}
+
+/// Builds a document from the blocks and the text that the importers visit in order. The text
+/// is added to the innermost open block, with the attributes that are open at the time.
+pub(crate) struct DocumentBuilder {
+  stack: Vec<OpenBlock>,
+  attributes: Vec<(&'static str, Value)>,
+}
+
+impl DocumentBuilder {
+  pub fn new() -> Self {
+    Self {
+      stack: vec![OpenBlock::new(PAGE, HashMap::new())],
+      attributes: vec![],
+    }
+  }
+
+  pub fn start_block(&mut self, ty: &str, data: HashMap<String, Value>) {
+    self.stack.push(OpenBlock::new(ty, data));
+  }
+
+  /// Closes the innermost open block and adds it to the children of its parent. The page is only
+  /// closed by [DocumentBuilder::build].
+  pub fn end_block(&mut self) {
+    if self.stack.len() > 1 {
+      let block = self.stack.pop().unwrap().into_block();
+      self.current_mut().children.push(block);
+    }
+  }
+
+  /// Starts a paragraph, unless the current block is a list item or a quote without any content
+  /// yet. Returns true if a paragraph was started, in which case it must be closed with
+  /// [DocumentBuilder::end_block].
+  pub fn start_paragraph(&mut self) -> bool {
+    let current = self.current();
+    if PARAGRAPH_CONTAINERS.contains(&current.ty.as_str()) && self.is_current_block_empty() {
+      return false;
+    }
+    self.start_block(PARAGRAPH, HashMap::new());
+    true
+  }
+
+  pub fn current_block_type(&self) -> &str {
+    &self.current().ty
+  }
+
+  pub fn is_current_block_empty(&self) -> bool {
+    !self.current_block_has_text() && !self.current_block_has_children()
+  }
+
+  pub fn current_block_has_text(&self) -> bool {
+    !self.current().delta.is_empty()
+  }
+
+  pub fn current_block_has_children(&self) -> bool {
+    !self.current().children.is_empty()
+  }
+
+  /// Changes the type of the current block and adds the data to it.
+  pub fn update_current_block(&mut self, ty: &str, data: HashMap<String, Value>) {
+    let current = self.current_mut();
+    current.ty = ty.to_string();
+    current.data.extend(data);
+  }
+
+  /// Turns the current paragraph into the image if the paragraph is empty, otherwise starts an
+  /// image block in the current block. Returns true if an image block was started, in which case
+  /// it must be closed with [DocumentBuilder::end_block].
+  pub fn start_image(&mut self, url: &str) -> bool {
+    let data = HashMap::from([(URL.to_string(), Value::String(url.to_string()))]);
+    if self.current_block_type() == PARAGRAPH && self.is_current_block_empty() {
+      self.update_current_block(IMAGE, data);
+      false
+    } else {
+      self.start_block(IMAGE, data);
+      true
+    }
+  }
+
+  pub fn start_attribute(&mut self, attribute: &'static str, value: Value) {
+    self.attributes.push((attribute, value));
+  }
+
+  pub fn end_attribute(&mut self, attribute: &str) {
+    if let Some(index) = self
+      .attributes
+      .iter()
+      .rposition(|(key, _)| *key == attribute)
+    {
+      self.attributes.remove(index);
+    }
+  }
+
+  pub fn push_text(&mut self, text: &str) {
+    if text.is_empty() {
+      return;
+    }
+    let attributes = self
+      .attributes
+      .iter()
+      .map(|(key, value)| (key.to_string(), value.clone()))
+      .collect::<Map<_, _>>();
+    let delta = &mut self.current_mut().delta;
+    match delta.last_mut() {
+      Some(op) if op.attributes == attributes => op.insert.push_str(text),
+      _ => delta.push(TextOp {
+        insert: text.to_string(),
+        attributes,
+      }),
+    }
+  }
+
+  pub fn build(mut self) -> DocumentData {
+    while self.stack.len() > 1 {
+      self.end_block();
+    }
+    let page = self.stack.pop().unwrap().into_block();
+    JsonToDocumentParser::serde_block_to_document(&page)
+  }
+
+  fn current(&self) -> &OpenBlock {
+    self.stack.last().unwrap()
+  }
+
+  fn current_mut(&mut self) -> &mut OpenBlock {
+    self.stack.last_mut().unwrap()
+  }
+}
+
+/// Returns the children of the block in order.
+pub(crate) fn block_children<'a>(data: &'a DocumentData, block: &Block) -> Vec<&'a Block> {
+  data
+    .meta
+    .children_map
+    .get(&block.children)
+    .map(|children| {
+      children
+        .iter()
+        .filter_map(|child_id| data.blocks.get(child_id))
+        .collect()
+    })
+    .unwrap_or_default()
+}
+
+/// Returns the text inserts of the block. The delta is either kept in the text map or, in the
+/// older documents, in the data of the block. Embedded inserts are skipped.
+pub(crate) fn block_delta(data: &DocumentData, block: &Block) -> Vec<TextOp> {
+  let text_delta = block.external_id.as_ref().and_then(|text_id| {
+    let delta = data.meta.text_map.as_ref()?.get(text_id)?;
+    serde_json::from_str::<Vec<Value>>(delta).ok()
+  });
+  let delta = text_delta.or_else(|| match block.data.get(DELTA) {
+    Some(Value::Array(delta)) => Some(delta.clone()),
+    _ => None,
+  });
+  delta
+    .unwrap_or_default()
+    .into_iter()
+    .filter_map(|op| serde_json::from_value::<TextOp>(op).ok())
+    .collect()
+}
+
+pub(crate) fn plain_text(delta: &[TextOp]) -> String {
+  delta.iter().map(|op| op.insert.as_str()).collect()
+}
+
+pub(crate) fn block_data(key: &str, value: Value) -> HashMap<String, Value> {
+  HashMap::from([(key.to_string(), value)])
+}
+
+pub(crate) fn data_str<'a>(block: &'a Block, key: &str) -> Option<&'a str> {
+  block.data.get(key).and_then(Value::as_str)
+}
+
+/// Returns the level of the heading, clamped to the levels that Markdown and HTML support.
+pub(crate) fn heading_level(block: &Block) -> usize {
+  block
+    .data
+    .get(LEVEL)
+    .and_then(Value::as_u64)
+    .unwrap_or(1)
+    .clamp(1, 6) as usize
+}
+
+pub(crate) fn is_checked(block: &Block) -> bool {
+  block.data.get(CHECKED).and_then(Value::as_bool) == Some(true)
+}
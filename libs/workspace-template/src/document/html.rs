@@ -0,0 +1,337 @@
+use std::collections::HashMap;
+
+use collab_document::blocks::{Block, DocumentData};
+use scraper::{ElementRef, Html, Node};
+use serde_json::Value;
+
+use crate::document::format::{
+  block_children, block_data, block_delta, data_str, heading_level, is_checked, plain_text,
+  DocumentBuilder, TextOp, BOLD, BULLETED_LIST, CHECKED, CODE, DIVIDER, HEADING, HREF, IMAGE,
+  INLINE_CODE, ITALIC, LANGUAGE, LEVEL, NUMBERED_LIST, PAGE, PARAGRAPH, QUOTE, STRIKETHROUGH,
+  TODO_LIST, UNDERLINE, URL,
+};
+
+pub struct HtmlToDocumentParser;
+
+impl HtmlToDocumentParser {
+  pub fn html_to_document(html: &str) -> DocumentData {
+    let html = Html::parse_document(html);
+    let mut visitor = HtmlVisitor {
+      builder: DocumentBuilder::new(),
+      lists: vec![],
+      implicit_paragraph: false,
+    };
+    visitor.visit_children(*html.root_element());
+    visitor.close_implicit_paragraph();
+    visitor.builder.build()
+  }
+}
+
+type NodeRef<'a> = ego_tree::NodeRef<'a, Node>;
+
+struct HtmlVisitor {
+  builder: DocumentBuilder,
+  // Whether each open list is ordered
+  lists: Vec<bool>,
+  // Whether the text outside of any block is collected in a paragraph that is not in the HTML
+  implicit_paragraph: bool,
+}
+
+impl HtmlVisitor {
+  fn visit_children(&mut self, node: NodeRef) {
+    for child in node.children() {
+      self.visit(child);
+    }
+  }
+
+  fn visit(&mut self, node: NodeRef) {
+    let element = match node.value() {
+      Node::Text(text) => return self.push_text(text),
+      Node::Element(element) => element,
+      _ => return,
+    };
+    let name = element.name();
+    match name {
+      "head" | "script" | "style" | "template" => {},
+      "p" => {
+        self.close_implicit_paragraph();
+        let started = self.builder.start_paragraph();
+        self.visit_children(node);
+        if started {
+          self.builder.end_block();
+        }
+      },
+      "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+        let level = name[1..].parse::<usize>().unwrap_or(1);
+        self.visit_block(node, HEADING, block_data(LEVEL, Value::from(level)));
+      },
+      "blockquote" => self.visit_block(node, QUOTE, HashMap::new()),
+      "pre" => {
+        let code = ElementRef::wrap(node)
+          .map(|element| element.text().collect::<String>())
+          .unwrap_or_default();
+        self.start_block(CODE, block_data(LANGUAGE, Value::from(code_language(node))));
+        self.builder.push_text(code.trim_end_matches('\n'));
+        self.builder.end_block();
+      },
+      "ul" | "ol" => {
+        self.close_implicit_paragraph();
+        self.lists.push(name == "ol");
+        self.visit_children(node);
+        self.lists.pop();
+      },
+      "li" => {
+        let ty = if self.lists.last() == Some(&true) {
+          NUMBERED_LIST
+        } else {
+          BULLETED_LIST
+        };
+        self.visit_block(node, ty, HashMap::new());
+      },
+      "input" if element.attr("type") == Some("checkbox") => {
+        if matches!(
+          self.builder.current_block_type(),
+          BULLETED_LIST | NUMBERED_LIST
+        ) {
+          let checked = element.attr("checked").is_some();
+          self
+            .builder
+            .update_current_block(TODO_LIST, block_data(CHECKED, Value::Bool(checked)));
+        }
+      },
+      "img" => {
+        if let Some(src) = element.attr("src") {
+          if self.builder.start_image(src) {
+            self.builder.end_block();
+          }
+        }
+      },
+      "hr" => {
+        self.start_block(DIVIDER, HashMap::new());
+        self.builder.end_block();
+      },
+      "br" => self.push_inline("\n"),
+      "strong" | "b" => self.visit_inline(node, BOLD, Value::Bool(true)),
+      "em" | "i" => self.visit_inline(node, ITALIC, Value::Bool(true)),
+      "u" => self.visit_inline(node, UNDERLINE, Value::Bool(true)),
+      "s" | "del" | "strike" => self.visit_inline(node, STRIKETHROUGH, Value::Bool(true)),
+      "code" => self.visit_inline(node, INLINE_CODE, Value::Bool(true)),
+      "a" => match element.attr("href") {
+        Some(href) => self.visit_inline(node, HREF, Value::from(href)),
+        None => self.visit_children(node),
+      },
+      // The other block elements only separate the text before and after them
+      "div" | "section" | "article" | "header" | "footer" | "main" | "nav" | "aside" => {
+        self.close_implicit_paragraph();
+        self.visit_children(node);
+        self.close_implicit_paragraph();
+      },
+      _ => self.visit_children(node),
+    }
+  }
+
+  fn visit_block(&mut self, node: NodeRef, ty: &str, data: HashMap<String, Value>) {
+    self.start_block(ty, data);
+    self.visit_children(node);
+    self.builder.end_block();
+  }
+
+  fn visit_inline(&mut self, node: NodeRef, attribute: &'static str, value: Value) {
+    self.builder.start_attribute(attribute, value);
+    self.visit_children(node);
+    self.builder.end_attribute(attribute);
+  }
+
+  fn start_block(&mut self, ty: &str, data: HashMap<String, Value>) {
+    self.close_implicit_paragraph();
+    self.builder.start_block(ty, data);
+  }
+
+  /// Adds the text with its white space collapsed like a browser renders it.
+  fn push_text(&mut self, text: &str) {
+    let mut collapsed = String::with_capacity(text.len());
+    for c in text.chars() {
+      if !c.is_whitespace() {
+        collapsed.push(c);
+      } else if !collapsed.ends_with(' ') {
+        collapsed.push(' ');
+      }
+    }
+    // The white space between the blocks is not part of any text
+    if !self.builder.current_block_has_text() {
+      collapsed = collapsed.trim_start().to_string();
+    } else if collapsed.trim().is_empty() && self.builder.current_block_has_children() {
+      return;
+    }
+    if !collapsed.is_empty() {
+      self.push_inline(&collapsed);
+    }
+  }
+
+  /// Adds the text to the current block, or to an implicit paragraph if the text is outside of
+  /// any block.
+  fn push_inline(&mut self, text: &str) {
+    if self.builder.current_block_type() == PAGE {
+      self.builder.start_block(PARAGRAPH, HashMap::new());
+      self.implicit_paragraph = true;
+    }
+    self.builder.push_text(text);
+  }
+
+  fn close_implicit_paragraph(&mut self) {
+    if self.implicit_paragraph {
+      self.builder.end_block();
+      self.implicit_paragraph = false;
+    }
+  }
+}
+
+/// Returns the language of the code in the `pre` element, which is given by the `language-` class
+/// of its `code` element.
+fn code_language(node: NodeRef) -> String {
+  node
+    .children()
+    .filter_map(ElementRef::wrap)
+    .filter(|element| element.value().name() == "code")
+    .flat_map(|element| element.value().classes())
+    .find_map(|class| class.strip_prefix("language-"))
+    .unwrap_or_default()
+    .to_string()
+}
+
+pub struct DocumentToHtmlExporter;
+
+impl DocumentToHtmlExporter {
+  pub fn document_to_html(data: &DocumentData) -> String {
+    let mut html = String::new();
+    if let Some(page) = data.blocks.get(&data.page_id) {
+      write_blocks(data, &block_children(data, page), &mut html);
+    }
+    html
+  }
+}
+
+/// Writes the sibling blocks. The consecutive items of the same list are written in one list
+/// element.
+fn write_blocks(data: &DocumentData, blocks: &[&Block], html: &mut String) {
+  let mut index = 0;
+  while index < blocks.len() {
+    let ty = blocks[index].ty.as_str();
+    let list_tag = match ty {
+      BULLETED_LIST | TODO_LIST => "ul",
+      NUMBERED_LIST => "ol",
+      _ => {
+        write_block(data, blocks[index], html);
+        index += 1;
+        continue;
+      },
+    };
+    let end = index
+      + blocks[index..]
+        .iter()
+        .take_while(|block| block.ty == ty)
+        .count();
+    html.push_str(&format!("<{}>", list_tag));
+    for block in &blocks[index..end] {
+      html.push_str("<li>");
+      if ty == TODO_LIST {
+        if is_checked(block) {
+          html.push_str(r#"<input type="checkbox" disabled checked>"#);
+        } else {
+          html.push_str(r#"<input type="checkbox" disabled>"#);
+        }
+      }
+      html.push_str(&delta_to_html(&block_delta(data, block)));
+      write_blocks(data, &block_children(data, block), html);
+      html.push_str("</li>");
+    }
+    html.push_str(&format!("</{}>\n", list_tag));
+    index = end;
+  }
+}
+
+fn write_block(data: &DocumentData, block: &Block, html: &mut String) {
+  let delta = block_delta(data, block);
+  let children = block_children(data, block);
+  match block.ty.as_str() {
+    HEADING => {
+      let level = heading_level(block);
+      html.push_str(&format!(
+        "<h{}>{}</h{}>\n",
+        level,
+        delta_to_html(&delta),
+        level
+      ));
+      write_blocks(data, &children, html);
+    },
+    QUOTE => {
+      html.push_str("<blockquote>");
+      html.push_str(&delta_to_html(&delta));
+      write_blocks(data, &children, html);
+      html.push_str("</blockquote>\n");
+    },
+    CODE => {
+      match data_str(block, LANGUAGE).filter(|language| !language.is_empty()) {
+        Some(language) => html.push_str(&format!(
+          r#"<pre><code class="language-{}">"#,
+          escape_html(language)
+        )),
+        None => html.push_str("<pre><code>"),
+      }
+      html.push_str(&escape_html(&plain_text(&delta)));
+      html.push_str("</code></pre>\n");
+      write_blocks(data, &children, html);
+    },
+    IMAGE => {
+      html.push_str(&format!(
+        "<img src=\"{}\">\n",
+        escape_html(data_str(block, URL).unwrap_or_default())
+      ));
+      write_blocks(data, &children, html);
+    },
+    DIVIDER => html.push_str("<hr>\n"),
+    _ => {
+      html.push_str(&format!("<p>{}</p>\n", delta_to_html(&delta)));
+      write_blocks(data, &children, html);
+    },
+  }
+}
+
+fn delta_to_html(delta: &[TextOp]) -> String {
+  delta
+    .iter()
+    .map(|op| {
+      let mut text = escape_html(&op.insert).replace('\n', "<br>");
+      for (attribute, tag) in [
+        (INLINE_CODE, "code"),
+        (UNDERLINE, "u"),
+        (STRIKETHROUGH, "s"),
+        (ITALIC, "em"),
+        (BOLD, "strong"),
+      ] {
+        if op.is_enabled(attribute) {
+          text = format!("<{}>{}</{}>", tag, text, tag);
+        }
+      }
+      if let Some(href) = op.href() {
+        text = format!("<a href=\"{}\">{}</a>", escape_html(href), text);
+      }
+      text
+    })
+    .collect()
+}
+
+fn escape_html(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+  for c in text.chars() {
+    match c {
+      '&' => escaped.push_str("&amp;"),
+      '<' => escaped.push_str("&lt;"),
+      '>' => escaped.push_str("&gt;"),
+      '"' => escaped.push_str("&quot;"),
+      '\'' => escaped.push_str("&#39;"),
+      _ => escaped.push(c),
+    }
+  }
+  escaped
+}
@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+
+use collab_document::blocks::{Block, DocumentData};
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag};
+use serde_json::Value;
+
+use crate::document::format::{
+  block_children, block_data, block_delta, data_str, heading_level, is_checked, plain_text,
+  DocumentBuilder, TextOp, BOLD, BULLETED_LIST, CHECKED, CODE, DIVIDER, HEADING, HREF, IMAGE,
+  INLINE_CODE, ITALIC, LANGUAGE, LEVEL, NUMBERED_LIST, QUOTE, STRIKETHROUGH, TODO_LIST, UNDERLINE,
+  URL,
+};
+
+pub struct MarkdownToDocumentParser;
+
+impl MarkdownToDocumentParser {
+  pub fn markdown_to_document(markdown: &str) -> DocumentData {
+    let mut builder = DocumentBuilder::new();
+    // Whether each open paragraph and image was started as a block
+    let mut paragraphs = vec![];
+    let mut images = vec![];
+    // Whether each open list is ordered
+    let mut lists = vec![];
+    let mut code = None::<String>;
+
+    let options = Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    for event in Parser::new_ext(markdown, options) {
+      match event {
+        Event::Start(tag) => match tag {
+          Tag::Paragraph => paragraphs.push(builder.start_paragraph()),
+          Tag::Heading(level, _, _) => {
+            builder.start_block(HEADING, block_data(LEVEL, Value::from(level as usize)))
+          },
+          Tag::BlockQuote => builder.start_block(QUOTE, HashMap::new()),
+          Tag::CodeBlock(kind) => {
+            let language = match kind {
+              CodeBlockKind::Fenced(language) => language.to_string(),
+              CodeBlockKind::Indented => String::new(),
+            };
+            builder.start_block(CODE, block_data(LANGUAGE, Value::from(language)));
+            code = Some(String::new());
+          },
+          Tag::List(start) => lists.push(start.is_some()),
+          Tag::Item => {
+            let ty = if lists.last() == Some(&true) {
+              NUMBERED_LIST
+            } else {
+              BULLETED_LIST
+            };
+            builder.start_block(ty, HashMap::new());
+          },
+          Tag::Emphasis => builder.start_attribute(ITALIC, Value::Bool(true)),
+          Tag::Strong => builder.start_attribute(BOLD, Value::Bool(true)),
+          Tag::Strikethrough => builder.start_attribute(STRIKETHROUGH, Value::Bool(true)),
+          Tag::Link(_, url, _) => builder.start_attribute(HREF, Value::from(url.to_string())),
+          Tag::Image(_, url, _) => images.push(builder.start_image(&url)),
+          _ => {},
+        },
+        Event::End(tag) => match tag {
+          Tag::Paragraph => {
+            if paragraphs.pop() == Some(true) {
+              builder.end_block();
+            }
+          },
+          Tag::Heading(..) | Tag::BlockQuote | Tag::Item => builder.end_block(),
+          Tag::CodeBlock(_) => {
+            if let Some(code) = code.take() {
+              builder.push_text(code.trim_end_matches('\n'));
+            }
+            builder.end_block();
+          },
+          Tag::List(_) => {
+            lists.pop();
+          },
+          Tag::Emphasis => builder.end_attribute(ITALIC),
+          Tag::Strong => builder.end_attribute(BOLD),
+          Tag::Strikethrough => builder.end_attribute(STRIKETHROUGH),
+          Tag::Link(..) => builder.end_attribute(HREF),
+          Tag::Image(..) => {
+            if images.pop() == Some(true) {
+              builder.end_block();
+            }
+          },
+          _ => {},
+        },
+        Event::Text(text) => match code.as_mut() {
+          Some(code) => code.push_str(&text),
+          // The alt text of the images is not kept
+          None if images.is_empty() => builder.push_text(&text),
+          None => {},
+        },
+        Event::Code(text) => {
+          builder.start_attribute(INLINE_CODE, Value::Bool(true));
+          builder.push_text(&text);
+          builder.end_attribute(INLINE_CODE);
+        },
+        Event::SoftBreak => builder.push_text(" "),
+        Event::HardBreak => builder.push_text("\n"),
+        Event::Rule => {
+          builder.start_block(DIVIDER, HashMap::new());
+          builder.end_block();
+        },
+        Event::TaskListMarker(checked) => {
+          builder.update_current_block(TODO_LIST, block_data(CHECKED, Value::Bool(checked)))
+        },
+        _ => {},
+      }
+    }
+    builder.build()
+  }
+}
+
+pub struct DocumentToMarkdownExporter;
+
+impl DocumentToMarkdownExporter {
+  pub fn document_to_markdown(data: &DocumentData) -> String {
+    let lines = match data.blocks.get(&data.page_id) {
+      Some(page) => blocks_to_lines(data, &block_children(data, page)),
+      None => vec![],
+    };
+    let mut markdown = lines.join("\n");
+    markdown.push('\n');
+    markdown
+  }
+}
+
+/// Returns the lines of the sibling blocks. The blocks are separated by a blank line, except the
+/// consecutive items of the same type of list.
+fn blocks_to_lines(data: &DocumentData, blocks: &[&Block]) -> Vec<String> {
+  let mut lines = vec![];
+  let mut number = 0;
+  let mut prev_ty: Option<&str> = None;
+  for block in blocks {
+    number = match prev_ty {
+      Some(ty) if ty == block.ty => number + 1,
+      _ => 1,
+    };
+    if let Some(prev_ty) = prev_ty {
+      if !(prev_ty == block.ty && is_list(prev_ty)) {
+        lines.push(String::new());
+      }
+    }
+    lines.extend(block_to_lines(data, block, number));
+    prev_ty = Some(block.ty.as_str());
+  }
+  lines
+}
+
+fn block_to_lines(data: &DocumentData, block: &Block, number: usize) -> Vec<String> {
+  let delta = block_delta(data, block);
+  let children = blocks_to_lines(data, &block_children(data, block));
+  let mut lines = vec![];
+  match block.ty.as_str() {
+    HEADING => {
+      let text = delta_to_markdown(&delta);
+      lines.push(format!("{} {}", "#".repeat(heading_level(block)), text));
+      append_children(&mut lines, children);
+    },
+    BULLETED_LIST | NUMBERED_LIST | TODO_LIST => {
+      let marker = match block.ty.as_str() {
+        NUMBERED_LIST => format!("{}. ", number),
+        TODO_LIST if is_checked(block) => "- [x] ".to_string(),
+        TODO_LIST => "- [ ] ".to_string(),
+        _ => "- ".to_string(),
+      };
+      // The continuation lines and the children are indented to the text of the item
+      let indent = " ".repeat(marker.len());
+      for (i, line) in delta_to_markdown(&delta).split('\n').enumerate() {
+        if i == 0 {
+          lines.push(format!("{}{}", marker, line));
+        } else {
+          lines.push(indent_line(&indent, line));
+        }
+      }
+      lines.extend(children.iter().map(|line| indent_line(&indent, line)));
+    },
+    QUOTE => {
+      let mut quote_lines = text_lines(&delta);
+      append_children(&mut quote_lines, children);
+      lines.extend(quote_lines.iter().map(|line| {
+        if line.is_empty() {
+          ">".to_string()
+        } else {
+          format!("> {}", line)
+        }
+      }));
+    },
+    CODE => {
+      lines.push(format!(
+        "```{}",
+        data_str(block, LANGUAGE).unwrap_or_default()
+      ));
+      lines.extend(plain_text(&delta).split('\n').map(str::to_string));
+      lines.push("```".to_string());
+      append_children(&mut lines, children);
+    },
+    IMAGE => {
+      lines.push(format!("![]({})", data_str(block, URL).unwrap_or_default()));
+      append_children(&mut lines, children);
+    },
+    DIVIDER => lines.push("---".to_string()),
+    _ => {
+      lines.extend(text_lines(&delta));
+      append_children(&mut lines, children);
+    },
+  }
+  lines
+}
+
+fn text_lines(delta: &[TextOp]) -> Vec<String> {
+  if delta.is_empty() {
+    return vec![];
+  }
+  delta_to_markdown(delta)
+    .split('\n')
+    .map(str::to_string)
+    .collect()
+}
+
+fn append_children(lines: &mut Vec<String>, children: Vec<String>) {
+  if !children.is_empty() {
+    if !lines.is_empty() {
+      lines.push(String::new());
+    }
+    lines.extend(children);
+  }
+}
+
+fn indent_line(indent: &str, line: &str) -> String {
+  if line.is_empty() {
+    String::new()
+  } else {
+    format!("{}{}", indent, line)
+  }
+}
+
+fn is_list(ty: &str) -> bool {
+  matches!(ty, BULLETED_LIST | NUMBERED_LIST | TODO_LIST)
+}
+
+/// Returns the text with the attributes of each insert as Markdown emphasis. Underlines have no
+/// Markdown syntax, so they are written as HTML.
+fn delta_to_markdown(delta: &[TextOp]) -> String {
+  delta
+    .iter()
+    .map(|op| {
+      let mut text = if op.is_enabled(INLINE_CODE) {
+        format!("`{}`", op.insert)
+      } else {
+        // The line breaks are hard breaks
+        escape_markdown(&op.insert).replace('\n', "\\\n")
+      };
+      if op.is_enabled(UNDERLINE) {
+        text = format!("<u>{}</u>", text);
+      }
+      if op.is_enabled(STRIKETHROUGH) {
+        text = format!("~~{}~~", text);
+      }
+      if op.is_enabled(ITALIC) {
+        text = format!("_{}_", text);
+      }
+      if op.is_enabled(BOLD) {
+        text = format!("**{}**", text);
+      }
+      if let Some(href) = op.href() {
+        text = format!("[{}]({})", text, href);
+      }
+      text
+    })
+    .collect()
+}
+
+fn escape_markdown(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+  for c in text.chars() {
+    if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']' | '~' | '<') {
+      escaped.push('\\');
+    }
+    escaped.push(c);
+  }
+  escaped
+}
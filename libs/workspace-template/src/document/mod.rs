@@ -1,4 +1,7 @@
-mod parser;
+mod format;
+pub mod html;
+pub mod markdown;
+pub mod parser;
 
 use crate::document::parser::JsonToDocumentParser;
 use crate::hierarchy_builder::WorkspaceViewBuilder;
@@ -11,6 +11,9 @@ use collab_entity::CollabType;
 use collab_folder::{
   timestamp, Folder, FolderData, RepeatedViewIdentifier, ViewIdentifier, ViewLayout, Workspace,
 };
+pub use document::html::{DocumentToHtmlExporter, HtmlToDocumentParser};
+pub use document::markdown::{DocumentToMarkdownExporter, MarkdownToDocumentParser};
+pub use document::parser::JsonToDocumentParser;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -0,0 +1,12 @@
+-- Append-only log of the yrs updates applied to a collab object. The updates are merged into
+-- the af_collab row by the compactor and removed from this table afterwards.
+CREATE TABLE IF NOT EXISTS af_collab_update (
+    id BIGSERIAL PRIMARY KEY,
+    oid TEXT NOT NULL,
+    partition_key INTEGER NOT NULL,
+    blob BYTEA NOT NULL,
+    len INTEGER NOT NULL,
+    workspace_id UUID NOT NULL REFERENCES af_workspace(workspace_id) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_af_collab_update_oid ON af_collab_update(oid, id);
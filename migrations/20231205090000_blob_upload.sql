@@ -0,0 +1,20 @@
+-- The multipart uploads of blobs that are not completed or aborted yet. The row is removed after
+-- the upload is completed, and the blob is recorded in af_blob_metadata.
+CREATE TABLE IF NOT EXISTS af_blob_upload (
+    upload_id TEXT PRIMARY KEY,
+    workspace_id UUID NOT NULL REFERENCES af_workspace(workspace_id) ON DELETE CASCADE,
+    file_id VARCHAR NOT NULL,
+    file_type VARCHAR NOT NULL,
+    file_size BIGINT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+-- The uploaded parts of the multipart uploads. Uploading the same part again replaces the row,
+-- which allows the client to resume an interrupted upload.
+CREATE TABLE IF NOT EXISTS af_blob_upload_part (
+    upload_id TEXT NOT NULL REFERENCES af_blob_upload(upload_id) ON DELETE CASCADE,
+    part_number INTEGER NOT NULL,
+    e_tag TEXT NOT NULL,
+    part_size BIGINT NOT NULL,
+    PRIMARY KEY (upload_id, part_number)
+);
@@ -0,0 +1,10 @@
+-- The blobs that were issued a presigned upload url but not completed yet. The client uploads the
+-- blob to the bucket directly, then asks the server to complete the upload, which moves the row
+-- to af_blob_metadata.
+CREATE TABLE IF NOT EXISTS af_blob_presigned_upload (
+    file_id VARCHAR PRIMARY KEY,
+    workspace_id UUID NOT NULL REFERENCES af_workspace(workspace_id) ON DELETE CASCADE,
+    file_type VARCHAR NOT NULL,
+    file_size BIGINT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
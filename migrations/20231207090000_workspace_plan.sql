@@ -0,0 +1,23 @@
+-- af_workspace_plan defines the limits of each workspace type. The workspace_type of af_workspace
+-- refers to this table.
+CREATE TABLE IF NOT EXISTS af_workspace_plan (
+    workspace_type INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    -- The total size of the blobs of the workspace in bytes
+    storage_limit BIGINT NOT NULL,
+    -- The maximum number of members of the workspace, including the owner
+    member_limit BIGINT NOT NULL,
+    -- The maximum size of a single blob in bytes
+    blob_size_limit BIGINT NOT NULL
+);
+
+INSERT INTO af_workspace_plan (workspace_type, name, storage_limit, member_limit, blob_size_limit)
+VALUES
+    (0, 'Free', 10737418240, 50, 104857600),
+    (1, 'Pro', 107374182400, 500, 1073741824),
+    (2, 'Team', 1099511627776, 5000, 5368709120)
+ON CONFLICT (workspace_type) DO NOTHING;
+
+ALTER TABLE af_workspace
+    ADD CONSTRAINT af_workspace_workspace_type_fkey
+    FOREIGN KEY (workspace_type) REFERENCES af_workspace_plan(workspace_type);
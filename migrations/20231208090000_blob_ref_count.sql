@@ -0,0 +1,6 @@
+-- The number of times the blob was uploaded to the workspace. The metadata is removed when the
+-- count drops to zero, and the blob is removed from the bucket when no workspace references it.
+ALTER TABLE af_blob_metadata ADD COLUMN IF NOT EXISTS ref_count BIGINT NOT NULL DEFAULT 1;
+
+-- The blobs are content addressed, so the same file_id can be shared by multiple workspaces
+CREATE INDEX IF NOT EXISTS idx_af_blob_metadata_file_id ON af_blob_metadata (file_id);
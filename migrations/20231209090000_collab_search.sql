@@ -0,0 +1,14 @@
+-- af_collab_search keeps the plain text of the documents and database rows for full-text search.
+-- The 'simple' configuration is used because the content can be in any language.
+CREATE TABLE IF NOT EXISTS af_collab_search (
+    workspace_id UUID NOT NULL REFERENCES af_workspace(workspace_id) ON DELETE CASCADE,
+    oid TEXT NOT NULL,
+    -- The partition_key of the collab in af_collab
+    collab_type INTEGER NOT NULL,
+    content TEXT NOT NULL,
+    content_tsv TSVECTOR GENERATED ALWAYS AS (to_tsvector('simple', content)) STORED,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    PRIMARY KEY (workspace_id, oid)
+);
+CREATE INDEX IF NOT EXISTS idx_af_collab_search_content_tsv ON af_collab_search USING GIN (content_tsv);
+CREATE INDEX IF NOT EXISTS idx_af_collab_search_oid ON af_collab_search (oid);
@@ -0,0 +1,19 @@
+-- af_workspace_invitation keeps the invitations of the workspaces. The invitee doesn't need to have
+-- an account, the invitation is accepted automatically when the invitee signs up.
+CREATE TABLE IF NOT EXISTS af_workspace_invitation (
+    id UUID NOT NULL PRIMARY KEY DEFAULT uuid_generate_v4(),
+    workspace_id UUID NOT NULL REFERENCES af_workspace(workspace_id) ON DELETE CASCADE,
+    inviter BIGINT NOT NULL REFERENCES af_user(uid) ON DELETE CASCADE,
+    -- Lower case email of the invitee
+    invitee_email TEXT NOT NULL,
+    role_id INT NOT NULL REFERENCES af_roles(id),
+    -- 0: pending, 1: accepted, 2: declined
+    status SMALLINT NOT NULL DEFAULT 0,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+-- A workspace has at most one pending invitation for an email
+CREATE UNIQUE INDEX IF NOT EXISTS idx_af_workspace_invitation_pending
+    ON af_workspace_invitation (workspace_id, invitee_email) WHERE status = 0;
+CREATE INDEX IF NOT EXISTS idx_af_workspace_invitation_invitee_email
+    ON af_workspace_invitation (invitee_email) WHERE status = 0;
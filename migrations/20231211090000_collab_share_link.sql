@@ -0,0 +1,18 @@
+-- af_collab_share_link keeps the links that publish a collab to the people outside the workspace.
+CREATE TABLE IF NOT EXISTS af_collab_share_link (
+    id UUID NOT NULL PRIMARY KEY DEFAULT uuid_generate_v4(),
+    -- The random token in the url of the link
+    token TEXT NOT NULL UNIQUE,
+    workspace_id UUID NOT NULL REFERENCES af_workspace(workspace_id) ON DELETE CASCADE,
+    oid TEXT NOT NULL,
+    -- The partition_key of the collab in af_collab
+    collab_type INTEGER NOT NULL,
+    created_by BIGINT NOT NULL REFERENCES af_user(uid) ON DELETE CASCADE,
+    -- The access_level of af_permissions, either read only or read and comment
+    access_level INTEGER NOT NULL,
+    expires_at TIMESTAMP WITH TIME ZONE,
+    -- The argon2 hash of the password, NULL if the link doesn't require a password
+    password_hash TEXT,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_af_collab_share_link_oid ON af_collab_share_link (workspace_id, oid);
@@ -0,0 +1,43 @@
+-- af_workspace_role keeps the custom roles of a workspace. A custom role is a set of permissions,
+-- which replaces the default permissions of the built-in role of the members that it's assigned to.
+CREATE TABLE IF NOT EXISTS af_workspace_role (
+    id SERIAL PRIMARY KEY,
+    workspace_id UUID NOT NULL REFERENCES af_workspace(workspace_id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    -- The bit flags of AFWorkspacePermission
+    permissions BIGINT NOT NULL DEFAULT 0,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    UNIQUE (workspace_id, name)
+);
+
+-- The custom role of the member, NULL if the member only has the permissions of the built-in role.
+-- Deleting the custom role updates the members, which notifies af_workspace_member_channel.
+ALTER TABLE af_workspace_member
+    ADD COLUMN IF NOT EXISTS custom_role_id INT REFERENCES af_workspace_role(id) ON DELETE SET NULL;
+
+-- Listener for af_workspace_role table
+DROP TRIGGER IF EXISTS af_workspace_role_change_trigger ON af_workspace_role;
+
+CREATE OR REPLACE FUNCTION notify_af_workspace_role_change() RETURNS trigger AS $$
+DECLARE
+    payload TEXT;
+BEGIN
+    payload := json_build_object(
+            'old', row_to_json(OLD),
+            'new', row_to_json(NEW),
+            'action_type', TG_OP
+            )::text;
+
+    PERFORM pg_notify('af_workspace_role_channel', payload);
+    IF TG_OP = 'DELETE' THEN
+        RETURN OLD;
+    ELSE
+        RETURN NEW;
+    END IF;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER af_workspace_role_change_trigger
+    AFTER INSERT OR UPDATE OR DELETE ON af_workspace_role
+    FOR EACH ROW EXECUTE FUNCTION notify_af_workspace_role_change();
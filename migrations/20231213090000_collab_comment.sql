@@ -0,0 +1,20 @@
+-- af_collab_comment keeps the comment threads of the collab objects. A thread is made of a root
+-- comment, whose reply_to is NULL, and the replies to the root comment.
+CREATE TABLE IF NOT EXISTS af_collab_comment (
+    id UUID NOT NULL PRIMARY KEY DEFAULT uuid_generate_v4(),
+    workspace_id UUID NOT NULL REFERENCES af_workspace(workspace_id) ON DELETE CASCADE,
+    oid TEXT NOT NULL,
+    -- The id of the block that the comment is anchored to, NULL if the comment is about the whole collab
+    block_id TEXT,
+    -- The root comment of the thread, NULL if the comment is the root comment
+    reply_to UUID REFERENCES af_collab_comment(id) ON DELETE CASCADE,
+    content TEXT NOT NULL,
+    created_by BIGINT NOT NULL REFERENCES af_user(uid) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    -- Only the root comment can be resolved, which resolves the whole thread
+    resolved_at TIMESTAMP WITH TIME ZONE,
+    resolved_by BIGINT REFERENCES af_user(uid) ON DELETE SET NULL
+);
+CREATE INDEX IF NOT EXISTS idx_af_collab_comment_oid ON af_collab_comment (workspace_id, oid);
+CREATE INDEX IF NOT EXISTS idx_af_collab_comment_reply_to ON af_collab_comment (reply_to);
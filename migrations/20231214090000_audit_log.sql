@@ -0,0 +1,17 @@
+-- af_audit_log records the security-relevant actions of the users. The logs outlive the users and
+-- the workspaces they refer to, so the columns don't reference other tables.
+CREATE TABLE IF NOT EXISTS af_audit_log (
+    id BIGSERIAL PRIMARY KEY,
+    actor_uid BIGINT NOT NULL,
+    -- NULL if the action doesn't belong to a workspace, e.g. updating the user profile
+    workspace_id UUID,
+    -- The AFAuditAction of the log
+    action SMALLINT NOT NULL,
+    -- The id of the object that the action is performed on, e.g. the object id of a collab
+    target TEXT NOT NULL,
+    metadata JSONB,
+    -- The x-request-id of the request that performed the action
+    request_id TEXT,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_af_audit_log_workspace_id ON af_audit_log (workspace_id, created_at DESC);
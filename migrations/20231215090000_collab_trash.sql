@@ -0,0 +1,4 @@
+-- The deleted collabs stay in the trash of their workspace, with af_collab.deleted_at set, until
+-- they are restored or purged after the retention period.
+CREATE INDEX IF NOT EXISTS idx_af_collab_trash ON af_collab (workspace_id, deleted_at)
+    WHERE deleted_at IS NOT NULL;
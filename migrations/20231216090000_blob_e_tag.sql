@@ -0,0 +1,4 @@
+-- The entity tag of the blob content. The file id of a blob uploaded in one request is the hash
+-- of its content, so the column is only set for the blobs with a random file id, i.e. the
+-- multipart and presigned uploads.
+ALTER TABLE af_blob_metadata ADD COLUMN IF NOT EXISTS e_tag TEXT;
@@ -0,0 +1,90 @@
+use crate::component::auth::jwt::Authorization;
+use crate::state::AppState;
+use actix_web::web::{Data, Json};
+use actix_web::Result;
+use actix_web::{web, Scope};
+use app_error::AppError;
+use database::workspace::{select_workspace_plans, update_workspace_type};
+use database_entity::dto::{AFBlobGCReport, RunBlobGCParams};
+use shared_entity::dto::workspace_dto::{UpdateWorkspacePlanParams, WorkspacePlans};
+use shared_entity::response::{AppResponse, AppResponseError, JsonAppResponse};
+use tracing::instrument;
+
+/// The endpoints that can only be accessed by the admin user.
+pub fn admin_scope() -> Scope {
+  web::scope("/api/admin")
+    .service(web::resource("/plan").route(web::get().to(get_workspace_plans_handler)))
+    .service(web::resource("/workspace/plan").route(web::put().to(update_workspace_plan_handler)))
+    .service(
+      web::resource("/blob/gc")
+        .route(web::get().to(get_blob_gc_report_handler))
+        .route(web::post().to(run_blob_gc_handler)),
+    )
+}
+
+#[instrument(level = "debug", skip(state, auth), err)]
+async fn get_workspace_plans_handler(
+  auth: Authorization,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<WorkspacePlans>> {
+  check_admin(&auth)?;
+  let plans = select_workspace_plans(&state.pg_pool)
+    .await
+    .map_err(AppResponseError::from)?;
+  Ok(AppResponse::Ok().with_data(WorkspacePlans(plans)).into())
+}
+
+#[instrument(level = "debug", skip(state, auth, payload), err)]
+async fn update_workspace_plan_handler(
+  auth: Authorization,
+  payload: Json<UpdateWorkspacePlanParams>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<()>> {
+  check_admin(&auth)?;
+  let params = payload.into_inner();
+  update_workspace_type(&state.pg_pool, &params.workspace_id, params.workspace_type)
+    .await
+    .map_err(AppResponseError::from)?;
+  Ok(AppResponse::Ok().into())
+}
+
+/// Returns the report of the last garbage collection of the blobs.
+#[instrument(level = "debug", skip(state, auth), err)]
+async fn get_blob_gc_report_handler(
+  auth: Authorization,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<AFBlobGCReport>> {
+  check_admin(&auth)?;
+  let report = state
+    .blob_gc
+    .last_report()
+    .await
+    .ok_or_else(|| AppError::RecordNotFound("the blob gc has not run yet".to_string()))
+    .map_err(AppResponseError::from)?;
+  Ok(AppResponse::Ok().with_data(report).into())
+}
+
+#[instrument(level = "debug", skip(state, auth), err)]
+async fn run_blob_gc_handler(
+  auth: Authorization,
+  payload: Json<RunBlobGCParams>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<AFBlobGCReport>> {
+  check_admin(&auth)?;
+  let report = state
+    .blob_gc
+    .run(payload.dry_run)
+    .await
+    .map_err(AppResponseError::from)?;
+  Ok(AppResponse::Ok().with_data(report).into())
+}
+
+fn check_admin(auth: &Authorization) -> Result<(), AppResponseError> {
+  if auth.is_admin() {
+    Ok(())
+  } else {
+    Err(
+      AppError::NotEnoughPermissions("only the admin user can access this api".to_string()).into(),
+    )
+  }
+}
@@ -1,7 +1,8 @@
 use actix_http::body::BoxBody;
 use actix_web::http::header::{
-  ContentLength, ContentType, CACHE_CONTROL, CONTENT_LENGTH, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE,
-  LAST_MODIFIED,
+  ByteRangeSpec, ContentLength, ContentType, HeaderName, Range, ACCEPT_RANGES, CACHE_CONTROL,
+  CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, IF_RANGE,
+  LAST_MODIFIED, RANGE,
 };
 use actix_web::web::{Json, Payload};
 use actix_web::{
@@ -11,20 +12,31 @@ use actix_web::{
 use actix_web::{HttpResponse, Result};
 use app_error::AppError;
 use chrono::DateTime;
-use database::file::{MAX_BLOB_SIZE, MAX_USAGE};
+use database::file::thumbnail::{thumbnail_id, thumbnail_mime};
+use database::file::MAX_BLOB_SIZE;
 use database::resource_usage::{get_all_workspace_blob_metadata, get_workspace_usage_size};
-use database_entity::dto::AFBlobRecord;
+use database::workspace::{select_workspace_member_count, select_workspace_plan};
+use database_entity::dto::{
+  AFAuditAction, AFBlobRecord, AFPresignedUrl, AFUpload, AFUploadPart, AFUploadParts,
+  CreatePresignedUploadParams, CreateUploadParams,
+};
 use database_entity::pg_row::AFBlobMetadataRow;
 use serde::Deserialize;
 use shared_entity::dto::workspace_dto::{WorkspaceBlobMetadata, WorkspaceSpaceUsage};
 use shared_entity::response::{AppResponse, AppResponseError, JsonAppResponse};
 use sqlx::types::Uuid;
+use std::ops::DerefMut;
 use std::pin::Pin;
 use tokio::io::AsyncRead;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
 use tokio_stream::StreamExt;
 use tokio_util::io::StreamReader;
-use tracing::{event, instrument};
+use tracing::{error, event, instrument};
 
+use crate::biz::audit::{record_audit_log, AuditActor};
+use crate::component::auth::jwt::UserUuid;
+use crate::middleware::request_id::RequestId;
 use crate::state::AppState;
 
 pub fn file_storage_scope() -> Scope {
@@ -46,6 +58,32 @@ pub fn file_storage_scope() -> Scope {
       web::resource("/{workspace_id}/blobs")
         .route(web::get().to(get_all_workspace_blob_metadata_handler)),
     )
+    .service(web::resource("/{workspace_id}/upload").route(web::post().to(create_upload_handler)))
+    .service(
+      web::resource("/{workspace_id}/upload/{upload_id}")
+        .route(web::get().to(get_upload_parts_handler))
+        .route(web::delete().to(abort_upload_handler)),
+    )
+    .service(
+      web::resource("/{workspace_id}/upload/{upload_id}/part/{part_number}")
+        .route(web::put().to(put_upload_part_handler)),
+    )
+    .service(
+      web::resource("/{workspace_id}/upload/{upload_id}/complete")
+        .route(web::post().to(complete_upload_handler)),
+    )
+    .service(
+      web::resource("/{workspace_id}/presigned/upload")
+        .route(web::post().to(create_presigned_upload_handler)),
+    )
+    .service(
+      web::resource("/{workspace_id}/presigned/upload/{file_id}/complete")
+        .route(web::post().to(complete_presigned_upload_handler)),
+    )
+    .service(
+      web::resource("/{workspace_id}/presigned/download/{file_id:.*}")
+        .route(web::get().to(create_presigned_download_handler)),
+    )
 }
 
 #[derive(Deserialize, Debug)]
@@ -54,6 +92,25 @@ struct PathInfo {
   file_id: String,
 }
 
+#[derive(Deserialize, Debug)]
+struct BlobQuery {
+  /// The width of the requested thumbnail. Only applies to the image blobs.
+  w: Option<u32>,
+}
+
+#[derive(Deserialize, Debug)]
+struct UploadPathInfo {
+  workspace_id: Uuid,
+  upload_id: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct UploadPartPathInfo {
+  workspace_id: Uuid,
+  upload_id: String,
+  part_number: i32,
+}
+
 #[instrument(skip(state, payload), err)]
 async fn put_blob_handler(
   state: Data<AppState>,
@@ -95,6 +152,8 @@ async fn put_blob_handler(
 
 #[instrument(level = "debug", skip(state), err)]
 async fn delete_blob_handler(
+  user_uuid: UserUuid,
+  request_id: RequestId,
   state: Data<AppState>,
   path: web::Path<PathInfo>,
 ) -> Result<JsonAppResponse<()>> {
@@ -103,11 +162,38 @@ async fn delete_blob_handler(
     file_id,
   } = path.into_inner();
 
-  state
+  // The log is recorded in the same transaction as the metadata, so a failure of recording
+  // doesn't leave the metadata of a removed blob behind.
+  let mut txn = state.pg_pool.begin().await.map_err(AppError::from)?;
+  let actor = AuditActor::new(&user_uuid, request_id);
+  record_audit_log(
+    txn.deref_mut(),
+    &actor,
+    Some(&workspace_id),
+    AFAuditAction::BlobDelete,
+    &file_id,
+    None,
+  )
+  .await?;
+
+  let is_unreferenced = state
     .bucket_storage
-    .delete_blob(&workspace_id, &file_id)
+    .delete_blob(&mut txn, &workspace_id, &file_id)
     .await
     .map_err(AppResponseError::from)?;
+  txn.commit().await.map_err(AppError::from)?;
+
+  // The blob is removed after the metadata is committed, a blob that is left behind is removed by
+  // the blob gc
+  if is_unreferenced {
+    if let Err(err) = state
+      .bucket_storage
+      .remove_unreferenced_blob(&file_id)
+      .await
+    {
+      error!("fail to remove blob:{}, err: {}", file_id, err);
+    }
+  }
   Ok(AppResponse::Ok().into())
 }
 
@@ -115,6 +201,7 @@ async fn delete_blob_handler(
 async fn get_blob_handler(
   state: Data<AppState>,
   path: web::Path<PathInfo>,
+  query: web::Query<BlobQuery>,
   req: HttpRequest,
 ) -> Result<HttpResponse<BoxBody>> {
   let PathInfo {
@@ -137,17 +224,70 @@ async fn get_blob_handler(
   }
 
   let metadata = result.unwrap();
-  // Check if the file is modified since the last time
-  if let Some(modified_since) = req
-    .headers()
-    .get(IF_MODIFIED_SINCE)
-    .and_then(|h| h.to_str().ok())
-    .and_then(|s| DateTime::parse_from_rfc2822(s).ok())
-  {
-    if metadata.modified_at.naive_utc() <= modified_since.naive_utc() {
-      return Ok(HttpResponse::NotModified().finish());
+  let last_modified = metadata
+    .modified_at
+    .format("%a, %d %b %Y %H:%M:%S GMT")
+    .to_string();
+
+  if let Some(width) = query.w.filter(|_| metadata.file_type.starts_with("image/")) {
+    if let Some((width, thumbnail)) = get_thumbnail(&state, &file_id, width).await {
+      let etag = format!("\"{}\"", thumbnail_id(&file_id, width));
+      if is_not_modified(&req, &etag, &metadata) {
+        return Ok(not_modified_response(etag, last_modified));
+      }
+      return Ok(
+        HttpResponse::Ok()
+          .append_header((ETAG, etag))
+          .append_header((CONTENT_TYPE, thumbnail_mime(&thumbnail)))
+          .append_header((LAST_MODIFIED, last_modified))
+          .append_header((CONTENT_LENGTH, thumbnail.len()))
+          .append_header((CACHE_CONTROL, "public, immutable, max-age=31536000"))
+          .body(thumbnail),
+      );
     }
   }
+
+  // The file id is the content hash of the blob, except for the blobs uploaded in parts or with a
+  // presigned url, whose e_tag is recorded when they are completed.
+  let etag = format!("\"{}\"", metadata.e_tag.as_deref().unwrap_or(&file_id));
+
+  if is_not_modified(&req, &etag, &metadata) {
+    return Ok(not_modified_response(etag, last_modified));
+  }
+
+  let file_size = metadata.file_size as u64;
+  if let Some(range) = blob_range(&req, &etag, &last_modified) {
+    let (start, end) = match range.to_satisfiable_range(file_size) {
+      Some(range) => range,
+      None => {
+        return Ok(
+          HttpResponse::RangeNotSatisfiable()
+            .append_header((CONTENT_RANGE, format!("bytes */{}", file_size)))
+            .finish(),
+        );
+      },
+    };
+    let blob = state
+      .bucket_storage
+      .get_blob_range(&file_id, start, end)
+      .await
+      .map_err(AppResponseError::from)?;
+    return Ok(
+      HttpResponse::PartialContent()
+        .append_header((ETAG, etag))
+        .append_header((CONTENT_TYPE, metadata.file_type))
+        .append_header((LAST_MODIFIED, last_modified))
+        .append_header((
+          CONTENT_RANGE,
+          format!("bytes {}-{}/{}", start, end, file_size),
+        ))
+        .append_header((CONTENT_LENGTH, blob.len()))
+        .append_header((ACCEPT_RANGES, "bytes"))
+        .append_header((CACHE_CONTROL, "public, immutable, max-age=31536000"))
+        .body(blob),
+    );
+  }
+
   let blob = state
     .bucket_storage
     .get_blob(&file_id)
@@ -155,16 +295,85 @@ async fn get_blob_handler(
     .map_err(AppResponseError::from)?;
 
   let response = HttpResponse::Ok()
-    .append_header((ETAG, file_id))
+    .append_header((ETAG, etag))
     .append_header((CONTENT_TYPE, metadata.file_type))
-    .append_header((LAST_MODIFIED, metadata.modified_at.to_rfc2822()))
+    .append_header((LAST_MODIFIED, last_modified))
     .append_header((CONTENT_LENGTH, blob.len()))
+    .append_header((ACCEPT_RANGES, "bytes"))
     .append_header((CACHE_CONTROL, "public, immutable, max-age=31536000"))// 31536000 seconds = 1 year
     .body(blob);
 
   Ok(response)
 }
 
+/// Returns the thumbnail that fits the requested width. Falls back to the original blob if the
+/// thumbnail is missing, e.g. the image is narrower than the width or its format is unsupported.
+async fn get_thumbnail(state: &AppState, file_id: &str, width: u32) -> Option<(u32, Vec<u8>)> {
+  match state.bucket_storage.get_thumbnail(file_id, width).await {
+    Ok(thumbnail) => thumbnail,
+    Err(err) => {
+      event!(
+        tracing::Level::DEBUG,
+        "fail to get thumbnail of file:{}, err: {}",
+        file_id,
+        err
+      );
+      None
+    },
+  }
+}
+
+/// Returns true if the client already has the file. If-None-Match takes precedence over
+/// If-Modified-Since.
+fn is_not_modified(req: &HttpRequest, etag: &str, metadata: &AFBlobMetadataRow) -> bool {
+  match header_str(req, IF_NONE_MATCH) {
+    Some(if_none_match) => is_etag_matched(if_none_match, etag),
+    None => header_str(req, IF_MODIFIED_SINCE)
+      .and_then(|s| DateTime::parse_from_rfc2822(s).ok())
+      // The http date has no sub-second precision
+      .map(|modified_since| metadata.modified_at.timestamp() <= modified_since.timestamp())
+      .unwrap_or(false),
+  }
+}
+
+fn not_modified_response(etag: String, last_modified: String) -> HttpResponse<BoxBody> {
+  HttpResponse::NotModified()
+    .append_header((ETAG, etag))
+    .append_header((LAST_MODIFIED, last_modified))
+    .finish()
+}
+
+#[inline]
+fn header_str(req: &HttpRequest, name: HeaderName) -> Option<&str> {
+  req.headers().get(name).and_then(|h| h.to_str().ok())
+}
+
+/// Returns true if the If-None-Match or If-Range header value contains the etag. The weak
+/// validators are compared the same way as the strong ones.
+fn is_etag_matched(header: &str, etag: &str) -> bool {
+  header
+    .split(',')
+    .map(|tag| tag.trim())
+    .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+/// Returns the requested byte range of the blob. Only a single range is supported, the whole
+/// blob is returned for multiple ranges. The range is ignored if the If-Range header doesn't
+/// match the current version of the blob.
+fn blob_range(req: &HttpRequest, etag: &str, last_modified: &str) -> Option<ByteRangeSpec> {
+  if let Some(if_range) = header_str(req, IF_RANGE) {
+    // If-Range is either a strong etag or a http date
+    if if_range != etag && if_range != last_modified {
+      return None;
+    }
+  }
+
+  match header_str(req, RANGE)?.parse::<Range>().ok()? {
+    Range::Bytes(mut ranges) if ranges.len() == 1 => ranges.pop(),
+    _ => None,
+  }
+}
+
 #[instrument(level = "debug", skip(state), err)]
 async fn get_blob_metadata_handler(
   state: Data<AppState>,
@@ -193,9 +402,20 @@ async fn get_workspace_usage_handler(
   let current = get_workspace_usage_size(&state.pg_pool, &workspace_id)
     .await
     .map_err(AppResponseError::from)?;
+  let plan = select_workspace_plan(&state.pg_pool, &workspace_id)
+    .await
+    .map_err(AppResponseError::from)?;
+  let member_count = select_workspace_member_count(&state.pg_pool, &workspace_id)
+    .await
+    .map_err(AppResponseError::from)?;
   let usage = WorkspaceSpaceUsage {
     consumed_capacity: current,
-    total_capacity: MAX_USAGE,
+    total_capacity: plan.storage_limit as u64,
+    workspace_type: plan.workspace_type,
+    plan_name: plan.name,
+    max_blob_size: plan.blob_size_limit as u64,
+    member_count: member_count as u64,
+    max_members: plan.member_limit as u64,
   };
   Ok(AppResponse::Ok().with_data(usage).into())
 }
@@ -215,9 +435,161 @@ async fn get_all_workspace_blob_metadata_handler(
       .into(),
   )
 }
+#[instrument(level = "debug", skip(state), err)]
+async fn create_upload_handler(
+  state: Data<AppState>,
+  workspace_id: web::Path<Uuid>,
+  payload: Json<CreateUploadParams>,
+) -> Result<JsonAppResponse<AFUpload>> {
+  let params = payload.into_inner();
+  let upload = state
+    .bucket_storage
+    .create_upload(&workspace_id, &params.file_type, params.file_size)
+    .await
+    .map_err(AppResponseError::from)?;
+  Ok(Json(AppResponse::Ok().with_data(upload)))
+}
+
+#[instrument(level = "debug", skip(state), err)]
+async fn get_upload_parts_handler(
+  state: Data<AppState>,
+  path: web::Path<UploadPathInfo>,
+) -> Result<JsonAppResponse<AFUploadParts>> {
+  let parts = state
+    .bucket_storage
+    .get_upload_parts(&path.workspace_id, &path.upload_id)
+    .await
+    .map_err(AppResponseError::from)?;
+  Ok(Json(AppResponse::Ok().with_data(AFUploadParts(parts))))
+}
+
+#[instrument(skip(state, payload), err)]
+async fn put_upload_part_handler(
+  state: Data<AppState>,
+  payload: Payload,
+  content_length: web::Header<ContentLength>,
+  path: web::Path<UploadPartPathInfo>,
+) -> Result<JsonAppResponse<AFUploadPart>> {
+  let content_length = content_length.into_inner().into_inner();
+  let UploadPartPathInfo {
+    workspace_id,
+    upload_id,
+    part_number,
+  } = path.into_inner();
+  let part = state
+    .bucket_storage
+    .put_upload_part(
+      &workspace_id,
+      &upload_id,
+      part_number,
+      content_length as u64,
+      payload_to_send_async_read(payload),
+    )
+    .await
+    .map_err(AppResponseError::from)?;
+  event!(tracing::Level::TRACE, "did put upload part: {:?}", part);
+  Ok(Json(AppResponse::Ok().with_data(part)))
+}
+
+#[instrument(level = "debug", skip(state), err)]
+async fn complete_upload_handler(
+  state: Data<AppState>,
+  path: web::Path<UploadPathInfo>,
+) -> Result<JsonAppResponse<AFBlobRecord>> {
+  let file_id = state
+    .bucket_storage
+    .complete_upload(&path.workspace_id, &path.upload_id)
+    .await
+    .map_err(AppResponseError::from)?;
+  Ok(Json(
+    AppResponse::Ok().with_data(AFBlobRecord::new(file_id)),
+  ))
+}
+
+#[instrument(level = "debug", skip(state), err)]
+async fn abort_upload_handler(
+  state: Data<AppState>,
+  path: web::Path<UploadPathInfo>,
+) -> Result<JsonAppResponse<()>> {
+  state
+    .bucket_storage
+    .abort_upload(&path.workspace_id, &path.upload_id)
+    .await
+    .map_err(AppResponseError::from)?;
+  Ok(AppResponse::Ok().into())
+}
+
+#[instrument(level = "debug", skip(state), err)]
+async fn create_presigned_upload_handler(
+  state: Data<AppState>,
+  workspace_id: web::Path<Uuid>,
+  payload: Json<CreatePresignedUploadParams>,
+) -> Result<JsonAppResponse<AFPresignedUrl>> {
+  let params = payload.into_inner();
+  let presigned_url = state
+    .bucket_storage
+    .create_presigned_upload(&workspace_id, &params.file_type, params.file_size)
+    .await
+    .map_err(AppResponseError::from)?;
+  Ok(Json(AppResponse::Ok().with_data(presigned_url)))
+}
+
+#[instrument(level = "debug", skip(state), err)]
+async fn complete_presigned_upload_handler(
+  state: Data<AppState>,
+  path: web::Path<PathInfo>,
+) -> Result<JsonAppResponse<AFBlobRecord>> {
+  let PathInfo {
+    workspace_id,
+    file_id,
+  } = path.into_inner();
+  state
+    .bucket_storage
+    .complete_presigned_upload(&workspace_id, &file_id)
+    .await
+    .map_err(AppResponseError::from)?;
+  Ok(Json(
+    AppResponse::Ok().with_data(AFBlobRecord::new(file_id)),
+  ))
+}
+
+#[instrument(level = "debug", skip(state), err)]
+async fn create_presigned_download_handler(
+  state: Data<AppState>,
+  path: web::Path<PathInfo>,
+) -> Result<JsonAppResponse<AFPresignedUrl>> {
+  let PathInfo {
+    workspace_id,
+    file_id,
+  } = path.into_inner();
+  let presigned_url = state
+    .bucket_storage
+    .create_presigned_download(&workspace_id, &file_id)
+    .await
+    .map_err(AppResponseError::from)?;
+  Ok(Json(AppResponse::Ok().with_data(presigned_url)))
+}
+
 fn payload_to_async_read(payload: Payload) -> Pin<Box<dyn AsyncRead>> {
   let mapped =
     payload.map(|chunk| chunk.map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e)));
   let reader = StreamReader::new(mapped);
   Box::pin(reader)
 }
+
+/// The payload is bound to the worker thread, so it's forwarded through a channel to get a
+/// stream that can be passed to the bucket client. The channel is bounded, so the payload is read
+/// no faster than the bucket consumes it.
+pub(crate) fn payload_to_send_async_read(mut payload: Payload) -> impl AsyncRead + Unpin + Send {
+  let (tx, rx) = mpsc::channel(4);
+  actix_web::rt::spawn(async move {
+    while let Some(chunk) = payload.next().await {
+      let chunk = chunk.map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e));
+      let is_err = chunk.is_err();
+      if tx.send(chunk).await.is_err() || is_err {
+        break;
+      }
+    }
+  });
+  StreamReader::new(ReceiverStream::new(rx))
+}
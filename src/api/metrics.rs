@@ -8,6 +8,7 @@ use prometheus_client::metrics::counter::Counter;
 use prometheus_client::metrics::exemplar::CounterWithExemplar;
 use prometheus_client::metrics::family::Family;
 use prometheus_client::registry::Registry;
+use realtime::collaborate::CollabRealtimeMetrics;
 use std::sync::Arc;
 
 pub fn metrics_scope() -> Scope {
@@ -27,11 +28,13 @@ async fn metrics_handler(reg: web::Data<Arc<Registry>>) -> Result<HttpResponse>
   )
 }
 
-pub fn metrics_registry() -> (AppFlowyCloudMetrics, Registry) {
+pub fn metrics_registry() -> (AppFlowyCloudMetrics, CollabRealtimeMetrics, Registry) {
   let metric = AppFlowyCloudMetrics::init();
+  let realtime_metric = CollabRealtimeMetrics::default();
   let mut registry = Registry::default();
   AppFlowyCloudMetrics::register(metric.clone(), &mut registry);
-  (metric, registry)
+  realtime_metric.register(registry.sub_registry_with_prefix("appflowy_cloud"));
+  (metric, realtime_metric, registry)
 }
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
@@ -1,5 +1,7 @@
+pub mod admin;
 pub mod file_storage;
 pub mod metrics;
+pub mod share;
 pub mod user;
 pub mod workspace;
 pub mod ws;
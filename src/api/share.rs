@@ -0,0 +1,42 @@
+use crate::biz;
+use crate::state::AppState;
+use actix_web::web::{Data, Path};
+use actix_web::{web, Scope};
+use actix_web::{HttpRequest, Result};
+use database_entity::dto::AFSharedCollab;
+use shared_entity::dto::workspace_dto::SHARE_LINK_PASSWORD_HEADER;
+use shared_entity::response::{AppResponse, AppResponseError, JsonAppResponse};
+use tracing::instrument;
+
+/// The endpoints of the share links. They can be accessed without signing in, the token of the
+/// link is the credential.
+pub fn share_scope() -> Scope {
+  web::scope("/api/share")
+    .service(web::resource("{token}").route(web::get().to(get_shared_collab_handler)))
+}
+
+/// Returns the password of the share link carried by the [SHARE_LINK_PASSWORD_HEADER].
+pub(crate) fn share_link_password(req: &HttpRequest) -> Option<String> {
+  req
+    .headers()
+    .get(SHARE_LINK_PASSWORD_HEADER)
+    .and_then(|value| value.to_str().ok())
+    .map(|value| value.to_string())
+}
+
+#[instrument(level = "debug", skip_all, err)]
+async fn get_shared_collab_handler(
+  req: HttpRequest,
+  token: Path<String>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<AFSharedCollab>> {
+  let collab = biz::collab::share_link::get_shared_collab(
+    &state.pg_pool,
+    &state.collab_storage,
+    &token,
+    share_link_password(&req),
+  )
+  .await
+  .map_err(AppResponseError::from)?;
+  Ok(AppResponse::Ok().with_data(collab).into())
+}
@@ -1,4 +1,5 @@
 use crate::biz;
+use crate::biz::audit::AuditActor;
 use crate::component::auth::{
   change_password, logged_user_from_request, login, logout, register, ChangePasswordRequest,
   RegisterRequest,
@@ -8,6 +9,7 @@ use crate::component::auth::{InputParamsError, LoginRequest};
 
 use crate::component::token_state::SessionToken;
 use crate::domain::{UserEmail, UserName, UserPassword};
+use crate::middleware::request_id::RequestId;
 use crate::state::AppState;
 use shared_entity::dto::auth_dto::{SignInTokenResponse, UpdateUserParams};
 use shared_entity::response::{AppResponse, JsonAppResponse};
@@ -78,10 +80,12 @@ async fn get_user_workspace_info_handler(
 async fn update_user_handler(
   auth: Authorization,
   payload: Json<UpdateUserParams>,
+  request_id: RequestId,
   state: Data<AppState>,
 ) -> Result<JsonAppResponse<()>> {
   let params = payload.into_inner();
-  biz::user::update_user(&state.pg_pool, auth.uuid()?, params).await?;
+  let actor = AuditActor::new(&auth.uuid()?, request_id);
+  biz::user::update_user(&state.pg_pool, &actor, params).await?;
   Ok(AppResponse::Ok().into())
 }
 
@@ -1,15 +1,21 @@
+use crate::api::file_storage::payload_to_send_async_read;
 use crate::api::ws::CollabServerImpl;
 use crate::biz;
+use crate::biz::audit::AuditActor;
 use crate::biz::user::RealtimeUserImpl;
 use crate::biz::workspace;
 use crate::component::auth::jwt::UserUuid;
+use crate::middleware::request_id::RequestId;
 use crate::state::AppState;
+use actix_web::http::header::CONTENT_DISPOSITION;
 use actix_web::web::Bytes;
-use actix_web::web::{Data, Json, PayloadConfig};
+use actix_web::web::{Data, Json, Payload, PayloadConfig};
+use actix_web::HttpResponse;
 use actix_web::Result;
 use actix_web::{web, Scope};
 use app_error::AppError;
 use collab::core::collab_plugin::EncodedCollabV1;
+use collab_document::blocks::DocumentData;
 use database::collab::CollabStorage;
 use database::user::{select_uid_from_email, select_uid_from_uuid};
 use database_entity::dto::*;
@@ -17,6 +23,7 @@ use prost::Message as ProstMessage;
 use realtime::collaborate::CollabAccessControl;
 use realtime::entities::{ClientMessage, RealtimeMessage};
 use realtime_entity::realtime_proto::HttpRealtimeMessage;
+use serde::Deserialize;
 use shared_entity::dto::workspace_dto::*;
 use shared_entity::response::AppResponseError;
 use shared_entity::response::{AppResponse, JsonAppResponse};
@@ -28,11 +35,32 @@ use uuid::Uuid;
 
 pub const WORKSPACE_ID_PATH: &str = "workspace_id";
 pub const COLLAB_OBJECT_ID_PATH: &str = "object_id";
+const DEFAULT_SEARCH_LIMIT: u32 = 20;
 
 pub fn workspace_scope() -> Scope {
   web::scope("/api/workspace")
+    .service(web::resource("").route(web::post().to(create_workspace_handler)))
     .service(web::resource("list").route(web::get().to(list_handler)))
+    .service(web::resource("archive").route(web::post().to(import_workspace_handler)))
+    .service(web::resource("invite").route(web::get().to(get_user_invitations_handler)))
+    .service(
+      web::resource("invite/{invite_id}/accept")
+        .route(web::post().to(accept_workspace_invitation_handler)),
+    )
+    .service(
+      web::resource("invite/{invite_id}/decline")
+        .route(web::post().to(decline_workspace_invitation_handler)),
+    )
+    .service(
+      web::resource("{workspace_id}")
+        .route(web::put().to(patch_workspace_handler))
+        .route(web::delete().to(delete_workspace_handler)),
+    )
+    .service(
+      web::resource("{workspace_id}/owner").route(web::put().to(transfer_workspace_owner_handler)),
+    )
     .service(web::resource("{workspace_id}/open").route(web::put().to(open_workspace_handler)))
+    .service(web::resource("{workspace_id}/archive").route(web::get().to(export_workspace_handler)))
     .service(
       web::resource("{workspace_id}/member")
         .route(web::get().to(get_workspace_members_handler))
@@ -40,6 +68,29 @@ pub fn workspace_scope() -> Scope {
         .route(web::put().to(update_workspace_member_handler))
         .route(web::delete().to(remove_workspace_member_handler)),
     )
+    .service(
+      web::resource("{workspace_id}/invite")
+        .route(web::get().to(get_workspace_invitations_handler))
+        .route(web::post().to(invite_workspace_members_handler)),
+    )
+    .service(
+      web::resource("{workspace_id}/invite/{invite_id}")
+        .route(web::delete().to(revoke_workspace_invitation_handler)),
+    )
+    .service(
+      web::resource("{workspace_id}/role")
+        .route(web::get().to(get_workspace_roles_handler))
+        .route(web::post().to(create_workspace_role_handler)),
+    )
+    .service(
+      web::resource("{workspace_id}/role/{role_id}")
+        .route(web::put().to(update_workspace_role_handler))
+        .route(web::delete().to(delete_workspace_role_handler)),
+    )
+    .service(
+      web::resource("{workspace_id}/member/role")
+        .route(web::put().to(assign_workspace_role_handler)),
+    )
     .service(
       web::resource("{workspace_id}/collab/{object_id}")
         .app_data(
@@ -61,6 +112,86 @@ pub fn workspace_scope() -> Scope {
       web::resource("{workspace_id}/collab/{object_id}/member/list")
         .route(web::get().to(get_collab_member_list_handler)),
     )
+    .service(
+      web::resource("{workspace_id}/collab/{object_id}/share")
+        .route(web::post().to(create_share_link_handler))
+        .route(web::get().to(get_share_links_handler)),
+    )
+    .service(
+      web::resource("{workspace_id}/collab/{object_id}/share/{share_id}")
+        .route(web::delete().to(delete_share_link_handler)),
+    )
+    .service(
+      web::resource("{workspace_id}/collab/{object_id}/comment")
+        .route(web::post().to(create_comment_handler))
+        .route(web::get().to(get_comments_handler)),
+    )
+    .service(
+      web::resource("{workspace_id}/collab/{object_id}/comment/{comment_id}")
+        .route(web::put().to(update_comment_handler))
+        .route(web::delete().to(delete_comment_handler)),
+    )
+    .service(
+      web::resource("{workspace_id}/collab/{object_id}/comment/{comment_id}/resolve")
+        .route(web::put().to(resolve_comment_handler)),
+    )
+    .service(
+      web::resource("{workspace_id}/collab/{object_id}/snapshot/diff")
+        .route(web::get().to(get_snapshot_diff_handler)),
+    )
+    .service(
+      web::resource("{workspace_id}/collab/{object_id}/snapshot/{snapshot_id}/restore")
+        .route(web::post().to(restore_snapshot_handler)),
+    )
+    .service(
+      web::resource("{workspace_id}/collab/{object_id}/document")
+        .route(web::get().to(get_document_handler)),
+    )
+    .service(
+      web::resource("{workspace_id}/collab/{object_id}/document/blocks")
+        .route(web::post().to(insert_document_blocks_handler)),
+    )
+    .service(
+      web::resource("{workspace_id}/collab/{object_id}/export")
+        .route(web::get().to(export_document_handler)),
+    )
+    .service(web::resource("{workspace_id}/import").route(web::post().to(import_document_handler)))
+    .service(web::resource("{workspace_id}/folder").route(web::get().to(get_folder_views_handler)))
+    .service(
+      web::resource("{workspace_id}/folder/view").route(web::post().to(create_folder_view_handler)),
+    )
+    .service(
+      web::resource("{workspace_id}/folder/view/{object_id}")
+        .route(web::put().to(update_folder_view_handler))
+        .route(web::delete().to(delete_folder_view_handler)),
+    )
+    .service(
+      web::resource("{workspace_id}/folder/view/{object_id}/move")
+        .route(web::put().to(move_folder_view_handler)),
+    )
+    .service(
+      web::resource("{workspace_id}/database/{object_id}/field")
+        .route(web::get().to(get_database_fields_handler)),
+    )
+    .service(
+      web::resource("{workspace_id}/database/{object_id}/row")
+        .route(web::post().to(insert_database_row_handler)),
+    )
+    .service(
+      web::resource("{workspace_id}/database/{object_id}/row/query")
+        .route(web::post().to(query_database_rows_handler)),
+    )
+    .service(
+      web::resource("{workspace_id}/database/{object_id}/row/{row_id}")
+        .route(web::put().to(update_database_row_handler)),
+    )
+    .service(web::resource("{workspace_id}/search").route(web::get().to(search_collab_handler)))
+    .service(web::resource("{workspace_id}/audit").route(web::get().to(get_audit_logs_handler)))
+    .service(web::resource("{workspace_id}/trash").route(web::get().to(get_trash_handler)))
+    .service(
+      web::resource("{workspace_id}/trash/{object_id}/restore")
+        .route(web::post().to(restore_trash_handler)),
+    )
     .service(
       web::resource("{workspace_id}/collab_list").route(web::get().to(batch_get_collab_handler)),
     )
@@ -102,83 +233,657 @@ async fn list_handler(
 }
 
 #[instrument(skip(payload, state), err)]
-async fn add_workspace_members_handler(
+async fn create_workspace_handler(
+  user_uuid: UserUuid,
+  payload: Json<CreateWorkspaceParams>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<AFWorkspace>> {
+  let params = payload.into_inner();
+  let workspace =
+    workspace::ops::create_workspace(&state.pg_pool, &user_uuid, params.workspace_name).await?;
+  Ok(AppResponse::Ok().with_data(workspace).into())
+}
+
+#[instrument(skip(state), err)]
+async fn export_workspace_handler(
+  user_uuid: UserUuid,
+  request_id: RequestId,
+  workspace_id: web::Path<Uuid>,
+  state: Data<AppState>,
+) -> Result<HttpResponse> {
+  let actor = AuditActor::new(&user_uuid, request_id);
+  let archive = workspace::archive::export_workspace(
+    &state.pg_pool,
+    state.collab_storage.clone(),
+    state.bucket_storage.clone(),
+    &actor,
+    &workspace_id,
+  )
+  .await?;
+  Ok(
+    HttpResponse::Ok()
+      .content_type(WORKSPACE_ARCHIVE_CONTENT_TYPE)
+      .insert_header((
+        CONTENT_DISPOSITION,
+        format!("attachment; filename=\"workspace-{}.tar\"", workspace_id),
+      ))
+      .streaming(archive),
+  )
+}
+
+#[instrument(skip(payload, state), err)]
+async fn import_workspace_handler(
+  user_uuid: UserUuid,
+  request_id: RequestId,
+  query: web::Query<ImportWorkspaceParams>,
+  payload: Payload,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<AFWorkspace>> {
+  let actor = AuditActor::new(&user_uuid, request_id);
+  let workspace = workspace::archive::import_workspace(
+    &state.pg_pool,
+    &state.bucket_storage,
+    &state.gotrue_client,
+    &state.config.gotrue,
+    &actor,
+    &query,
+    payload_to_send_async_read(payload),
+  )
+  .await?;
+  Ok(AppResponse::Ok().with_data(workspace).into())
+}
+
+#[instrument(skip(payload, state), err)]
+async fn patch_workspace_handler(
+  user_uuid: UserUuid,
+  request_id: RequestId,
+  payload: Json<PatchWorkspaceParams>,
+  state: Data<AppState>,
+  workspace_id: web::Path<Uuid>,
+) -> Result<JsonAppResponse<()>> {
+  let params = payload.into_inner();
+  let actor = AuditActor::new(&user_uuid, request_id);
+  workspace::ops::rename_workspace(
+    &state.pg_pool,
+    &actor,
+    &workspace_id,
+    &params.workspace_name,
+  )
+  .await?;
+  Ok(AppResponse::Ok().into())
+}
+
+#[instrument(skip(state), err)]
+async fn delete_workspace_handler(
+  user_uuid: UserUuid,
+  request_id: RequestId,
+  state: Data<AppState>,
+  workspace_id: web::Path<Uuid>,
+) -> Result<JsonAppResponse<()>> {
+  let actor = AuditActor::new(&user_uuid, request_id);
+  workspace::ops::delete_workspace(&state.pg_pool, &actor, &workspace_id).await?;
+  Ok(AppResponse::Ok().into())
+}
+
+#[instrument(skip(payload, state), err)]
+async fn transfer_workspace_owner_handler(
+  user_uuid: UserUuid,
+  request_id: RequestId,
+  payload: Json<TransferWorkspaceOwnershipParams>,
+  state: Data<AppState>,
+  workspace_id: web::Path<Uuid>,
+) -> Result<JsonAppResponse<()>> {
+  let workspace_id = workspace_id.into_inner();
+  let params = payload.into_inner();
+  let actor = AuditActor::new(&user_uuid, request_id);
+  let (new_owner_uid, old_owner_uid) = workspace::ops::transfer_workspace_ownership(
+    &state.pg_pool,
+    &actor,
+    &workspace_id,
+    &params.new_owner_email,
+  )
+  .await?;
+
+  state
+    .workspace_access_control
+    .update_member(&new_owner_uid, &workspace_id)
+    .await;
+  state
+    .workspace_access_control
+    .update_member(&old_owner_uid, &workspace_id)
+    .await;
+  Ok(AppResponse::Ok().into())
+}
+
+#[instrument(skip(payload, state), err)]
+async fn add_workspace_members_handler(
+  user_uuid: UserUuid,
+  request_id: RequestId,
+  workspace_id: web::Path<Uuid>,
+  payload: Json<CreateWorkspaceMembers>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<()>> {
+  let create_members = payload.into_inner();
+  workspace::role::check_grantable_roles(
+    &state.workspace_access_control,
+    &user_uuid,
+    &workspace_id,
+    create_members.0.iter().map(|member| &member.role),
+  )
+  .await?;
+  let actor = AuditActor::new(&user_uuid, request_id);
+  let role_by_uid =
+    workspace::ops::add_workspace_members(&state.pg_pool, &actor, &workspace_id, create_members.0)
+      .await?;
+
+  for uid in role_by_uid.keys() {
+    state
+      .workspace_access_control
+      .update_member(uid, &workspace_id)
+      .await;
+  }
+  Ok(AppResponse::Ok().into())
+}
+
+#[instrument(skip_all, err)]
+async fn get_workspace_members_handler(
+  user_uuid: UserUuid,
+  state: Data<AppState>,
+  workspace_id: web::Path<Uuid>,
+) -> Result<JsonAppResponse<Vec<AFWorkspaceMember>>> {
+  let members = workspace::ops::get_workspace_members(&state.pg_pool, &user_uuid, &workspace_id)
+    .await?
+    .into_iter()
+    .map(|member| AFWorkspaceMember {
+      name: member.name,
+      email: member.email,
+      role: member.role,
+      avatar_url: None,
+    })
+    .collect();
+
+  Ok(AppResponse::Ok().with_data(members).into())
+}
+
+#[derive(Deserialize, Debug)]
+struct SearchQuery {
+  q: String,
+  limit: Option<u32>,
+}
+
+#[instrument(level = "debug", skip(state), err)]
+async fn search_collab_handler(
+  user_uuid: UserUuid,
+  state: Data<AppState>,
+  workspace_id: web::Path<Uuid>,
+  query: web::Query<SearchQuery>,
+) -> Result<JsonAppResponse<AFCollabSearchResults>> {
+  let uid = select_uid_from_uuid(&state.pg_pool, &user_uuid)
+    .await
+    .map_err(AppResponseError::from)?;
+  let results = biz::collab::search::search_workspace_collabs(
+    &state.pg_pool,
+    &state.collab_access_control,
+    &uid,
+    &workspace_id,
+    &query.q,
+    query.limit.unwrap_or(DEFAULT_SEARCH_LIMIT),
+  )
+  .await
+  .map_err(AppResponseError::from)?;
+  Ok(
+    AppResponse::Ok()
+      .with_data(AFCollabSearchResults(results))
+      .into(),
+  )
+}
+
+#[instrument(skip(state), err)]
+async fn get_audit_logs_handler(
+  workspace_id: web::Path<Uuid>,
+  query: web::Query<QueryAuditLogParams>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<Vec<AFAuditLog>>> {
+  let logs = biz::audit::get_audit_logs(&state.pg_pool, &workspace_id, query.into_inner()).await?;
+  Ok(AppResponse::Ok().with_data(logs).into())
+}
+
+#[instrument(skip(state), err)]
+async fn get_trash_handler(
+  workspace_id: web::Path<Uuid>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<Vec<AFTrashedCollab>>> {
+  let collabs = biz::collab::trash::get_trashed_collabs(&state.pg_pool, &workspace_id).await?;
+  Ok(AppResponse::Ok().with_data(collabs).into())
+}
+
+#[instrument(skip(server, state), err)]
+async fn restore_trash_handler(
+  user_uuid: UserUuid,
+  request_id: RequestId,
+  path: web::Path<(Uuid, String)>,
+  server: Data<CollabServerImpl>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<()>> {
+  let (workspace_id, object_id) = path.into_inner();
+  let uid = select_uid_from_uuid(&state.pg_pool, &user_uuid)
+    .await
+    .map_err(AppResponseError::from)?;
+  let actor = AuditActor::new(&user_uuid, request_id);
+  biz::collab::trash::restore_collab(
+    &state.pg_pool,
+    &state.collab_storage,
+    &server.get_ref().clone().recipient(),
+    &actor,
+    &uid,
+    &workspace_id,
+    &object_id,
+  )
+  .await?;
+  Ok(AppResponse::Ok().into())
+}
+
+#[instrument(skip(state, payload), err)]
+async fn create_share_link_handler(
+  user_uuid: UserUuid,
+  request_id: RequestId,
+  path: web::Path<(Uuid, String)>,
+  payload: Json<CreateCollabShareLinkParams>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<AFCollabShareLink>> {
+  let (workspace_id, object_id) = path.into_inner();
+  let uid = select_uid_from_uuid(&state.pg_pool, &user_uuid)
+    .await
+    .map_err(AppResponseError::from)?;
+  let actor = AuditActor::new(&user_uuid, request_id);
+  let link = biz::collab::share_link::create_share_link(
+    &state.pg_pool,
+    &state.collab_storage,
+    &actor,
+    uid,
+    &workspace_id,
+    &object_id,
+    payload.into_inner(),
+  )
+  .await
+  .map_err(AppResponseError::from)?;
+  Ok(AppResponse::Ok().with_data(link).into())
+}
+
+#[instrument(skip(state), err)]
+async fn get_share_links_handler(
+  path: web::Path<(Uuid, String)>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<Vec<AFCollabShareLink>>> {
+  let (workspace_id, object_id) = path.into_inner();
+  let links = biz::collab::share_link::get_share_links(&state.pg_pool, &workspace_id, &object_id)
+    .await
+    .map_err(AppResponseError::from)?;
+  Ok(AppResponse::Ok().with_data(links).into())
+}
+
+#[instrument(skip(state), err)]
+async fn delete_share_link_handler(
+  user_uuid: UserUuid,
+  request_id: RequestId,
+  path: web::Path<(Uuid, String, Uuid)>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<()>> {
+  let (workspace_id, object_id, share_id) = path.into_inner();
+  let actor = AuditActor::new(&user_uuid, request_id);
+  biz::collab::share_link::delete_share_link(
+    &state.pg_pool,
+    &state.collab_access_control,
+    &actor,
+    &workspace_id,
+    &object_id,
+    &share_id,
+  )
+  .await
+  .map_err(AppResponseError::from)?;
+  Ok(AppResponse::Ok().into())
+}
+
+#[instrument(skip(state), err)]
+async fn get_comments_handler(
+  user_uuid: UserUuid,
+  path: web::Path<(Uuid, String)>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<Vec<AFCollabComment>>> {
+  let (workspace_id, object_id) = path.into_inner();
+  let uid = select_uid_from_uuid(&state.pg_pool, &user_uuid)
+    .await
+    .map_err(AppResponseError::from)?;
+  let comments = biz::collab::comment::get_comments(
+    &state.pg_pool,
+    state.collab_access_control.as_ref(),
+    uid,
+    &workspace_id,
+    &object_id,
+  )
+  .await
+  .map_err(AppResponseError::from)?;
+  Ok(AppResponse::Ok().with_data(comments).into())
+}
+
+#[instrument(skip(payload, server, state), err)]
+async fn create_comment_handler(
+  user_uuid: UserUuid,
+  path: web::Path<(Uuid, String)>,
+  payload: Json<CreateCollabCommentParams>,
+  server: Data<CollabServerImpl>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<AFCollabComment>> {
+  let (workspace_id, object_id) = path.into_inner();
+  let uid = select_uid_from_uuid(&state.pg_pool, &user_uuid)
+    .await
+    .map_err(AppResponseError::from)?;
+  let comment = biz::collab::comment::create_comment(
+    &state.pg_pool,
+    state.collab_access_control.as_ref(),
+    &server.get_ref().clone().recipient(),
+    uid,
+    &workspace_id,
+    &object_id,
+    payload.into_inner(),
+  )
+  .await
+  .map_err(AppResponseError::from)?;
+  Ok(AppResponse::Ok().with_data(comment).into())
+}
+
+#[instrument(skip(payload, server, state), err)]
+async fn update_comment_handler(
+  user_uuid: UserUuid,
+  path: web::Path<(Uuid, String, Uuid)>,
+  payload: Json<UpdateCollabCommentParams>,
+  server: Data<CollabServerImpl>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<AFCollabComment>> {
+  let (workspace_id, object_id, comment_id) = path.into_inner();
+  let uid = select_uid_from_uuid(&state.pg_pool, &user_uuid)
+    .await
+    .map_err(AppResponseError::from)?;
+  let comment = biz::collab::comment::update_comment(
+    &state.pg_pool,
+    state.collab_access_control.as_ref(),
+    &server.get_ref().clone().recipient(),
+    uid,
+    &workspace_id,
+    &object_id,
+    &comment_id,
+    payload.into_inner(),
+  )
+  .await
+  .map_err(AppResponseError::from)?;
+  Ok(AppResponse::Ok().with_data(comment).into())
+}
+
+#[instrument(skip(payload, server, state), err)]
+async fn resolve_comment_handler(
+  user_uuid: UserUuid,
+  path: web::Path<(Uuid, String, Uuid)>,
+  payload: Json<ResolveCollabCommentParams>,
+  server: Data<CollabServerImpl>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<AFCollabComment>> {
+  let (workspace_id, object_id, comment_id) = path.into_inner();
+  let uid = select_uid_from_uuid(&state.pg_pool, &user_uuid)
+    .await
+    .map_err(AppResponseError::from)?;
+  let comment = biz::collab::comment::resolve_comment(
+    &state.pg_pool,
+    state.collab_access_control.as_ref(),
+    &server.get_ref().clone().recipient(),
+    uid,
+    &workspace_id,
+    &object_id,
+    &comment_id,
+    payload.into_inner(),
+  )
+  .await
+  .map_err(AppResponseError::from)?;
+  Ok(AppResponse::Ok().with_data(comment).into())
+}
+
+#[instrument(skip(server, state), err)]
+async fn delete_comment_handler(
+  user_uuid: UserUuid,
+  path: web::Path<(Uuid, String, Uuid)>,
+  server: Data<CollabServerImpl>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<()>> {
+  let (workspace_id, object_id, comment_id) = path.into_inner();
+  let uid = select_uid_from_uuid(&state.pg_pool, &user_uuid)
+    .await
+    .map_err(AppResponseError::from)?;
+  biz::collab::comment::delete_comment(
+    &state.pg_pool,
+    state.collab_access_control.as_ref(),
+    &server.get_ref().clone().recipient(),
+    uid,
+    &workspace_id,
+    &object_id,
+    &comment_id,
+  )
+  .await
+  .map_err(AppResponseError::from)?;
+  Ok(AppResponse::Ok().into())
+}
+
+#[instrument(skip_all, err)]
+async fn remove_workspace_member_handler(
+  user_uuid: UserUuid,
+  request_id: RequestId,
+  payload: Json<WorkspaceMembers>,
+  state: Data<AppState>,
+  workspace_id: web::Path<Uuid>,
+) -> Result<JsonAppResponse<()>> {
+  let member_emails = payload
+    .into_inner()
+    .0
+    .into_iter()
+    .map(|member| member.0)
+    .collect::<Vec<String>>();
+  let actor = AuditActor::new(&user_uuid, request_id);
+  workspace::ops::remove_workspace_members(&actor, &state.pg_pool, &workspace_id, &member_emails)
+    .await?;
+
+  for email in member_emails {
+    if let Ok(uid) = select_uid_from_email(&state.pg_pool, &email)
+      .await
+      .map_err(AppResponseError::from)
+    {
+      state
+        .workspace_access_control
+        .remove_member(&uid, &workspace_id)
+        .await;
+    }
+  }
+
+  Ok(AppResponse::Ok().into())
+}
+
+#[instrument(skip(payload, state), err)]
+async fn invite_workspace_members_handler(
+  user_uuid: UserUuid,
+  workspace_id: web::Path<Uuid>,
+  payload: Json<WorkspaceMemberInvitations>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<()>> {
+  let invitations = payload.into_inner();
+  workspace::role::check_grantable_roles(
+    &state.workspace_access_control,
+    &user_uuid,
+    &workspace_id,
+    invitations.0.iter().map(|invitation| &invitation.role),
+  )
+  .await?;
+  workspace::invitation::invite_workspace_members(
+    &state.pg_pool,
+    &state.gotrue_client,
+    &state.config.gotrue,
+    &user_uuid,
+    &workspace_id,
+    invitations.0,
+  )
+  .await?;
+  Ok(AppResponse::Ok().into())
+}
+
+#[instrument(skip(state), err)]
+async fn get_workspace_roles_handler(
+  workspace_id: web::Path<Uuid>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<Vec<AFWorkspaceRole>>> {
+  let roles = workspace::role::get_workspace_roles(&state.pg_pool, &workspace_id).await?;
+  Ok(AppResponse::Ok().with_data(roles).into())
+}
+
+#[instrument(skip(payload, state), err)]
+async fn create_workspace_role_handler(
+  user_uuid: UserUuid,
+  request_id: RequestId,
+  workspace_id: web::Path<Uuid>,
+  payload: Json<CreateWorkspaceRoleParams>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<AFWorkspaceRole>> {
+  let actor = AuditActor::new(&user_uuid, request_id);
+  let role = workspace::role::create_workspace_role(
+    &state.pg_pool,
+    &actor,
+    &workspace_id,
+    payload.into_inner(),
+  )
+  .await?;
+  Ok(AppResponse::Ok().with_data(role).into())
+}
+
+#[instrument(skip(payload, state), err)]
+async fn update_workspace_role_handler(
+  user_uuid: UserUuid,
+  request_id: RequestId,
+  path: web::Path<(Uuid, i32)>,
+  payload: Json<UpdateWorkspaceRoleParams>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<AFWorkspaceRole>> {
+  let (workspace_id, role_id) = path.into_inner();
+  let actor = AuditActor::new(&user_uuid, request_id);
+  let role = workspace::role::update_workspace_custom_role(
+    &state.pg_pool,
+    &actor,
+    &workspace_id,
+    role_id,
+    payload.into_inner(),
+  )
+  .await?;
+  state
+    .workspace_access_control
+    .remove_custom_role(role_id)
+    .await;
+  state.collab_access_control.clear_cache().await;
+  Ok(AppResponse::Ok().with_data(role).into())
+}
+
+#[instrument(skip(state), err)]
+async fn delete_workspace_role_handler(
+  user_uuid: UserUuid,
+  request_id: RequestId,
+  path: web::Path<(Uuid, i32)>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<()>> {
+  let (workspace_id, role_id) = path.into_inner();
+  let actor = AuditActor::new(&user_uuid, request_id);
+  workspace::role::delete_workspace_custom_role(&state.pg_pool, &actor, &workspace_id, role_id)
+    .await?;
+  state
+    .workspace_access_control
+    .remove_custom_role(role_id)
+    .await;
+  state.collab_access_control.clear_cache().await;
+  Ok(AppResponse::Ok().into())
+}
+
+#[instrument(skip(payload, state), err)]
+async fn assign_workspace_role_handler(
   user_uuid: UserUuid,
+  request_id: RequestId,
   workspace_id: web::Path<Uuid>,
-  payload: Json<CreateWorkspaceMembers>,
+  payload: Json<AssignWorkspaceRoleParams>,
   state: Data<AppState>,
 ) -> Result<JsonAppResponse<()>> {
-  let create_members = payload.into_inner();
-  let role_by_uid = workspace::ops::add_workspace_members(
+  let workspace_id = workspace_id.into_inner();
+  let actor = AuditActor::new(&user_uuid, request_id);
+  let uid = workspace::role::assign_workspace_role(
     &state.pg_pool,
-    &user_uuid,
+    &state.workspace_access_control,
+    &actor,
     &workspace_id,
-    create_members.0,
+    &payload,
   )
   .await?;
+  state
+    .workspace_access_control
+    .update_member(&uid, &workspace_id)
+    .await;
+  state.collab_access_control.clear_user_cache(&uid).await;
+  Ok(AppResponse::Ok().into())
+}
 
-  for (uid, role) in role_by_uid {
-    state
-      .workspace_access_control
-      .update_member(&uid, &workspace_id, role)
-      .await;
-  }
+#[instrument(skip(state), err)]
+async fn get_workspace_invitations_handler(
+  workspace_id: web::Path<Uuid>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<Vec<AFWorkspaceInvitation>>> {
+  let invitations =
+    workspace::invitation::get_workspace_invitations(&state.pg_pool, &workspace_id).await?;
+  Ok(AppResponse::Ok().with_data(invitations).into())
+}
+
+#[instrument(skip(state), err)]
+async fn revoke_workspace_invitation_handler(
+  path: web::Path<(Uuid, Uuid)>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<()>> {
+  let (workspace_id, invite_id) = path.into_inner();
+  workspace::invitation::revoke_workspace_invitation(&state.pg_pool, &workspace_id, &invite_id)
+    .await?;
   Ok(AppResponse::Ok().into())
 }
 
-#[instrument(skip_all, err)]
-async fn get_workspace_members_handler(
+#[instrument(skip(state), err)]
+async fn get_user_invitations_handler(
   user_uuid: UserUuid,
   state: Data<AppState>,
-  workspace_id: web::Path<Uuid>,
-) -> Result<JsonAppResponse<Vec<AFWorkspaceMember>>> {
-  let members = workspace::ops::get_workspace_members(&state.pg_pool, &user_uuid, &workspace_id)
-    .await?
-    .into_iter()
-    .map(|member| AFWorkspaceMember {
-      name: member.name,
-      email: member.email,
-      role: member.role,
-      avatar_url: None,
-    })
-    .collect();
-
-  Ok(AppResponse::Ok().with_data(members).into())
+) -> Result<JsonAppResponse<Vec<AFWorkspaceInvitation>>> {
+  let invitations = workspace::invitation::get_user_invitations(&state.pg_pool, &user_uuid).await?;
+  Ok(AppResponse::Ok().with_data(invitations).into())
 }
 
-#[instrument(skip_all, err)]
-async fn remove_workspace_member_handler(
+#[instrument(skip(state), err)]
+async fn accept_workspace_invitation_handler(
   user_uuid: UserUuid,
-  payload: Json<WorkspaceMembers>,
+  invite_id: web::Path<Uuid>,
   state: Data<AppState>,
-  workspace_id: web::Path<Uuid>,
 ) -> Result<JsonAppResponse<()>> {
-  let member_emails = payload
-    .into_inner()
-    .0
-    .into_iter()
-    .map(|member| member.0)
-    .collect::<Vec<String>>();
-  workspace::ops::remove_workspace_members(
-    &user_uuid,
-    &state.pg_pool,
-    &workspace_id,
-    &member_emails,
-  )
-  .await?;
-
-  for email in member_emails {
-    if let Ok(uid) = select_uid_from_email(&state.pg_pool, &email)
-      .await
-      .map_err(AppResponseError::from)
-    {
-      state
-        .workspace_access_control
-        .remove_member(&uid, &workspace_id)
-        .await;
-    }
-  }
+  let (uid, workspace_id, _) =
+    workspace::invitation::accept_workspace_invitation(&state.pg_pool, &user_uuid, &invite_id)
+      .await?;
+  state
+    .workspace_access_control
+    .update_member(&uid, &workspace_id)
+    .await;
+  Ok(AppResponse::Ok().into())
+}
 
+#[instrument(skip(state), err)]
+async fn decline_workspace_invitation_handler(
+  user_uuid: UserUuid,
+  invite_id: web::Path<Uuid>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<()>> {
+  workspace::invitation::decline_workspace_invitation(&state.pg_pool, &user_uuid, &invite_id)
+    .await?;
   Ok(AppResponse::Ok().into())
 }
 
@@ -195,13 +900,17 @@ async fn open_workspace_handler(
 
 #[instrument(skip_all, err)]
 async fn update_workspace_member_handler(
+  user_uuid: UserUuid,
+  request_id: RequestId,
   payload: Json<WorkspaceMemberChangeset>,
   state: Data<AppState>,
   workspace_id: web::Path<Uuid>,
 ) -> Result<JsonAppResponse<()>> {
   let workspace_id = workspace_id.into_inner();
   let changeset = payload.into_inner();
-  workspace::ops::update_workspace_member(&state.pg_pool, &workspace_id, &changeset).await?;
+  let actor = AuditActor::new(&user_uuid, request_id);
+  workspace::ops::update_workspace_member(&state.pg_pool, &actor, &workspace_id, &changeset)
+    .await?;
 
   if let Some(role) = changeset.role {
     let uid = select_uid_from_email(&state.pg_pool, &changeset.email)
@@ -209,7 +918,7 @@ async fn update_workspace_member_handler(
       .map_err(AppResponseError::from)?;
     state
       .workspace_access_control
-      .update_member(&uid, &workspace_id, role)
+      .update_member(&uid, &workspace_id)
       .await;
   }
 
@@ -244,6 +953,297 @@ async fn get_collab_handler(
   Ok(Json(AppResponse::Ok().with_data(data)))
 }
 
+#[instrument(level = "debug", skip(state), err)]
+async fn get_document_handler(
+  user_uuid: UserUuid,
+  path: web::Path<(String, String)>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<DocumentData>> {
+  let (workspace_id, object_id) = path.into_inner();
+  let uid = select_uid_from_uuid(&state.pg_pool, &user_uuid)
+    .await
+    .map_err(AppResponseError::from)?;
+  let document =
+    biz::collab::document::get_document(&state.collab_storage, &uid, &workspace_id, &object_id)
+      .await?;
+  Ok(AppResponse::Ok().with_data(document).into())
+}
+
+#[instrument(level = "debug", skip(payload, server, state), err)]
+async fn insert_document_blocks_handler(
+  user_uuid: UserUuid,
+  path: web::Path<(String, String)>,
+  payload: Json<InsertDocumentBlocksParams>,
+  server: Data<CollabServerImpl>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<Vec<String>>> {
+  let (workspace_id, object_id) = path.into_inner();
+  let uid = select_uid_from_uuid(&state.pg_pool, &user_uuid)
+    .await
+    .map_err(AppResponseError::from)?;
+  let block_ids = biz::collab::document::insert_document_blocks(
+    &state.collab_storage,
+    &server.get_ref().clone().recipient(),
+    &uid,
+    &workspace_id,
+    &object_id,
+    payload.into_inner(),
+  )
+  .await?;
+  Ok(AppResponse::Ok().with_data(block_ids).into())
+}
+
+#[instrument(level = "debug", skip(state), err)]
+async fn export_document_handler(
+  user_uuid: UserUuid,
+  path: web::Path<(String, String)>,
+  query: web::Query<ExportDocumentParams>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<AFExportedDocument>> {
+  let (workspace_id, object_id) = path.into_inner();
+  let uid = select_uid_from_uuid(&state.pg_pool, &user_uuid)
+    .await
+    .map_err(AppResponseError::from)?;
+  let document = biz::collab::document::export_document(
+    &state.collab_storage,
+    &uid,
+    &workspace_id,
+    &object_id,
+    query.format,
+  )
+  .await?;
+  Ok(AppResponse::Ok().with_data(document).into())
+}
+
+#[instrument(level = "debug", skip(payload, state), err)]
+async fn import_document_handler(
+  user_uuid: UserUuid,
+  workspace_id: web::Path<Uuid>,
+  payload: Json<ImportDocumentParams>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<AFImportedDocument>> {
+  let document = biz::collab::document::import_document(
+    &state.pg_pool,
+    &user_uuid,
+    &workspace_id,
+    payload.into_inner(),
+  )
+  .await?;
+  Ok(AppResponse::Ok().with_data(document).into())
+}
+
+#[instrument(level = "debug", skip(state), err)]
+async fn get_folder_views_handler(
+  user_uuid: UserUuid,
+  workspace_id: web::Path<Uuid>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<Vec<AFFolderView>>> {
+  let uid = select_uid_from_uuid(&state.pg_pool, &user_uuid)
+    .await
+    .map_err(AppResponseError::from)?;
+  let views = biz::collab::folder::get_folder_views(
+    &state.collab_storage,
+    state.collab_access_control.as_ref(),
+    &uid,
+    &workspace_id,
+  )
+  .await?;
+  Ok(AppResponse::Ok().with_data(views).into())
+}
+
+#[instrument(level = "debug", skip(payload, server, state), err)]
+async fn create_folder_view_handler(
+  user_uuid: UserUuid,
+  workspace_id: web::Path<Uuid>,
+  payload: Json<CreateFolderViewParams>,
+  server: Data<CollabServerImpl>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<AFFolderView>> {
+  let uid = select_uid_from_uuid(&state.pg_pool, &user_uuid)
+    .await
+    .map_err(AppResponseError::from)?;
+  let view = biz::collab::folder::create_folder_view(
+    &state.pg_pool,
+    &state.collab_storage,
+    state.collab_access_control.as_ref(),
+    &server.get_ref().clone().recipient(),
+    &user_uuid,
+    &uid,
+    &workspace_id,
+    payload.into_inner(),
+  )
+  .await?;
+  Ok(AppResponse::Ok().with_data(view).into())
+}
+
+#[instrument(level = "debug", skip(payload, server, state), err)]
+async fn update_folder_view_handler(
+  user_uuid: UserUuid,
+  path: web::Path<(Uuid, String)>,
+  payload: Json<UpdateFolderViewParams>,
+  server: Data<CollabServerImpl>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<()>> {
+  let (workspace_id, view_id) = path.into_inner();
+  let uid = select_uid_from_uuid(&state.pg_pool, &user_uuid)
+    .await
+    .map_err(AppResponseError::from)?;
+  biz::collab::folder::update_folder_view(
+    &state.collab_storage,
+    state.collab_access_control.as_ref(),
+    &server.get_ref().clone().recipient(),
+    &uid,
+    &workspace_id,
+    &view_id,
+    payload.into_inner(),
+  )
+  .await?;
+  Ok(AppResponse::Ok().into())
+}
+
+#[instrument(level = "debug", skip(payload, server, state), err)]
+async fn move_folder_view_handler(
+  user_uuid: UserUuid,
+  path: web::Path<(Uuid, String)>,
+  payload: Json<MoveFolderViewParams>,
+  server: Data<CollabServerImpl>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<()>> {
+  let (workspace_id, view_id) = path.into_inner();
+  let uid = select_uid_from_uuid(&state.pg_pool, &user_uuid)
+    .await
+    .map_err(AppResponseError::from)?;
+  biz::collab::folder::move_folder_view(
+    &state.collab_storage,
+    state.collab_access_control.as_ref(),
+    &server.get_ref().clone().recipient(),
+    &uid,
+    &workspace_id,
+    &view_id,
+    payload.into_inner(),
+  )
+  .await?;
+  Ok(AppResponse::Ok().into())
+}
+
+#[instrument(level = "debug", skip(server, state), err)]
+async fn delete_folder_view_handler(
+  user_uuid: UserUuid,
+  request_id: RequestId,
+  path: web::Path<(Uuid, String)>,
+  server: Data<CollabServerImpl>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<()>> {
+  let (workspace_id, view_id) = path.into_inner();
+  let uid = select_uid_from_uuid(&state.pg_pool, &user_uuid)
+    .await
+    .map_err(AppResponseError::from)?;
+  let actor = AuditActor::new(&user_uuid, request_id);
+  biz::collab::folder::delete_folder_view(
+    &state.pg_pool,
+    &state.collab_storage,
+    state.collab_access_control.as_ref(),
+    &server.get_ref().clone().recipient(),
+    &actor,
+    &uid,
+    &workspace_id,
+    &view_id,
+  )
+  .await?;
+  Ok(AppResponse::Ok().into())
+}
+
+#[instrument(level = "debug", skip(state), err)]
+async fn get_database_fields_handler(
+  user_uuid: UserUuid,
+  path: web::Path<(String, String)>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<Vec<AFDatabaseField>>> {
+  let (workspace_id, database_id) = path.into_inner();
+  let uid = select_uid_from_uuid(&state.pg_pool, &user_uuid)
+    .await
+    .map_err(AppResponseError::from)?;
+  let fields = biz::collab::database::get_database_fields(
+    &state.collab_storage,
+    &uid,
+    &workspace_id,
+    &database_id,
+  )
+  .await?;
+  Ok(AppResponse::Ok().with_data(fields).into())
+}
+
+#[instrument(level = "debug", skip(payload, state), err)]
+async fn query_database_rows_handler(
+  user_uuid: UserUuid,
+  path: web::Path<(String, String)>,
+  payload: Json<QueryDatabaseRowsParams>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<AFDatabaseRows>> {
+  let (workspace_id, database_id) = path.into_inner();
+  let uid = select_uid_from_uuid(&state.pg_pool, &user_uuid)
+    .await
+    .map_err(AppResponseError::from)?;
+  let rows = biz::collab::database::query_database_rows(
+    &state.collab_storage,
+    &uid,
+    &workspace_id,
+    &database_id,
+    payload.into_inner(),
+  )
+  .await?;
+  Ok(AppResponse::Ok().with_data(rows).into())
+}
+
+#[instrument(level = "debug", skip(payload, server, state), err)]
+async fn insert_database_row_handler(
+  user_uuid: UserUuid,
+  path: web::Path<(String, String)>,
+  payload: Json<InsertDatabaseRowParams>,
+  server: Data<CollabServerImpl>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<AFDatabaseRow>> {
+  let (workspace_id, database_id) = path.into_inner();
+  let uid = select_uid_from_uuid(&state.pg_pool, &user_uuid)
+    .await
+    .map_err(AppResponseError::from)?;
+  let row = biz::collab::database::insert_database_row(
+    &state.collab_storage,
+    &server.get_ref().clone().recipient(),
+    &uid,
+    &workspace_id,
+    &database_id,
+    payload.into_inner(),
+  )
+  .await?;
+  Ok(AppResponse::Ok().with_data(row).into())
+}
+
+#[instrument(level = "debug", skip(payload, server, state), err)]
+async fn update_database_row_handler(
+  user_uuid: UserUuid,
+  path: web::Path<(String, String, String)>,
+  payload: Json<UpdateDatabaseRowParams>,
+  server: Data<CollabServerImpl>,
+  state: Data<AppState>,
+) -> Result<JsonAppResponse<AFDatabaseRow>> {
+  let (workspace_id, database_id, row_id) = path.into_inner();
+  let uid = select_uid_from_uuid(&state.pg_pool, &user_uuid)
+    .await
+    .map_err(AppResponseError::from)?;
+  let row = biz::collab::database::update_database_row(
+    &state.collab_storage,
+    &server.get_ref().clone().recipient(),
+    &uid,
+    &workspace_id,
+    &database_id,
+    &row_id,
+    payload.into_inner(),
+  )
+  .await?;
+  Ok(AppResponse::Ok().with_data(row).into())
+}
+
 #[instrument(level = "debug", skip(payload, state), err)]
 async fn batch_get_collab_handler(
   user_uuid: UserUuid,
@@ -275,10 +1275,15 @@ async fn update_collab_handler(
 #[instrument(level = "info", skip(state, payload), err)]
 async fn delete_collab_handler(
   user_uuid: UserUuid,
+  request_id: RequestId,
+  path: web::Path<(Uuid, String)>,
   payload: Json<DeleteCollabParams>,
   state: Data<AppState>,
 ) -> Result<Json<AppResponse<()>>> {
-  biz::collab::ops::delete_collab(&state.pg_pool, &user_uuid, &payload.into_inner()).await?;
+  let (workspace_id, _object_id) = path.into_inner();
+  let actor = AuditActor::new(&user_uuid, request_id);
+  biz::collab::ops::delete_collab(&state.pg_pool, &actor, &workspace_id, &payload.into_inner())
+    .await?;
   Ok(AppResponse::Ok().into())
 }
 
@@ -305,13 +1310,74 @@ async fn retrieve_snapshots_handler(
   Ok(Json(AppResponse::Ok().with_data(data)))
 }
 
+#[instrument(level = "debug", skip(payload, server, state), err)]
+async fn restore_snapshot_handler(
+  user_uuid: UserUuid,
+  request_id: RequestId,
+  path: web::Path<(Uuid, String, i64)>,
+  payload: Json<RestoreSnapshotParams>,
+  server: Data<CollabServerImpl>,
+  state: Data<AppState>,
+) -> Result<Json<AppResponse<()>>> {
+  let (workspace_id, object_id, snapshot_id) = path.into_inner();
+  let uid = select_uid_from_uuid(&state.pg_pool, &user_uuid)
+    .await
+    .map_err(AppResponseError::from)?;
+  let actor = AuditActor::new(&user_uuid, request_id);
+  biz::collab::snapshot::restore_collab_snapshot(
+    &state.pg_pool,
+    &state.collab_storage,
+    state.collab_access_control.as_ref(),
+    &server.get_ref().clone().recipient(),
+    &actor,
+    &uid,
+    &workspace_id,
+    &object_id,
+    snapshot_id,
+    payload.into_inner(),
+  )
+  .await
+  .map_err(AppResponseError::from)?;
+  Ok(Json(AppResponse::Ok()))
+}
+
+#[instrument(level = "debug", skip(query, state), err)]
+async fn get_snapshot_diff_handler(
+  user_uuid: UserUuid,
+  path: web::Path<(Uuid, String)>,
+  query: web::Query<QuerySnapshotDiffParams>,
+  state: Data<AppState>,
+) -> Result<Json<AppResponse<AFSnapshotDiff>>> {
+  let (workspace_id, object_id) = path.into_inner();
+  let uid = select_uid_from_uuid(&state.pg_pool, &user_uuid)
+    .await
+    .map_err(AppResponseError::from)?;
+  let diff = biz::collab::snapshot::get_collab_snapshot_diff(
+    &state.pg_pool,
+    &state.collab_storage,
+    state.collab_access_control.as_ref(),
+    &uid,
+    &workspace_id,
+    &object_id,
+    query.into_inner(),
+  )
+  .await
+  .map_err(AppResponseError::from)?;
+  Ok(Json(AppResponse::Ok().with_data(diff)))
+}
+
 #[instrument(skip(state, payload), err)]
 async fn add_collab_member_handler(
+  user_uuid: UserUuid,
+  request_id: RequestId,
+  path: web::Path<(Uuid, String)>,
   payload: Json<InsertCollabMemberParams>,
   state: Data<AppState>,
 ) -> Result<Json<AppResponse<()>>> {
+  let (workspace_id, _object_id) = path.into_inner();
   let payload = payload.into_inner();
-  biz::collab::ops::create_collab_member(&state.pg_pool, &payload).await?;
+  let actor = AuditActor::new(&user_uuid, request_id);
+  biz::collab::ops::create_collab_member(&state.pg_pool, &actor, &workspace_id, &payload).await?;
   state
     .collab_access_control
     .update_member(&payload.uid, &payload.object_id, payload.access_level)
@@ -322,11 +1388,15 @@ async fn add_collab_member_handler(
 #[instrument(skip(state, payload), err)]
 async fn update_collab_member_handler(
   user_uuid: UserUuid,
+  request_id: RequestId,
+  path: web::Path<(Uuid, String)>,
   payload: Json<UpdateCollabMemberParams>,
   state: Data<AppState>,
 ) -> Result<Json<AppResponse<()>>> {
+  let (workspace_id, _object_id) = path.into_inner();
   let payload = payload.into_inner();
-  biz::collab::ops::upsert_collab_member(&state.pg_pool, &user_uuid, &payload).await?;
+  let actor = AuditActor::new(&user_uuid, request_id);
+  biz::collab::ops::upsert_collab_member(&state.pg_pool, &actor, &workspace_id, &payload).await?;
 
   state
     .collab_access_control
@@ -347,11 +1417,16 @@ async fn get_collab_member_handler(
 
 #[instrument(skip(state, payload), err)]
 async fn remove_collab_member_handler(
+  user_uuid: UserUuid,
+  request_id: RequestId,
+  path: web::Path<(Uuid, String)>,
   payload: Json<CollabMemberIdentify>,
   state: Data<AppState>,
 ) -> Result<Json<AppResponse<()>>> {
+  let (workspace_id, _object_id) = path.into_inner();
   let payload = payload.into_inner();
-  biz::collab::ops::delete_collab_member(&state.pg_pool, &payload).await?;
+  let actor = AuditActor::new(&user_uuid, request_id);
+  biz::collab::ops::delete_collab_member(&state.pg_pool, &actor, &workspace_id, &payload).await?;
   state
     .collab_access_control
     .remove_member(&payload.uid, &payload.object_id)
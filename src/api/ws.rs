@@ -8,20 +8,28 @@ use std::sync::Arc;
 use realtime::client::ClientSession;
 use realtime::collaborate::CollabServer;
 
-use crate::biz::collab::access_control::CollabAccessControlImpl;
+use crate::api::share::share_link_password;
+use crate::biz::collab::access_control::{CollabAccessControlImpl, ShareLinkSession};
+use crate::biz::collab::share_link::open_share_link;
 use crate::biz::collab::storage::CollabPostgresDBStorage;
 use crate::biz::user::RealtimeUserImpl;
 use crate::component::auth::jwt::{authorization_from_token, UserUuid};
+use chrono::Utc;
 use database::user::select_uid_from_uuid;
 use shared_entity::response::AppResponseError;
 use std::time::Duration;
 use tracing::instrument;
 
 pub fn ws_scope() -> Scope {
-  web::scope("/ws").service(establish_ws_connection)
+  web::scope("/ws")
+    .service(establish_share_ws_connection)
+    .service(establish_ws_connection)
 }
 const MAX_FRAME_SIZE: usize = 65_536; // 64 KiB
 
+/// The longest time a websocket session opened through a share link stays valid.
+const SHARE_SESSION_TTL_HOURS: i64 = 12;
+
 pub type CollabServerImpl =
   Addr<CollabServer<CollabPostgresDBStorage, Arc<RealtimeUserImpl>, Arc<CollabAccessControlImpl>>>;
 
@@ -71,3 +79,57 @@ pub async fn establish_ws_connection(
     },
   }
 }
+
+/// Opens a read-only websocket session for the collab of the share link. The session receives the
+/// updates of the collab, but the updates sent through it are rejected. The session ends when the
+/// websocket is disconnected.
+#[instrument(skip_all, err)]
+#[get("/share/{token}/{device_id}")]
+pub async fn establish_share_ws_connection(
+  request: HttpRequest,
+  payload: Payload,
+  path: Path<(String, String)>,
+  state: Data<AppState>,
+  server: Data<CollabServerImpl>,
+) -> Result<HttpResponse> {
+  let (token, device_id) = path.into_inner();
+  let link = open_share_link(&state.pg_pool, &token, share_link_password(&request))
+    .await
+    .map_err(AppResponseError::from)?;
+
+  // The session uid is negative, so it never collides with the uid of a user.
+  let session_uid = -state.next_user_id().await;
+  let session_expires_at = Utc::now() + chrono::Duration::hours(SHARE_SESSION_TTL_HOURS);
+  state
+    .collab_access_control
+    .add_share_session(
+      session_uid,
+      ShareLinkSession {
+        token,
+        oid: link.oid,
+        created_by: link.created_by,
+        expires_at: link.expires_at.map_or(session_expires_at, |expires_at| {
+          expires_at.min(session_expires_at)
+        }),
+      },
+    )
+    .await;
+
+  // The session has no profile, so the receiver of the user changes is closed right away.
+  let (_, user_change_recv) = tokio::sync::mpsc::channel(1);
+  let realtime_user = Arc::new(RealtimeUserImpl::share_session(
+    session_uid,
+    link.created_by,
+    device_id,
+  ));
+  let client = ClientSession::new(
+    realtime_user,
+    user_change_recv,
+    server.get_ref().clone(),
+    Duration::from_secs(state.config.websocket.heartbeat_interval as u64),
+    Duration::from_secs(state.config.websocket.client_timeout as u64),
+  );
+  ws::WsResponseBuilder::new(client, &request, payload)
+    .frame_size(MAX_FRAME_SIZE * 2)
+    .start()
+}
@@ -1,6 +1,10 @@
 use crate::api::metrics::{metrics_registry, metrics_scope};
+use crate::component::auth::jwt::ADMIN_ROLE;
 use crate::component::auth::HEADER_TOKEN;
-use crate::config::config::{Config, DatabaseSetting, GoTrueSetting, S3Setting, TlsConfig};
+use crate::config::config::{
+  BucketBackendSetting, CollabFanoutSetting, Config, DatabaseSetting, GoTrueSetting, S3Setting,
+  TlsConfig,
+};
 use crate::middleware::cors_mw::default_cors;
 use crate::middleware::request_id::RequestIdMiddleware;
 use crate::self_signed::create_self_signed_certificate;
@@ -25,12 +29,17 @@ use std::time::Duration;
 use tokio::sync::RwLock;
 use tracing::info;
 
+use crate::api::admin::admin_scope;
 use crate::api::file_storage::file_storage_scope;
+use crate::api::share::share_scope;
 use crate::api::user::user_scope;
 use crate::api::workspace::{collab_scope, workspace_scope};
 use crate::api::ws::ws_scope;
+use crate::biz::blob_gc::BlobGC;
 use crate::biz::collab::access_control::{CollabAccessControlImpl, CollabHttpAccessControl};
+use crate::biz::collab::fanout::RedisCollabFanout;
 use crate::biz::collab::storage::init_collab_storage;
+use crate::biz::collab::trash::spawn_purge_expired_trash;
 use crate::biz::pg_listener::PgListeners;
 use crate::biz::user::RealtimeUserImpl;
 use crate::biz::workspace::access_control::{
@@ -39,8 +48,11 @@ use crate::biz::workspace::access_control::{
 use crate::middleware::access_control_mw::WorkspaceAccessControl;
 
 use crate::middleware::metrics_mw::MetricsMiddleware;
-use database::file::bucket_s3_impl::S3BucketStorage;
-use realtime::collaborate::CollabServer;
+use database::file::bucket_client_impl::{BucketClientImpl, BucketStorageImpl};
+use database::file::bucket_fs_impl::BucketClientFsImpl;
+use database::file::bucket_memory_impl::BucketClientMemoryImpl;
+use database::file::bucket_s3_impl::BucketClientS3Impl;
+use realtime::collaborate::{CollabFanout, CollabServer, LocalCollabFanout};
 
 pub struct Application {
   port: u16,
@@ -86,10 +98,25 @@ pub async fn run(
     .map(|(_, server_key)| Key::from(server_key.expose_secret().as_bytes()))
     .unwrap_or_else(Key::generate);
 
+  // Initialize metrics that which are registered in the registry.
+  let (metrics, realtime_metrics, registry) = metrics_registry();
+  let registry_arc = Arc::new(registry);
+  let metrics_arc = Arc::new(metrics);
+
+  let collab_fanout: Arc<dyn CollabFanout> = match config.websocket.collab_fanout {
+    CollabFanoutSetting::InProcess => Arc::new(LocalCollabFanout::new()),
+    CollabFanoutSetting::Redis => Arc::new(
+      RedisCollabFanout::new(config.redis_uri.expose_secret(), state.redis_client.clone()).await?,
+    ),
+  };
+
   let storage = state.collab_storage.clone();
   let collab_server = CollabServer::<_, Arc<RealtimeUserImpl>, _>::new(
     storage.clone(),
     state.collab_access_control.clone(),
+    Duration::from_secs(config.websocket.collab_group_idle_timeout),
+    Arc::new(realtime_metrics),
+    collab_fanout,
   )
   .unwrap()
   .start();
@@ -100,11 +127,6 @@ pub async fn run(
     ))
     .with_acs(CollabHttpAccessControl(state.collab_access_control.clone()));
 
-  // Initialize metrics that which are registered in the registry.
-  let (metrics, registry) = metrics_registry();
-  let registry_arc = Arc::new(registry);
-  let metrics_arc = Arc::new(metrics);
-
   let mut server = HttpServer::new(move || {
     App::new()
        // Middleware is registered for each App, scope, or Resource and executed in opposite order as registration
@@ -124,6 +146,8 @@ pub async fn run(
       .service(collab_scope())
       .service(ws_scope())
       .service(file_storage_scope())
+      .service(admin_scope())
+      .service(share_scope())
       .service(metrics_scope())
       .app_data(Data::new(metrics_arc.clone()))
       .app_data(Data::new(registry_arc.clone()))
@@ -156,8 +180,11 @@ pub async fn init_state(config: &Config) -> Result<AppState, Error> {
   migrate(&pg_pool).await?;
 
   // Bucket storage
-  let s3_bucket = get_aws_s3_bucket(&config.s3).await?;
-  let bucket_storage = Arc::new(S3BucketStorage::from_s3_bucket(s3_bucket, pg_pool.clone()));
+  let bucket_storage = Arc::new(get_bucket_storage(config, pg_pool.clone()).await?);
+  let blob_gc = BlobGC::new(bucket_storage.clone(), config.s3.gc.clone());
+
+  // Trash
+  spawn_purge_expired_trash(pg_pool.clone(), config.trash.clone());
 
   // Gotrue
   let gotrue_client = get_gotrue_client(&config.gotrue).await?;
@@ -174,6 +201,8 @@ pub async fn init_state(config: &Config) -> Result<AppState, Error> {
   let collab_access_control = Arc::new(CollabAccessControlImpl::new(
     pg_pool.clone(),
     collab_member_listener,
+    pg_listeners.subscribe_workspace_member_change(),
+    pg_listeners.subscribe_workspace_role_change(),
   ));
 
   // Workspace access control
@@ -181,6 +210,7 @@ pub async fn init_state(config: &Config) -> Result<AppState, Error> {
   let workspace_access_control = Arc::new(WorkspaceAccessControlImpl::new(
     pg_pool.clone(),
     workspace_member_listener,
+    pg_listeners.subscribe_workspace_role_change(),
   ));
 
   let collab_storage = Arc::new(
@@ -203,6 +233,7 @@ pub async fn init_state(config: &Config) -> Result<AppState, Error> {
     collab_access_control,
     workspace_access_control,
     bucket_storage,
+    blob_gc,
     pg_listeners,
   })
 }
@@ -237,7 +268,7 @@ async fn setup_admin_account(
         }
       };
       match admin_user.role.as_str() {
-        "supabase_admin" => {
+        ADMIN_ROLE => {
           info!("Admin user already created and set role to supabase_admin");
           Ok(())
         },
@@ -274,6 +305,22 @@ async fn get_redis_client(redis_uri: &str) -> Result<redis::aio::ConnectionManag
   Ok(manager)
 }
 
+async fn get_bucket_storage(config: &Config, pg_pool: PgPool) -> Result<BucketStorageImpl, Error> {
+  let client = match config.s3.backend {
+    BucketBackendSetting::S3 => {
+      let s3_bucket = get_aws_s3_bucket(&config.s3).await?;
+      BucketClientImpl::S3(BucketClientS3Impl::new(s3_bucket))
+    },
+    BucketBackendSetting::FileSystem => {
+      let blob_dir = config.application.blob_dir();
+      info!("Store blobs in: {:?}", blob_dir);
+      BucketClientImpl::FileSystem(BucketClientFsImpl::new(blob_dir).await?)
+    },
+    BucketBackendSetting::Memory => BucketClientImpl::Memory(BucketClientMemoryImpl::new()),
+  };
+  Ok(BucketStorageImpl::new(client, pg_pool))
+}
+
 async fn get_aws_s3_bucket(s3_setting: &S3Setting) -> Result<s3::Bucket, Error> {
   let region = {
     match s3_setting.use_minio {
@@ -0,0 +1,74 @@
+use crate::middleware::request_id::RequestId;
+use app_error::AppError;
+use database::audit::{insert_audit_log, select_audit_logs};
+use database_entity::dto::{AFAuditAction, AFAuditLog};
+use shared_entity::dto::workspace_dto::QueryAuditLogParams;
+use sqlx::{Executor, PgPool, Postgres};
+use uuid::Uuid;
+
+const DEFAULT_AUDIT_LOG_LIMIT: i64 = 50;
+const MAX_AUDIT_LOG_LIMIT: i64 = 200;
+
+/// The user and the request that perform an audited action.
+#[derive(Debug, Clone)]
+pub struct AuditActor {
+  pub user_uuid: Uuid,
+  pub request_id: String,
+}
+
+impl AuditActor {
+  pub fn new(user_uuid: &Uuid, request_id: RequestId) -> Self {
+    Self {
+      user_uuid: *user_uuid,
+      request_id: request_id.0,
+    }
+  }
+}
+
+/// Records the action in the audit log. Pass the transaction of the action when there is one, so
+/// the log is only kept when the action is committed.
+pub async fn record_audit_log<'a, E: Executor<'a, Database = Postgres>>(
+  executor: E,
+  actor: &AuditActor,
+  workspace_id: Option<&Uuid>,
+  action: AFAuditAction,
+  target: &str,
+  metadata: Option<serde_json::Value>,
+) -> Result<(), AppError> {
+  insert_audit_log(
+    executor,
+    &actor.user_uuid,
+    workspace_id,
+    action as i16,
+    target,
+    metadata,
+    &actor.request_id,
+  )
+  .await
+}
+
+pub async fn get_audit_logs(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  params: QueryAuditLogParams,
+) -> Result<Vec<AFAuditLog>, AppError> {
+  let limit = params
+    .limit
+    .unwrap_or(DEFAULT_AUDIT_LOG_LIMIT)
+    .clamp(1, MAX_AUDIT_LOG_LIMIT);
+  let offset = params.offset.unwrap_or(0).max(0);
+  let logs = select_audit_logs(
+    pg_pool,
+    workspace_id,
+    params.action.map(|action| action as i16),
+    params.actor_uid,
+    params.target.as_deref(),
+    offset,
+    limit,
+  )
+  .await?
+  .into_iter()
+  .map(AFAuditLog::from)
+  .collect();
+  Ok(logs)
+}
@@ -0,0 +1,71 @@
+use crate::config::config::BlobGCSetting;
+use app_error::AppError;
+use chrono::Duration;
+use database::file::bucket_client_impl::BucketStorageImpl;
+use database_entity::dto::AFBlobGCReport;
+use std::sync::Arc;
+use tokio::sync::{Mutex, RwLock};
+use tokio::time::{interval_at, Instant};
+use tracing::{error, info};
+
+/// Collects the orphan blobs and the missing blobs of the bucket. See
+/// [BucketStorageImpl::gc_blobs] for the details.
+pub struct BlobGC {
+  bucket_storage: Arc<BucketStorageImpl>,
+  setting: BlobGCSetting,
+  /// Prevents the periodic run and the admin run from overlapping.
+  running: Mutex<()>,
+  last_report: RwLock<Option<AFBlobGCReport>>,
+}
+
+impl BlobGC {
+  pub fn new(bucket_storage: Arc<BucketStorageImpl>, setting: BlobGCSetting) -> Arc<Self> {
+    let gc = Arc::new(Self {
+      bucket_storage,
+      setting,
+      running: Mutex::new(()),
+      last_report: RwLock::new(None),
+    });
+    if gc.setting.enable {
+      spawn_periodic_blob_gc(gc.clone());
+    }
+    gc
+  }
+
+  pub async fn run(&self, dry_run: bool) -> Result<AFBlobGCReport, AppError> {
+    let _guard = self.running.lock().await;
+    let grace_period = Duration::seconds(self.setting.grace_period_secs);
+    let upload_expiration = Duration::seconds(self.setting.upload_expiration_secs);
+    let report = self
+      .bucket_storage
+      .gc_blobs(dry_run, grace_period, upload_expiration)
+      .await?;
+    *self.last_report.write().await = Some(report.clone());
+    Ok(report)
+  }
+
+  pub async fn last_report(&self) -> Option<AFBlobGCReport> {
+    self.last_report.read().await.clone()
+  }
+}
+
+/// Runs the garbage collection every [BlobGCSetting::interval_secs], starting one interval after
+/// the server starts.
+fn spawn_periodic_blob_gc(gc: Arc<BlobGC>) {
+  tokio::spawn(async move {
+    let period = std::time::Duration::from_secs(gc.setting.interval_secs);
+    let mut interval = interval_at(Instant::now() + period, period);
+    loop {
+      interval.tick().await;
+      match gc.run(gc.setting.dry_run).await {
+        Ok(report) => info!(
+          "did run blob gc, orphan blobs: {}, missing blobs: {}, expired uploads: {}",
+          report.orphan_blobs.len(),
+          report.missing_blobs.len(),
+          report.expired_uploads.len()
+        ),
+        Err(err) => error!("fail to run blob gc: {:?}", err),
+      }
+    }
+  });
+}
@@ -1,18 +1,23 @@
 use crate::biz::collab::member_listener::{CollabMemberAction, CollabMemberNotification};
 use crate::biz::workspace::access_control::WorkspaceAccessControl;
+use crate::biz::workspace::member_listener::WorkspaceMemberNotification;
+use crate::biz::workspace::role_listener::WorkspaceRoleNotification;
 use crate::middleware::access_control_mw::{AccessResource, HttpAccessControlService};
 use actix_router::{Path, Url};
 use actix_web::http::Method;
 use app_error::AppError;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use database::collab::CollabStorageAccessControl;
 use database::user::select_uid_from_uuid;
-use database_entity::dto::{AFAccessLevel, AFRole};
+use database::workspace_role::select_collab_custom_role_permissions;
+use database_entity::dto::{AFAccessLevel, AFRole, AFWorkspacePermissions};
 use realtime::collaborate::{CollabAccessControl, CollabUserId};
 use sqlx::PgPool;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::{broadcast, RwLock};
 use tracing::{instrument, warn};
 use uuid::Uuid;
@@ -40,6 +45,27 @@ type MemberStatusByUid = HashMap<i64, CollabMemberStatusByOid>;
 pub struct CollabAccessControlImpl {
   pg_pool: PgPool,
   member_status_by_uid: Arc<RwLock<MemberStatusByUid>>,
+  /// The websocket sessions opened through share links, keyed by the uid of the session.
+  share_sessions: Arc<RwLock<HashMap<i64, ShareLinkSession>>>,
+}
+
+/// A read-only session opened through a share link. The session can only receive the updates of
+/// the shared collab.
+///
+/// The server loads and persists the collab on behalf of the user who created the link, since the
+/// collab group might be created by the session when no member has opened the collab.
+#[derive(Clone, Debug)]
+pub struct ShareLinkSession {
+  pub token: String,
+  pub oid: String,
+  pub created_by: i64,
+  pub expires_at: DateTime<Utc>,
+}
+
+impl ShareLinkSession {
+  fn is_valid_for(&self, oid: &str) -> bool {
+    self.oid == oid && self.expires_at > Utc::now()
+  }
 }
 
 #[derive(Clone, Debug)]
@@ -53,17 +79,54 @@ enum MemberStatus {
 }
 
 impl CollabAccessControlImpl {
-  pub fn new(pg_pool: PgPool, listener: broadcast::Receiver<CollabMemberNotification>) -> Self {
+  pub fn new(
+    pg_pool: PgPool,
+    listener: broadcast::Receiver<CollabMemberNotification>,
+    workspace_member_listener: broadcast::Receiver<WorkspaceMemberNotification>,
+    workspace_role_listener: broadcast::Receiver<WorkspaceRoleNotification>,
+  ) -> Self {
     let member_status_by_uid = Arc::new(RwLock::new(HashMap::new()));
 
     // Listen to the changes of the collab member and update the memory cache
     spawn_listen_on_collab_member_change(listener, pg_pool.clone(), member_status_by_uid.clone());
+    spawn_listen_on_workspace_role_change(
+      workspace_member_listener,
+      workspace_role_listener,
+      member_status_by_uid.clone(),
+    );
     Self {
       pg_pool,
       member_status_by_uid,
+      share_sessions: Default::default(),
     }
   }
 
+  /// Registers the session of a share link. The expired sessions are removed at the same time.
+  pub async fn add_share_session(&self, session_uid: i64, session: ShareLinkSession) {
+    let mut share_sessions = self.share_sessions.write().await;
+    let now = Utc::now();
+    share_sessions.retain(|_, session| session.expires_at > now);
+    share_sessions.insert(session_uid, session);
+  }
+
+  /// Ends the sessions of the share link, e.g. when the link is deleted.
+  pub async fn remove_share_sessions(&self, token: &str) {
+    self
+      .share_sessions
+      .write()
+      .await
+      .retain(|_, session| session.token != token);
+  }
+
+  /// Ends the session of a share link, e.g. when its websocket is disconnected.
+  pub async fn remove_share_session(&self, session_uid: &i64) {
+    self.share_sessions.write().await.remove(session_uid);
+  }
+
+  async fn get_share_session(&self, uid: &i64) -> Option<ShareLinkSession> {
+    self.share_sessions.read().await.get(uid).cloned()
+  }
+
   /// The member's access level may be altered by PostgreSQL notifications. However, there are instances
   /// where these notifications aren't received promptly, leading to potential inconsistencies in the user's access level.
   /// Therefore, it's essential to update the user's access level in the cache whenever there's a change.
@@ -71,6 +134,17 @@ impl CollabAccessControlImpl {
     cache_collab_member_status(uid, oid, access_level, &self.member_status_by_uid).await;
   }
 
+  /// Removes the cached access levels of the user, they are reloaded from the database when they
+  /// are accessed next time.
+  pub async fn clear_user_cache(&self, uid: &i64) {
+    self.member_status_by_uid.write().await.remove(uid);
+  }
+
+  /// Removes all the cached access levels, e.g. when the permissions of a custom role are changed.
+  pub async fn clear_cache(&self) {
+    self.member_status_by_uid.write().await.clear();
+  }
+
   pub async fn remove_member(&self, uid: &i64, oid: &str) {
     if let Some(inner_map) = self.member_status_by_uid.write().await.get_mut(uid) {
       if let Entry::Occupied(mut entry) = inner_map.entry(oid.to_string()) {
@@ -146,6 +220,42 @@ fn spawn_listen_on_collab_member_change(
   });
 }
 
+/// The access level of the member who has a custom role is limited by the permissions of the role.
+/// The cached access levels are removed when the custom role of a member or the permissions of a
+/// custom role are changed, and they are reloaded from the database when they are accessed next
+/// time.
+fn spawn_listen_on_workspace_role_change(
+  mut member_listener: broadcast::Receiver<WorkspaceMemberNotification>,
+  mut role_listener: broadcast::Receiver<WorkspaceRoleNotification>,
+  member_status_by_uid: Arc<RwLock<MemberStatusByUid>>,
+) {
+  tokio::spawn(async move {
+    loop {
+      tokio::select! {
+        result = member_listener.recv() => match result {
+          Ok(change) => {
+            if let Some(uid) = change.new.or(change.old).map(|row| row.uid) {
+              member_status_by_uid.write().await.remove(&uid);
+            }
+          },
+          // The missed changes are unknown, so all the cached access levels are removed.
+          Err(RecvError::Lagged(_)) => member_status_by_uid.write().await.clear(),
+          Err(RecvError::Closed) => break,
+        },
+        result = role_listener.recv() => match result {
+          Ok(change) => {
+            if change.changed_role_id().is_some() {
+              member_status_by_uid.write().await.clear();
+            }
+          },
+          Err(RecvError::Lagged(_)) => member_status_by_uid.write().await.clear(),
+          Err(RecvError::Closed) => break,
+        },
+      }
+    }
+  });
+}
+
 #[inline]
 async fn cache_collab_member_status(
   uid: &i64,
@@ -166,14 +276,15 @@ async fn reload_collab_member_status_from_db(
   member_status_by_uid: &Arc<RwLock<MemberStatusByUid>>,
 ) -> Result<MemberStatus, AppError> {
   let member = database::collab::select_collab_member(uid, oid, pg_pool).await?;
-  let status = MemberStatus::Valid(member.permission.access_level.clone());
-  cache_collab_member_status(
-    uid,
-    oid,
-    member.permission.access_level,
-    member_status_by_uid,
-  )
-  .await;
+  let mut access_level = member.permission.access_level;
+  if let Some(permissions) = select_collab_custom_role_permissions(pg_pool, uid, oid).await? {
+    let max_access_level = AFWorkspacePermissions(permissions).max_access_level();
+    if i32::from(max_access_level.clone()) < i32::from(access_level.clone()) {
+      access_level = max_access_level;
+    }
+  }
+  let status = MemberStatus::Valid(access_level.clone());
+  cache_collab_member_status(uid, oid, access_level, member_status_by_uid).await;
   Ok(status)
 }
 
@@ -184,6 +295,20 @@ impl CollabAccessControl for CollabAccessControlImpl {
     user: CollabUserId<'_>,
     oid: &str,
   ) -> Result<AFAccessLevel, AppError> {
+    if let CollabUserId::UserId(uid) = user {
+      if let Some(session) = self.get_share_session(uid).await {
+        if !session.is_valid_for(oid) {
+          return Err(AppError::NotEnoughPermissions(format!(
+            "share session:{} can't access collab:{}",
+            uid, oid
+          )));
+        }
+        return self
+          .get_user_collab_access_level(&session.created_by, oid)
+          .await;
+      }
+    }
+
     let level = match user {
       CollabUserId::UserId(uid) => self.get_user_collab_access_level(uid, oid).await,
       CollabUserId::UserUuid(uuid) => {
@@ -236,6 +361,10 @@ impl CollabAccessControl for CollabAccessControlImpl {
 
   #[inline]
   async fn can_send_collab_update(&self, uid: &i64, oid: &str) -> Result<bool, AppError> {
+    if self.get_share_session(uid).await.is_some() {
+      return Ok(false);
+    }
+
     let result = self
       .get_collab_access_level(CollabUserId::UserId(uid), oid)
       .await;
@@ -258,6 +387,10 @@ impl CollabAccessControl for CollabAccessControlImpl {
 
   #[inline]
   async fn can_receive_collab_update(&self, uid: &i64, oid: &str) -> Result<bool, AppError> {
+    if let Some(session) = self.get_share_session(uid).await {
+      return Ok(session.is_valid_for(oid));
+    }
+
     Ok(
       self
         .get_collab_access_level(CollabUserId::UserId(uid), oid)
@@ -265,6 +398,10 @@ impl CollabAccessControl for CollabAccessControlImpl {
         .is_ok(),
     )
   }
+
+  async fn did_disconnect(&self, uid: &i64) {
+    self.remove_share_session(uid).await;
+  }
 }
 
 #[derive(Clone)]
@@ -284,8 +421,32 @@ where
     oid: &str,
     user_uuid: &Uuid,
     method: Method,
-    _path: &Path<Url>,
+    path: &Path<Url>,
   ) -> Result<(), AppError> {
+    // The users who can comment are allowed to send the comment requests, which don't modify
+    // the collab. The author checks are done by the comment handlers. Unlike the collab requests,
+    // the comments of a collab that the user isn't a member of are never accessible.
+    if is_comment_request(oid, path.as_str()) {
+      return match self
+        .0
+        .get_collab_access_level(CollabUserId::UserUuid(user_uuid), oid)
+        .await
+      {
+        Ok(level) if method != Method::GET && !level.can_comment() => {
+          Err(AppError::NotEnoughPermissions(format!(
+            "Not enough permissions to comment on the collab: {}",
+            oid
+          )))
+        },
+        Ok(_) => Ok(()),
+        Err(err) if err.is_record_not_found() => Err(AppError::NotEnoughPermissions(format!(
+          "Not enough permissions to access the comments of the collab: {}",
+          oid
+        ))),
+        Err(err) => Err(err),
+      };
+    }
+
     let can_access = self
       .0
       .can_access_http_method(CollabUserId::UserUuid(user_uuid), oid, &method)
@@ -301,6 +462,11 @@ where
   }
 }
 
+fn is_comment_request(oid: &str, path: &str) -> bool {
+  let mut segments = path.trim_matches('/').split('/');
+  segments.any(|segment| segment == oid) && segments.next() == Some("comment")
+}
+
 #[derive(Clone)]
 pub struct CollabStorageAccessControlImpl<CollabAC, WorkspaceAC> {
   pub(crate) collab_access_control: Arc<CollabAC>,
@@ -340,4 +506,15 @@ where
       .get_role_from_uid(uid, &workspace_id.parse()?)
       .await
   }
+
+  async fn get_user_permissions(
+    &self,
+    uid: &i64,
+    workspace_id: &str,
+  ) -> Result<AFWorkspacePermissions, AppError> {
+    self
+      .workspace_access_control
+      .get_permissions_from_uid(uid, &workspace_id.parse()?)
+      .await
+  }
 }
@@ -0,0 +1,239 @@
+use actix::Recipient;
+use app_error::AppError;
+use database::comment::{
+  delete_collab_comment, insert_collab_comment, select_collab_comment, select_collab_comments,
+  update_collab_comment_content, update_collab_comment_resolved,
+};
+use database_entity::dto::{
+  AFAccessLevel, AFCollabComment, CreateCollabCommentParams, ResolveCollabCommentParams,
+  UpdateCollabCommentParams,
+};
+use realtime::collaborate::{CollabAccessControl, CollabUserId};
+use realtime::entities::BroadcastComment;
+use realtime_entity::comment::{CommentEvent, CommentMessage};
+use sqlx::PgPool;
+use tracing::instrument;
+use uuid::Uuid;
+
+/// Returns the comments of the collab. The comments are visible to all the members of the collab.
+pub async fn get_comments<AC>(
+  pg_pool: &PgPool,
+  access_control: &AC,
+  uid: i64,
+  workspace_id: &Uuid,
+  object_id: &str,
+) -> Result<Vec<AFCollabComment>, AppError>
+where
+  AC: CollabAccessControl,
+{
+  check_can_read_comments(access_control, &uid, object_id).await?;
+  let comments = select_collab_comments(pg_pool, workspace_id, object_id)
+    .await?
+    .into_iter()
+    .map(AFCollabComment::from)
+    .collect();
+  Ok(comments)
+}
+
+#[allow(clippy::too_many_arguments)]
+#[instrument(level = "debug", skip(pg_pool, access_control, server, params), err)]
+pub async fn create_comment<AC>(
+  pg_pool: &PgPool,
+  access_control: &AC,
+  server: &Recipient<BroadcastComment>,
+  uid: i64,
+  workspace_id: &Uuid,
+  object_id: &str,
+  params: CreateCollabCommentParams,
+) -> Result<AFCollabComment, AppError>
+where
+  AC: CollabAccessControl,
+{
+  check_can_comment(access_control, &uid, object_id).await?;
+  let content = validate_content(&params.content)?;
+
+  // A reply belongs to the thread of the root comment, so it's anchored to the same block.
+  let block_id = match &params.reply_to {
+    None => params.block_id,
+    Some(reply_to) => {
+      let root = select_collab_comment(pg_pool, workspace_id, object_id, reply_to).await?;
+      if root.reply_to.is_some() {
+        return Err(AppError::InvalidRequest(
+          "can only reply to the root comment of a thread".to_string(),
+        ));
+      }
+      root.block_id
+    },
+  };
+
+  let row = insert_collab_comment(
+    pg_pool,
+    workspace_id,
+    object_id,
+    block_id,
+    params.reply_to,
+    content,
+    uid,
+  )
+  .await?;
+  let comment = AFCollabComment::from(row);
+  broadcast_comment(server, CommentEvent::Created(comment.clone()));
+  Ok(comment)
+}
+
+/// Edits the content of the comment. Only the author of the comment can edit it.
+#[allow(clippy::too_many_arguments)]
+#[instrument(level = "debug", skip(pg_pool, access_control, server, params), err)]
+pub async fn update_comment<AC>(
+  pg_pool: &PgPool,
+  access_control: &AC,
+  server: &Recipient<BroadcastComment>,
+  uid: i64,
+  workspace_id: &Uuid,
+  object_id: &str,
+  comment_id: &Uuid,
+  params: UpdateCollabCommentParams,
+) -> Result<AFCollabComment, AppError>
+where
+  AC: CollabAccessControl,
+{
+  check_can_comment(access_control, &uid, object_id).await?;
+  let content = validate_content(&params.content)?;
+  let comment = select_collab_comment(pg_pool, workspace_id, object_id, comment_id).await?;
+  if comment.created_by != uid {
+    return Err(AppError::NotEnoughPermissions(
+      "only the author can edit the comment".to_string(),
+    ));
+  }
+
+  let row = update_collab_comment_content(pg_pool, comment_id, content).await?;
+  let comment = AFCollabComment::from(row);
+  broadcast_comment(server, CommentEvent::Updated(comment.clone()));
+  Ok(comment)
+}
+
+/// Resolves or reopens the thread of the root comment.
+#[allow(clippy::too_many_arguments)]
+#[instrument(level = "debug", skip(pg_pool, access_control, server), err)]
+pub async fn resolve_comment<AC>(
+  pg_pool: &PgPool,
+  access_control: &AC,
+  server: &Recipient<BroadcastComment>,
+  uid: i64,
+  workspace_id: &Uuid,
+  object_id: &str,
+  comment_id: &Uuid,
+  params: ResolveCollabCommentParams,
+) -> Result<AFCollabComment, AppError>
+where
+  AC: CollabAccessControl,
+{
+  check_can_comment(access_control, &uid, object_id).await?;
+  let comment = select_collab_comment(pg_pool, workspace_id, object_id, comment_id).await?;
+  if comment.reply_to.is_some() {
+    return Err(AppError::InvalidRequest(
+      "only the root comment of a thread can be resolved".to_string(),
+    ));
+  }
+
+  let resolved_by = params.resolved.then_some(uid);
+  let row = update_collab_comment_resolved(pg_pool, comment_id, resolved_by).await?;
+  let comment = AFCollabComment::from(row);
+  broadcast_comment(server, CommentEvent::Updated(comment.clone()));
+  Ok(comment)
+}
+
+/// Deletes the comment. The author of the comment and the users who have the full access to the
+/// collab can delete the comment.
+#[instrument(level = "debug", skip(pg_pool, access_control, server), err)]
+pub async fn delete_comment<AC>(
+  pg_pool: &PgPool,
+  access_control: &AC,
+  server: &Recipient<BroadcastComment>,
+  uid: i64,
+  workspace_id: &Uuid,
+  object_id: &str,
+  comment_id: &Uuid,
+) -> Result<(), AppError>
+where
+  AC: CollabAccessControl,
+{
+  let level = check_can_comment(access_control, &uid, object_id).await?;
+  let comment = select_collab_comment(pg_pool, workspace_id, object_id, comment_id).await?;
+  if comment.created_by != uid && !level.can_delete() {
+    return Err(AppError::NotEnoughPermissions(
+      "only the author can delete the comment".to_string(),
+    ));
+  }
+
+  delete_collab_comment(pg_pool, comment_id).await?;
+  broadcast_comment(
+    server,
+    CommentEvent::Deleted(AFCollabComment::from(comment)),
+  );
+  Ok(())
+}
+
+async fn check_can_comment<AC>(
+  access_control: &AC,
+  uid: &i64,
+  object_id: &str,
+) -> Result<AFAccessLevel, AppError>
+where
+  AC: CollabAccessControl,
+{
+  let level = access_control
+    .get_collab_access_level(CollabUserId::UserId(uid), object_id)
+    .await?;
+  if !level.can_comment() {
+    return Err(AppError::NotEnoughPermissions(format!(
+      "user:{} can't comment on collab:{}",
+      uid, object_id
+    )));
+  }
+  Ok(level)
+}
+
+async fn check_can_read_comments<AC>(
+  access_control: &AC,
+  uid: &i64,
+  object_id: &str,
+) -> Result<AFAccessLevel, AppError>
+where
+  AC: CollabAccessControl,
+{
+  match access_control
+    .get_collab_access_level(CollabUserId::UserId(uid), object_id)
+    .await
+  {
+    Ok(level) => Ok(level),
+    Err(err) if err.is_record_not_found() => Err(AppError::NotEnoughPermissions(format!(
+      "user:{} can't read the comments of collab:{}",
+      uid, object_id
+    ))),
+    Err(err) => Err(err),
+  }
+}
+
+fn validate_content(content: &str) -> Result<&str, AppError> {
+  let content = content.trim();
+  if content.is_empty() {
+    return Err(AppError::InvalidRequest(
+      "the content of the comment should not be empty".to_string(),
+    ));
+  }
+  Ok(content)
+}
+
+/// The comment is pushed to the users who opened the collab. The users who didn't open the collab
+/// get the comments by [get_comments].
+fn broadcast_comment(server: &Recipient<BroadcastComment>, event: CommentEvent) {
+  let object_id = match &event {
+    CommentEvent::Created(comment)
+    | CommentEvent::Updated(comment)
+    | CommentEvent::Deleted(comment) => comment.object_id.clone(),
+  };
+  server.do_send(BroadcastComment {
+    message: CommentMessage { object_id, event },
+  });
+}
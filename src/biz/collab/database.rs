@@ -0,0 +1,593 @@
+use crate::biz::collab::ops::apply_collab_update;
+use crate::biz::collab::snapshot::doc_from_encoded_collab;
+use actix::Recipient;
+use anyhow::anyhow;
+use app_error::AppError;
+use collab::core::collab::MutexCollab;
+use collab::core::collab_plugin::EncodedCollabV1;
+use collab::core::origin::CollabOrigin;
+use collab_database::fields::{field_from_map_ref, Field};
+use collab_database::rows::{new_cell_builder, Cell, DatabaseRow, Row, RowId};
+use collab_database::views::{view_from_map_ref, DatabaseView, RowOrder};
+use collab_entity::CollabType;
+use database::collab::CollabStorage;
+use database_entity::dto::{
+  AFDatabaseField, AFDatabaseRow, AFDatabaseRows, BatchQueryCollab, DatabaseRowFilter,
+  DatabaseRowFilterCondition, DatabaseRowSort, InsertCollabParams, InsertCollabUpdateParams,
+  InsertDatabaseRowParams, QueryCollabParams, QueryCollabResult, QueryDatabaseRowsParams,
+  UpdateDatabaseRowParams,
+};
+use realtime::entities::ApplyCollabUpdate;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
+use tracing::{instrument, warn};
+use yrs::types::Value;
+use yrs::{Any, Array, ArrayRef, Map, MapRef, ReadTxn, Transact};
+
+// The fields and the views of the database are kept in the `database` root map of its collab, and
+// they are read with `collab_database`. Each row is a separate collab, which is read and written
+// with [DatabaseRow].
+const DATABASE: &str = "database";
+const DATABASE_FIELDS: &str = "fields";
+const DATABASE_VIEWS: &str = "views";
+const DATABASE_METAS: &str = "metas";
+const DATABASE_INLINE_VIEW: &str = "iid";
+const VIEW_ROW_ORDERS: &str = "row_orders";
+/// The content of a cell is kept in its `data` entry, next to the type of its field.
+const CELL_DATA: &str = "data";
+
+const DEFAULT_ROW_HEIGHT: i32 = 60;
+const DEFAULT_QUERY_LIMIT: usize = 100;
+const MAX_QUERY_LIMIT: usize = 1000;
+/// The number of the row collabs that are loaded at a time when querying the rows.
+const ROW_BATCH_SIZE: usize = 100;
+
+/// Returns the fields of the database in the order of its inline view.
+pub async fn get_database_fields<S>(
+  collab_storage: &S,
+  uid: &i64,
+  workspace_id: &str,
+  database_id: &str,
+) -> Result<Vec<AFDatabaseField>, AppError>
+where
+  S: CollabStorage,
+{
+  let database = get_database(collab_storage, uid, workspace_id, database_id).await?;
+  let fields = database
+    .fields
+    .into_iter()
+    .map(|field| AFDatabaseField {
+      field_id: field.id,
+      name: field.name,
+      field_type: field.field_type,
+      is_primary: field.is_primary,
+    })
+    .collect();
+  Ok(fields)
+}
+
+/// Returns a page of the rows of the database that match the filters of the params.
+#[instrument(level = "debug", skip(collab_storage, params), err)]
+pub async fn query_database_rows<S>(
+  collab_storage: &S,
+  uid: &i64,
+  workspace_id: &str,
+  database_id: &str,
+  params: QueryDatabaseRowsParams,
+) -> Result<AFDatabaseRows, AppError>
+where
+  S: CollabStorage,
+{
+  let database = get_database(collab_storage, uid, workspace_id, database_id).await?;
+  for field_id in params
+    .filters
+    .iter()
+    .map(|filter| &filter.field_id)
+    .chain(params.sorts.iter().map(|sort| &sort.field_id))
+  {
+    database.field(field_id)?;
+  }
+
+  let offset = params.offset.unwrap_or(0);
+  let limit = params
+    .limit
+    .unwrap_or(DEFAULT_QUERY_LIMIT)
+    .clamp(1, MAX_QUERY_LIMIT);
+
+  // Without filters and sorts, the page is taken from the row order of the database, so only the
+  // rows of the page are loaded.
+  if params.filters.is_empty() && params.sorts.is_empty() {
+    let row_ids = database
+      .row_ids
+      .iter()
+      .skip(offset)
+      .take(limit)
+      .cloned()
+      .collect::<Vec<_>>();
+    let rows = get_rows(collab_storage, uid, database_id, &row_ids).await;
+    return Ok(AFDatabaseRows {
+      rows,
+      total: database.row_ids.len(),
+    });
+  }
+
+  // Otherwise the rows are loaded in batches and filtered right away. Without sorts, the matched
+  // rows outside of the page are only counted.
+  let mut rows = vec![];
+  let mut total = 0;
+  for row_ids in database.row_ids.chunks(ROW_BATCH_SIZE) {
+    for row in get_rows(collab_storage, uid, database_id, row_ids).await {
+      if !params.filters.iter().all(|filter| is_match(&row, filter)) {
+        continue;
+      }
+      if !params.sorts.is_empty() || (offset..offset + limit).contains(&total) {
+        rows.push(row);
+      }
+      total += 1;
+    }
+  }
+
+  if !params.sorts.is_empty() {
+    rows.sort_by(|a, b| compare_rows(a, b, &params.sorts));
+    rows = rows.into_iter().skip(offset).take(limit).collect();
+  }
+  Ok(AFDatabaseRows { rows, total })
+}
+
+/// Loads the rows in the order of the given row ids. The rows that fail to load are skipped.
+async fn get_rows<S>(
+  collab_storage: &S,
+  uid: &i64,
+  database_id: &str,
+  row_ids: &[String],
+) -> Vec<AFDatabaseRow>
+where
+  S: CollabStorage,
+{
+  let queries = row_ids
+    .iter()
+    .map(|row_id| BatchQueryCollab {
+      object_id: row_id.clone(),
+      collab_type: CollabType::DatabaseRow,
+    })
+    .collect();
+  let mut results = collab_storage.batch_get_collab(uid, queries).await;
+
+  let mut rows = Vec::with_capacity(row_ids.len());
+  for row_id in row_ids {
+    match results.remove(row_id) {
+      Some(QueryCollabResult::Success { encode_collab_v1 }) => {
+        let row = EncodedCollabV1::decode_from_bytes(&encode_collab_v1)
+          .map_err(|err| AppError::Internal(anyhow!("fail to decode EncodedCollabV1: {:?}", err)))
+          .and_then(|encoded_collab| row_from_encoded_collab(uid, row_id, &encoded_collab));
+        match row {
+          Ok(row) => rows.push(row),
+          Err(err) => warn!(
+            "fail to read row:{} of database:{}: {}",
+            row_id, database_id, err
+          ),
+        }
+      },
+      Some(QueryCollabResult::Failed { error }) => {
+        warn!(
+          "fail to get row:{} of database:{}: {}",
+          row_id, database_id, error
+        )
+      },
+      None => warn!("row:{} of database:{} is not found", row_id, database_id),
+    }
+  }
+  rows
+}
+
+/// Creates a row collab with the given cells and appends the row to all the views of the database.
+#[instrument(level = "debug", skip(collab_storage, server, params), err)]
+pub async fn insert_database_row<S>(
+  collab_storage: &S,
+  server: &Recipient<ApplyCollabUpdate>,
+  uid: &i64,
+  workspace_id: &str,
+  database_id: &str,
+  params: InsertDatabaseRowParams,
+) -> Result<AFDatabaseRow, AppError>
+where
+  S: CollabStorage,
+{
+  let database = get_database(collab_storage, uid, workspace_id, database_id).await?;
+  let cells = database.cells_with_field_type(params.cells)?;
+  let row_id = uuid::Uuid::new_v4().to_string();
+
+  let mut row = Row::new(row_id.clone(), database_id);
+  row.height = DEFAULT_ROW_HEIGHT;
+  row.cells.extend(cells);
+  let collab = Arc::new(MutexCollab::new(CollabOrigin::Empty, &row_id, vec![]));
+  let database_row = open_database_row(uid, &row_id, collab.clone(), Some(row));
+  let row = row_from_database_row(&row_id, &database_row)?;
+  let encoded_collab_v1 = collab
+    .encode_collab_v1()
+    .encode_to_bytes()
+    .map_err(|err| AppError::Internal(anyhow!("fail to encode EncodedCollabV1: {:?}", err)))?;
+  collab_storage
+    .insert_collab(
+      uid,
+      InsertCollabParams::new(
+        &row_id,
+        CollabType::DatabaseRow,
+        encoded_collab_v1,
+        workspace_id.to_string(),
+      ),
+    )
+    .await?;
+
+  let update = gen_append_row_update(&database.encoded_collab, &row_id)?;
+  apply_collab_update(
+    collab_storage,
+    server,
+    uid,
+    InsertCollabUpdateParams {
+      object_id: database_id.to_string(),
+      update,
+      workspace_id: workspace_id.to_string(),
+      collab_type: CollabType::Database,
+    },
+  )
+  .await?;
+  Ok(row)
+}
+
+/// Updates the given cells of the row. The connected editors of the row receive the changes.
+#[instrument(level = "debug", skip(collab_storage, server, params), err)]
+pub async fn update_database_row<S>(
+  collab_storage: &S,
+  server: &Recipient<ApplyCollabUpdate>,
+  uid: &i64,
+  workspace_id: &str,
+  database_id: &str,
+  row_id: &str,
+  params: UpdateDatabaseRowParams,
+) -> Result<AFDatabaseRow, AppError>
+where
+  S: CollabStorage,
+{
+  let database = get_database(collab_storage, uid, workspace_id, database_id).await?;
+  if !database.row_ids.iter().any(|id| id == row_id) {
+    return Err(AppError::RecordNotFound(format!(
+      "row:{} is not found in database:{}",
+      row_id, database_id
+    )));
+  }
+  let cells = database.cells_with_field_type(params.cells)?;
+  let encoded_collab = collab_storage
+    .get_collab_encoded_v1(
+      uid,
+      QueryCollabParams {
+        object_id: row_id.to_string(),
+        workspace_id: workspace_id.to_string(),
+        collab_type: CollabType::DatabaseRow,
+      },
+    )
+    .await?;
+
+  let collab = open_row_collab(row_id, &encoded_collab)?;
+  let database_row = open_database_row(uid, row_id, collab.clone(), None);
+  database_row.update(|update| {
+    update
+      .update_cells(|cells_update| {
+        cells
+          .into_iter()
+          .fold(cells_update, |cells_update, (field_id, cell)| {
+            cells_update.insert_cell(&field_id, cell)
+          });
+      })
+      .set_last_modified(chrono::Utc::now().timestamp());
+  });
+  let row = row_from_database_row(row_id, &database_row)?;
+  let state_vector = doc_from_encoded_collab(&encoded_collab)?
+    .transact()
+    .state_vector();
+  let update = doc_from_encoded_collab(&collab.encode_collab_v1())?
+    .transact()
+    .encode_state_as_update_v1(&state_vector);
+
+  apply_collab_update(
+    collab_storage,
+    server,
+    uid,
+    InsertCollabUpdateParams {
+      object_id: row_id.to_string(),
+      update,
+      workspace_id: workspace_id.to_string(),
+      collab_type: CollabType::DatabaseRow,
+    },
+  )
+  .await?;
+  Ok(row)
+}
+
+struct DatabaseData {
+  encoded_collab: EncodedCollabV1,
+  /// The fields in the order of the inline view.
+  fields: Vec<Field>,
+  /// The rows in the order of the inline view.
+  row_ids: Vec<String>,
+}
+
+impl DatabaseData {
+  fn field(&self, field_id: &str) -> Result<&Field, AppError> {
+    self
+      .fields
+      .iter()
+      .find(|field| field.id == field_id)
+      .ok_or_else(|| AppError::InvalidRequest(format!("field:{} is not found", field_id)))
+  }
+
+  fn cells_with_field_type(
+    &self,
+    cells: HashMap<String, String>,
+  ) -> Result<Vec<(String, Cell)>, AppError> {
+    cells
+      .into_iter()
+      .map(|(field_id, data)| {
+        let mut cell = new_cell_builder(self.field(&field_id)?.field_type);
+        cell.insert(CELL_DATA.to_string(), Any::from(data));
+        Ok((field_id, cell))
+      })
+      .collect()
+  }
+}
+
+async fn get_database<S>(
+  collab_storage: &S,
+  uid: &i64,
+  workspace_id: &str,
+  database_id: &str,
+) -> Result<DatabaseData, AppError>
+where
+  S: CollabStorage,
+{
+  let encoded_collab = collab_storage
+    .get_collab_encoded_v1(
+      uid,
+      QueryCollabParams {
+        object_id: database_id.to_string(),
+        workspace_id: workspace_id.to_string(),
+        collab_type: CollabType::Database,
+      },
+    )
+    .await?;
+
+  let (mut fields, inline_view) = {
+    let doc = doc_from_encoded_collab(&encoded_collab)?;
+    let txn = doc.transact();
+    let database = txn.get_map(DATABASE).ok_or_else(|| {
+      AppError::InvalidRequest(format!("collab:{} is not a database", database_id))
+    })?;
+    let fields = maps_of(&txn, &database, DATABASE_FIELDS)
+      .iter()
+      .filter_map(|field| field_from_map_ref(field, &txn))
+      .collect::<Vec<_>>();
+    let views = maps_of(&txn, &database, DATABASE_VIEWS)
+      .iter()
+      .filter_map(|view| view_from_map_ref(view, &txn))
+      .collect::<Vec<_>>();
+    (fields, inline_view(&txn, &database, views))
+  };
+
+  let field_ids = inline_view
+    .as_ref()
+    .map(|view| {
+      view
+        .field_orders
+        .iter()
+        .map(|order| order.id.clone())
+        .collect::<Vec<_>>()
+    })
+    .unwrap_or_default();
+  fields.sort_by_key(|field| {
+    field_ids
+      .iter()
+      .position(|id| *id == field.id)
+      .unwrap_or(usize::MAX)
+  });
+  let row_ids = inline_view
+    .map(|view| {
+      view
+        .row_orders
+        .iter()
+        .map(|order| order.id.to_string())
+        .collect()
+    })
+    .unwrap_or_default();
+
+  Ok(DatabaseData {
+    encoded_collab,
+    fields,
+    row_ids,
+  })
+}
+
+/// Returns the maps kept in the `key` map of the database, e.g. its fields or views.
+fn maps_of<T: ReadTxn>(txn: &T, database: &MapRef, key: &str) -> Vec<MapRef> {
+  match database.get(txn, key) {
+    Some(Value::YMap(map)) => map
+      .iter(txn)
+      .filter_map(|(_, value)| match value {
+        Value::YMap(map) => Some(map),
+        _ => None,
+      })
+      .collect(),
+    _ => vec![],
+  }
+}
+
+/// Returns the inline view of the database, which is created with the database and keeps all the
+/// rows. The first view is used if the database doesn't record its inline view.
+fn inline_view<T: ReadTxn>(
+  txn: &T,
+  database: &MapRef,
+  mut views: Vec<DatabaseView>,
+) -> Option<DatabaseView> {
+  let inline_view_id = match database.get(txn, DATABASE_METAS) {
+    Some(Value::YMap(metas)) => match metas.get(txn, DATABASE_INLINE_VIEW) {
+      Some(Value::Any(Any::String(view_id))) => Some(view_id.to_string()),
+      _ => None,
+    },
+    _ => None,
+  };
+  let index = inline_view_id
+    .and_then(|view_id| views.iter().position(|view| view.id == view_id))
+    .unwrap_or(0);
+  if index < views.len() {
+    Some(views.swap_remove(index))
+  } else {
+    None
+  }
+}
+
+/// Opens the collab of the database row. The row is only kept in memory, so it's not bound to a
+/// local database.
+fn open_database_row(
+  uid: &i64,
+  row_id: &str,
+  collab: Arc<MutexCollab>,
+  row: Option<Row>,
+) -> DatabaseRow {
+  DatabaseRow::create(
+    row,
+    *uid,
+    RowId::from(row_id.to_string()),
+    Weak::new(),
+    collab,
+  )
+}
+
+fn open_row_collab(
+  row_id: &str,
+  encoded_collab: &EncodedCollabV1,
+) -> Result<Arc<MutexCollab>, AppError> {
+  let collab = MutexCollab::new_with_raw_data(
+    CollabOrigin::Empty,
+    row_id,
+    vec![encoded_collab.doc_state.to_vec()],
+    vec![],
+  )
+  .map_err(|err| AppError::Internal(anyhow!("fail to open collab: {:?}", err)))?;
+  Ok(Arc::new(collab))
+}
+
+fn row_from_encoded_collab(
+  uid: &i64,
+  row_id: &str,
+  encoded_collab: &EncodedCollabV1,
+) -> Result<AFDatabaseRow, AppError> {
+  let collab = open_row_collab(row_id, encoded_collab)?;
+  row_from_database_row(row_id, &open_database_row(uid, row_id, collab, None))
+}
+
+fn row_from_database_row(
+  row_id: &str,
+  database_row: &DatabaseRow,
+) -> Result<AFDatabaseRow, AppError> {
+  let row = database_row
+    .get_row()
+    .ok_or_else(|| AppError::Internal(anyhow!("collab:{} is not a database row", row_id)))?;
+  let cells = row
+    .cells
+    .iter()
+    .filter_map(|(field_id, cell)| {
+      let data = match cell.get(CELL_DATA)? {
+        Any::Null | Any::Undefined => return None,
+        Any::String(data) => data.to_string(),
+        data => {
+          let mut json = String::new();
+          data.to_json(&mut json);
+          json
+        },
+      };
+      Some((field_id.clone(), data))
+    })
+    .collect();
+  Ok(AFDatabaseRow {
+    row_id: row_id.to_string(),
+    cells,
+    created_at: row.created_at,
+    last_modified: row.modified_at,
+  })
+}
+
+/// Returns the update that appends the row to the row orders of all the views of the database.
+fn gen_append_row_update(
+  encoded_collab: &EncodedCollabV1,
+  row_id: &str,
+) -> Result<Vec<u8>, AppError> {
+  let doc = doc_from_encoded_collab(encoded_collab)?;
+  let database = doc.get_or_insert_map(DATABASE);
+  let state_vector = doc.transact().state_vector();
+  {
+    let mut txn = doc.transact_mut();
+    let row_orders = maps_of(&txn, &database, DATABASE_VIEWS)
+      .into_iter()
+      .filter_map(|view| match view.get(&txn, VIEW_ROW_ORDERS) {
+        Some(Value::YArray(row_orders)) => Some(row_orders),
+        _ => None,
+      })
+      .collect::<Vec<ArrayRef>>();
+    for row_orders in row_orders {
+      let row_order = RowOrder::new(RowId::from(row_id.to_string()), DEFAULT_ROW_HEIGHT);
+      row_orders.push_back(&mut txn, Any::from(row_order));
+    }
+  }
+  let update = doc.transact().encode_state_as_update_v1(&state_vector);
+  Ok(update)
+}
+
+fn is_match(row: &AFDatabaseRow, filter: &DatabaseRowFilter) -> bool {
+  let data = row
+    .cells
+    .get(&filter.field_id)
+    .map(|data| data.as_str())
+    .unwrap_or_default();
+  match filter.condition {
+    DatabaseRowFilterCondition::Is => data == filter.value,
+    DatabaseRowFilterCondition::IsNot => data != filter.value,
+    DatabaseRowFilterCondition::Contains => {
+      data.to_lowercase().contains(&filter.value.to_lowercase())
+    },
+    DatabaseRowFilterCondition::DoesNotContain => {
+      !data.to_lowercase().contains(&filter.value.to_lowercase())
+    },
+    DatabaseRowFilterCondition::IsEmpty => data.is_empty(),
+    DatabaseRowFilterCondition::IsNotEmpty => !data.is_empty(),
+  }
+}
+
+/// Compares the cells of the sort fields one by one. The cells are compared as numbers when both
+/// of them are numbers, otherwise as strings.
+fn compare_rows(a: &AFDatabaseRow, b: &AFDatabaseRow, sorts: &[DatabaseRowSort]) -> Ordering {
+  for sort in sorts {
+    let a = a
+      .cells
+      .get(&sort.field_id)
+      .map(|data| data.as_str())
+      .unwrap_or_default();
+    let b = b
+      .cells
+      .get(&sort.field_id)
+      .map(|data| data.as_str())
+      .unwrap_or_default();
+    let ordering = match (a.parse::<f64>(), b.parse::<f64>()) {
+      (Ok(a), Ok(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+      _ => a.cmp(b),
+    };
+    let ordering = if sort.descending {
+      ordering.reverse()
+    } else {
+      ordering
+    };
+    if ordering != Ordering::Equal {
+      return ordering;
+    }
+  }
+  Ordering::Equal
+}
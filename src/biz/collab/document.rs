@@ -0,0 +1,309 @@
+use crate::biz::collab::ops::{apply_collab_update, create_collab};
+use crate::biz::collab::snapshot::{
+  doc_from_encoded_collab, document_data_from_encoded_collab, open_document,
+};
+use actix::Recipient;
+use anyhow::anyhow;
+use app_error::AppError;
+use collab::core::collab::MutexCollab;
+use collab::core::collab_plugin::EncodedCollabV1;
+use collab::core::origin::CollabOrigin;
+use collab_document::blocks::{BlockAction, BlockActionPayload, BlockActionType, DocumentData};
+use collab_document::document::Document;
+use collab_entity::CollabType;
+use database::collab::CollabStorage;
+use database::resource_usage::is_blob_metadata_exists;
+use database_entity::dto::{
+  AFDocumentFormat, AFExportedDocument, AFImportedDocument, ImportDocumentParams,
+  InsertCollabParams, InsertCollabUpdateParams, InsertDocumentBlocksParams, QueryCollabParams,
+};
+use realtime::entities::ApplyCollabUpdate;
+use serde_json::Value;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::instrument;
+use uuid::Uuid;
+use validator::Validate;
+use workspace_template::{
+  DocumentToHtmlExporter, DocumentToMarkdownExporter, HtmlToDocumentParser, JsonToDocumentParser,
+  MarkdownToDocumentParser,
+};
+use yrs::{ReadTxn, Transact};
+
+const IMAGE_BLOCK: &str = "image";
+const IMAGE_URL: &str = "url";
+
+/// Returns the blocks, children and text of the document.
+pub async fn get_document<S>(
+  collab_storage: &S,
+  uid: &i64,
+  workspace_id: &str,
+  object_id: &str,
+) -> Result<DocumentData, AppError>
+where
+  S: CollabStorage,
+{
+  let encoded_collab = get_document_collab(collab_storage, uid, workspace_id, object_id).await?;
+  document_data_from_encoded_collab(object_id, &encoded_collab)
+}
+
+/// Inserts the blocks under the parent block of the document and returns the ids of the inserted
+/// blocks. The blocks are applied to the collab group of the document, so the connected editors
+/// receive them like the changes of any other client.
+#[instrument(level = "debug", skip(collab_storage, server, params), err)]
+pub async fn insert_document_blocks<S>(
+  collab_storage: &S,
+  server: &Recipient<ApplyCollabUpdate>,
+  uid: &i64,
+  workspace_id: &str,
+  object_id: &str,
+  params: InsertDocumentBlocksParams,
+) -> Result<Vec<String>, AppError>
+where
+  S: CollabStorage,
+{
+  params.validate()?;
+  let current = get_document_collab(collab_storage, uid, workspace_id, object_id).await?;
+  let (update, block_ids) = gen_insert_blocks_update(object_id, &current, params)?;
+  apply_collab_update(
+    collab_storage,
+    server,
+    uid,
+    InsertCollabUpdateParams {
+      object_id: object_id.to_string(),
+      update,
+      workspace_id: workspace_id.to_string(),
+      collab_type: CollabType::Document,
+    },
+  )
+  .await?;
+  Ok(block_ids)
+}
+
+/// Creates a document in the workspace from the Markdown or HTML content.
+#[instrument(level = "debug", skip(pg_pool, params), err)]
+pub async fn import_document(
+  pg_pool: &PgPool,
+  user_uuid: &Uuid,
+  workspace_id: &Uuid,
+  params: ImportDocumentParams,
+) -> Result<AFImportedDocument, AppError> {
+  let mut data = match params.format {
+    AFDocumentFormat::Markdown => MarkdownToDocumentParser::markdown_to_document(&params.content),
+    AFDocumentFormat::Html => HtmlToDocumentParser::html_to_document(&params.content),
+  };
+  link_blob_images(pg_pool, workspace_id, &mut data).await?;
+
+  let object_id = Uuid::new_v4().to_string();
+  create_document(pg_pool, user_uuid, workspace_id, &object_id, data).await?;
+  Ok(AFImportedDocument { object_id })
+}
+
+/// Creates the collab of a document with the given content in the workspace.
+pub async fn create_document(
+  pg_pool: &PgPool,
+  user_uuid: &Uuid,
+  workspace_id: &Uuid,
+  object_id: &str,
+  data: DocumentData,
+) -> Result<(), AppError> {
+  let collab = Arc::new(MutexCollab::new(CollabOrigin::Empty, object_id, vec![]));
+  let document = Document::create_with_data(collab, data)
+    .map_err(|err| AppError::Internal(anyhow!("fail to create document: {:?}", err)))?;
+  let encoded_collab_v1 = document
+    .get_collab()
+    .encode_collab_v1()
+    .encode_to_bytes()
+    .map_err(|err| AppError::Internal(anyhow!("fail to encode EncodedCollabV1: {:?}", err)))?;
+  create_collab(
+    pg_pool,
+    user_uuid,
+    &InsertCollabParams::new(
+      object_id,
+      CollabType::Document,
+      encoded_collab_v1,
+      workspace_id.to_string(),
+    ),
+  )
+  .await
+}
+
+/// Returns the content of the document as Markdown or HTML.
+pub async fn export_document<S>(
+  collab_storage: &S,
+  uid: &i64,
+  workspace_id: &str,
+  object_id: &str,
+  format: AFDocumentFormat,
+) -> Result<AFExportedDocument, AppError>
+where
+  S: CollabStorage,
+{
+  let data = get_document(collab_storage, uid, workspace_id, object_id).await?;
+  let content = match format {
+    AFDocumentFormat::Markdown => DocumentToMarkdownExporter::document_to_markdown(&data),
+    AFDocumentFormat::Html => DocumentToHtmlExporter::document_to_html(&data),
+  };
+  Ok(AFExportedDocument { format, content })
+}
+
+/// Links the images whose source is the file id of a blob of the workspace to the blob.
+async fn link_blob_images(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  data: &mut DocumentData,
+) -> Result<(), AppError> {
+  for block in data.blocks.values_mut() {
+    if block.ty != IMAGE_BLOCK {
+      continue;
+    }
+    let file_id = match block.data.get(IMAGE_URL).and_then(Value::as_str) {
+      Some(url) if !url.is_empty() && !url.contains('/') && !url.contains(':') => url.to_string(),
+      _ => continue,
+    };
+    if is_blob_metadata_exists(pg_pool, workspace_id, &file_id).await? {
+      let url = format!("/api/file_storage/{}/blob/{}", workspace_id, file_id);
+      block.data.insert(IMAGE_URL.to_string(), Value::String(url));
+    }
+  }
+  Ok(())
+}
+
+async fn get_document_collab<S>(
+  collab_storage: &S,
+  uid: &i64,
+  workspace_id: &str,
+  object_id: &str,
+) -> Result<EncodedCollabV1, AppError>
+where
+  S: CollabStorage,
+{
+  let encoded_collab = collab_storage
+    .get_collab_encoded_v1(
+      uid,
+      QueryCollabParams {
+        object_id: object_id.to_string(),
+        workspace_id: workspace_id.to_string(),
+        collab_type: CollabType::Document,
+      },
+    )
+    .await?;
+  Ok(encoded_collab)
+}
+
+/// Generates the update that inserts the blocks into the `current` document, along with the ids
+/// of the inserted top level blocks.
+fn gen_insert_blocks_update(
+  object_id: &str,
+  current: &EncodedCollabV1,
+  params: InsertDocumentBlocksParams,
+) -> Result<(Vec<u8>, Vec<String>), AppError> {
+  let document = open_document(object_id, current)?;
+  let data = document
+    .get_document_data()
+    .map_err(|err| AppError::Internal(anyhow!("fail to read document: {:?}", err)))?;
+
+  let parent_id = params.parent_id.unwrap_or_else(|| data.page_id.clone());
+  let parent = data.blocks.get(&parent_id).ok_or_else(|| {
+    AppError::InvalidRequest(format!(
+      "block:{} is not found in document:{}",
+      parent_id, object_id
+    ))
+  })?;
+  let siblings = data
+    .meta
+    .children_map
+    .get(&parent.children)
+    .cloned()
+    .unwrap_or_default();
+  let mut prev_id = match params.prev_id {
+    Some(prev_id) if !siblings.contains(&prev_id) => {
+      return Err(AppError::InvalidRequest(format!(
+        "block:{} is not a child of block:{}",
+        prev_id, parent_id
+      )));
+    },
+    Some(prev_id) => Some(prev_id),
+    None => siblings.last().cloned(),
+  };
+
+  let mut actions = vec![];
+  let mut block_ids = vec![];
+  for block in params.blocks {
+    // The parser turns the block into a document whose page block is the block itself
+    let block_data = JsonToDocumentParser::json_str_to_document(&serde_json::to_string(&block)?)
+      .map_err(|err| AppError::InvalidRequest(format!("invalid block: {}", err)))?;
+    push_insert_actions(
+      &block_data,
+      &block_data.page_id,
+      &parent_id,
+      prev_id.take(),
+      &mut actions,
+    );
+    prev_id = Some(block_data.page_id.clone());
+    block_ids.push(block_data.page_id);
+  }
+  document.apply_action(actions);
+
+  let state_vector = doc_from_encoded_collab(current)?.transact().state_vector();
+  let update = doc_from_encoded_collab(&document.get_collab().encode_collab_v1())?
+    .transact()
+    .encode_state_as_update_v1(&state_vector);
+  Ok((update, block_ids))
+}
+
+/// Pushes the actions that insert the block after `prev_id`, followed by the actions that insert
+/// its children in order.
+fn push_insert_actions(
+  data: &DocumentData,
+  block_id: &str,
+  parent_id: &str,
+  prev_id: Option<String>,
+  actions: &mut Vec<BlockAction>,
+) {
+  let mut block = match data.blocks.get(block_id) {
+    Some(block) => block.clone(),
+    None => return,
+  };
+  block.parent = parent_id.to_string();
+
+  let delta = block
+    .external_id
+    .as_ref()
+    .and_then(|text_id| data.meta.text_map.as_ref()?.get(text_id));
+  if let Some(delta) = delta {
+    actions.push(BlockAction {
+      action: BlockActionType::InsertText,
+      payload: BlockActionPayload {
+        block: None,
+        prev_id: None,
+        parent_id: None,
+        text_id: block.external_id.clone(),
+        delta: Some(delta.clone()),
+      },
+    });
+  }
+
+  let children = data
+    .meta
+    .children_map
+    .get(&block.children)
+    .cloned()
+    .unwrap_or_default();
+  actions.push(BlockAction {
+    action: BlockActionType::Insert,
+    payload: BlockActionPayload {
+      block: Some(block),
+      prev_id,
+      parent_id: Some(parent_id.to_string()),
+      text_id: None,
+      delta: None,
+    },
+  });
+
+  let mut prev_child_id = None;
+  for child_id in children {
+    push_insert_actions(data, &child_id, block_id, prev_child_id, actions);
+    prev_child_id = Some(child_id);
+  }
+}
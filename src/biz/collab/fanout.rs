@@ -0,0 +1,129 @@
+use anyhow::Context;
+use futures_util::StreamExt;
+use realtime::collaborate::{CollabFanout, CollabFanoutMessage, LocalCollabFanout};
+use redis::aio::{ConnectionManager, PubSub};
+use redis::AsyncCommands;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tracing::{error, info, warn};
+
+const COLLAB_FANOUT_CHANNEL: &str = "af_collab_fanout";
+const RESUBSCRIBE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A [CollabFanout] backed by redis pub/sub. It relays the collab messages between the
+/// appflowy-cloud instances that connect to the same redis.
+///
+/// All the messages are published to a single channel. Each instance dispatches the received
+/// messages to the groups it hosts and drops the messages of the objects it doesn't host.
+///
+/// The channel is subscribed again when the pub/sub connection is closed. The messages published
+/// while the connection is down are lost.
+pub struct RedisCollabFanout {
+  local: Arc<LocalCollabFanout>,
+  publish_tx: mpsc::UnboundedSender<CollabFanoutMessage>,
+}
+
+impl RedisCollabFanout {
+  pub async fn new(redis_uri: &str, connection_manager: ConnectionManager) -> anyhow::Result<Self> {
+    let local = Arc::new(LocalCollabFanout::new());
+    let client = redis::Client::open(redis_uri).context("failed to connect to redis")?;
+    let pubsub = subscribe_fanout_channel(&client).await?;
+
+    // Receive the messages published by all instances, including the current one. The messages
+    // of the current instance are ignored by the collab broadcast.
+    let weak_local = Arc::downgrade(&local);
+    tokio::spawn(async move {
+      let mut pubsub = pubsub;
+      loop {
+        receive_fanout_messages(pubsub, &weak_local).await;
+        if weak_local.upgrade().is_none() {
+          break;
+        }
+        warn!("the collab fanout subscription is closed, subscribe again");
+        pubsub = resubscribe_fanout_channel(&client).await;
+      }
+    });
+
+    // Publishing requires network IO, so the messages are queued and published in order
+    let (publish_tx, mut publish_rx) = mpsc::unbounded_channel::<CollabFanoutMessage>();
+    let mut conn = connection_manager;
+    tokio::spawn(async move {
+      while let Some(message) = publish_rx.recv().await {
+        match serde_json::to_vec(&message) {
+          Ok(payload) => {
+            let result: Result<(), redis::RedisError> =
+              conn.publish(COLLAB_FANOUT_CHANNEL, payload).await;
+            if let Err(err) = result {
+              error!("fail to publish collab fanout message: {:?}", err);
+            }
+          },
+          Err(err) => error!("fail to serialize collab fanout message: {:?}", err),
+        }
+      }
+    });
+
+    Ok(Self { local, publish_tx })
+  }
+}
+
+async fn subscribe_fanout_channel(client: &redis::Client) -> anyhow::Result<PubSub> {
+  let mut pubsub = client
+    .get_async_connection()
+    .await
+    .context("failed to get the redis connection for collab fanout")?
+    .into_pubsub();
+  pubsub
+    .subscribe(COLLAB_FANOUT_CHANNEL)
+    .await
+    .context("failed to subscribe the collab fanout channel")?;
+  Ok(pubsub)
+}
+
+/// Subscribes the fanout channel until it succeeds.
+async fn resubscribe_fanout_channel(client: &redis::Client) -> PubSub {
+  loop {
+    tokio::time::sleep(RESUBSCRIBE_INTERVAL).await;
+    match subscribe_fanout_channel(client).await {
+      Ok(pubsub) => {
+        info!("did subscribe the collab fanout channel again");
+        return pubsub;
+      },
+      Err(err) => error!("fail to subscribe the collab fanout channel: {:?}", err),
+    }
+  }
+}
+
+/// Dispatches the received messages to the local fanout. Returns when the pub/sub connection is
+/// closed or the fanout is dropped.
+async fn receive_fanout_messages(pubsub: PubSub, local: &Weak<LocalCollabFanout>) {
+  let mut stream = pubsub.into_on_message();
+  while let Some(msg) = stream.next().await {
+    let local = match local.upgrade() {
+      Some(local) => local,
+      None => return,
+    };
+    let message = msg
+      .get_payload::<Vec<u8>>()
+      .map_err(anyhow::Error::from)
+      .and_then(|payload| {
+        serde_json::from_slice::<CollabFanoutMessage>(&payload).map_err(anyhow::Error::from)
+      });
+    match message {
+      Ok(message) => local.publish(message),
+      Err(err) => error!("fail to parse collab fanout message: {:?}", err),
+    }
+  }
+}
+
+impl CollabFanout for RedisCollabFanout {
+  fn publish(&self, message: CollabFanoutMessage) {
+    if let Err(err) = self.publish_tx.send(message) {
+      error!("fail to queue collab fanout message: {}", err);
+    }
+  }
+
+  fn subscribe(&self, object_id: &str) -> broadcast::Receiver<CollabFanoutMessage> {
+    self.local.subscribe(object_id)
+  }
+}
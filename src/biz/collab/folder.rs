@@ -0,0 +1,703 @@
+use crate::biz::audit::AuditActor;
+use crate::biz::collab::document::create_document;
+use crate::biz::collab::ops::{apply_collab_update, delete_collab};
+use crate::biz::collab::snapshot::doc_from_encoded_collab;
+use actix::Recipient;
+use anyhow::anyhow;
+use app_error::AppError;
+use collab::core::collab_plugin::EncodedCollabV1;
+use collab::core::origin::CollabOrigin;
+use collab_entity::CollabType;
+use collab_folder::{timestamp, Folder, RepeatedViewIdentifier, View, ViewLayout};
+use database::collab::{collab_exists, CollabStorage};
+use database_entity::dto::{
+  AFAccessLevel, AFFolderView, AFViewLayout, CreateFolderViewParams, DeleteCollabParams,
+  InsertCollabUpdateParams, MoveFolderViewParams, QueryCollabParams, UpdateFolderViewParams,
+};
+use realtime::collaborate::{CollabAccessControl, CollabUserId};
+use realtime::entities::ApplyCollabUpdate;
+use sqlx::PgPool;
+use std::collections::HashSet;
+use tracing::instrument;
+use uuid::Uuid;
+use validator::Validate;
+use workspace_template::JsonToDocumentParser;
+use yrs::{ReadTxn, Transact};
+
+const EMPTY_DOCUMENT: &str = r#"{"type":"page"}"#;
+
+/// Returns the views of the folder of the workspace as a tree. The views that the user can't
+/// access are left out along with their child views.
+pub async fn get_folder_views<S, AC>(
+  collab_storage: &S,
+  access_control: &AC,
+  uid: &i64,
+  workspace_id: &Uuid,
+) -> Result<Vec<AFFolderView>, AppError>
+where
+  S: CollabStorage,
+  AC: CollabAccessControl,
+{
+  let current = get_folder_collab(collab_storage, uid, workspace_id).await?;
+  let views = {
+    let folder = open_folder(uid, workspace_id, &current)?;
+    folder_view_tree(&folder, &workspace_id.to_string())
+  };
+
+  let mut hidden_view_ids = HashSet::new();
+  for view_id in flatten_view_ids(&views) {
+    if !has_view_access(access_control, uid, &view_id, |_| true).await? {
+      hidden_view_ids.insert(view_id);
+    }
+  }
+  Ok(remove_views(views, &hidden_view_ids))
+}
+
+/// Creates a view in the folder of the workspace. The update is applied to the collab group of the
+/// folder, so the connected clients receive the view like the changes of any other client.
+#[allow(clippy::too_many_arguments)]
+#[instrument(
+  level = "debug",
+  skip(pg_pool, collab_storage, access_control, server, params),
+  err
+)]
+pub async fn create_folder_view<S, AC>(
+  pg_pool: &PgPool,
+  collab_storage: &S,
+  access_control: &AC,
+  server: &Recipient<ApplyCollabUpdate>,
+  user_uuid: &Uuid,
+  uid: &i64,
+  workspace_id: &Uuid,
+  params: CreateFolderViewParams,
+) -> Result<AFFolderView, AppError>
+where
+  S: CollabStorage,
+  AC: CollabAccessControl,
+{
+  params.validate()?;
+  if let Some(parent_view_id) = &params.parent_view_id {
+    check_view_access(
+      access_control,
+      uid,
+      parent_view_id,
+      AFAccessLevel::can_write,
+    )
+    .await?;
+  }
+
+  let view_id = match &params.object_id {
+    Some(object_id) => {
+      if params.layout == AFViewLayout::Document {
+        // Fails when the document doesn't exist or the user can't access it
+        get_collab(
+          collab_storage,
+          uid,
+          workspace_id,
+          object_id,
+          CollabType::Document,
+        )
+        .await?;
+      }
+      object_id.clone()
+    },
+    None if params.layout == AFViewLayout::Document => Uuid::new_v4().to_string(),
+    None => {
+      return Err(AppError::InvalidRequest(format!(
+        "the object id of the {:?} view is required",
+        params.layout
+      )));
+    },
+  };
+
+  let current = get_folder_collab(collab_storage, uid, workspace_id).await?;
+  let (update, view) = gen_create_view_update(uid, workspace_id, &current, &view_id, &params)?;
+  if params.object_id.is_none() {
+    let data = JsonToDocumentParser::json_str_to_document(EMPTY_DOCUMENT)
+      .map_err(|err| AppError::Internal(anyhow!("fail to create empty document: {:?}", err)))?;
+    create_document(pg_pool, user_uuid, workspace_id, &view_id, data).await?;
+  }
+  apply_folder_update(collab_storage, server, uid, workspace_id, update).await?;
+  Ok(view)
+}
+
+/// Renames the view of the folder of the workspace.
+#[instrument(
+  level = "debug",
+  skip(collab_storage, access_control, server, params),
+  err
+)]
+pub async fn update_folder_view<S, AC>(
+  collab_storage: &S,
+  access_control: &AC,
+  server: &Recipient<ApplyCollabUpdate>,
+  uid: &i64,
+  workspace_id: &Uuid,
+  view_id: &str,
+  params: UpdateFolderViewParams,
+) -> Result<(), AppError>
+where
+  S: CollabStorage,
+  AC: CollabAccessControl,
+{
+  params.validate()?;
+  check_view_access(access_control, uid, view_id, AFAccessLevel::can_write).await?;
+  let current = get_folder_collab(collab_storage, uid, workspace_id).await?;
+  let update = {
+    let folder = open_folder(uid, workspace_id, &current)?;
+    get_view(&folder, workspace_id, view_id)?;
+    folder
+      .views
+      .update_view(view_id, |update| update.set_name(&params.name).done());
+    gen_folder_update(&current, &folder)?
+  };
+  apply_folder_update(collab_storage, server, uid, workspace_id, update).await
+}
+
+/// Moves the view of the folder of the workspace under another parent, or to another position
+/// among its siblings.
+#[instrument(
+  level = "debug",
+  skip(collab_storage, access_control, server, params),
+  err
+)]
+pub async fn move_folder_view<S, AC>(
+  collab_storage: &S,
+  access_control: &AC,
+  server: &Recipient<ApplyCollabUpdate>,
+  uid: &i64,
+  workspace_id: &Uuid,
+  view_id: &str,
+  params: MoveFolderViewParams,
+) -> Result<(), AppError>
+where
+  S: CollabStorage,
+  AC: CollabAccessControl,
+{
+  check_view_access(access_control, uid, view_id, AFAccessLevel::can_write).await?;
+  let current = get_folder_collab(collab_storage, uid, workspace_id).await?;
+  let current_parent_view_id = {
+    let folder = open_folder(uid, workspace_id, &current)?;
+    get_view(&folder, workspace_id, view_id)?.parent_view_id
+  };
+  // The view is removed from the children of its current parent, which changes that parent too
+  let parent_view_ids = [
+    Some(&current_parent_view_id),
+    params.parent_view_id.as_ref(),
+  ];
+  for parent_view_id in parent_view_ids.into_iter().flatten() {
+    if parent_view_id != &workspace_id.to_string() {
+      check_view_access(
+        access_control,
+        uid,
+        parent_view_id,
+        AFAccessLevel::can_write,
+      )
+      .await?;
+    }
+  }
+  let update = gen_move_view_update(uid, workspace_id, &current, view_id, params)?;
+  apply_folder_update(collab_storage, server, uid, workspace_id, update).await
+}
+
+/// Deletes the view of the folder of the workspace along with its child views. The collabs of the
+/// deleted views are moved to the trash of the workspace, and their views are only detached from
+/// the folder, so they are put back by [restore_folder_view] when the collabs are restored.
+#[allow(clippy::too_many_arguments)]
+#[instrument(
+  level = "debug",
+  skip(pg_pool, collab_storage, access_control, server),
+  err
+)]
+pub async fn delete_folder_view<S, AC>(
+  pg_pool: &PgPool,
+  collab_storage: &S,
+  access_control: &AC,
+  server: &Recipient<ApplyCollabUpdate>,
+  actor: &AuditActor,
+  uid: &i64,
+  workspace_id: &Uuid,
+  view_id: &str,
+) -> Result<(), AppError>
+where
+  S: CollabStorage,
+  AC: CollabAccessControl,
+{
+  let current = get_folder_collab(collab_storage, uid, workspace_id).await?;
+  let view_ids = {
+    let folder = open_folder(uid, workspace_id, &current)?;
+    let view = get_view(&folder, workspace_id, view_id)?;
+    flatten_view_ids(&[to_folder_view(&view, folder_view_tree(&folder, view_id))])
+  };
+  for view_id in &view_ids {
+    check_view_access(access_control, uid, view_id, AFAccessLevel::can_delete).await?;
+  }
+
+  // The views of the databases are not backed by a collab of their own, so they can't be restored
+  // and are removed from the folder
+  let mut trashed_view_ids = vec![];
+  let mut removed_view_ids = vec![];
+  for view_id in view_ids {
+    if collab_exists(pg_pool, &view_id).await? {
+      trashed_view_ids.push(view_id);
+    } else {
+      removed_view_ids.push(view_id);
+    }
+  }
+  let update = gen_delete_view_update(
+    uid,
+    workspace_id,
+    &current,
+    &trashed_view_ids,
+    &removed_view_ids,
+  )?;
+  apply_folder_update(collab_storage, server, uid, workspace_id, update).await?;
+
+  for view_id in trashed_view_ids {
+    delete_collab(
+      pg_pool,
+      actor,
+      workspace_id,
+      &DeleteCollabParams {
+        object_id: view_id,
+        workspace_id: workspace_id.to_string(),
+      },
+    )
+    .await?;
+  }
+  Ok(())
+}
+
+/// Puts the view of the collab that is restored from the trash back into the folder. The view
+/// goes back under its parent if the parent is still in the folder, otherwise it's moved to the
+/// top level of the workspace. Does nothing if the collab has no detached view in the folder.
+#[instrument(level = "debug", skip(collab_storage, server), err)]
+pub async fn restore_folder_view<S>(
+  collab_storage: &S,
+  server: &Recipient<ApplyCollabUpdate>,
+  uid: &i64,
+  workspace_id: &Uuid,
+  view_id: &str,
+) -> Result<(), AppError>
+where
+  S: CollabStorage,
+{
+  let current = get_folder_collab(collab_storage, uid, workspace_id).await?;
+  if let Some(update) = gen_restore_view_update(uid, workspace_id, &current, view_id)? {
+    apply_folder_update(collab_storage, server, uid, workspace_id, update).await?;
+  }
+  Ok(())
+}
+
+/// Returns whether the access level of the user to the collab of the view passes the check. The
+/// views that are not backed by a collab, like the views of the databases, only follow the
+/// permissions of the workspace.
+async fn has_view_access<AC>(
+  access_control: &AC,
+  uid: &i64,
+  view_id: &str,
+  check: impl Fn(&AFAccessLevel) -> bool,
+) -> Result<bool, AppError>
+where
+  AC: CollabAccessControl,
+{
+  match access_control
+    .get_collab_access_level(CollabUserId::UserId(uid), view_id)
+    .await
+  {
+    Ok(level) => Ok(check(&level)),
+    Err(err) if err.is_record_not_found() => Ok(true),
+    Err(AppError::NotEnoughPermissions(_)) => Ok(false),
+    Err(err) => Err(err),
+  }
+}
+
+async fn check_view_access<AC>(
+  access_control: &AC,
+  uid: &i64,
+  view_id: &str,
+  check: impl Fn(&AFAccessLevel) -> bool,
+) -> Result<(), AppError>
+where
+  AC: CollabAccessControl,
+{
+  if !has_view_access(access_control, uid, view_id, check).await? {
+    return Err(AppError::NotEnoughPermissions(format!(
+      "user:{} doesn't have enough permissions to view:{}",
+      uid, view_id
+    )));
+  }
+  Ok(())
+}
+
+async fn get_collab<S>(
+  collab_storage: &S,
+  uid: &i64,
+  workspace_id: &Uuid,
+  object_id: &str,
+  collab_type: CollabType,
+) -> Result<EncodedCollabV1, AppError>
+where
+  S: CollabStorage,
+{
+  let encoded_collab = collab_storage
+    .get_collab_encoded_v1(
+      uid,
+      QueryCollabParams {
+        object_id: object_id.to_string(),
+        workspace_id: workspace_id.to_string(),
+        collab_type,
+      },
+    )
+    .await?;
+  Ok(encoded_collab)
+}
+
+/// The object id of the folder of the workspace is the id of the workspace.
+async fn get_folder_collab<S>(
+  collab_storage: &S,
+  uid: &i64,
+  workspace_id: &Uuid,
+) -> Result<EncodedCollabV1, AppError>
+where
+  S: CollabStorage,
+{
+  let folder_id = workspace_id.to_string();
+  get_collab(
+    collab_storage,
+    uid,
+    workspace_id,
+    &folder_id,
+    CollabType::Folder,
+  )
+  .await
+}
+
+async fn apply_folder_update<S>(
+  collab_storage: &S,
+  server: &Recipient<ApplyCollabUpdate>,
+  uid: &i64,
+  workspace_id: &Uuid,
+  update: Vec<u8>,
+) -> Result<(), AppError>
+where
+  S: CollabStorage,
+{
+  apply_collab_update(
+    collab_storage,
+    server,
+    uid,
+    InsertCollabUpdateParams {
+      object_id: workspace_id.to_string(),
+      update,
+      workspace_id: workspace_id.to_string(),
+      collab_type: CollabType::Folder,
+    },
+  )
+  .await
+}
+
+fn open_folder(
+  uid: &i64,
+  workspace_id: &Uuid,
+  encoded_collab: &EncodedCollabV1,
+) -> Result<Folder, AppError> {
+  Folder::from_collab_raw_data(
+    *uid,
+    CollabOrigin::Empty,
+    vec![encoded_collab.doc_state.to_vec()],
+    &workspace_id.to_string(),
+    vec![],
+  )
+  .map_err(|err| AppError::Internal(anyhow!("fail to open folder: {:?}", err)))
+}
+
+/// Generates the update that turns the `current` folder into the content of the `folder`.
+fn gen_folder_update(current: &EncodedCollabV1, folder: &Folder) -> Result<Vec<u8>, AppError> {
+  let state_vector = doc_from_encoded_collab(current)?.transact().state_vector();
+  let update = doc_from_encoded_collab(&folder.encode_collab_v1())?
+    .transact()
+    .encode_state_as_update_v1(&state_vector);
+  Ok(update)
+}
+
+fn gen_create_view_update(
+  uid: &i64,
+  workspace_id: &Uuid,
+  current: &EncodedCollabV1,
+  view_id: &str,
+  params: &CreateFolderViewParams,
+) -> Result<(Vec<u8>, AFFolderView), AppError> {
+  let folder = open_folder(uid, workspace_id, current)?;
+  if folder.views.get_view(view_id).is_some() {
+    return Err(AppError::RecordAlreadyExists(format!(
+      "view:{} already exists in the folder of workspace:{}",
+      view_id, workspace_id
+    )));
+  }
+
+  let parent_view_id = params
+    .parent_view_id
+    .clone()
+    .unwrap_or_else(|| workspace_id.to_string());
+  let siblings = child_view_ids(&folder, workspace_id, &parent_view_id)?;
+  let index = match &params.prev_view_id {
+    Some(prev_view_id) => {
+      let position = siblings
+        .iter()
+        .position(|id| id == prev_view_id)
+        .ok_or_else(|| not_a_child_error(prev_view_id, &parent_view_id))?;
+      Some(position as u32 + 1)
+    },
+    None => None,
+  };
+
+  let now = timestamp();
+  let view = View {
+    id: view_id.to_string(),
+    parent_view_id,
+    name: params.name.clone(),
+    desc: String::new(),
+    created_at: now,
+    is_favorite: false,
+    layout: view_layout(params.layout),
+    icon: None,
+    created_by: Some(*uid),
+    last_edited_time: now,
+    children: RepeatedViewIdentifier::new(vec![]),
+    last_edited_by: Some(*uid),
+  };
+  let folder_view = to_folder_view(&view, vec![]);
+  folder.insert_view(view, index);
+  Ok((gen_folder_update(current, &folder)?, folder_view))
+}
+
+fn gen_move_view_update(
+  uid: &i64,
+  workspace_id: &Uuid,
+  current: &EncodedCollabV1,
+  view_id: &str,
+  params: MoveFolderViewParams,
+) -> Result<Vec<u8>, AppError> {
+  let folder = open_folder(uid, workspace_id, current)?;
+  get_view(&folder, workspace_id, view_id)?;
+  let parent_view_id = params
+    .parent_view_id
+    .unwrap_or_else(|| workspace_id.to_string());
+  if parent_view_id == view_id || is_descendant_of(&folder, &parent_view_id, view_id) {
+    return Err(AppError::InvalidRequest(format!(
+      "view:{} can't be moved under itself",
+      view_id
+    )));
+  }
+
+  let siblings = child_view_ids(&folder, workspace_id, &parent_view_id)?
+    .into_iter()
+    .filter(|id| id != view_id)
+    .collect::<Vec<_>>();
+  let prev_view_id = match params.prev_view_id {
+    Some(prev_view_id) if !siblings.contains(&prev_view_id) => {
+      return Err(not_a_child_error(&prev_view_id, &parent_view_id));
+    },
+    Some(prev_view_id) => Some(prev_view_id),
+    None => siblings.last().cloned(),
+  };
+  folder.move_nested_view(view_id, &parent_view_id, prev_view_id);
+  gen_folder_update(current, &folder)
+}
+
+/// Generates the update that detaches the trashed views from their parents and removes the
+/// removed views. The detached views keep their content and parent, so they can be restored.
+fn gen_delete_view_update(
+  uid: &i64,
+  workspace_id: &Uuid,
+  current: &EncodedCollabV1,
+  trashed_view_ids: &[String],
+  removed_view_ids: &[String],
+) -> Result<Vec<u8>, AppError> {
+  let folder = open_folder(uid, workspace_id, current)?;
+  for view_id in trashed_view_ids.iter().chain(removed_view_ids) {
+    if let Some(view) = folder.views.get_view(view_id) {
+      folder
+        .views
+        .dissociate_parent_child(&view.parent_view_id, view_id);
+    }
+  }
+  folder.views.delete_views(removed_view_ids.to_vec());
+  gen_folder_update(current, &folder)
+}
+
+/// Generates the update that attaches the detached view to the end of the children of its parent,
+/// or of the workspace if the parent is not in the folder. Returns None if there is no detached
+/// view to restore.
+fn gen_restore_view_update(
+  uid: &i64,
+  workspace_id: &Uuid,
+  current: &EncodedCollabV1,
+  view_id: &str,
+) -> Result<Option<Vec<u8>>, AppError> {
+  let folder = open_folder(uid, workspace_id, current)?;
+  let view = match folder.views.get_view(view_id) {
+    Some(view) if !is_in_folder(&folder, workspace_id, view_id) => view,
+    _ => return Ok(None),
+  };
+  let parent_view_id = if is_in_folder(&folder, workspace_id, &view.parent_view_id) {
+    view.parent_view_id.clone()
+  } else {
+    workspace_id.to_string()
+  };
+  let prev_view_id = folder
+    .views
+    .get_views_belong_to(&parent_view_id)
+    .last()
+    .map(|view| view.id.clone());
+  folder.move_nested_view(view_id, &parent_view_id, prev_view_id);
+  Ok(Some(gen_folder_update(current, &folder)?))
+}
+
+/// Returns whether the view can be reached from the workspace through the children of its
+/// ancestors. The views of the trashed collabs stay in the folder, but they are detached from
+/// their parents. The workspace itself is always in the folder.
+fn is_in_folder(folder: &Folder, workspace_id: &Uuid, view_id: &str) -> bool {
+  let workspace_id = workspace_id.to_string();
+  let mut visited = HashSet::new();
+  let mut view_id = view_id.to_string();
+  while view_id != workspace_id {
+    if !visited.insert(view_id.clone()) {
+      return false;
+    }
+    let parent_view_id = match folder.views.get_view(&view_id) {
+      Some(view) => view.parent_view_id.clone(),
+      None => return false,
+    };
+    let is_child = folder
+      .views
+      .get_views_belong_to(&parent_view_id)
+      .iter()
+      .any(|child| child.id == view_id);
+    if !is_child {
+      return false;
+    }
+    view_id = parent_view_id;
+  }
+  true
+}
+
+/// Returns the view of the folder. The detached views of the trashed collabs are not found.
+fn get_view(folder: &Folder, workspace_id: &Uuid, view_id: &str) -> Result<View, AppError> {
+  folder
+    .views
+    .get_view(view_id)
+    .filter(|_| is_in_folder(folder, workspace_id, view_id))
+    .map(|view| view.as_ref().clone())
+    .ok_or_else(|| {
+      AppError::RecordNotFound(format!(
+        "view:{} is not found in the folder of workspace:{}",
+        view_id, workspace_id
+      ))
+    })
+}
+
+/// Returns the ids of the child views of the parent, which is either the workspace or a view of
+/// the folder.
+fn child_view_ids(
+  folder: &Folder,
+  workspace_id: &Uuid,
+  parent_view_id: &str,
+) -> Result<Vec<String>, AppError> {
+  if !is_in_folder(folder, workspace_id, parent_view_id) {
+    return Err(AppError::InvalidRequest(format!(
+      "view:{} is not found in the folder of workspace:{}",
+      parent_view_id, workspace_id
+    )));
+  }
+  Ok(
+    folder
+      .views
+      .get_views_belong_to(parent_view_id)
+      .iter()
+      .map(|view| view.id.clone())
+      .collect(),
+  )
+}
+
+fn not_a_child_error(view_id: &str, parent_view_id: &str) -> AppError {
+  AppError::InvalidRequest(format!(
+    "view:{} is not a child of view:{}",
+    view_id, parent_view_id
+  ))
+}
+
+fn is_descendant_of(folder: &Folder, view_id: &str, ancestor_id: &str) -> bool {
+  let mut visited = HashSet::new();
+  let mut current = folder.views.get_view(view_id);
+  while let Some(view) = current {
+    if view.parent_view_id == ancestor_id {
+      return true;
+    }
+    if !visited.insert(view.id.clone()) {
+      break;
+    }
+    current = folder.views.get_view(&view.parent_view_id);
+  }
+  false
+}
+
+fn folder_view_tree(folder: &Folder, parent_view_id: &str) -> Vec<AFFolderView> {
+  folder
+    .views
+    .get_views_belong_to(parent_view_id)
+    .iter()
+    .map(|view| to_folder_view(view, folder_view_tree(folder, &view.id)))
+    .collect()
+}
+
+fn to_folder_view(view: &View, children: Vec<AFFolderView>) -> AFFolderView {
+  AFFolderView {
+    view_id: view.id.clone(),
+    parent_view_id: view.parent_view_id.clone(),
+    name: view.name.clone(),
+    layout: af_view_layout(&view.layout),
+    created_at: view.created_at,
+    last_edited_time: view.last_edited_time,
+    children,
+  }
+}
+
+fn flatten_view_ids(views: &[AFFolderView]) -> Vec<String> {
+  let mut view_ids = vec![];
+  for view in views {
+    view_ids.push(view.view_id.clone());
+    view_ids.extend(flatten_view_ids(&view.children));
+  }
+  view_ids
+}
+
+fn remove_views(views: Vec<AFFolderView>, view_ids: &HashSet<String>) -> Vec<AFFolderView> {
+  views
+    .into_iter()
+    .filter(|view| !view_ids.contains(&view.view_id))
+    .map(|mut view| {
+      view.children = remove_views(view.children, view_ids);
+      view
+    })
+    .collect()
+}
+
+fn view_layout(layout: AFViewLayout) -> ViewLayout {
+  match layout {
+    AFViewLayout::Document => ViewLayout::Document,
+    AFViewLayout::Grid => ViewLayout::Grid,
+    AFViewLayout::Board => ViewLayout::Board,
+    AFViewLayout::Calendar => ViewLayout::Calendar,
+  }
+}
+
+fn af_view_layout(layout: &ViewLayout) -> AFViewLayout {
+  match layout {
+    ViewLayout::Document => AFViewLayout::Document,
+    ViewLayout::Grid => AFViewLayout::Grid,
+    ViewLayout::Board => AFViewLayout::Board,
+    ViewLayout::Calendar => AFViewLayout::Calendar,
+  }
+}
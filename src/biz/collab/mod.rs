@@ -1,4 +1,13 @@
 pub mod access_control;
+pub mod comment;
+pub mod database;
+pub mod document;
+pub mod fanout;
+pub mod folder;
 pub mod member_listener;
 pub mod ops;
+pub mod search;
+pub mod share_link;
+pub mod snapshot;
 pub mod storage;
+pub mod trash;
@@ -1,18 +1,54 @@
+use crate::biz::audit::{record_audit_log, AuditActor};
+use actix::Recipient;
 use anyhow::Context;
+use database::collab::CollabStorage;
 use database::user;
+use realtime::entities::ApplyCollabUpdate;
 use std::ops::DerefMut;
 
 use app_error::AppError;
 use database_entity::dto::{
-  AFCollabMember, AFCollabSnapshots, CollabMemberIdentify, DeleteCollabParams,
-  InsertCollabMemberParams, InsertCollabParams, QueryCollabMembers, QueryObjectSnapshotParams,
-  QuerySnapshotParams, UpdateCollabMemberParams,
+  AFAuditAction, AFCollabMember, AFCollabSnapshots, CollabMemberIdentify, DeleteCollabParams,
+  InsertCollabMemberParams, InsertCollabParams, InsertCollabUpdateParams, QueryCollabMembers,
+  QueryObjectSnapshotParams, QuerySnapshotParams, UpdateCollabMemberParams,
 };
 
+use serde_json::json;
 use sqlx::{types::Uuid, PgPool};
 use tracing::trace;
 use validator::Validate;
 
+/// Applies the update to the collab. When the collab is opened, the realtime server applies the
+/// update to the group of the collab, which persists it and broadcasts it to the connected
+/// clients. Otherwise, the update is appended to the update log of the collab.
+pub async fn apply_collab_update<S>(
+  collab_storage: &S,
+  server: &Recipient<ApplyCollabUpdate>,
+  uid: &i64,
+  params: InsertCollabUpdateParams,
+) -> Result<(), AppError>
+where
+  S: CollabStorage,
+{
+  let is_applied = server
+    .send(ApplyCollabUpdate {
+      object_id: params.object_id.clone(),
+      update: params.update.clone(),
+    })
+    .await
+    .map_err(|err| AppError::Unhandled(err.to_string()))?
+    .map_err(|err| AppError::Internal(anyhow::Error::from(err)))?;
+
+  if !is_applied {
+    trace!(
+      "collab:{} is not opened, save the update to storage",
+      params.object_id
+    );
+    collab_storage.insert_collab_update(uid, params).await?;
+  }
+  Ok(())
+}
+
 pub async fn create_collab(
   pg_pool: &PgPool,
   user_uuid: &Uuid,
@@ -66,11 +102,30 @@ pub async fn get_all_collab_snapshot(
 }
 pub async fn delete_collab(
   pg_pool: &PgPool,
-  _user_uuid: &Uuid,
+  actor: &AuditActor,
+  workspace_id: &Uuid,
   params: &DeleteCollabParams,
 ) -> Result<(), AppError> {
   params.validate()?;
-  database::collab::delete_collab(pg_pool, &params.object_id).await?;
+  let mut txn = pg_pool
+    .begin()
+    .await
+    .context("acquire transaction to delete collab")?;
+  database::collab::delete_collab(txn.deref_mut(), &params.object_id).await?;
+  record_audit_log(
+    txn.deref_mut(),
+    actor,
+    Some(workspace_id),
+    AFAuditAction::CollabDelete,
+    &params.object_id,
+    None,
+  )
+  .await?;
+
+  txn
+    .commit()
+    .await
+    .context("fail to commit the transaction to delete collab")?;
   Ok(())
 }
 
@@ -79,6 +134,8 @@ pub async fn delete_collab(
 /// If the collab member does not exist, create a new one
 pub async fn create_collab_member(
   pg_pool: &PgPool,
+  actor: &AuditActor,
+  workspace_id: &Uuid,
   params: &InsertCollabMemberParams,
 ) -> Result<(), AppError> {
   params.validate()?;
@@ -112,6 +169,15 @@ pub async fn create_collab_member(
     &mut txn,
   )
   .await?;
+  record_audit_log(
+    txn.deref_mut(),
+    actor,
+    Some(workspace_id),
+    AFAuditAction::CollabMemberAdd,
+    &params.object_id,
+    Some(json!({ "uid": params.uid, "access_level": params.access_level })),
+  )
+  .await?;
 
   txn
     .commit()
@@ -122,7 +188,8 @@ pub async fn create_collab_member(
 
 pub async fn upsert_collab_member(
   pg_pool: &PgPool,
-  _user_uuid: &Uuid,
+  actor: &AuditActor,
+  workspace_id: &Uuid,
   params: &UpdateCollabMemberParams,
 ) -> Result<(), AppError> {
   params.validate()?;
@@ -145,6 +212,15 @@ pub async fn upsert_collab_member(
     &mut txn,
   )
   .await?;
+  record_audit_log(
+    txn.deref_mut(),
+    actor,
+    Some(workspace_id),
+    AFAuditAction::CollabMemberUpdate,
+    &params.object_id,
+    Some(json!({ "uid": params.uid, "access_level": params.access_level })),
+  )
+  .await?;
 
   txn
     .commit()
@@ -165,10 +241,30 @@ pub async fn get_collab_member(
 
 pub async fn delete_collab_member(
   pg_pool: &PgPool,
+  actor: &AuditActor,
+  workspace_id: &Uuid,
   params: &CollabMemberIdentify,
 ) -> Result<(), AppError> {
   params.validate()?;
-  database::collab::delete_collab_member(params.uid, &params.object_id, pg_pool).await?;
+  let mut txn = pg_pool
+    .begin()
+    .await
+    .context("acquire transaction to delete collab member")?;
+  database::collab::delete_collab_member(params.uid, &params.object_id, txn.deref_mut()).await?;
+  record_audit_log(
+    txn.deref_mut(),
+    actor,
+    Some(workspace_id),
+    AFAuditAction::CollabMemberRemove,
+    &params.object_id,
+    Some(json!({ "uid": params.uid })),
+  )
+  .await?;
+
+  txn
+    .commit()
+    .await
+    .context("fail to commit the transaction to delete collab member")?;
   Ok(())
 }
 pub async fn get_collab_member_list(
@@ -0,0 +1,195 @@
+use crate::biz::collab::access_control::CollabAccessControlImpl;
+use crate::biz::collab::snapshot::{doc_from_encoded_collab, document_data_from_encoded_collab};
+use anyhow::anyhow;
+use app_error::AppError;
+use collab::core::collab_plugin::EncodedCollabV1;
+use collab_document::blocks::DocumentData;
+use collab_entity::CollabType;
+use database::search::{delete_collab_search_content, search_collab, upsert_collab_search_content};
+use database_entity::dto::{AFCollabSearchResult, InsertCollabParams};
+use realtime::collaborate::{CollabAccessControl, CollabUserId};
+use serde_json::Value;
+use sqlx::PgPool;
+use tracing::{error, trace};
+use uuid::Uuid;
+use yrs::types::ToJson;
+use yrs::{ReadTxn, Transact};
+
+/// The maximum number of results of a search.
+pub const MAX_SEARCH_LIMIT: u32 = 100;
+
+/// Extracts the plain text of the documents and database rows when they are persisted, and keeps
+/// it in the full-text search index of the workspace.
+#[derive(Clone)]
+pub struct CollabIndexer {
+  pg_pool: PgPool,
+}
+
+impl CollabIndexer {
+  pub fn new(pg_pool: PgPool) -> Self {
+    Self { pg_pool }
+  }
+
+  /// Indexes the content of the collab. The collabs that are not searchable are ignored. Indexing
+  /// is best-effort, the errors are logged instead of failing the write of the collab.
+  pub async fn index_collab(&self, params: &InsertCollabParams) {
+    if !is_searchable(&params.collab_type) {
+      return;
+    }
+    if let Err(err) = self.try_index_collab(params).await {
+      error!("fail to index collab:{}: {:?}", params.object_id, err);
+    }
+  }
+
+  async fn try_index_collab(&self, params: &InsertCollabParams) -> Result<(), AppError> {
+    let workspace_id = Uuid::parse_str(&params.workspace_id)?;
+    let encoded_collab = EncodedCollabV1::decode_from_bytes(&params.encoded_collab_v1)
+      .map_err(|err| AppError::Internal(anyhow!("fail to decode EncodedCollabV1: {:?}", err)))?;
+    let content = match params.collab_type {
+      CollabType::Document => document_text(&params.object_id, &encoded_collab)?,
+      _ => database_row_text(&encoded_collab)?,
+    };
+    upsert_collab_search_content(
+      &self.pg_pool,
+      &workspace_id,
+      &params.object_id,
+      params.collab_type.value(),
+      &content,
+    )
+    .await?;
+    trace!(
+      "did index collab:{} with {} chars",
+      params.object_id,
+      content.len()
+    );
+    Ok(())
+  }
+
+  pub async fn remove_collab(&self, object_id: &str) {
+    if let Err(err) = delete_collab_search_content(&self.pg_pool, object_id).await {
+      error!("fail to remove collab:{} from index: {:?}", object_id, err);
+    }
+  }
+}
+
+#[inline]
+fn is_searchable(collab_type: &CollabType) -> bool {
+  matches!(collab_type, CollabType::Document | CollabType::DatabaseRow)
+}
+
+/// Searches the documents and database rows of the workspace. Only the collabs that the user can
+/// read are returned.
+pub async fn search_workspace_collabs(
+  pg_pool: &PgPool,
+  access_control: &CollabAccessControlImpl,
+  uid: &i64,
+  workspace_id: &Uuid,
+  query: &str,
+  limit: u32,
+) -> Result<Vec<AFCollabSearchResult>, AppError> {
+  if query.trim().is_empty() {
+    return Err(AppError::InvalidRequest(
+      "the search query is empty".to_string(),
+    ));
+  }
+
+  let limit = limit.min(MAX_SEARCH_LIMIT) as usize;
+  // Fetch more rows than the limit, since some of them may be filtered out by the access control
+  let rows = search_collab(pg_pool, workspace_id, query, (limit * 2) as i64).await?;
+  let mut results = Vec::with_capacity(limit);
+  for row in rows {
+    if results.len() >= limit {
+      break;
+    }
+    let can_read = access_control
+      .get_collab_access_level(CollabUserId::from(uid), &row.oid)
+      .await
+      .is_ok();
+    if can_read {
+      results.push(AFCollabSearchResult {
+        object_id: row.oid,
+        collab_type: CollabType::from(row.collab_type),
+        rank: row.rank,
+        snippet: row.snippet,
+      });
+    }
+  }
+  Ok(results)
+}
+
+/// Returns the text of the blocks in the order they appear in the document, one block per line.
+fn document_text(object_id: &str, encoded_collab: &EncodedCollabV1) -> Result<String, AppError> {
+  let data = document_data_from_encoded_collab(object_id, encoded_collab)?;
+  let mut lines = vec![];
+  collect_block_text(&data, &data.page_id, &mut lines);
+  Ok(lines.join("\n"))
+}
+
+fn collect_block_text(data: &DocumentData, block_id: &str, lines: &mut Vec<String>) {
+  let block = match data.blocks.get(block_id) {
+    Some(block) => block,
+    None => return,
+  };
+
+  // The delta of the block is either kept in the text map or, in the older documents, in the data
+  // of the block
+  let delta = block
+    .external_id
+    .as_ref()
+    .and_then(|external_id| data.meta.text_map.as_ref()?.get(external_id))
+    .and_then(|delta| serde_json::from_str::<Value>(delta).ok())
+    .or_else(|| block.data.get("delta").cloned());
+  if let Some(text) = delta
+    .as_ref()
+    .map(delta_text)
+    .filter(|text| !text.is_empty())
+  {
+    lines.push(text);
+  }
+
+  if let Some(children) = data.meta.children_map.get(&block.children) {
+    for child_id in children {
+      collect_block_text(data, child_id, lines);
+    }
+  }
+}
+
+/// Concatenates the inserted strings of the delta, e.g. `[{"insert": "Hello"}]`.
+fn delta_text(delta: &Value) -> String {
+  delta
+    .as_array()
+    .map(|ops| {
+      ops
+        .iter()
+        .filter_map(|op| op.get("insert")?.as_str())
+        .collect::<String>()
+    })
+    .unwrap_or_default()
+}
+
+/// Returns the text of the cells of the database row, one cell per line. The cells are kept in
+/// the `data.cells` map of the row, and the content of each cell is in its `data` field.
+fn database_row_text(encoded_collab: &EncodedCollabV1) -> Result<String, AppError> {
+  let doc = doc_from_encoded_collab(encoded_collab)?;
+  let txn = doc.transact();
+  let row = match txn.get_map("data") {
+    Some(row) => row,
+    None => return Ok(String::new()),
+  };
+  let row = serde_json::to_value(row.to_json(&txn))
+    .map_err(|err| AppError::Internal(anyhow!("fail to read database row: {:?}", err)))?;
+
+  let lines = row
+    .get("cells")
+    .and_then(|cells| cells.as_object())
+    .map(|cells| {
+      cells
+        .values()
+        .filter_map(|cell| cell.get("data")?.as_str())
+        .filter(|text| !text.is_empty())
+        .map(|text| text.to_string())
+        .collect::<Vec<_>>()
+    })
+    .unwrap_or_default();
+  Ok(lines.join("\n"))
+}
@@ -0,0 +1,198 @@
+use crate::biz::audit::{record_audit_log, AuditActor};
+use crate::biz::collab::access_control::CollabAccessControlImpl;
+use crate::component::auth::{compute_hash_password, verify_password_hash};
+use crate::telemetry::spawn_blocking_with_tracing;
+use anyhow::{anyhow, Context};
+use app_error::AppError;
+use chrono::Utc;
+use collab_entity::CollabType;
+use database::collab::CollabStorage;
+use database::share_link::{
+  delete_collab_share_link, insert_collab_share_link, select_collab_share_link_by_token,
+  select_collab_share_links,
+};
+use database_entity::dto::{
+  AFAccessLevel, AFAuditAction, AFCollabShareLink, AFSharedCollab, CreateCollabShareLinkParams,
+  QueryCollabParams,
+};
+use database_entity::pg_row::AFCollabShareLinkRow;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use secrecy::{ExposeSecret, Secret};
+use serde_json::json;
+use sqlx::PgPool;
+use tracing::instrument;
+use uuid::Uuid;
+
+const SHARE_TOKEN_LEN: usize = 32;
+
+#[instrument(level = "debug", skip(pg_pool, collab_storage, params), err)]
+pub async fn create_share_link<S>(
+  pg_pool: &PgPool,
+  collab_storage: &S,
+  actor: &AuditActor,
+  uid: i64,
+  workspace_id: &Uuid,
+  object_id: &str,
+  params: CreateCollabShareLinkParams,
+) -> Result<AFCollabShareLink, AppError>
+where
+  S: CollabStorage,
+{
+  if !matches!(
+    params.access_level,
+    AFAccessLevel::ReadOnly | AFAccessLevel::ReadAndComment
+  ) {
+    return Err(AppError::InvalidRequest(
+      "a share link can only grant the read or comment access".to_string(),
+    ));
+  }
+  if matches!(params.expires_at, Some(expires_at) if expires_at <= Utc::now()) {
+    return Err(AppError::InvalidRequest(
+      "the expiry of the share link must be in the future".to_string(),
+    ));
+  }
+  if !collab_storage.is_collab_exist(object_id).await? {
+    return Err(AppError::RecordNotFound(format!(
+      "collab:{} doesn't exist",
+      object_id
+    )));
+  }
+
+  let password_hash = match params.password.filter(|password| !password.is_empty()) {
+    None => None,
+    Some(password) => {
+      let hash = spawn_blocking_with_tracing(move || compute_hash_password(password.as_bytes()))
+        .await
+        .context("fail to hash the password of the share link")??;
+      Some(hash.expose_secret().clone())
+    },
+  };
+
+  let token = rand::thread_rng()
+    .sample_iter(&Alphanumeric)
+    .take(SHARE_TOKEN_LEN)
+    .map(char::from)
+    .collect::<String>();
+  let row = insert_collab_share_link(
+    pg_pool,
+    workspace_id,
+    object_id,
+    params.collab_type.value(),
+    uid,
+    &token,
+    i32::from(params.access_level),
+    params.expires_at,
+    password_hash,
+  )
+  .await?;
+  record_audit_log(
+    pg_pool,
+    actor,
+    Some(workspace_id),
+    AFAuditAction::CollabShareLinkCreate,
+    object_id,
+    Some(json!({ "share_id": row.id, "access_level": row.access_level })),
+  )
+  .await?;
+  Ok(AFCollabShareLink::from(row))
+}
+
+pub async fn get_share_links(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  object_id: &str,
+) -> Result<Vec<AFCollabShareLink>, AppError> {
+  let links = select_collab_share_links(pg_pool, workspace_id, object_id)
+    .await?
+    .into_iter()
+    .map(AFCollabShareLink::from)
+    .collect();
+  Ok(links)
+}
+
+/// Deletes the share link and ends the websocket sessions opened through it.
+pub async fn delete_share_link(
+  pg_pool: &PgPool,
+  access_control: &CollabAccessControlImpl,
+  actor: &AuditActor,
+  workspace_id: &Uuid,
+  object_id: &str,
+  share_id: &Uuid,
+) -> Result<(), AppError> {
+  let token = delete_collab_share_link(pg_pool, workspace_id, object_id, share_id).await?;
+  access_control.remove_share_sessions(&token).await;
+  record_audit_log(
+    pg_pool,
+    actor,
+    Some(workspace_id),
+    AFAuditAction::CollabShareLinkDelete,
+    object_id,
+    Some(json!({ "share_id": share_id })),
+  )
+  .await
+}
+
+/// Returns the share link of the token if it's not expired and the password matches.
+pub async fn open_share_link(
+  pg_pool: &PgPool,
+  token: &str,
+  password: Option<String>,
+) -> Result<AFCollabShareLinkRow, AppError> {
+  let link = select_collab_share_link_by_token(pg_pool, token)
+    .await?
+    .ok_or_else(|| AppError::RecordNotFound("share link doesn't exist".to_string()))?;
+  if matches!(link.expires_at, Some(expires_at) if expires_at <= Utc::now()) {
+    return Err(AppError::NotEnoughPermissions(
+      "share link is expired".to_string(),
+    ));
+  }
+
+  if let Some(password_hash) = link.password_hash.clone() {
+    let password = password.ok_or_else(|| {
+      AppError::NotEnoughPermissions("share link requires a password".to_string())
+    })?;
+    spawn_blocking_with_tracing(move || {
+      verify_password_hash(Secret::new(password_hash), Secret::new(password))
+    })
+    .await
+    .context("fail to verify the password of the share link")?
+    .map_err(|_| AppError::NotEnoughPermissions("invalid password of share link".to_string()))?;
+  }
+  Ok(link)
+}
+
+/// Returns the collab of the share link. The collab is read on behalf of the user who created the
+/// link.
+pub async fn get_shared_collab<S>(
+  pg_pool: &PgPool,
+  collab_storage: &S,
+  token: &str,
+  password: Option<String>,
+) -> Result<AFSharedCollab, AppError>
+where
+  S: CollabStorage,
+{
+  let link = open_share_link(pg_pool, token, password).await?;
+  let collab_type = CollabType::from(link.collab_type);
+  let encoded_collab = collab_storage
+    .get_collab_encoded_v1(
+      &link.created_by,
+      QueryCollabParams {
+        object_id: link.oid.clone(),
+        workspace_id: link.workspace_id.to_string(),
+        collab_type: collab_type.clone(),
+      },
+    )
+    .await?;
+  let encoded_collab_v1 = encoded_collab
+    .encode_to_bytes()
+    .map_err(|err| AppError::Internal(anyhow!("fail to encode collab: {:?}", err)))?;
+  Ok(AFSharedCollab {
+    object_id: link.oid,
+    workspace_id: link.workspace_id,
+    collab_type,
+    access_level: AFAccessLevel::from(link.access_level),
+    encoded_collab_v1,
+  })
+}
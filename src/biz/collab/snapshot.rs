@@ -0,0 +1,302 @@
+use crate::biz::audit::{record_audit_log, AuditActor};
+use crate::biz::collab::ops::apply_collab_update;
+use actix::Recipient;
+use anyhow::anyhow;
+use app_error::AppError;
+use collab::core::collab::{MutexCollab, TransactionMutExt};
+use collab::core::collab_plugin::EncodedCollabV1;
+use collab::core::origin::CollabOrigin;
+use collab_document::blocks::{Block, DocumentData};
+use collab_document::document::Document;
+use collab_entity::CollabType;
+use database::collab::CollabStorage;
+use database_entity::dto::{
+  AFAuditAction, AFDocumentDiff, AFSnapshotDiff, InsertCollabUpdateParams, QueryCollabParams,
+  QuerySnapshotDiffParams, RestoreSnapshotParams,
+};
+use realtime::collaborate::{CollabAccessControl, CollabUserId};
+use realtime::entities::ApplyCollabUpdate;
+use serde_json::json;
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+use yrs::updates::decoder::Decode;
+use yrs::{Doc, ReadTxn, StateVector, Transact, UndoManager, Update};
+
+/// Restores the collab to the content of the snapshot.
+///
+/// The restored content is applied as a new update on top of the current state of the collab,
+/// so the connected clients converge on the restored state without reloading the collab.
+#[allow(clippy::too_many_arguments)]
+pub async fn restore_collab_snapshot<S, AC>(
+  pg_pool: &PgPool,
+  collab_storage: &S,
+  access_control: &AC,
+  server: &Recipient<ApplyCollabUpdate>,
+  actor: &AuditActor,
+  uid: &i64,
+  workspace_id: &Uuid,
+  object_id: &str,
+  snapshot_id: i64,
+  params: RestoreSnapshotParams,
+) -> Result<(), AppError>
+where
+  S: CollabStorage,
+  AC: CollabAccessControl,
+{
+  let level = access_control
+    .get_collab_access_level(CollabUserId::UserId(uid), object_id)
+    .await?;
+  if !level.can_write() {
+    return Err(AppError::NotEnoughPermissions(format!(
+      "user:{} can't restore the snapshot of collab:{}",
+      uid, object_id
+    )));
+  }
+
+  let snapshot = get_snapshot(pg_pool, snapshot_id, object_id).await?;
+  let current = collab_storage
+    .get_collab_encoded_v1(
+      uid,
+      QueryCollabParams {
+        object_id: object_id.to_string(),
+        workspace_id: workspace_id.to_string(),
+        collab_type: params.collab_type.clone(),
+      },
+    )
+    .await?;
+  let update = gen_restore_update(&current, &snapshot)?;
+  apply_collab_update(
+    collab_storage,
+    server,
+    uid,
+    InsertCollabUpdateParams {
+      object_id: object_id.to_string(),
+      update,
+      workspace_id: workspace_id.to_string(),
+      collab_type: params.collab_type,
+    },
+  )
+  .await?;
+
+  record_audit_log(
+    pg_pool,
+    actor,
+    Some(workspace_id),
+    AFAuditAction::CollabSnapshotRestore,
+    object_id,
+    Some(json!({ "snapshot_id": snapshot_id })),
+  )
+  .await
+}
+
+/// Returns the changes between two snapshots of the collab, or between a snapshot and the current
+/// state of the collab. Both snapshots must belong to the collab.
+pub async fn get_collab_snapshot_diff<S, AC>(
+  pg_pool: &PgPool,
+  collab_storage: &S,
+  access_control: &AC,
+  uid: &i64,
+  workspace_id: &Uuid,
+  object_id: &str,
+  params: QuerySnapshotDiffParams,
+) -> Result<AFSnapshotDiff, AppError>
+where
+  S: CollabStorage,
+  AC: CollabAccessControl,
+{
+  // Fails if the user can't read the collab
+  access_control
+    .get_collab_access_level(CollabUserId::UserId(uid), object_id)
+    .await?;
+
+  let from = get_snapshot(pg_pool, params.from_snapshot_id, object_id).await?;
+  let to = match params.to_snapshot_id {
+    Some(snapshot_id) => get_snapshot(pg_pool, snapshot_id, object_id).await?,
+    None => {
+      collab_storage
+        .get_collab_encoded_v1(
+          uid,
+          QueryCollabParams {
+            object_id: object_id.to_string(),
+            workspace_id: workspace_id.to_string(),
+            collab_type: params.collab_type.clone(),
+          },
+        )
+        .await?
+    },
+  };
+
+  let state_vector = doc_from_encoded_collab(&from)?.transact().state_vector();
+  let update = doc_from_encoded_collab(&to)?
+    .transact()
+    .encode_state_as_update_v1(&state_vector);
+  let document_diff = match params.collab_type {
+    CollabType::Document => Some(gen_document_diff(object_id, &from, &to)?),
+    _ => None,
+  };
+  Ok(AFSnapshotDiff {
+    update,
+    document_diff,
+  })
+}
+
+/// Returns the snapshot after checking that it belongs to the collab.
+async fn get_snapshot(
+  pg_pool: &PgPool,
+  snapshot_id: i64,
+  object_id: &str,
+) -> Result<EncodedCollabV1, AppError> {
+  let (snapshot_object_id, blob) =
+    database::collab::get_snapshot_object_id_and_blob(pg_pool, snapshot_id).await?;
+  if snapshot_object_id != object_id {
+    return Err(AppError::InvalidRequest(format!(
+      "snapshot:{} doesn't belong to collab:{}",
+      snapshot_id, object_id
+    )));
+  }
+  decode_encoded_collab(&blob)
+}
+
+/// Compares the blocks of the two documents. A block is modified if its type, data, position
+/// or text was changed.
+fn gen_document_diff(
+  object_id: &str,
+  from: &EncodedCollabV1,
+  to: &EncodedCollabV1,
+) -> Result<AFDocumentDiff, AppError> {
+  let from = document_data_from_encoded_collab(object_id, from)?;
+  let to = document_data_from_encoded_collab(object_id, to)?;
+
+  let mut diff = AFDocumentDiff::default();
+  for (block_id, block) in &to.blocks {
+    match from.blocks.get(block_id) {
+      None => diff.inserted_blocks.push(block_id.clone()),
+      Some(old_block) => {
+        if !is_same_block(old_block, &from, block, &to) {
+          diff.modified_blocks.push(block_id.clone());
+        }
+      },
+    }
+  }
+  diff.deleted_blocks = from
+    .blocks
+    .keys()
+    .filter(|block_id| !to.blocks.contains_key(*block_id))
+    .cloned()
+    .collect();
+
+  diff.inserted_blocks.sort();
+  diff.deleted_blocks.sort();
+  diff.modified_blocks.sort();
+  Ok(diff)
+}
+
+fn is_same_block(
+  old_block: &Block,
+  old_document: &DocumentData,
+  new_block: &Block,
+  new_document: &DocumentData,
+) -> bool {
+  old_block.ty == new_block.ty
+    && old_block.parent == new_block.parent
+    && old_block.data == new_block.data
+    && old_document.meta.children_map.get(&old_block.children)
+      == new_document.meta.children_map.get(&new_block.children)
+    && block_text(old_block, old_document) == block_text(new_block, new_document)
+}
+
+fn block_text<'a>(block: &Block, document: &'a DocumentData) -> Option<&'a String> {
+  let external_id = block.external_id.as_ref()?;
+  document.meta.text_map.as_ref()?.get(external_id)
+}
+
+pub(crate) fn document_data_from_encoded_collab(
+  object_id: &str,
+  encoded_collab: &EncodedCollabV1,
+) -> Result<DocumentData, AppError> {
+  open_document(object_id, encoded_collab)?
+    .get_document_data()
+    .map_err(|err| AppError::Internal(anyhow!("fail to read document: {:?}", err)))
+}
+
+pub(crate) fn open_document(
+  object_id: &str,
+  encoded_collab: &EncodedCollabV1,
+) -> Result<Document, AppError> {
+  let collab = MutexCollab::new_with_raw_data(
+    CollabOrigin::Empty,
+    object_id,
+    vec![encoded_collab.doc_state.to_vec()],
+    vec![],
+  )
+  .map_err(|err| AppError::Internal(anyhow!("fail to open collab: {:?}", err)))?;
+  Document::open(Arc::new(collab))
+    .map_err(|err| AppError::Internal(anyhow!("fail to open document: {:?}", err)))
+}
+
+/// Generates an update that turns the `current` collab into the content of the `snapshot`.
+///
+/// Applying the snapshot itself doesn't change anything, because its items are already part of
+/// the current state. Instead, the changes made after the snapshot are applied on top of the
+/// snapshot and recorded by an [UndoManager]. Undoing them deletes the items inserted after the
+/// snapshot and re-inserts the deleted items as new items.
+fn gen_restore_update(
+  current: &EncodedCollabV1,
+  snapshot: &EncodedCollabV1,
+) -> Result<Vec<u8>, AppError> {
+  let current_doc = doc_from_encoded_collab(current)?;
+  let current_txn = current_doc.transact();
+
+  // The content of the collab is stored in the root maps of the document
+  let doc = doc_from_encoded_collab(snapshot)?;
+  let mut undo_manager: Option<UndoManager> = None;
+  for (name, _) in current_txn.root_refs() {
+    let root = doc.get_or_insert_map(name);
+    match undo_manager.as_mut() {
+      None => undo_manager = Some(UndoManager::new(&doc, &root)),
+      Some(undo_manager) => undo_manager.expand_scope(&root),
+    }
+  }
+  let mut undo_manager =
+    undo_manager.ok_or_else(|| AppError::InvalidRequest("the collab is empty".to_string()))?;
+
+  {
+    let update = current_txn.encode_state_as_update_v1(&StateVector::default());
+    let mut txn = doc.transact_mut();
+    txn
+      .try_apply_update(decode_update(&update)?)
+      .map_err(|err| AppError::Internal(anyhow!("fail to apply collab update: {:?}", err)))?;
+  }
+  undo_manager
+    .undo()
+    .map_err(|err| AppError::Internal(anyhow!("fail to restore snapshot: {:?}", err)))?;
+
+  let update = doc
+    .transact()
+    .encode_state_as_update_v1(&current_txn.state_vector());
+  Ok(update)
+}
+
+pub(crate) fn doc_from_encoded_collab(encoded_collab: &EncodedCollabV1) -> Result<Doc, AppError> {
+  let doc = Doc::new();
+  {
+    let mut txn = doc.transact_mut();
+    txn
+      .try_apply_update(decode_update(&encoded_collab.doc_state)?)
+      .map_err(|err| AppError::Internal(anyhow!("fail to apply collab update: {:?}", err)))?;
+  }
+  Ok(doc)
+}
+
+#[inline]
+fn decode_encoded_collab(data: &[u8]) -> Result<EncodedCollabV1, AppError> {
+  EncodedCollabV1::decode_from_bytes(data)
+    .map_err(|err| AppError::Internal(anyhow!("fail to decode data to EncodedDocV1: {:?}", err)))
+}
+
+#[inline]
+fn decode_update(data: &[u8]) -> Result<Update, AppError> {
+  Update::decode_v1(data)
+    .map_err(|err| AppError::Internal(anyhow!("fail to decode collab update: {:?}", err)))
+}
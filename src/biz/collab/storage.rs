@@ -4,23 +4,28 @@ use database::collab::{
   CollabStorage, CollabStorageAccessControl, CollabStoragePgImpl, DatabaseResult, WriteConfig,
 };
 use database_entity::dto::{
-  AFCollabSnapshots, BatchQueryCollab, InsertCollabParams, InsertSnapshotParams, QueryCollabParams,
-  QueryCollabResult, QueryObjectSnapshotParams, QuerySnapshotParams, RawData,
+  AFCollabSnapshots, AFWorkspacePermission, BatchQueryCollab, InsertCollabParams,
+  InsertCollabUpdateParams, InsertSnapshotParams, QueryCollabParams, QueryCollabResult,
+  QueryObjectSnapshotParams, QuerySnapshotParams, RawData,
 };
 use itertools::{Either, Itertools};
 
 use crate::biz::collab::access_control::{CollabAccessControlImpl, CollabStorageAccessControlImpl};
+use crate::biz::collab::search::CollabIndexer;
 use crate::biz::workspace::access_control::WorkspaceAccessControlImpl;
-use anyhow::Context;
+use anyhow::{anyhow, Context};
 use app_error::AppError;
 use collab::core::collab_plugin::EncodedCollabV1;
+use collab_entity::CollabType;
 use sqlx::PgPool;
 use std::{
   collections::HashMap,
   sync::{Arc, Weak},
+  time::Duration,
 };
 use tokio::sync::RwLock;
-use tracing::{event, info, instrument};
+use tracing::{error, event, info, instrument, trace};
+use uuid::Uuid;
 use validator::Validate;
 
 pub type CollabPostgresDBStorage = CollabStorageWrapper<
@@ -36,8 +41,28 @@ pub async fn init_collab_storage(
     collab_access_control,
     workspace_access_control,
   };
+  let indexer = CollabIndexer::new(pg_pool.clone());
   let collab_storage_impl = CollabStoragePgImpl::new(pg_pool);
-  CollabStorageWrapper::new(collab_storage_impl, access_control)
+  spawn_compact_collab_updates(collab_storage_impl.clone());
+  CollabStorageWrapper::new(collab_storage_impl, access_control, indexer)
+}
+
+/// Periodically merges the update log of the collabs into the collabs.
+fn spawn_compact_collab_updates(storage: CollabStoragePgImpl) {
+  tokio::spawn(async move {
+    let mut interval = tokio::time::interval(Duration::from_secs(30));
+    loop {
+      interval.tick().await;
+      match storage.compact_collab_updates().await {
+        Ok(count) => {
+          if count > 0 {
+            trace!("did compact the updates of {} collabs", count);
+          }
+        },
+        Err(err) => error!("fail to compact collab updates: {:?}", err),
+      }
+    }
+  });
 }
 
 /// A wrapper around the actual storage implementation that provides access control and caching.
@@ -45,6 +70,7 @@ pub async fn init_collab_storage(
 pub struct CollabStorageWrapper<AC> {
   inner: CollabStoragePgImpl,
   access_control: AC,
+  indexer: CollabIndexer,
   collab_by_object_id: Arc<RwLock<HashMap<String, Weak<MutexCollab>>>>,
 }
 
@@ -52,13 +78,71 @@ impl<AC> CollabStorageWrapper<AC>
 where
   AC: CollabStorageAccessControl,
 {
-  pub fn new(inner: CollabStoragePgImpl, access_control: AC) -> Self {
+  pub fn new(inner: CollabStoragePgImpl, access_control: AC, indexer: CollabIndexer) -> Self {
     Self {
       inner,
       access_control,
+      indexer,
       collab_by_object_id: Arc::new(RwLock::new(HashMap::new())),
     }
   }
+
+  /// Returns the latest state of the collab without checking the access of the user. It's only
+  /// used by the operations that are authorized for the whole workspace, e.g. the export of the
+  /// workspace.
+  pub async fn get_collab_encoded_v1_unchecked(
+    &self,
+    uid: &i64,
+    params: QueryCollabParams,
+  ) -> DatabaseResult<EncodedCollabV1> {
+    let collab = self
+      .collab_by_object_id
+      .read()
+      .await
+      .get(&params.object_id)
+      .and_then(|collab| collab.upgrade());
+
+    match collab {
+      None => self.inner.get_collab_encoded_v1(uid, params).await,
+      Some(collab) => {
+        info!("Get collab data:{} from memory", params.object_id);
+        let data = collab.encode_collab_v1();
+        Ok(data)
+      },
+    }
+  }
+
+  /// Adds the collab back to the search index. The content of a collab is removed from the index
+  /// when it's moved to the trash, so it must be indexed again after it's restored.
+  pub async fn reindex_collab(
+    &self,
+    uid: &i64,
+    workspace_id: &Uuid,
+    object_id: &str,
+    collab_type: CollabType,
+  ) -> DatabaseResult<()> {
+    let encoded_collab = self
+      .get_collab_encoded_v1_unchecked(
+        uid,
+        QueryCollabParams {
+          object_id: object_id.to_string(),
+          workspace_id: workspace_id.to_string(),
+          collab_type: collab_type.clone(),
+        },
+      )
+      .await?;
+    let encoded_collab_v1 = encoded_collab
+      .encode_to_bytes()
+      .map_err(|err| AppError::Internal(anyhow!("fail to encode EncodedCollabV1: {:?}", err)))?;
+    let params = InsertCollabParams::new(
+      object_id,
+      collab_type,
+      encoded_collab_v1,
+      workspace_id.to_string(),
+    );
+    self.indexer.index_collab(&params).await;
+    Ok(())
+  }
 }
 
 #[async_trait]
@@ -87,6 +171,10 @@ where
     self.inner.is_collab_exist(oid).await
   }
 
+  async fn is_collab_trashed(&self, oid: &str) -> DatabaseResult<bool> {
+    self.inner.is_collab_trashed(oid).await
+  }
+
   #[instrument(level = "trace", skip(self, params), oid = %params.oid, err)]
   async fn insert_collab(&self, uid: &i64, params: InsertCollabParams) -> DatabaseResult<()> {
     params.validate()?;
@@ -113,19 +201,20 @@ where
       level.can_write()
     } else {
       // If the collab doesn't exist, check if the user has enough permissions to create collab.
-      // If the user is the owner or member of the workspace, the user can create collab.
-      let role = self
+      // The owner and the member of the workspace can create collab, unless the custom role of the
+      // member doesn't allow it.
+      let permissions = self
         .access_control
-        .get_user_role(uid, &params.workspace_id)
+        .get_user_permissions(uid, &params.workspace_id)
         .await?;
       event!(
         tracing::Level::TRACE,
         "[{:?}]user:{} try to insert new collab:{}",
-        role,
+        permissions,
         uid,
         params.object_id
       );
-      role.can_create_collab()
+      permissions.contains(AFWorkspacePermission::CreateCollab)
     };
 
     if !has_permission {
@@ -134,7 +223,31 @@ where
         uid, params.object_id
       )));
     }
-    self.inner.insert_collab(uid, params).await
+    self.inner.insert_collab(uid, params.clone()).await?;
+    self.indexer.index_collab(&params).await;
+    Ok(())
+  }
+
+  async fn insert_collab_update(
+    &self,
+    uid: &i64,
+    params: InsertCollabUpdateParams,
+  ) -> DatabaseResult<()> {
+    let level = self
+      .access_control
+      .get_collab_access_level(uid, &params.object_id)
+      .await
+      .context(format!(
+        "Can't find the access level when user:{} try to insert collab update",
+        uid
+      ))?;
+    if !level.can_write() {
+      return Err(AppError::NotEnoughPermissions(format!(
+        "user:{} doesn't have enough permissions to update collab {}",
+        uid, params.object_id
+      )));
+    }
+    self.inner.insert_collab_update(uid, params).await
   }
 
   async fn get_collab_encoded_v1(
@@ -147,22 +260,7 @@ where
       .access_control
       .get_collab_access_level(uid, &params.object_id)
       .await?;
-
-    let collab = self
-      .collab_by_object_id
-      .read()
-      .await
-      .get(&params.object_id)
-      .and_then(|collab| collab.upgrade());
-
-    match collab {
-      None => self.inner.get_collab_encoded_v1(uid, params).await,
-      Some(collab) => {
-        info!("Get collab data:{} from memory", params.object_id);
-        let data = collab.encode_collab_v1();
-        Ok(data)
-      },
-    }
+    self.get_collab_encoded_v1_unchecked(uid, params).await
   }
 
   async fn batch_get_collab(
@@ -224,7 +322,9 @@ where
         uid, object_id
       )));
     }
-    self.inner.delete_collab(uid, object_id).await
+    self.inner.delete_collab(uid, object_id).await?;
+    self.indexer.remove_collab(object_id).await;
+    Ok(())
   }
 
   async fn create_snapshot(
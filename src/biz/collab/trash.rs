@@ -0,0 +1,106 @@
+use crate::biz::audit::{record_audit_log, AuditActor};
+use crate::biz::collab::folder::restore_folder_view;
+use crate::biz::collab::storage::CollabPostgresDBStorage;
+use crate::config::config::TrashSetting;
+use actix::Recipient;
+use anyhow::Context;
+use app_error::AppError;
+use chrono::{Duration, Utc};
+use collab_entity::CollabType;
+use database::trash::{
+  delete_expired_trashed_collabs, restore_trashed_collab, select_trashed_collabs,
+};
+use database_entity::dto::{AFAuditAction, AFTrashedCollab};
+use realtime::entities::ApplyCollabUpdate;
+use sqlx::PgPool;
+use std::ops::DerefMut;
+use tokio::time::{interval_at, Instant};
+use tracing::{error, info, instrument};
+use uuid::Uuid;
+
+pub async fn get_trashed_collabs(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+) -> Result<Vec<AFTrashedCollab>, AppError> {
+  let collabs = select_trashed_collabs(pg_pool, workspace_id)
+    .await?
+    .into_iter()
+    .map(AFTrashedCollab::from)
+    .collect();
+  Ok(collabs)
+}
+
+/// Moves the collab out of the trash. The view of the collab is put back into the folder of the
+/// workspace if the collab was deleted along with its view.
+#[instrument(level = "debug", skip(pg_pool, collab_storage, server), err)]
+pub async fn restore_collab(
+  pg_pool: &PgPool,
+  collab_storage: &CollabPostgresDBStorage,
+  server: &Recipient<ApplyCollabUpdate>,
+  actor: &AuditActor,
+  uid: &i64,
+  workspace_id: &Uuid,
+  object_id: &str,
+) -> Result<(), AppError> {
+  let mut txn = pg_pool
+    .begin()
+    .await
+    .context("acquire transaction to restore collab")?;
+  let partition_key = restore_trashed_collab(txn.deref_mut(), workspace_id, object_id).await?;
+  record_audit_log(
+    txn.deref_mut(),
+    actor,
+    Some(workspace_id),
+    AFAuditAction::CollabRestore,
+    object_id,
+    None,
+  )
+  .await?;
+  txn
+    .commit()
+    .await
+    .context("fail to commit the transaction to restore collab")?;
+
+  // The restored collab is already out of the trash, so failing to index it or to put its view
+  // back is only logged
+  let collab_type = CollabType::from(partition_key);
+  let is_document = collab_type == CollabType::Document;
+  if let Err(err) = collab_storage
+    .reindex_collab(uid, workspace_id, object_id, collab_type)
+    .await
+  {
+    error!("fail to index restored collab:{}: {:?}", object_id, err);
+  }
+  if is_document {
+    if let Err(err) =
+      restore_folder_view(collab_storage, server, uid, workspace_id, object_id).await
+    {
+      error!(
+        "fail to restore the view of collab:{}: {:?}",
+        object_id, err
+      );
+    }
+  }
+  Ok(())
+}
+
+/// Purges the collabs that have been in the trash longer than [TrashSetting::retention_days]
+/// every [TrashSetting::purge_interval_secs].
+pub fn spawn_purge_expired_trash(pg_pool: PgPool, setting: TrashSetting) {
+  tokio::spawn(async move {
+    let period = std::time::Duration::from_secs(setting.purge_interval_secs);
+    let mut interval = interval_at(Instant::now() + period, period);
+    loop {
+      interval.tick().await;
+      let deleted_before = Utc::now() - Duration::days(setting.retention_days);
+      match delete_expired_trashed_collabs(&pg_pool, deleted_before).await {
+        Ok(object_ids) => {
+          if !object_ids.is_empty() {
+            info!("did purge {} collabs from the trash", object_ids.len());
+          }
+        },
+        Err(err) => error!("fail to purge the trash: {:?}", err),
+      }
+    }
+  });
+}
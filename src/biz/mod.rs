@@ -1,3 +1,5 @@
+pub mod audit;
+pub mod blob_gc;
 pub mod collab;
 pub mod pg_listener;
 pub mod user;
@@ -3,6 +3,7 @@ use crate::biz::user::UserListener;
 use crate::biz::workspace::member_listener::{
   WorkspaceMemberListener, WorkspaceMemberNotification,
 };
+use crate::biz::workspace::role_listener::{WorkspaceRoleListener, WorkspaceRoleNotification};
 use anyhow::Error;
 use database_entity::pg_row::AFUserNotification;
 use serde::de::DeserializeOwned;
@@ -15,6 +16,7 @@ pub struct PgListeners {
   user_listener: UserListener,
   workspace_member_listener: WorkspaceMemberListener,
   collab_member_listener: CollabMemberListener,
+  workspace_role_listener: WorkspaceRoleListener,
 }
 
 impl PgListeners {
@@ -27,10 +29,14 @@ impl PgListeners {
     let collab_member_listener =
       CollabMemberListener::new(pg_pool, "af_collab_member_channel").await?;
 
+    let workspace_role_listener =
+      WorkspaceRoleListener::new(pg_pool, "af_workspace_role_channel").await?;
+
     Ok(Self {
       user_listener,
       workspace_member_listener,
       collab_member_listener,
+      workspace_role_listener,
     })
   }
 
@@ -44,6 +50,10 @@ impl PgListeners {
     self.collab_member_listener.notify.subscribe()
   }
 
+  pub fn subscribe_workspace_role_change(&self) -> broadcast::Receiver<WorkspaceRoleNotification> {
+    self.workspace_role_listener.notify.subscribe()
+  }
+
   pub fn subscribe_user_change(&self, uid: i64) -> tokio::sync::mpsc::Receiver<AFUserNotification> {
     let (tx, rx) = tokio::sync::mpsc::channel(1);
     let mut user_notify = self.user_listener.notify.subscribe();
@@ -8,9 +8,13 @@ use std::ops::DerefMut;
 use std::sync::Arc;
 use uuid::Uuid;
 
-use database::workspace::{select_user_profile, select_user_workspace, select_workspace};
-use database_entity::dto::{AFUserProfile, AFUserWorkspaceInfo, AFWorkspace, InsertCollabParams};
+use database::workspace::{select_user_profile, select_user_workspace};
+use database_entity::dto::{
+  AFAuditAction, AFUserProfile, AFUserWorkspaceInfo, AFWorkspace, InsertCollabParams,
+};
 
+use crate::biz::audit::{record_audit_log, AuditActor};
+use crate::biz::workspace::invitation::accept_invitations_on_sign_up;
 use app_error::AppError;
 use database::collab::insert_into_af_collab;
 use database::user::{create_user, is_user_exist};
@@ -82,6 +86,9 @@ pub async fn verify_token(
       )
       .await?;
     }
+
+    // Join the workspaces that the user has been invited to before signing up
+    accept_invitations_on_sign_up(&mut txn, new_uid, &user.email).await?;
   }
   txn
     .commit()
@@ -118,15 +125,6 @@ pub async fn get_user_workspace_info(
       uuid
     )))?;
 
-  // Get the latest workspace that the user has visited recently
-  // TODO(nathan): the visiting_workspace might be None if the user get deleted from the workspace
-  let visiting_workspace = AFWorkspace::try_from(
-    select_workspace(txn.deref_mut(), &row.latest_workspace_id.unwrap()).await?,
-  )?;
-
-  // Get the user profile
-  let user_profile = AFUserProfile::try_from(row)?;
-
   // Get all workspaces that the user can access to
   let workspaces = select_user_workspace(txn.deref_mut(), uuid)
     .await?
@@ -134,6 +132,21 @@ pub async fn get_user_workspace_info(
     .flat_map(|row| AFWorkspace::try_from(row).ok())
     .collect::<Vec<AFWorkspace>>();
 
+  // Get the latest workspace that the user has visited recently. Fall back to the first workspace
+  // if the latest one has been deleted.
+  let visiting_workspace = workspaces
+    .iter()
+    .find(|workspace| Some(workspace.workspace_id) == row.latest_workspace_id)
+    .or_else(|| workspaces.first())
+    .cloned()
+    .ok_or(AppError::RecordNotFound(format!(
+      "Can't find any workspace for {}",
+      uuid
+    )))?;
+
+  // Get the user profile
+  let user_profile = AFUserProfile::try_from(row)?;
+
   txn
     .commit()
     .await
@@ -148,11 +161,48 @@ pub async fn get_user_workspace_info(
 
 pub async fn update_user(
   pg_pool: &PgPool,
-  user_uuid: Uuid,
+  actor: &AuditActor,
   params: UpdateUserParams,
 ) -> Result<(), AppResponseError> {
+  // Only the names of the updated fields are recorded, the values may contain personal data.
+  let fields = [
+    ("name", params.name.is_some()),
+    ("email", params.email.is_some()),
+    ("metadata", params.metadata.is_some()),
+  ]
+  .into_iter()
+  .filter_map(|(field, is_updated)| is_updated.then_some(field))
+  .collect::<Vec<_>>();
+
   let metadata = params.metadata.map(|m| json!(m.into_inner()));
-  Ok(database::user::update_user(pg_pool, &user_uuid, params.name, params.email, metadata).await?)
+  let mut txn = pg_pool
+    .begin()
+    .await
+    .context("acquire transaction to update user")?;
+  database::user::update_user(
+    txn.deref_mut(),
+    &actor.user_uuid,
+    params.name,
+    params.email,
+    metadata,
+  )
+  .await?;
+  if !fields.is_empty() {
+    record_audit_log(
+      txn.deref_mut(),
+      actor,
+      None,
+      AFAuditAction::UserUpdate,
+      &actor.user_uuid.to_string(),
+      Some(json!({ "fields": fields })),
+    )
+    .await?;
+  }
+  txn
+    .commit()
+    .await
+    .context("fail to commit the transaction to update user")?;
+  Ok(())
 }
 
 // Best effort to get user's name after oauth
@@ -171,11 +221,27 @@ pub type UserListener = crate::biz::pg_listener::PostgresDBListener<AFUserNotifi
 pub struct RealtimeUserImpl {
   pub uid: i64,
   pub device_id: String,
+  /// The uid of the user that the session acts on behalf of, e.g. the creator of a share link.
+  pub on_behalf_of: Option<i64>,
 }
 
 impl RealtimeUserImpl {
   pub fn new(uid: i64, device_id: String) -> Self {
-    Self { uid, device_id }
+    Self {
+      uid,
+      device_id,
+      on_behalf_of: None,
+    }
+  }
+
+  /// Creates the user of a share link session. The collabs opened by the session are saved with
+  /// the uid of the creator of the link, because the session uid doesn't belong to any user.
+  pub fn share_session(session_uid: i64, created_by: i64, device_id: String) -> Self {
+    Self {
+      uid: session_uid,
+      device_id,
+      on_behalf_of: Some(created_by),
+    }
   }
 }
 
@@ -192,4 +258,8 @@ impl RealtimeUser for RealtimeUserImpl {
   fn uid(&self) -> i64 {
     self.uid
   }
+
+  fn storage_uid(&self) -> i64 {
+    self.on_behalf_of.unwrap_or(self.uid)
+  }
 }
@@ -1,17 +1,21 @@
 use crate::biz::workspace::member_listener::{WorkspaceMemberAction, WorkspaceMemberNotification};
+use crate::biz::workspace::role_listener::WorkspaceRoleNotification;
 use crate::component::auth::jwt::UserUuid;
 use crate::middleware::access_control_mw::{AccessResource, HttpAccessControlService};
 use actix_http::Method;
+use actix_router::{Path, Url};
 use async_trait::async_trait;
 use database::user::select_uid_from_uuid;
+use database::workspace_role::select_workspace_member_permissions;
 
 use sqlx::PgPool;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 
 use app_error::AppError;
-use database_entity::dto::AFRole;
+use database_entity::dto::{AFRole, AFWorkspacePermission, AFWorkspacePermissions};
 use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::{broadcast, RwLock};
 use tracing::{instrument, trace, warn};
 use uuid::Uuid;
@@ -24,6 +28,20 @@ pub trait WorkspaceAccessControl: Send + Sync + 'static {
     workspace_id: &Uuid,
   ) -> Result<AFRole, AppError>;
   async fn get_role_from_uid(&self, uid: &i64, workspace_id: &Uuid) -> Result<AFRole, AppError>;
+
+  /// Returns the permissions of the user in the workspace, which are the permissions of the custom
+  /// role of the user, or the default permissions of the built-in role.
+  async fn get_permissions_from_uid(
+    &self,
+    uid: &i64,
+    workspace_id: &Uuid,
+  ) -> Result<AFWorkspacePermissions, AppError>;
+
+  async fn get_permissions_from_uuid(
+    &self,
+    user_uuid: &Uuid,
+    workspace_id: &Uuid,
+  ) -> Result<AFWorkspacePermissions, AppError>;
 }
 
 /// Represents the role of the user in the workspace by the workspace id.
@@ -43,7 +61,14 @@ enum MemberStatus {
   /// when the user is not the member of the workspace.
   Deleted,
   /// The user is the member of the workspace
-  Valid(AFRole),
+  Valid(MemberAccess),
+}
+
+#[derive(Clone, Debug)]
+struct MemberAccess {
+  role: AFRole,
+  custom_role_id: Option<i32>,
+  permissions: AFWorkspacePermissions,
 }
 
 pub struct WorkspaceAccessControlImpl {
@@ -52,13 +77,18 @@ pub struct WorkspaceAccessControlImpl {
 }
 
 impl WorkspaceAccessControlImpl {
-  pub fn new(pg_pool: PgPool, listener: broadcast::Receiver<WorkspaceMemberNotification>) -> Self {
+  pub fn new(
+    pg_pool: PgPool,
+    listener: broadcast::Receiver<WorkspaceMemberNotification>,
+    role_listener: broadcast::Receiver<WorkspaceRoleNotification>,
+  ) -> Self {
     let member_status_by_uid = Arc::new(RwLock::new(HashMap::new()));
     spawn_listen_on_workspace_member_change(
       listener,
       pg_pool.clone(),
       member_status_by_uid.clone(),
     );
+    spawn_listen_on_workspace_role_change(role_listener, member_status_by_uid.clone());
 
     WorkspaceAccessControlImpl {
       pg_pool,
@@ -66,8 +96,24 @@ impl WorkspaceAccessControlImpl {
     }
   }
 
-  pub async fn update_member(&self, uid: &i64, workspace_id: &Uuid, role: AFRole) {
-    update_workspace_member_status(uid, workspace_id, role, &self.member_status_by_uid).await;
+  /// Reloads the role and the permissions of the member from the database. It's called after the
+  /// member is changed, since the notification of the change might arrive later.
+  pub async fn update_member(&self, uid: &i64, workspace_id: &Uuid) {
+    if let Err(err) = reload_workspace_member_status_from_db(
+      uid,
+      workspace_id,
+      &self.pg_pool,
+      &self.member_status_by_uid,
+    )
+    .await
+    {
+      warn!("Failed to reload workspace member status from db: {}", err);
+    }
+  }
+
+  /// Removes the cached permissions of the members who have the custom role.
+  pub async fn remove_custom_role(&self, role_id: i32) {
+    remove_custom_role_members(role_id, &self.member_status_by_uid).await;
   }
 
   pub async fn remove_member(&self, uid: &i64, workspace_id: &Uuid) {
@@ -79,11 +125,11 @@ impl WorkspaceAccessControlImpl {
   }
 
   #[inline]
-  async fn get_user_workspace_role(
+  async fn get_user_workspace_access(
     &self,
     uid: &i64,
     workspace_id: &Uuid,
-  ) -> Result<AFRole, AppError> {
+  ) -> Result<MemberAccess, AppError> {
     let member_status = self
       .member_status_by_uid
       .read()
@@ -109,23 +155,11 @@ impl WorkspaceAccessControlImpl {
         "user:{} is not a member of workspace:{}",
         uid, workspace_id
       ))),
-      MemberStatus::Valid(access_level) => Ok(access_level),
+      MemberStatus::Valid(access) => Ok(access),
     }
   }
 }
 
-#[inline]
-async fn update_workspace_member_status(
-  uid: &i64,
-  workspace_id: &Uuid,
-  role: AFRole,
-  member_status_by_uid: &Arc<RwLock<MemberStatusByUid>>,
-) {
-  let mut outer_map = member_status_by_uid.write().await;
-  let inner_map = outer_map.entry(*uid).or_insert_with(HashMap::new);
-  inner_map.insert(*workspace_id, MemberStatus::Valid(role));
-}
-
 #[inline]
 async fn reload_workspace_member_status_from_db(
   uid: &i64,
@@ -133,12 +167,60 @@ async fn reload_workspace_member_status_from_db(
   pg_pool: &PgPool,
   member_status_by_uid: &Arc<RwLock<MemberStatusByUid>>,
 ) -> Result<MemberStatus, AppError> {
-  let member = database::workspace::select_workspace_member(pg_pool, uid, workspace_id).await?;
-  let status = MemberStatus::Valid(member.role.clone());
-  update_workspace_member_status(uid, workspace_id, member.role, member_status_by_uid).await;
+  let member = select_workspace_member_permissions(pg_pool, uid, workspace_id).await?;
+  let role = AFRole::from(member.role_id);
+  // The owner always has all the permissions
+  let permissions = match member.permissions {
+    Some(permissions) if role != AFRole::Owner => AFWorkspacePermissions(permissions),
+    _ => role.default_permissions(),
+  };
+  let status = MemberStatus::Valid(MemberAccess {
+    role,
+    custom_role_id: member.custom_role_id,
+    permissions,
+  });
+  member_status_by_uid
+    .write()
+    .await
+    .entry(*uid)
+    .or_insert_with(HashMap::new)
+    .insert(*workspace_id, status.clone());
   Ok(status)
 }
 
+/// Removes the cached permissions of the members whose custom role is changed, they are reloaded
+/// from the database when they are accessed next time.
+fn spawn_listen_on_workspace_role_change(
+  mut listener: broadcast::Receiver<WorkspaceRoleNotification>,
+  member_status_by_uid: Arc<RwLock<MemberStatusByUid>>,
+) {
+  tokio::spawn(async move {
+    loop {
+      match listener.recv().await {
+        Ok(change) => {
+          if let Some(role_id) = change.changed_role_id() {
+            remove_custom_role_members(role_id, &member_status_by_uid).await;
+          }
+        },
+        // The missed changes are unknown, so all the cached member statuses are removed.
+        Err(RecvError::Lagged(_)) => member_status_by_uid.write().await.clear(),
+        Err(RecvError::Closed) => break,
+      }
+    }
+  });
+}
+
+async fn remove_custom_role_members(
+  role_id: i32,
+  member_status_by_uid: &Arc<RwLock<MemberStatusByUid>>,
+) {
+  for inner_map in member_status_by_uid.write().await.values_mut() {
+    inner_map.retain(|_, status| {
+      !matches!(status, MemberStatus::Valid(access) if access.custom_role_id == Some(role_id))
+    });
+  }
+}
+
 fn spawn_listen_on_workspace_member_change(
   mut listener: broadcast::Receiver<WorkspaceMemberNotification>,
   pg_pool: PgPool,
@@ -185,13 +267,31 @@ impl WorkspaceAccessControl for WorkspaceAccessControlImpl {
     workspace_id: &Uuid,
   ) -> Result<AFRole, AppError> {
     let uid = select_uid_from_uuid(&self.pg_pool, user_uuid).await?;
-    let role = self.get_user_workspace_role(&uid, workspace_id).await?;
-    Ok(role)
+    let access = self.get_user_workspace_access(&uid, workspace_id).await?;
+    Ok(access.role)
   }
 
   async fn get_role_from_uid(&self, uid: &i64, workspace_id: &Uuid) -> Result<AFRole, AppError> {
-    let role = self.get_user_workspace_role(uid, workspace_id).await?;
-    Ok(role)
+    let access = self.get_user_workspace_access(uid, workspace_id).await?;
+    Ok(access.role)
+  }
+
+  async fn get_permissions_from_uid(
+    &self,
+    uid: &i64,
+    workspace_id: &Uuid,
+  ) -> Result<AFWorkspacePermissions, AppError> {
+    let access = self.get_user_workspace_access(uid, workspace_id).await?;
+    Ok(access.permissions)
+  }
+
+  async fn get_permissions_from_uuid(
+    &self,
+    user_uuid: &Uuid,
+    workspace_id: &Uuid,
+  ) -> Result<AFWorkspacePermissions, AppError> {
+    let uid = select_uid_from_uuid(&self.pg_pool, user_uuid).await?;
+    self.get_permissions_from_uid(&uid, workspace_id).await
   }
 }
 
@@ -212,6 +312,7 @@ where
     workspace_id: &Uuid,
     user_uuid: &UserUuid,
     method: Method,
+    path: &Path<Url>,
   ) -> Result<(), AppError> {
     trace!(
       "workspace_id: {:?}, user_uuid: {:?}",
@@ -219,21 +320,28 @@ where
       user_uuid
     );
 
-    match self.0.get_role_from_uuid(user_uuid, workspace_id).await {
-      Ok(role) => {
-        if method == Method::DELETE || method == Method::POST || method == Method::PUT {
-          if matches!(role, AFRole::Owner) {
-            Ok(())
-          } else {
-            Err(AppError::NotEnoughPermissions(format!(
-              "User:{:?} doesn't have the enough permission to access workspace:{}",
-              user_uuid, workspace_id
-            )))
-          }
-        } else {
-          Ok(())
-        }
-      },
+    let is_write = method == Method::DELETE || method == Method::POST || method == Method::PUT;
+    let requires_owner = is_write || is_owner_only_read(workspace_id, path.as_str());
+    let result = match required_permission(&method, workspace_id, path.as_str()) {
+      Some(permission) if is_write => self
+        .0
+        .get_permissions_from_uuid(user_uuid, workspace_id)
+        .await
+        .map(|permissions| permissions.contains(permission)),
+      // The requests that don't require a specific permission can only be sent by the owner
+      _ => self
+        .0
+        .get_role_from_uuid(user_uuid, workspace_id)
+        .await
+        .map(|role| !requires_owner || matches!(role, AFRole::Owner)),
+    };
+
+    match result {
+      Ok(true) => Ok(()),
+      Ok(false) => Err(AppError::NotEnoughPermissions(format!(
+        "User:{:?} doesn't have the enough permission to access workspace:{}",
+        user_uuid, workspace_id
+      ))),
       Err(err) => Err(AppError::NotEnoughPermissions(format!(
         "Can't find the role of the user:{:?} in the workspace:{:?}. error: {}",
         user_uuid, workspace_id, err
@@ -241,3 +349,84 @@ where
     }
   }
 }
+
+/// Returns true if the request reads the data that only the owner of the workspace can see.
+fn is_owner_only_read(workspace_id: &Uuid, path: &str) -> bool {
+  matches!(
+    split_workspace_path(workspace_id, path),
+    Some(("workspace", rest)) if matches!(rest.first(), Some(&"audit") | Some(&"archive"))
+  )
+}
+
+/// Splits the path of the request into the scope, e.g. "workspace" or "file_storage", and the
+/// segments after the workspace id.
+fn split_workspace_path<'a>(workspace_id: &Uuid, path: &'a str) -> Option<(&'a str, Vec<&'a str>)> {
+  let workspace_id = workspace_id.to_string();
+  let segments = path.trim_matches('/').split('/').collect::<Vec<_>>();
+  let position = segments
+    .iter()
+    .position(|segment| *segment == workspace_id)?;
+  let scope = *segments.get(position.checked_sub(1)?)?;
+  Some((scope, segments[position + 1..].to_vec()))
+}
+
+/// Returns the permission that the request to the workspace requires, or None if the request can
+/// only be sent by the owner of the workspace.
+fn required_permission(
+  method: &Method,
+  workspace_id: &Uuid,
+  path: &str,
+) -> Option<AFWorkspacePermission> {
+  let (scope, rest) = split_workspace_path(workspace_id, path)?;
+  match (scope, rest.as_slice()) {
+    ("workspace", ["collab", _]) => {
+      if *method == Method::POST {
+        Some(AFWorkspacePermission::CreateCollab)
+      } else if *method == Method::PUT {
+        Some(AFWorkspacePermission::EditCollab)
+      } else if *method == Method::DELETE {
+        Some(AFWorkspacePermission::DeleteCollab)
+      } else {
+        None
+      }
+    },
+    ("workspace", ["collab", _, "comment", ..]) => Some(AFWorkspacePermission::Comment),
+    ("workspace", ["collab", _, "document", "blocks"]) if *method == Method::POST => {
+      Some(AFWorkspacePermission::EditCollab)
+    },
+    ("workspace", ["collab", _, "snapshot", _, "restore"]) if *method == Method::POST => {
+      Some(AFWorkspacePermission::EditCollab)
+    },
+    ("workspace", ["import"]) => Some(AFWorkspacePermission::CreateCollab),
+    ("workspace", ["folder", "view"]) if *method == Method::POST => {
+      Some(AFWorkspacePermission::CreateCollab)
+    },
+    ("workspace", ["folder", "view", _]) => {
+      if *method == Method::PUT {
+        Some(AFWorkspacePermission::EditCollab)
+      } else if *method == Method::DELETE {
+        Some(AFWorkspacePermission::DeleteCollab)
+      } else {
+        None
+      }
+    },
+    ("workspace", ["folder", "view", _, "move"]) if *method == Method::PUT => {
+      Some(AFWorkspacePermission::EditCollab)
+    },
+    ("workspace", ["trash", _, "restore"]) => Some(AFWorkspacePermission::DeleteCollab),
+    ("workspace", ["database", _, "row"]) if *method == Method::POST => {
+      Some(AFWorkspacePermission::CreateCollab)
+    },
+    ("workspace", ["database", _, "row", _]) if *method == Method::PUT => {
+      Some(AFWorkspacePermission::EditCollab)
+    },
+    ("workspace", ["member"]) if *method == Method::POST => {
+      Some(AFWorkspacePermission::InviteMember)
+    },
+    ("workspace", ["invite", ..]) => Some(AFWorkspacePermission::InviteMember),
+    ("file_storage", ["blob", ..] | ["upload", ..] | ["presigned", "upload", ..]) => {
+      Some(AFWorkspacePermission::ManageBlob)
+    },
+    _ => None,
+  }
+}
@@ -0,0 +1,661 @@
+use crate::biz::audit::{record_audit_log, AuditActor};
+use crate::biz::collab::storage::CollabPostgresDBStorage;
+use crate::biz::workspace::invitation::{insert_workspace_invitations, send_invite_emails};
+use crate::config::config::GoTrueSetting;
+use anyhow::{anyhow, Context};
+use app_error::AppError;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use collab::core::collab_plugin::EncodedCollabV1;
+use collab_entity::CollabType;
+use database::collab::{
+  get_snapshot_blob, insert_into_af_collab, insert_snapshot_with_txn, select_workspace_collabs,
+  select_workspace_snapshots,
+};
+use database::file::bucket_client_impl::BucketStorageImpl;
+use database::resource_usage::get_all_workspace_blob_metadata;
+use database::user::{select_email_from_uuid, select_uid_from_uuid};
+use database::workspace::{insert_user_workspace, select_workspace, select_workspace_member_list};
+use database_entity::dto::{
+  AFAuditAction, AFRole, AFWorkspace, InsertCollabParams, QueryCollabParams,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use shared_entity::dto::workspace_dto::{ImportWorkspaceParams, WorkspaceMemberInvitation};
+use sqlx::{PgPool, Postgres, Transaction};
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::ops::DerefMut;
+use std::sync::Arc;
+use tokio::io::AsyncRead;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::io::SyncIoBridge;
+use tracing::{error, instrument};
+use uuid::Uuid;
+
+/// The version of the archive format. It's increased whenever the layout of the archive changes,
+/// so an archive written by a newer server is refused instead of being imported partially.
+pub const WORKSPACE_ARCHIVE_VERSION: u32 = 1;
+
+const MANIFEST_PATH: &str = "manifest.json";
+const COLLAB_DIR: &str = "collabs";
+const SNAPSHOT_DIR: &str = "snapshots";
+const BLOB_DIR: &str = "blobs";
+
+/// The limit of the size of the archive that is imported.
+const MAX_WORKSPACE_ARCHIVE_SIZE: u64 = 1024 * 1024 * 1024; // 1 GB
+/// The limit of the size of each entry of the archive that is imported, since the entries are
+/// kept in memory while they are imported.
+const MAX_ARCHIVE_ENTRY_SIZE: u64 = 256 * 1024 * 1024; // 256 MB
+
+/// The length of the ids that are remapped in the encoded collabs, which is the length of a
+/// hyphenated UUID.
+const REMAPPED_ID_LEN: usize = 36;
+
+/// The first entry of the workspace archive. It lists the content of the archive, each collab,
+/// snapshot and blob is stored in its own entry:
+/// - `collabs/{object_id}`: the encoded collab. The folder of the workspace is the collab whose id
+///   is the workspace id.
+/// - `snapshots/{snapshot_id}`: the encoded snapshot.
+/// - `blobs/{file_id}`: the content of the blob.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceArchiveManifest {
+  pub version: u32,
+  pub workspace_id: Uuid,
+  pub workspace_name: String,
+  pub exported_at: DateTime<Utc>,
+  pub collabs: Vec<ArchivedCollab>,
+  pub snapshots: Vec<ArchivedSnapshot>,
+  pub members: Vec<ArchivedMember>,
+  pub blobs: Vec<ArchivedBlob>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedCollab {
+  pub object_id: String,
+  pub collab_type: CollabType,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedSnapshot {
+  pub snapshot_id: i64,
+  pub object_id: String,
+  pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedMember {
+  pub email: String,
+  pub role: AFRole,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedBlob {
+  pub file_id: String,
+  pub file_type: String,
+  pub file_size: i64,
+}
+
+pub type WorkspaceArchiveStream = ReceiverStream<Result<Bytes, AppError>>;
+
+/// Returns the tar archive of the workspace as a stream. The manifest is read before the response
+/// starts, and the entries are read one by one while the archive is sent, so the whole workspace
+/// is never kept in memory.
+#[instrument(level = "debug", skip(pg_pool, collab_storage, bucket_storage), err)]
+pub async fn export_workspace(
+  pg_pool: &PgPool,
+  collab_storage: Arc<CollabPostgresDBStorage>,
+  bucket_storage: Arc<BucketStorageImpl>,
+  actor: &AuditActor,
+  workspace_id: &Uuid,
+) -> Result<WorkspaceArchiveStream, AppError> {
+  let uid = select_uid_from_uuid(pg_pool, &actor.user_uuid).await?;
+  // The export is recorded along with the manifest, so the recorded counts are the ones exported
+  let mut txn = pg_pool
+    .begin()
+    .await
+    .context("Begin transaction to export workspace")?;
+  let manifest = read_manifest(&mut txn, workspace_id).await?;
+  record_audit_log(
+    txn.deref_mut(),
+    actor,
+    Some(workspace_id),
+    AFAuditAction::WorkspaceExport,
+    &workspace_id.to_string(),
+    Some(json!({
+      "collabs": manifest.collabs.len(),
+      "snapshots": manifest.snapshots.len(),
+      "blobs": manifest.blobs.len(),
+    })),
+  )
+  .await?;
+  txn
+    .commit()
+    .await
+    .context("Commit transaction to export workspace")?;
+
+  let (sender, receiver) = mpsc::channel(8);
+  let pg_pool = pg_pool.clone();
+  tokio::spawn(async move {
+    let mut writer = ArchiveWriter::new(sender.clone());
+    let result = write_archive(
+      &mut writer,
+      &pg_pool,
+      &collab_storage,
+      &bucket_storage,
+      &uid,
+      manifest,
+    )
+    .await;
+    let result = match result {
+      Ok(_) => writer.finish().await,
+      Err(err) => Err(err),
+    };
+    if let Err(err) = result {
+      error!("fail to export workspace: {:?}", err);
+      // The response is aborted, so the client doesn't mistake the archive for a complete one
+      let _ = sender.send(Err(err)).await;
+    }
+  });
+  Ok(ReceiverStream::new(receiver))
+}
+
+/// Recreates the workspace of the archive as a new workspace owned by the user. The workspace and
+/// its collabs get new ids, so the archive can be imported into any server, including the server
+/// that it was exported from, and more than once.
+///
+/// The archive is read entry by entry while it's received, and each entry is imported as soon as
+/// it's read. The blobs keep their ids, so the links in the documents stay valid. The members of
+/// the archived workspace are only invited if [ImportWorkspaceParams::invite_members] is set, they
+/// join the workspace once they accept the invitation.
+#[instrument(
+  level = "debug",
+  skip(pg_pool, bucket_storage, gotrue_client, gotrue_setting, archive),
+  err
+)]
+pub async fn import_workspace<R>(
+  pg_pool: &PgPool,
+  bucket_storage: &BucketStorageImpl,
+  gotrue_client: &gotrue::api::Client,
+  gotrue_setting: &GoTrueSetting,
+  actor: &AuditActor,
+  params: &ImportWorkspaceParams,
+  archive: R,
+) -> Result<AFWorkspace, AppError>
+where
+  R: AsyncRead + Unpin + Send + 'static,
+{
+  // The tar reader is blocking, so the entries are read on a blocking thread. The channel holds a
+  // single entry, so only a few entries are kept in memory at once.
+  let (sender, mut entries) = mpsc::channel(1);
+  let reader = SyncIoBridge::new(archive);
+  tokio::task::spawn_blocking(move || {
+    if let Err(err) = read_archive_entries(reader, &sender) {
+      let _ = sender.blocking_send(Err(err));
+    }
+  });
+
+  let manifest = match entries.recv().await.transpose()? {
+    Some(entry) if entry.path == MANIFEST_PATH => parse_manifest(&entry.content)?,
+    _ => {
+      return Err(AppError::InvalidRequest(format!(
+        "the workspace archive must start with {}",
+        MANIFEST_PATH
+      )))
+    },
+  };
+
+  let mut txn = pg_pool
+    .begin()
+    .await
+    .context("Begin transaction to import workspace")?;
+  let uid = select_uid_from_uuid(txn.deref_mut(), &actor.user_uuid).await?;
+  let row = insert_user_workspace(&mut txn, &uid, &manifest.workspace_name).await?;
+  let workspace_id = row.workspace_id;
+
+  let id_map = remap_object_ids(&manifest, &workspace_id);
+  let mut pending = HashMap::new();
+  for collab in &manifest.collabs {
+    pending.insert(
+      collab_path(&collab.object_id),
+      ArchiveEntryKind::Collab(collab),
+    );
+  }
+  // The snapshots of the collabs that are not in the archive are skipped
+  for snapshot in &manifest.snapshots {
+    if id_map.object_ids.contains_key(&snapshot.object_id) {
+      pending.insert(
+        snapshot_path(snapshot.snapshot_id),
+        ArchiveEntryKind::Snapshot(snapshot),
+      );
+    }
+  }
+  for blob in &manifest.blobs {
+    pending.insert(blob_path(&blob.file_id), ArchiveEntryKind::Blob(blob));
+  }
+
+  while let Some(entry) = entries.recv().await {
+    let ArchiveEntry { path, mut content } = entry?;
+    // The entries that are not listed in the manifest, or listed more than once, are ignored
+    match pending.remove(&path) {
+      Some(ArchiveEntryKind::Collab(collab)) => {
+        replace_ids(&mut content, &id_map.replacements);
+        if let Err(err) = EncodedCollabV1::decode_from_bytes(&content) {
+          return Err(AppError::InvalidRequest(format!(
+            "collab:{} in the archive is invalid: {:?}",
+            collab.object_id, err
+          )));
+        }
+        insert_into_af_collab(
+          &mut txn,
+          &uid,
+          &InsertCollabParams {
+            object_id: id_map.object_ids[&collab.object_id].clone(),
+            encoded_collab_v1: content,
+            workspace_id: workspace_id.to_string(),
+            collab_type: collab.collab_type.clone(),
+          },
+        )
+        .await?;
+      },
+      Some(ArchiveEntryKind::Snapshot(snapshot)) => {
+        replace_ids(&mut content, &id_map.replacements);
+        insert_snapshot_with_txn(
+          &mut txn,
+          &id_map.object_ids[&snapshot.object_id],
+          &content,
+          &workspace_id,
+          snapshot.created_at,
+        )
+        .await?;
+      },
+      Some(ArchiveEntryKind::Blob(blob)) => {
+        bucket_storage
+          .put_blob_with_id(
+            &mut txn,
+            &workspace_id,
+            &blob.file_id,
+            &blob.file_type,
+            content,
+          )
+          .await?;
+      },
+      None => {},
+    }
+  }
+  if let Some(path) = pending.keys().next() {
+    return Err(AppError::InvalidRequest(format!(
+      "the workspace archive has no {}",
+      path
+    )));
+  }
+
+  // The importer is the owner of the new workspace, so the owner of the archive is invited as a
+  // member
+  let mut new_user_emails = vec![];
+  let mut invited_members = 0;
+  if params.invite_members {
+    let importer_email = select_email_from_uuid(txn.deref_mut(), &actor.user_uuid).await?;
+    let invitations = manifest
+      .members
+      .iter()
+      .filter(|member| !member.email.eq_ignore_ascii_case(&importer_email))
+      .map(|member| WorkspaceMemberInvitation {
+        email: member.email.clone(),
+        role: if member.role == AFRole::Owner {
+          AFRole::Member
+        } else {
+          member.role.clone()
+        },
+      })
+      .collect::<Vec<_>>();
+    invited_members = invitations.len();
+    new_user_emails =
+      insert_workspace_invitations(&mut txn, &uid, &workspace_id, invitations).await?;
+  }
+
+  record_audit_log(
+    txn.deref_mut(),
+    actor,
+    Some(&workspace_id),
+    AFAuditAction::WorkspaceImport,
+    &workspace_id.to_string(),
+    Some(json!({
+      "source_workspace_id": manifest.workspace_id,
+      "collabs": manifest.collabs.len(),
+      "snapshots": manifest.snapshots.len(),
+      "blobs": manifest.blobs.len(),
+      "invited_members": invited_members,
+    })),
+  )
+  .await?;
+  txn
+    .commit()
+    .await
+    .context("Commit transaction to import workspace")?;
+
+  send_invite_emails(
+    gotrue_client,
+    gotrue_setting,
+    &workspace_id,
+    &new_user_emails,
+  )
+  .await;
+  AFWorkspace::try_from(row)
+}
+
+async fn read_manifest(
+  txn: &mut Transaction<'_, Postgres>,
+  workspace_id: &Uuid,
+) -> Result<WorkspaceArchiveManifest, AppError> {
+  let workspace = select_workspace(txn.deref_mut(), workspace_id).await?;
+  let collabs = select_workspace_collabs(txn.deref_mut(), workspace_id)
+    .await?
+    .into_iter()
+    .map(|(object_id, collab_type)| ArchivedCollab {
+      object_id,
+      collab_type,
+    })
+    .collect::<Vec<_>>();
+  // The snapshots of the deleted collabs are not exported along with the collabs
+  let object_ids = collabs
+    .iter()
+    .map(|collab| collab.object_id.as_str())
+    .collect::<HashSet<_>>();
+  let snapshots = select_workspace_snapshots(txn.deref_mut(), workspace_id)
+    .await?
+    .into_iter()
+    .filter(|(_, object_id, _)| object_ids.contains(object_id.as_str()))
+    .map(|(snapshot_id, object_id, created_at)| ArchivedSnapshot {
+      snapshot_id,
+      object_id,
+      created_at,
+    })
+    .collect();
+  let members = select_workspace_member_list(txn.deref_mut(), workspace_id)
+    .await?
+    .into_iter()
+    .map(|member| ArchivedMember {
+      email: member.email,
+      role: member.role,
+    })
+    .collect();
+  let blobs = get_all_workspace_blob_metadata(txn.deref_mut(), workspace_id)
+    .await?
+    .into_iter()
+    .map(|blob| ArchivedBlob {
+      file_id: blob.file_id,
+      file_type: blob.file_type,
+      file_size: blob.file_size,
+    })
+    .collect();
+
+  Ok(WorkspaceArchiveManifest {
+    version: WORKSPACE_ARCHIVE_VERSION,
+    workspace_id: *workspace_id,
+    workspace_name: workspace.workspace_name.unwrap_or_default(),
+    exported_at: Utc::now(),
+    collabs,
+    snapshots,
+    members,
+    blobs,
+  })
+}
+
+async fn write_archive(
+  writer: &mut ArchiveWriter,
+  pg_pool: &PgPool,
+  collab_storage: &CollabPostgresDBStorage,
+  bucket_storage: &BucketStorageImpl,
+  uid: &i64,
+  manifest: WorkspaceArchiveManifest,
+) -> Result<(), AppError> {
+  writer
+    .append(MANIFEST_PATH, &serde_json::to_vec(&manifest)?)
+    .await?;
+
+  let workspace_id = manifest.workspace_id.to_string();
+  for collab in &manifest.collabs {
+    // The collab is read from the storage, so the changes that are only in memory or in the update
+    // log are exported too
+    let encoded_collab = collab_storage
+      .get_collab_encoded_v1_unchecked(
+        uid,
+        QueryCollabParams {
+          object_id: collab.object_id.clone(),
+          workspace_id: workspace_id.clone(),
+          collab_type: collab.collab_type.clone(),
+        },
+      )
+      .await?
+      .encode_to_bytes()
+      .map_err(|err| AppError::Internal(anyhow!("fail to encode EncodedCollabV1: {:?}", err)))?;
+    writer
+      .append(&collab_path(&collab.object_id), &encoded_collab)
+      .await?;
+  }
+
+  for snapshot in &manifest.snapshots {
+    let blob = get_snapshot_blob(pg_pool, snapshot.snapshot_id).await?;
+    writer
+      .append(&snapshot_path(snapshot.snapshot_id), &blob)
+      .await?;
+  }
+
+  for blob in &manifest.blobs {
+    let data = bucket_storage.get_blob(&blob.file_id).await?;
+    writer.append(&blob_path(&blob.file_id), &data).await?;
+  }
+  Ok(())
+}
+
+/// Writes the entries of the tar archive into the channel of the response as soon as they are
+/// appended.
+struct ArchiveWriter {
+  builder: tar::Builder<Vec<u8>>,
+  sender: mpsc::Sender<Result<Bytes, AppError>>,
+}
+
+impl ArchiveWriter {
+  fn new(sender: mpsc::Sender<Result<Bytes, AppError>>) -> Self {
+    Self {
+      builder: tar::Builder::new(Vec::new()),
+      sender,
+    }
+  }
+
+  async fn append(&mut self, path: &str, data: &[u8]) -> Result<(), AppError> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(Utc::now().timestamp() as u64);
+    self.builder.append_data(&mut header, path, data)?;
+    let chunk = std::mem::take(self.builder.get_mut());
+    self.send(chunk).await
+  }
+
+  async fn finish(self) -> Result<(), AppError> {
+    let sender = self.sender;
+    let chunk = self.builder.into_inner()?;
+    send_chunk(&sender, chunk).await
+  }
+
+  async fn send(&self, chunk: Vec<u8>) -> Result<(), AppError> {
+    send_chunk(&self.sender, chunk).await
+  }
+}
+
+async fn send_chunk(
+  sender: &mpsc::Sender<Result<Bytes, AppError>>,
+  chunk: Vec<u8>,
+) -> Result<(), AppError> {
+  sender
+    .send(Ok(Bytes::from(chunk)))
+    .await
+    .map_err(|_| AppError::Internal(anyhow!("the workspace archive is no longer received")))
+}
+
+/// An entry of the archive that is imported.
+struct ArchiveEntry {
+  path: String,
+  content: Vec<u8>,
+}
+
+/// The item of the manifest that an entry of the archive belongs to.
+enum ArchiveEntryKind<'a> {
+  Collab(&'a ArchivedCollab),
+  Snapshot(&'a ArchivedSnapshot),
+  Blob(&'a ArchivedBlob),
+}
+
+/// Reads the entries of the archive and sends them one by one. Returns early if the receiver is
+/// dropped, which means the import failed.
+fn read_archive_entries<R: Read>(
+  reader: R,
+  sender: &mpsc::Sender<Result<ArchiveEntry, AppError>>,
+) -> Result<(), AppError> {
+  let mut archive = tar::Archive::new(LimitedReader {
+    inner: reader,
+    remaining: MAX_WORKSPACE_ARCHIVE_SIZE,
+  });
+  for entry in archive.entries().map_err(invalid_archive)? {
+    let entry = entry.map_err(invalid_archive)?;
+    let path = entry
+      .path()
+      .map_err(invalid_archive)?
+      .to_string_lossy()
+      .to_string();
+    // The size in the header is not trusted to allocate the content, it's only used to refuse
+    // the large entries early
+    if entry.size() > MAX_ARCHIVE_ENTRY_SIZE {
+      return Err(AppError::PayloadTooLarge(format!(
+        "{} in the workspace archive exceeds the limit of {} bytes",
+        path, MAX_ARCHIVE_ENTRY_SIZE
+      )));
+    }
+    let mut content = Vec::new();
+    entry
+      .take(MAX_ARCHIVE_ENTRY_SIZE)
+      .read_to_end(&mut content)
+      .map_err(invalid_archive)?;
+    if sender
+      .blocking_send(Ok(ArchiveEntry { path, content }))
+      .is_err()
+    {
+      return Ok(());
+    }
+  }
+  Ok(())
+}
+
+/// Fails the read once the limit is exceeded. Unlike [Read::take], the archive that is too large
+/// is refused instead of being cut off silently.
+struct LimitedReader<R> {
+  inner: R,
+  remaining: u64,
+}
+
+impl<R: Read> Read for LimitedReader<R> {
+  fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+    let len = self.inner.read(buf)?;
+    self.remaining = self.remaining.checked_sub(len as u64).ok_or_else(|| {
+      std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!(
+          "the archive exceeds the limit of {} bytes",
+          MAX_WORKSPACE_ARCHIVE_SIZE
+        ),
+      )
+    })?;
+    Ok(len)
+  }
+}
+
+fn invalid_archive(err: std::io::Error) -> AppError {
+  AppError::InvalidRequest(format!("invalid workspace archive: {}", err))
+}
+
+/// Parses the manifest after checking its version, so the archives of an unknown format are
+/// refused before anything is imported.
+fn parse_manifest(content: &[u8]) -> Result<WorkspaceArchiveManifest, AppError> {
+  let value = serde_json::from_slice::<serde_json::Value>(content)
+    .map_err(|err| AppError::InvalidRequest(format!("invalid {}: {}", MANIFEST_PATH, err)))?;
+  let version = value.get("version").and_then(|version| version.as_u64());
+  if version != Some(WORKSPACE_ARCHIVE_VERSION as u64) {
+    return Err(AppError::InvalidRequest(format!(
+      "unsupported workspace archive version: {:?}, expected: {}",
+      version, WORKSPACE_ARCHIVE_VERSION
+    )));
+  }
+  serde_json::from_value(value)
+    .map_err(|err| AppError::InvalidRequest(format!("invalid {}: {}", MANIFEST_PATH, err)))
+}
+
+/// The new ids of the objects of the imported workspace.
+struct ObjectIdMap {
+  /// The new object id of each collab in the archive.
+  object_ids: HashMap<String, String>,
+  /// The ids that are replaced in the encoded collabs, e.g. the id of a document in the folder.
+  replacements: HashMap<String, String>,
+}
+
+/// Gives the workspace and each collab a new id. Only the ids that are UUIDs can be replaced in
+/// the encoded collabs, the collabs created by the clients always have such ids.
+fn remap_object_ids(manifest: &WorkspaceArchiveManifest, workspace_id: &Uuid) -> ObjectIdMap {
+  let mut replacements = HashMap::new();
+  replacements.insert(manifest.workspace_id.to_string(), workspace_id.to_string());
+
+  let mut object_ids = HashMap::new();
+  for collab in &manifest.collabs {
+    if let Some(new_id) = replacements.get(&collab.object_id) {
+      object_ids.insert(collab.object_id.clone(), new_id.clone());
+      continue;
+    }
+    let new_id = Uuid::new_v4().to_string();
+    if collab.object_id.len() == REMAPPED_ID_LEN && Uuid::parse_str(&collab.object_id).is_ok() {
+      replacements.insert(collab.object_id.clone(), new_id.clone());
+    }
+    object_ids.insert(collab.object_id.clone(), new_id);
+  }
+  ObjectIdMap {
+    object_ids,
+    replacements,
+  }
+}
+
+/// Replaces the ids in the encoded collab in place. The strings of the encoded collab are prefixed
+/// by their length, so replacing an id with another id of the same length keeps it valid.
+fn replace_ids(data: &mut [u8], replacements: &HashMap<String, String>) {
+  let mut start = 0;
+  while start + REMAPPED_ID_LEN <= data.len() {
+    let end = start + REMAPPED_ID_LEN;
+    // Check the first hyphen of the UUID before looking up the id
+    let new_id = if data[start + 8] == b'-' {
+      std::str::from_utf8(&data[start..end])
+        .ok()
+        .and_then(|id| replacements.get(id))
+    } else {
+      None
+    };
+    match new_id {
+      Some(new_id) => {
+        data[start..end].copy_from_slice(new_id.as_bytes());
+        start = end;
+      },
+      None => start += 1,
+    }
+  }
+}
+
+fn collab_path(object_id: &str) -> String {
+  format!("{}/{}", COLLAB_DIR, object_id)
+}
+
+fn snapshot_path(snapshot_id: i64) -> String {
+  format!("{}/{}", SNAPSHOT_DIR, snapshot_id)
+}
+
+fn blob_path(file_id: &str) -> String {
+  format!("{}/{}", BLOB_DIR, file_id)
+}
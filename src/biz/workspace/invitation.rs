@@ -0,0 +1,319 @@
+use crate::biz::workspace::ops::access_level_for_role;
+use crate::config::config::GoTrueSetting;
+use anyhow::Context;
+use app_error::AppError;
+use database::collab::upsert_collab_member_with_txn;
+use database::user::{select_email_from_uuid, select_uid_from_email, select_uid_from_uuid};
+use database::workspace::{
+  delete_workspace_invitation, insert_workspace_member_with_txn, is_workspace_member_email,
+  select_invitations_for_email, select_pending_invitation_for_update, select_workspace_invitations,
+  select_workspace_member_count, select_workspace_plan, update_workspace_invitation_status,
+  upsert_workspace_invitation,
+};
+use database_entity::dto::{AFRole, AFWorkspaceInvitation, AFWorkspaceInvitationStatus};
+use database_entity::pg_row::AFWorkspaceInvitationRow;
+use gotrue::grant::{Grant, PasswordGrant};
+use gotrue::params::InviteUserParams;
+use shared_entity::dto::workspace_dto::WorkspaceMemberInvitation;
+use sqlx::{PgPool, Transaction};
+use std::collections::BTreeMap;
+use std::ops::DerefMut;
+use tracing::{instrument, warn};
+use uuid::Uuid;
+
+/// Invites the emails to the workspace. The emails that don't belong to any user are invited to
+/// sign up through gotrue, and they join the workspace when they sign up.
+#[instrument(
+  level = "debug",
+  skip(pg_pool, gotrue_client, gotrue_setting, invitations),
+  err
+)]
+pub async fn invite_workspace_members(
+  pg_pool: &PgPool,
+  gotrue_client: &gotrue::api::Client,
+  gotrue_setting: &GoTrueSetting,
+  inviter_uuid: &Uuid,
+  workspace_id: &Uuid,
+  invitations: Vec<WorkspaceMemberInvitation>,
+) -> Result<(), AppError> {
+  let mut txn = pg_pool
+    .begin()
+    .await
+    .context("Begin transaction to invite workspace members")?;
+  let inviter_uid = select_uid_from_uuid(txn.deref_mut(), inviter_uuid).await?;
+  let new_user_emails =
+    insert_workspace_invitations(&mut txn, &inviter_uid, workspace_id, invitations).await?;
+  txn
+    .commit()
+    .await
+    .context("Commit transaction to invite workspace members")?;
+
+  send_invite_emails(
+    gotrue_client,
+    gotrue_setting,
+    workspace_id,
+    &new_user_emails,
+  )
+  .await;
+  Ok(())
+}
+
+/// Records the invitations to the workspace. Returns the emails that don't belong to any user,
+/// which should be passed to [send_invite_emails] after the transaction is committed.
+pub(crate) async fn insert_workspace_invitations(
+  txn: &mut Transaction<'_, sqlx::Postgres>,
+  inviter_uid: &i64,
+  workspace_id: &Uuid,
+  invitations: Vec<WorkspaceMemberInvitation>,
+) -> Result<Vec<String>, AppError> {
+  let mut new_user_emails = vec![];
+  for invitation in invitations {
+    let email = invitation.email.trim().to_lowercase();
+    if email.is_empty() {
+      return Err(AppError::InvalidRequest(
+        "the email of the invitation can't be empty".to_string(),
+      ));
+    }
+    if is_workspace_member_email(txn.deref_mut(), workspace_id, &email).await? {
+      return Err(AppError::InvalidRequest(format!(
+        "{} is already a member of the workspace",
+        email
+      )));
+    }
+
+    upsert_workspace_invitation(txn, workspace_id, inviter_uid, &email, invitation.role).await?;
+    match select_uid_from_email(txn.deref_mut(), &email).await {
+      Ok(_) => {},
+      Err(err) if err.is_record_not_found() => new_user_emails.push(email),
+      Err(err) => return Err(err),
+    }
+  }
+  Ok(new_user_emails)
+}
+
+/// Invites the emails that don't belong to any user to sign up through gotrue. The invitations
+/// are kept even if gotrue fails to send the invite emails, the invitees still join the workspace
+/// when they sign up.
+pub(crate) async fn send_invite_emails(
+  gotrue_client: &gotrue::api::Client,
+  gotrue_setting: &GoTrueSetting,
+  workspace_id: &Uuid,
+  emails: &[String],
+) {
+  if emails.is_empty() {
+    return;
+  }
+  if let Err(err) =
+    send_gotrue_invitations(gotrue_client, gotrue_setting, workspace_id, emails).await
+  {
+    warn!("fail to send the invite emails: {}", err);
+  }
+}
+
+async fn send_gotrue_invitations(
+  gotrue_client: &gotrue::api::Client,
+  gotrue_setting: &GoTrueSetting,
+  workspace_id: &Uuid,
+  emails: &[String],
+) -> Result<(), AppError> {
+  let admin_token = gotrue_client
+    .token(&Grant::Password(PasswordGrant {
+      email: gotrue_setting.admin_email.clone(),
+      password: gotrue_setting.admin_password.clone(),
+    }))
+    .await?
+    .access_token;
+
+  for email in emails {
+    let mut data = BTreeMap::new();
+    data.insert(
+      "workspace_id".to_string(),
+      serde_json::Value::String(workspace_id.to_string()),
+    );
+    let params = InviteUserParams {
+      email: email.clone(),
+      data,
+    };
+    // Gotrue refuses to invite an email that has already been registered, which happens when the
+    // user has an account but has never signed in.
+    if let Err(err) = gotrue_client.admin_invite_user(&admin_token, &params).await {
+      warn!("fail to invite {} through gotrue: {}", email, err);
+    }
+  }
+  Ok(())
+}
+
+pub async fn get_workspace_invitations(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+) -> Result<Vec<AFWorkspaceInvitation>, AppError> {
+  let invitations = select_workspace_invitations(pg_pool, workspace_id)
+    .await?
+    .into_iter()
+    .map(AFWorkspaceInvitation::from)
+    .collect();
+  Ok(invitations)
+}
+
+pub async fn revoke_workspace_invitation(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+  invite_id: &Uuid,
+) -> Result<(), AppError> {
+  delete_workspace_invitation(pg_pool, workspace_id, invite_id).await
+}
+
+/// Returns the pending invitations sent to the user.
+pub async fn get_user_invitations(
+  pg_pool: &PgPool,
+  user_uuid: &Uuid,
+) -> Result<Vec<AFWorkspaceInvitation>, AppError> {
+  let email = select_email_from_uuid(pg_pool, user_uuid).await?;
+  let invitations = select_invitations_for_email(pg_pool, &email)
+    .await?
+    .into_iter()
+    .map(AFWorkspaceInvitation::from)
+    .collect();
+  Ok(invitations)
+}
+
+/// Accepts the invitation sent to the user and joins the workspace.
+///
+/// # Returns
+/// The uid of the user, the id of the workspace and the role of the user in the workspace.
+#[instrument(level = "debug", skip(pg_pool), err)]
+pub async fn accept_workspace_invitation(
+  pg_pool: &PgPool,
+  user_uuid: &Uuid,
+  invite_id: &Uuid,
+) -> Result<(i64, Uuid, AFRole), AppError> {
+  let mut txn = pg_pool
+    .begin()
+    .await
+    .context("Begin transaction to accept workspace invitation")?;
+  let uid = select_uid_from_uuid(txn.deref_mut(), user_uuid).await?;
+  let email = select_email_from_uuid(txn.deref_mut(), user_uuid).await?;
+  let invitation = select_user_invitation_for_update(&mut txn, invite_id, &email).await?;
+  let role = AFRole::from(invitation.role_id);
+
+  join_invited_workspace(
+    &mut txn,
+    uid,
+    &email,
+    &invitation.workspace_id,
+    role.clone(),
+  )
+  .await?;
+  update_workspace_invitation_status(
+    &mut txn,
+    invite_id,
+    AFWorkspaceInvitationStatus::Accepted as i16,
+  )
+  .await?;
+
+  txn
+    .commit()
+    .await
+    .context("Commit transaction to accept workspace invitation")?;
+  Ok((uid, invitation.workspace_id, role))
+}
+
+#[instrument(level = "debug", skip(pg_pool), err)]
+pub async fn decline_workspace_invitation(
+  pg_pool: &PgPool,
+  user_uuid: &Uuid,
+  invite_id: &Uuid,
+) -> Result<(), AppError> {
+  let mut txn = pg_pool
+    .begin()
+    .await
+    .context("Begin transaction to decline workspace invitation")?;
+  let email = select_email_from_uuid(txn.deref_mut(), user_uuid).await?;
+  select_user_invitation_for_update(&mut txn, invite_id, &email).await?;
+  update_workspace_invitation_status(
+    &mut txn,
+    invite_id,
+    AFWorkspaceInvitationStatus::Declined as i16,
+  )
+  .await?;
+  txn
+    .commit()
+    .await
+    .context("Commit transaction to decline workspace invitation")?;
+  Ok(())
+}
+
+/// Joins the new user to the workspaces that the email has been invited to. The invitations of the
+/// workspaces that are full are left pending.
+pub async fn accept_invitations_on_sign_up(
+  txn: &mut Transaction<'_, sqlx::Postgres>,
+  uid: i64,
+  email: &str,
+) -> Result<(), AppError> {
+  let invitations = select_invitations_for_email(txn.deref_mut(), email).await?;
+  for invitation in invitations {
+    let role = AFRole::from(invitation.role_id);
+    match join_invited_workspace(txn, uid, email, &invitation.workspace_id, role).await {
+      Ok(_) => {
+        update_workspace_invitation_status(
+          txn,
+          &invitation.invite_id,
+          AFWorkspaceInvitationStatus::Accepted as i16,
+        )
+        .await?
+      },
+      Err(AppError::PlanLimitExceeded(msg)) => {
+        warn!(
+          "{} can't join workspace:{}: {}",
+          email, invitation.workspace_id, msg
+        );
+      },
+      Err(err) => return Err(err),
+    }
+  }
+  Ok(())
+}
+
+/// Returns the pending invitation if it's sent to the email. The invitation of another email is
+/// reported as not found, so the invitations can't be probed by id.
+async fn select_user_invitation_for_update(
+  txn: &mut Transaction<'_, sqlx::Postgres>,
+  invite_id: &Uuid,
+  email: &str,
+) -> Result<AFWorkspaceInvitationRow, AppError> {
+  let invitation = select_pending_invitation_for_update(txn, invite_id).await?;
+  if !invitation.invitee_email.eq_ignore_ascii_case(email) {
+    return Err(AppError::RecordNotFound(format!(
+      "invitation:{} doesn't exist",
+      invite_id
+    )));
+  }
+  Ok(invitation)
+}
+
+async fn join_invited_workspace(
+  txn: &mut Transaction<'_, sqlx::Postgres>,
+  uid: i64,
+  email: &str,
+  workspace_id: &Uuid,
+  role: AFRole,
+) -> Result<(), AppError> {
+  // The user might have been added to the workspace after being invited
+  if is_workspace_member_email(txn.deref_mut(), workspace_id, email).await? {
+    return Ok(());
+  }
+
+  let plan = select_workspace_plan(txn.deref_mut(), workspace_id).await?;
+  let member_count = select_workspace_member_count(txn.deref_mut(), workspace_id).await?;
+  if member_count >= plan.member_limit {
+    return Err(AppError::PlanLimitExceeded(format!(
+      "the {} plan allows at most {} members in the workspace",
+      plan.name, plan.member_limit
+    )));
+  }
+
+  let access_level = access_level_for_role(&role);
+  insert_workspace_member_with_txn(txn, workspace_id, email, role).await?;
+  upsert_collab_member_with_txn(uid, workspace_id.to_string(), &access_level, txn).await?;
+  Ok(())
+}
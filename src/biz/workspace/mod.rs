@@ -1,3 +1,7 @@
 pub mod access_control;
+pub mod archive;
+pub mod invitation;
 pub mod member_listener;
 pub mod ops;
+pub mod role;
+pub mod role_listener;
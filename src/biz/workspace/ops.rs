@@ -1,22 +1,29 @@
-use crate::component::auth::jwt::UserUuid;
+use crate::biz::audit::{record_audit_log, AuditActor};
 use anyhow::Context;
 use app_error::AppError;
-use database::collab::upsert_collab_member_with_txn;
-use database::user::select_uid_from_email;
+use database::collab::{insert_into_af_collab, upsert_collab_member_with_txn};
+use database::user::{select_uid_from_email, select_uid_from_uuid};
 use database::workspace::{
-  delete_workspace_members, insert_workspace_member_with_txn, select_all_user_workspaces,
-  select_workspace, select_workspace_member_list, update_updated_at_of_workspace,
-  upsert_workspace_member,
+  delete_all_workspace_members, delete_workspace_members, insert_user_workspace,
+  insert_workspace_member_with_txn, select_all_user_workspaces,
+  select_members_without_other_workspace, select_user_role, select_user_workspace,
+  select_workspace, select_workspace_member_count, select_workspace_member_list,
+  select_workspace_plan, soft_delete_workspace, update_updated_at_of_workspace,
+  update_workspace_name, update_workspace_owner, upsert_workspace_member,
 };
-use database_entity::dto::{AFAccessLevel, AFRole, AFWorkspace};
+use database_entity::dto::{AFAccessLevel, AFAuditAction, AFRole, AFWorkspace, InsertCollabParams};
 use database_entity::pg_row::{AFWorkspaceMemberRow, AFWorkspaceRow};
+use serde_json::json;
 use shared_entity::dto::workspace_dto::{CreateWorkspaceMember, WorkspaceMemberChangeset};
 use shared_entity::response::AppResponseError;
-use sqlx::{types::uuid, PgPool};
+use sqlx::{types::uuid, PgPool, Postgres, Transaction};
 use std::collections::HashMap;
 use std::ops::DerefMut;
-use tracing::instrument;
+use tracing::{debug, instrument};
 use uuid::Uuid;
+use workspace_template::WorkspaceTemplateBuilder;
+
+const DEFAULT_WORKSPACE_NAME: &str = "My Workspace";
 
 pub async fn get_all_user_workspaces(
   pg_pool: &PgPool,
@@ -26,6 +33,222 @@ pub async fn get_all_user_workspaces(
   Ok(workspaces)
 }
 
+/// Creates a workspace owned by the user. The workspace is seeded with the same templates as the
+/// default workspace of a new user.
+#[instrument(level = "debug", skip(pg_pool), err)]
+pub async fn create_workspace(
+  pg_pool: &PgPool,
+  user_uuid: &Uuid,
+  workspace_name: Option<String>,
+) -> Result<AFWorkspace, AppError> {
+  let workspace_name = match workspace_name {
+    None => DEFAULT_WORKSPACE_NAME.to_string(),
+    Some(name) => validate_workspace_name(&name)?,
+  };
+
+  let mut txn = pg_pool
+    .begin()
+    .await
+    .context("Begin transaction to create workspace")?;
+  let uid = select_uid_from_uuid(txn.deref_mut(), user_uuid).await?;
+  let row = insert_workspace_with_templates(&mut txn, uid, &workspace_name).await?;
+  txn
+    .commit()
+    .await
+    .context("Commit transaction to create workspace")?;
+  AFWorkspace::try_from(row)
+}
+
+async fn insert_workspace_with_templates(
+  txn: &mut Transaction<'_, Postgres>,
+  uid: i64,
+  workspace_name: &str,
+) -> Result<AFWorkspaceRow, AppError> {
+  let row = insert_user_workspace(txn, &uid, workspace_name).await?;
+  let workspace_id = row.workspace_id.to_string();
+
+  let templates = WorkspaceTemplateBuilder::new(uid, &workspace_id)
+    .default_workspace()
+    .await?;
+  debug!(
+    "create {} templates for workspace:{}",
+    templates.len(),
+    workspace_id
+  );
+  for template in templates {
+    insert_into_af_collab(
+      txn,
+      &uid,
+      &InsertCollabParams {
+        object_id: template.object_id,
+        encoded_collab_v1: template
+          .object_data
+          .encode_to_bytes()
+          .map_err(|err| AppError::Internal(anyhow::Error::from(err)))?,
+        workspace_id: workspace_id.clone(),
+        collab_type: template.object_type,
+      },
+    )
+    .await?;
+  }
+  Ok(row)
+}
+
+pub async fn rename_workspace(
+  pg_pool: &PgPool,
+  actor: &AuditActor,
+  workspace_id: &Uuid,
+  workspace_name: &str,
+) -> Result<(), AppError> {
+  let workspace_name = validate_workspace_name(workspace_name)?;
+  let mut txn = pg_pool
+    .begin()
+    .await
+    .context("Begin transaction to rename workspace")?;
+  update_workspace_name(txn.deref_mut(), workspace_id, &workspace_name).await?;
+  record_audit_log(
+    txn.deref_mut(),
+    actor,
+    Some(workspace_id),
+    AFAuditAction::WorkspaceRename,
+    &workspace_id.to_string(),
+    Some(json!({ "name": workspace_name })),
+  )
+  .await?;
+  txn
+    .commit()
+    .await
+    .context("Commit transaction to rename workspace")?;
+  Ok(())
+}
+
+/// Soft-deletes the workspace. The user must keep at least one workspace, so the last workspace of
+/// the user can't be deleted.
+///
+/// The members lose the access to the workspace and its collabs. The members who have no other
+/// workspace get a new default workspace.
+#[instrument(level = "debug", skip(pg_pool), err)]
+pub async fn delete_workspace(
+  pg_pool: &PgPool,
+  actor: &AuditActor,
+  workspace_id: &Uuid,
+) -> Result<(), AppError> {
+  let mut txn = pg_pool
+    .begin()
+    .await
+    .context("Begin transaction to delete workspace")?;
+  let workspaces = select_user_workspace(txn.deref_mut(), &actor.user_uuid).await?;
+  if !workspaces.iter().any(|w| &w.workspace_id == workspace_id) {
+    return Err(AppError::RecordNotFound(format!(
+      "workspace:{} doesn't exist",
+      workspace_id
+    )));
+  }
+  if workspaces.len() <= 1 {
+    return Err(AppError::InvalidRequest(
+      "the last workspace of the user can't be deleted".to_string(),
+    ));
+  }
+
+  soft_delete_workspace(txn.deref_mut(), workspace_id).await?;
+  let orphaned_uids = select_members_without_other_workspace(txn.deref_mut(), workspace_id).await?;
+  delete_all_workspace_members(&mut txn, workspace_id).await?;
+  for uid in orphaned_uids {
+    insert_workspace_with_templates(&mut txn, uid, DEFAULT_WORKSPACE_NAME).await?;
+  }
+  record_audit_log(
+    txn.deref_mut(),
+    actor,
+    Some(workspace_id),
+    AFAuditAction::WorkspaceDelete,
+    &workspace_id.to_string(),
+    None,
+  )
+  .await?;
+  txn
+    .commit()
+    .await
+    .context("Commit transaction to delete workspace")?;
+  Ok(())
+}
+
+/// Transfers the ownership of the workspace to one of its members. The previous owner stays in the
+/// workspace as a member.
+///
+/// # Returns
+/// The uid of the new owner and the uid of the previous owner.
+#[instrument(level = "debug", skip(pg_pool), err)]
+pub async fn transfer_workspace_ownership(
+  pg_pool: &PgPool,
+  actor: &AuditActor,
+  workspace_id: &Uuid,
+  new_owner_email: &str,
+) -> Result<(i64, i64), AppError> {
+  let mut txn = pg_pool
+    .begin()
+    .await
+    .context("Begin transaction to transfer workspace ownership")?;
+  let new_owner_uid = select_uid_from_email(txn.deref_mut(), new_owner_email).await?;
+  let workspace = select_workspace(txn.deref_mut(), workspace_id).await?;
+  if workspace.owner_uid == Some(new_owner_uid) {
+    return Err(AppError::InvalidRequest(format!(
+      "{} is already the owner of the workspace",
+      new_owner_email
+    )));
+  }
+  match select_user_role(txn.deref_mut(), &new_owner_uid, workspace_id).await {
+    Ok(_) => {},
+    Err(err) if err.is_record_not_found() => {
+      return Err(AppError::InvalidRequest(format!(
+        "{} is not a member of the workspace",
+        new_owner_email
+      )));
+    },
+    Err(err) => return Err(err),
+  }
+
+  let old_owner_uid = update_workspace_owner(&mut txn, workspace_id, &new_owner_uid).await?;
+  upsert_collab_member_with_txn(
+    new_owner_uid,
+    workspace_id.to_string(),
+    &AFAccessLevel::FullAccess,
+    &mut txn,
+  )
+  .await?;
+  upsert_collab_member_with_txn(
+    old_owner_uid,
+    workspace_id.to_string(),
+    &AFAccessLevel::ReadAndWrite,
+    &mut txn,
+  )
+  .await?;
+  record_audit_log(
+    txn.deref_mut(),
+    actor,
+    Some(workspace_id),
+    AFAuditAction::WorkspaceOwnershipTransfer,
+    new_owner_email,
+    Some(json!({ "new_owner_uid": new_owner_uid, "old_owner_uid": old_owner_uid })),
+  )
+  .await?;
+
+  txn
+    .commit()
+    .await
+    .context("Commit transaction to transfer workspace ownership")?;
+  Ok((new_owner_uid, old_owner_uid))
+}
+
+fn validate_workspace_name(workspace_name: &str) -> Result<String, AppError> {
+  let workspace_name = workspace_name.trim();
+  if workspace_name.is_empty() {
+    return Err(AppError::InvalidRequest(
+      "the workspace name can't be empty".to_string(),
+    ));
+  }
+  Ok(workspace_name.to_string())
+}
+
 /// Returns the workspace with the given workspace_id and update the updated_at field of the
 /// workspace.
 pub async fn open_workspace(
@@ -67,7 +290,7 @@ pub async fn open_workspace(
 #[instrument(level = "debug", skip_all, err)]
 pub async fn add_workspace_members(
   pg_pool: &PgPool,
-  _user_uuid: &Uuid,
+  actor: &AuditActor,
   workspace_id: &Uuid,
   members: Vec<CreateWorkspaceMember>,
 ) -> Result<HashMap<i64, AFRole>, AppError> {
@@ -78,11 +301,7 @@ pub async fn add_workspace_members(
 
   let mut role_by_uid = HashMap::new();
   for member in members.into_iter() {
-    let access_level = match &member.role {
-      AFRole::Owner => AFAccessLevel::FullAccess,
-      AFRole::Member => AFAccessLevel::ReadAndWrite,
-      AFRole::Guest => AFAccessLevel::ReadOnly,
-    };
+    let access_level = access_level_for_role(&member.role);
 
     let uid = select_uid_from_email(txn.deref_mut(), &member.email).await?;
     // .context(format!(
@@ -92,9 +311,29 @@ pub async fn add_workspace_members(
     insert_workspace_member_with_txn(&mut txn, workspace_id, &member.email, member.role.clone())
       .await?;
     upsert_collab_member_with_txn(uid, workspace_id.to_string(), &access_level, &mut txn).await?;
+    record_audit_log(
+      txn.deref_mut(),
+      actor,
+      Some(workspace_id),
+      AFAuditAction::WorkspaceMemberAdd,
+      &member.email,
+      Some(json!({ "uid": uid, "role": member.role })),
+    )
+    .await?;
     role_by_uid.insert(uid, member.role);
   }
 
+  // The existing members are updated instead of inserted, so the limit is checked after inserting
+  // the members. The transaction is rolled back if the limit is exceeded.
+  let plan = select_workspace_plan(txn.deref_mut(), workspace_id).await?;
+  let member_count = select_workspace_member_count(txn.deref_mut(), workspace_id).await?;
+  if member_count > plan.member_limit {
+    return Err(AppError::PlanLimitExceeded(format!(
+      "the {} plan allows at most {} members in the workspace",
+      plan.name, plan.member_limit
+    )));
+  }
+
   txn
     .commit()
     .await
@@ -102,8 +341,17 @@ pub async fn add_workspace_members(
   Ok(role_by_uid)
 }
 
+/// Returns the access level of the workspace's collab for the role of the workspace member.
+pub(crate) fn access_level_for_role(role: &AFRole) -> AFAccessLevel {
+  match role {
+    AFRole::Owner => AFAccessLevel::FullAccess,
+    AFRole::Member => AFAccessLevel::ReadAndWrite,
+    AFRole::Guest => AFAccessLevel::ReadOnly,
+  }
+}
+
 pub async fn remove_workspace_members(
-  user_uuid: &UserUuid,
+  actor: &AuditActor,
   pg_pool: &PgPool,
   workspace_id: &Uuid,
   member_emails: &[String],
@@ -114,7 +362,16 @@ pub async fn remove_workspace_members(
     .context("Begin transaction to delete workspace members")?;
 
   for email in member_emails {
-    delete_workspace_members(user_uuid, &mut txn, workspace_id, email.as_str()).await?;
+    delete_workspace_members(&actor.user_uuid, &mut txn, workspace_id, email.as_str()).await?;
+    record_audit_log(
+      txn.deref_mut(),
+      actor,
+      Some(workspace_id),
+      AFAuditAction::WorkspaceMemberRemove,
+      email,
+      None,
+    )
+    .await?;
   }
 
   txn
@@ -134,15 +391,35 @@ pub async fn get_workspace_members(
 
 pub async fn update_workspace_member(
   pg_pool: &PgPool,
+  actor: &AuditActor,
   workspace_id: &Uuid,
   changeset: &WorkspaceMemberChangeset,
 ) -> Result<(), AppError> {
+  let mut txn = pg_pool
+    .begin()
+    .await
+    .context("Begin transaction to update workspace member")?;
   upsert_workspace_member(
-    pg_pool,
+    &mut txn,
     workspace_id,
     &changeset.email,
     changeset.role.clone(),
   )
   .await?;
+  if let Some(role) = &changeset.role {
+    record_audit_log(
+      txn.deref_mut(),
+      actor,
+      Some(workspace_id),
+      AFAuditAction::WorkspaceMemberRoleUpdate,
+      &changeset.email,
+      Some(json!({ "role": role })),
+    )
+    .await?;
+  }
+  txn
+    .commit()
+    .await
+    .context("Commit transaction to update workspace member")?;
   Ok(())
 }
@@ -0,0 +1,177 @@
+use crate::biz::audit::{record_audit_log, AuditActor};
+use crate::biz::workspace::access_control::{WorkspaceAccessControl, WorkspaceAccessControlImpl};
+use app_error::AppError;
+use database::user::select_uid_from_email;
+use database::workspace_role::{
+  delete_workspace_role, insert_workspace_role, select_workspace_role, select_workspace_roles,
+  update_workspace_member_custom_role, update_workspace_role,
+};
+use database_entity::dto::{AFAuditAction, AFRole, AFWorkspacePermissions, AFWorkspaceRole};
+use serde_json::json;
+use shared_entity::dto::workspace_dto::{
+  AssignWorkspaceRoleParams, CreateWorkspaceRoleParams, UpdateWorkspaceRoleParams,
+};
+use sqlx::PgPool;
+use tracing::instrument;
+use uuid::Uuid;
+
+#[instrument(level = "debug", skip(pg_pool, params), err)]
+pub async fn create_workspace_role(
+  pg_pool: &PgPool,
+  actor: &AuditActor,
+  workspace_id: &Uuid,
+  params: CreateWorkspaceRoleParams,
+) -> Result<AFWorkspaceRole, AppError> {
+  let name = validate_role_name(&params.name)?;
+  let permissions = AFWorkspacePermissions::from(params.permissions.as_slice());
+  let row = insert_workspace_role(pg_pool, workspace_id, name, permissions.0)
+    .await?
+    .ok_or_else(|| AppError::InvalidRequest(format!("role:{} already exists", name)))?;
+  let role = AFWorkspaceRole::from(row);
+  record_audit_log(
+    pg_pool,
+    actor,
+    Some(workspace_id),
+    AFAuditAction::WorkspaceRoleCreate,
+    &role.role_id.to_string(),
+    Some(json!({ "name": role.name, "permissions": role.permissions })),
+  )
+  .await?;
+  Ok(role)
+}
+
+pub async fn get_workspace_roles(
+  pg_pool: &PgPool,
+  workspace_id: &Uuid,
+) -> Result<Vec<AFWorkspaceRole>, AppError> {
+  let roles = select_workspace_roles(pg_pool, workspace_id)
+    .await?
+    .into_iter()
+    .map(AFWorkspaceRole::from)
+    .collect();
+  Ok(roles)
+}
+
+#[instrument(level = "debug", skip(pg_pool, params), err)]
+pub async fn update_workspace_custom_role(
+  pg_pool: &PgPool,
+  actor: &AuditActor,
+  workspace_id: &Uuid,
+  role_id: i32,
+  params: UpdateWorkspaceRoleParams,
+) -> Result<AFWorkspaceRole, AppError> {
+  let name = params.name.as_deref().map(validate_role_name).transpose()?;
+  if let Some(name) = name {
+    let is_taken = select_workspace_roles(pg_pool, workspace_id)
+      .await?
+      .iter()
+      .any(|role| role.id != role_id && role.name == name);
+    if is_taken {
+      return Err(AppError::InvalidRequest(format!(
+        "role:{} already exists",
+        name
+      )));
+    }
+  }
+  let permissions = params
+    .permissions
+    .map(|permissions| AFWorkspacePermissions::from(permissions.as_slice()).0);
+  let row = update_workspace_role(pg_pool, workspace_id, role_id, name, permissions).await?;
+  let role = AFWorkspaceRole::from(row);
+  record_audit_log(
+    pg_pool,
+    actor,
+    Some(workspace_id),
+    AFAuditAction::WorkspaceRoleUpdate,
+    &role_id.to_string(),
+    Some(json!({ "name": role.name, "permissions": role.permissions })),
+  )
+  .await?;
+  Ok(role)
+}
+
+pub async fn delete_workspace_custom_role(
+  pg_pool: &PgPool,
+  actor: &AuditActor,
+  workspace_id: &Uuid,
+  role_id: i32,
+) -> Result<(), AppError> {
+  delete_workspace_role(pg_pool, workspace_id, role_id).await?;
+  record_audit_log(
+    pg_pool,
+    actor,
+    Some(workspace_id),
+    AFAuditAction::WorkspaceRoleDelete,
+    &role_id.to_string(),
+    None,
+  )
+  .await
+}
+
+/// Assigns the custom role to the member of the workspace, or removes the custom role of the member.
+///
+/// # Returns
+/// The uid of the member.
+#[instrument(level = "debug", skip(pg_pool, access_control), err)]
+pub async fn assign_workspace_role(
+  pg_pool: &PgPool,
+  access_control: &WorkspaceAccessControlImpl,
+  actor: &AuditActor,
+  workspace_id: &Uuid,
+  params: &AssignWorkspaceRoleParams,
+) -> Result<i64, AppError> {
+  let uid = select_uid_from_email(pg_pool, &params.email).await?;
+  let role = access_control.get_role_from_uid(&uid, workspace_id).await?;
+  if role == AFRole::Owner {
+    return Err(AppError::InvalidRequest(
+      "the owner of the workspace can't have a custom role".to_string(),
+    ));
+  }
+  if let Some(role_id) = params.role_id {
+    // The role must belong to the same workspace
+    select_workspace_role(pg_pool, workspace_id, role_id).await?;
+  }
+  update_workspace_member_custom_role(pg_pool, workspace_id, uid, params.role_id).await?;
+  record_audit_log(
+    pg_pool,
+    actor,
+    Some(workspace_id),
+    AFAuditAction::WorkspaceRoleAssign,
+    &params.email,
+    Some(json!({ "uid": uid, "role_id": params.role_id })),
+  )
+  .await?;
+  Ok(uid)
+}
+
+/// Only the owner of the workspace can grant the owner role. The members who are allowed to invite
+/// others by their custom role can only grant the member and guest roles.
+pub async fn check_grantable_roles<'a>(
+  access_control: &WorkspaceAccessControlImpl,
+  user_uuid: &Uuid,
+  workspace_id: &Uuid,
+  mut roles: impl Iterator<Item = &'a AFRole>,
+) -> Result<(), AppError> {
+  if !roles.any(|role| *role == AFRole::Owner) {
+    return Ok(());
+  }
+  let role = access_control
+    .get_role_from_uuid(user_uuid, workspace_id)
+    .await?;
+  if role != AFRole::Owner {
+    return Err(AppError::NotEnoughPermissions(
+      "only the owner of the workspace can grant the owner role".to_string(),
+    ));
+  }
+  Ok(())
+}
+
+fn validate_role_name(name: &str) -> Result<&str, AppError> {
+  let name = name.trim();
+  if name.is_empty() {
+    return Err(AppError::InvalidRequest(
+      "the name of the role can't be empty".to_string(),
+    ));
+  }
+  Ok(name)
+}
@@ -0,0 +1,38 @@
+use crate::biz::pg_listener::PostgresDBListener;
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Deserialize, Clone, Debug)]
+pub enum WorkspaceRoleAction {
+  INSERT,
+  UPDATE,
+  DELETE,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct WorkspaceRoleNotification {
+  pub old: Option<WorkspaceRoleRow>,
+  pub new: Option<WorkspaceRoleRow>,
+  pub action_type: WorkspaceRoleAction,
+}
+
+impl WorkspaceRoleNotification {
+  /// Returns the id of the custom role whose permissions might be changed.
+  pub fn changed_role_id(&self) -> Option<i32> {
+    match self.action_type {
+      WorkspaceRoleAction::INSERT => None,
+      WorkspaceRoleAction::UPDATE => self.new.as_ref().map(|row| row.id),
+      WorkspaceRoleAction::DELETE => self.old.as_ref().map(|row| row.id),
+    }
+  }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct WorkspaceRoleRow {
+  pub id: i32,
+  pub workspace_id: Uuid,
+  pub permissions: i64,
+}
+
+pub type WorkspaceRoleListener = PostgresDBListener<WorkspaceRoleNotification>;
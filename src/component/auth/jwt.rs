@@ -66,7 +66,14 @@ pub struct Authorization {
   pub claims: GoTrueJWTClaims,
 }
 
+/// The role of the admin user, which is set up by the server when it starts.
+pub const ADMIN_ROLE: &str = "supabase_admin";
+
 impl Authorization {
+  pub fn is_admin(&self) -> bool {
+    self.claims.role == ADMIN_ROLE
+  }
+
   pub fn uuid(&self) -> Result<uuid::Uuid, actix_web::Error> {
     self
       .claims
@@ -14,16 +14,56 @@ pub struct Config {
   pub websocket: WebsocketSetting,
   pub redis_uri: Secret<String>,
   pub s3: S3Setting,
+  pub trash: TrashSetting,
 }
 
 #[derive(serde::Deserialize, Clone, Debug)]
 pub struct S3Setting {
+  /// Where the blobs are stored. The other fields are only used by the s3 backend.
+  pub backend: BucketBackendSetting,
   pub use_minio: bool,
   pub minio_url: String,
   pub access_key: String,
   pub secret_key: String,
   pub bucket: String,
   pub region: String,
+  pub gc: BlobGCSetting,
+}
+
+/// The garbage collection of the blobs that are not referenced by any workspace, and the blob
+/// metadata whose blob is missing in the bucket.
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct BlobGCSetting {
+  /// Whether to run the garbage collection periodically. It can always be run by the admin.
+  pub enable: bool,
+  pub interval_secs: u64,
+  /// The blobs written within the grace period are never collected, which gives the uploads
+  /// time to save their metadata.
+  pub grace_period_secs: i64,
+  /// The multipart and presigned uploads that are not completed within this period are
+  /// discarded.
+  pub upload_expiration_secs: i64,
+  /// Only reports the garbage without removing it.
+  pub dry_run: bool,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum BucketBackendSetting {
+  /// Store the blobs in S3 or a S3 compatible service, e.g. MinIO.
+  S3,
+  /// Store the blobs in the `blobs` directory of [ApplicationSetting::data_dir].
+  FileSystem,
+  /// Keep the blobs in memory. The blobs are lost when the server stops.
+  Memory,
+}
+
+/// The deleted collabs are kept in the trash of their workspace for the retention period, then
+/// they are purged by a periodic job.
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct TrashSetting {
+  pub retention_days: i64,
+  pub purge_interval_secs: u64,
 }
 
 #[derive(serde::Deserialize, Clone, Debug)]
@@ -67,6 +107,10 @@ impl ApplicationSetting {
   pub fn rocksdb_db_dir(&self) -> PathBuf {
     self.data_dir.join("rocksdb")
   }
+
+  pub fn blob_dir(&self) -> PathBuf {
+    self.data_dir.join("blobs")
+  }
 }
 
 #[derive(serde::Deserialize, Clone, Debug)]
@@ -167,4 +211,17 @@ impl TryFrom<String> for Environment {
 pub struct WebsocketSetting {
   pub heartbeat_interval: u8,
   pub client_timeout: u8,
+  /// The collab group that has not been edited for this many seconds will be removed from memory.
+  pub collab_group_idle_timeout: u64,
+  /// How the collab updates are relayed between multiple server instances.
+  pub collab_fanout: CollabFanoutSetting,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum CollabFanoutSetting {
+  /// Only relay the updates within the current process. Use it when running a single instance.
+  InProcess,
+  /// Relay the updates between the instances via redis pub/sub.
+  Redis,
 }
@@ -40,6 +40,7 @@ pub trait HttpAccessControlService: Send + Sync {
     workspace_id: &Uuid,
     user_uuid: &UserUuid,
     method: Method,
+    path: &Path<Url>,
   ) -> Result<(), AppError> {
     Ok(())
   }
@@ -70,10 +71,11 @@ where
     workspace_id: &Uuid,
     user_uuid: &UserUuid,
     method: Method,
+    path: &Path<Url>,
   ) -> Result<(), AppError> {
     self
       .as_ref()
-      .check_workspace_permission(workspace_id, user_uuid, method)
+      .check_workspace_permission(workspace_id, user_uuid, method, path)
       .await
   }
 
@@ -195,7 +197,7 @@ where
           .and_then(|id| Uuid::parse_str(id).ok());
         let collab_object_id = path.get(COLLAB_OBJECT_ID_PATH).map(|id| id.to_string());
 
-        let method = req.method().clone();
+        let method = access_method(&req);
         let fut = self.service.call(req);
         let services = self.access_control_service.clone();
 
@@ -208,7 +210,7 @@ where
             if let Some(workspace_id) = workspace_id {
               if let Some(acs) = services.get(&AccessResource::Workspace) {
                 if let Err(err) = acs
-                  .check_workspace_permission(&workspace_id, &user_uuid, method.clone())
+                  .check_workspace_permission(&workspace_id, &user_uuid, method.clone(), &path)
                   .await
                 {
                   error!(
@@ -246,3 +248,22 @@ where
     }
   }
 }
+
+/// The routes of the queries whose params don't fit into the query string, so they are sent with
+/// POST. They don't modify anything, so they are checked as GET requests.
+const READ_ONLY_POST_PATTERNS: [&str; 1] =
+  ["/api/workspace/{workspace_id}/database/{object_id}/row/query"];
+
+/// Returns the method that the permission of the request is checked with.
+fn access_method(req: &ServiceRequest) -> Method {
+  let method = req.method();
+  let is_read_only = req
+    .match_pattern()
+    .map(|pattern| READ_ONLY_POST_PATTERNS.contains(&pattern.as_str()))
+    .unwrap_or(false);
+  if *method == Method::POST && is_read_only {
+    Method::GET
+  } else {
+    method.clone()
+  }
+}
@@ -2,8 +2,10 @@ use actix_http::header::HeaderName;
 use std::future::{ready, Ready};
 use tracing::{Instrument, Level};
 
+use actix_http::header::HeaderMap;
 use actix_service::{forward_ready, Service, Transform};
-use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::{FromRequest, HttpRequest};
 use futures_util::future::LocalBoxFuture;
 use reqwest::header::HeaderValue;
 
@@ -81,7 +83,11 @@ where
 }
 
 pub fn get_request_id(req: &ServiceRequest) -> Option<String> {
-  match req.headers().get(HeaderName::from_static(X_REQUEST_ID)) {
+  request_id_from_headers(req.headers())
+}
+
+fn request_id_from_headers(headers: &HeaderMap) -> Option<String> {
+  match headers.get(HeaderName::from_static(X_REQUEST_ID)) {
     Some(h) => match h.to_str() {
       Ok(s) => Some(s.to_owned()),
       Err(e) => {
@@ -92,3 +98,19 @@ pub fn get_request_id(req: &ServiceRequest) -> Option<String> {
     None => None,
   }
 }
+
+/// The id of the request. It's set by the [RequestIdMiddleware] when the client doesn't provide
+/// one, so it's only missing when the route is not wrapped by the middleware.
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+impl FromRequest for RequestId {
+  type Error = actix_web::Error;
+
+  type Future = Ready<Result<Self, Self::Error>>;
+
+  fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+    let request_id = request_id_from_headers(req.headers()).unwrap_or_default();
+    ready(Ok(RequestId(request_id)))
+  }
+}
@@ -1,3 +1,4 @@
+use crate::biz::blob_gc::BlobGC;
 use crate::biz::collab::access_control::CollabAccessControlImpl;
 use crate::biz::collab::storage::CollabPostgresDBStorage;
 use crate::biz::pg_listener::PgListeners;
@@ -5,7 +6,7 @@ use crate::biz::workspace::access_control::WorkspaceAccessControlImpl;
 use crate::component::auth::LoggedUser;
 use crate::config::config::Config;
 use chrono::{DateTime, Utc};
-use database::file::bucket_s3_impl::S3BucketStorage;
+use database::file::bucket_client_impl::BucketStorageImpl;
 use snowflake::Snowflake;
 use sqlx::PgPool;
 use std::collections::BTreeMap;
@@ -23,7 +24,8 @@ pub struct AppState {
   pub collab_storage: Arc<CollabPostgresDBStorage>,
   pub collab_access_control: Arc<CollabAccessControlImpl>,
   pub workspace_access_control: Arc<WorkspaceAccessControlImpl>,
-  pub bucket_storage: Arc<S3BucketStorage>,
+  pub bucket_storage: Arc<BucketStorageImpl>,
+  pub blob_gc: Arc<BlobGC>,
   pub pg_listeners: Arc<PgListeners>,
 }
 
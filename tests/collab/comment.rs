@@ -0,0 +1,262 @@
+use crate::util::test_client::TestClient;
+use app_error::ErrorCode;
+use collab::core::collab_plugin::EncodedCollabV1;
+use collab_entity::CollabType;
+use database_entity::dto::{
+  AFAccessLevel, AFRole, CreateCollabCommentParams, InsertCollabParams, UpdateCollabCommentParams,
+};
+use realtime_entity::comment::CommentEvent;
+use std::time::Duration;
+use uuid::Uuid;
+
+async fn create_document(c: &TestClient, workspace_id: &str) -> String {
+  let object_id = Uuid::new_v4().to_string();
+  let encoded_collab_v1 = EncodedCollabV1::new(vec![], b"hello world".to_vec())
+    .encode_to_bytes()
+    .unwrap();
+  c.api_client
+    .create_collab(InsertCollabParams::new(
+      &object_id,
+      CollabType::Document,
+      encoded_collab_v1,
+      workspace_id.to_string(),
+    ))
+    .await
+    .unwrap();
+  object_id
+}
+
+fn comment_params(content: &str, reply_to: Option<Uuid>) -> CreateCollabCommentParams {
+  CreateCollabCommentParams {
+    content: content.to_string(),
+    block_id: Some("block_1".to_string()),
+    reply_to,
+  }
+}
+
+#[tokio::test]
+async fn comment_thread_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let c2 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+  let object_id = create_document(&c1, &workspace_id).await;
+  c1.add_workspace_member(&workspace_id, &c2, AFRole::Member)
+    .await;
+  c1.add_client_as_collab_member(&workspace_id, &object_id, &c2, AFAccessLevel::ReadAndWrite)
+    .await;
+
+  let root = c2
+    .api_client
+    .create_collab_comment(&workspace_id, &object_id, comment_params("typo", None))
+    .await
+    .unwrap();
+  assert_eq!(root.created_by, c2.uid().await);
+  let reply = c1
+    .api_client
+    .create_collab_comment(
+      &workspace_id,
+      &object_id,
+      CreateCollabCommentParams {
+        content: "fixed".to_string(),
+        block_id: None,
+        reply_to: Some(root.comment_id),
+      },
+    )
+    .await
+    .unwrap();
+  // The reply is anchored to the block of the thread
+  assert_eq!(reply.block_id, root.block_id);
+
+  // Can't reply to a reply
+  let error = c2
+    .api_client
+    .create_collab_comment(
+      &workspace_id,
+      &object_id,
+      comment_params("thanks", Some(reply.comment_id)),
+    )
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::InvalidRequest);
+
+  // Only the author can edit the comment
+  let error = c2
+    .api_client
+    .update_collab_comment(
+      &workspace_id,
+      &object_id,
+      &reply.comment_id,
+      UpdateCollabCommentParams {
+        content: "not fixed".to_string(),
+      },
+    )
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::NotEnoughPermissions);
+  let updated = c1
+    .api_client
+    .update_collab_comment(
+      &workspace_id,
+      &object_id,
+      &reply.comment_id,
+      UpdateCollabCommentParams {
+        content: "fixed in v2".to_string(),
+      },
+    )
+    .await
+    .unwrap();
+  assert_eq!(updated.content, "fixed in v2");
+
+  let resolved = c2
+    .api_client
+    .resolve_collab_comment(&workspace_id, &object_id, &root.comment_id, true)
+    .await
+    .unwrap();
+  assert_eq!(resolved.resolved_by, Some(c2.uid().await));
+  assert!(resolved.resolved_at.is_some());
+  let reopened = c2
+    .api_client
+    .resolve_collab_comment(&workspace_id, &object_id, &root.comment_id, false)
+    .await
+    .unwrap();
+  assert!(reopened.resolved_at.is_none());
+
+  // The replies are deleted along with the root comment
+  c1.api_client
+    .delete_collab_comment(&workspace_id, &object_id, &root.comment_id)
+    .await
+    .unwrap();
+  let comments = c1
+    .api_client
+    .get_collab_comments(&workspace_id, &object_id)
+    .await
+    .unwrap();
+  assert!(comments.is_empty());
+}
+
+#[tokio::test]
+async fn read_and_comment_member_can_not_edit_collab_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let c2 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+  let object_id = create_document(&c1, &workspace_id).await;
+  c1.add_workspace_member(&workspace_id, &c2, AFRole::Member)
+    .await;
+  c1.add_client_as_collab_member(
+    &workspace_id,
+    &object_id,
+    &c2,
+    AFAccessLevel::ReadAndComment,
+  )
+  .await;
+
+  let comment = c2
+    .api_client
+    .create_collab_comment(&workspace_id, &object_id, comment_params("nice", None))
+    .await
+    .unwrap();
+  c2.api_client
+    .resolve_collab_comment(&workspace_id, &object_id, &comment.comment_id, true)
+    .await
+    .unwrap();
+
+  let encoded_collab_v1 = EncodedCollabV1::new(vec![], b"hello".to_vec())
+    .encode_to_bytes()
+    .unwrap();
+  let error = c2
+    .api_client
+    .update_collab(InsertCollabParams::new(
+      &object_id,
+      CollabType::Document,
+      encoded_collab_v1,
+      workspace_id.clone(),
+    ))
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::NotEnoughPermissions);
+}
+
+#[tokio::test]
+async fn read_only_member_can_not_comment_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let c2 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+  let object_id = create_document(&c1, &workspace_id).await;
+  c1.add_workspace_member(&workspace_id, &c2, AFRole::Member)
+    .await;
+  c1.add_client_as_collab_member(&workspace_id, &object_id, &c2, AFAccessLevel::ReadOnly)
+    .await;
+
+  let error = c2
+    .api_client
+    .create_collab_comment(&workspace_id, &object_id, comment_params("nice", None))
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::NotEnoughPermissions);
+
+  // The comments are still visible to the read only member
+  c1.api_client
+    .create_collab_comment(&workspace_id, &object_id, comment_params("hello", None))
+    .await
+    .unwrap();
+  let comments = c2
+    .api_client
+    .get_collab_comments(&workspace_id, &object_id)
+    .await
+    .unwrap();
+  assert_eq!(comments.len(), 1);
+}
+
+#[tokio::test]
+async fn non_member_can_not_read_comments_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let c2 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+  let object_id = create_document(&c1, &workspace_id).await;
+  c1.api_client
+    .create_collab_comment(&workspace_id, &object_id, comment_params("hello", None))
+    .await
+    .unwrap();
+
+  let error = c2
+    .api_client
+    .get_collab_comments(&workspace_id, &object_id)
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::NotEnoughPermissions);
+}
+
+#[tokio::test]
+async fn receive_comment_from_websocket_test() {
+  let mut c1 = TestClient::new_user().await;
+  let c2 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+  let object_id = c1.create_collab(&workspace_id, CollabType::Document).await;
+  c1.wait_object_sync_complete(&object_id).await;
+  c1.add_workspace_member(&workspace_id, &c2, AFRole::Member)
+    .await;
+  c1.add_client_as_collab_member(
+    &workspace_id,
+    &object_id,
+    &c2,
+    AFAccessLevel::ReadAndComment,
+  )
+  .await;
+
+  let mut comment_rx = c1.ws_client.subscribe_comment_changed();
+  let comment = c2
+    .api_client
+    .create_collab_comment(&workspace_id, &object_id, comment_params("hi", None))
+    .await
+    .unwrap();
+
+  let message = tokio::time::timeout(Duration::from_secs(10), comment_rx.recv())
+    .await
+    .unwrap()
+    .unwrap();
+  assert_eq!(message.object_id, object_id);
+  match message.event {
+    CommentEvent::Created(created) => assert_eq!(created, comment),
+    event => panic!("unexpected comment event: {:?}", event),
+  }
+}
@@ -0,0 +1,296 @@
+use crate::util::test_client::TestClient;
+use app_error::ErrorCode;
+use collab::core::collab_plugin::EncodedCollabV1;
+use collab_entity::CollabType;
+use database_entity::dto::{
+  AFAccessLevel, AFRole, DatabaseRowFilter, DatabaseRowFilterCondition, DatabaseRowSort,
+  InsertCollabParams, InsertDatabaseRowParams, QueryDatabaseRowsParams, UpdateDatabaseRowParams,
+};
+use std::collections::HashMap;
+use uuid::Uuid;
+use yrs::updates::encoder::Encode;
+use yrs::{Any, Array, ArrayPrelim, Doc, Map, MapPrelim, ReadTxn, StateVector, Transact};
+
+const NAME_FIELD: &str = "name_field";
+const AMOUNT_FIELD: &str = "amount_field";
+
+/// Returns a database with a rich text field and a number field, laid out like the databases
+/// created by `collab_database`.
+fn encoded_database(database_id: &str) -> Vec<u8> {
+  let view_id = Uuid::new_v4().to_string();
+  let doc = Doc::new();
+  let database = doc.get_or_insert_map("database");
+  {
+    let mut txn = doc.transact_mut();
+    database.insert(&mut txn, "id", database_id);
+    let fields = database.insert(&mut txn, "fields", MapPrelim::<Any>::from(HashMap::new()));
+    for (field_id, name, ty, is_primary) in [
+      (NAME_FIELD, "Name", 0, true),
+      (AMOUNT_FIELD, "Amount", 1, false),
+    ] {
+      let field = fields.insert(&mut txn, field_id, MapPrelim::<Any>::from(HashMap::new()));
+      field.insert(&mut txn, "id", field_id);
+      field.insert(&mut txn, "name", name);
+      field.insert(&mut txn, "ty", ty as i64);
+      field.insert(&mut txn, "is_primary", is_primary);
+    }
+
+    let views = database.insert(&mut txn, "views", MapPrelim::<Any>::from(HashMap::new()));
+    let view = views.insert(
+      &mut txn,
+      view_id.clone(),
+      MapPrelim::<Any>::from(HashMap::new()),
+    );
+    view.insert(&mut txn, "id", view_id.clone());
+    view.insert(&mut txn, "database_id", database_id);
+    view.insert(&mut txn, "row_orders", ArrayPrelim::from(Vec::<Any>::new()));
+    let field_orders = view.insert(
+      &mut txn,
+      "field_orders",
+      ArrayPrelim::from(Vec::<Any>::new()),
+    );
+    for field_id in [NAME_FIELD, AMOUNT_FIELD] {
+      let field_order = HashMap::from([("id".to_string(), Any::from(field_id))]);
+      field_orders.push_back(&mut txn, Any::from(field_order));
+    }
+
+    let metas = database.insert(&mut txn, "metas", MapPrelim::<Any>::from(HashMap::new()));
+    metas.insert(&mut txn, "iid", view_id);
+  }
+
+  let txn = doc.transact();
+  EncodedCollabV1::new(
+    txn.state_vector().encode_v1(),
+    txn.encode_state_as_update_v1(&StateVector::default()),
+  )
+  .encode_to_bytes()
+  .unwrap()
+}
+
+async fn create_database(c: &TestClient, workspace_id: &str) -> String {
+  let database_id = Uuid::new_v4().to_string();
+  c.api_client
+    .create_collab(InsertCollabParams::new(
+      &database_id,
+      CollabType::Database,
+      encoded_database(&database_id),
+      workspace_id.to_string(),
+    ))
+    .await
+    .unwrap();
+  database_id
+}
+
+fn row_cells(name: &str, amount: &str) -> HashMap<String, String> {
+  HashMap::from([
+    (NAME_FIELD.to_string(), name.to_string()),
+    (AMOUNT_FIELD.to_string(), amount.to_string()),
+  ])
+}
+
+#[tokio::test]
+async fn insert_and_query_database_rows_test() {
+  let c = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c.workspace_id().await;
+  let database_id = create_database(&c, &workspace_id).await;
+
+  let fields = c
+    .api_client
+    .get_database_fields(&workspace_id, &database_id)
+    .await
+    .unwrap();
+  let field_ids = fields
+    .iter()
+    .map(|f| f.field_id.as_str())
+    .collect::<Vec<_>>();
+  assert_eq!(field_ids, vec![NAME_FIELD, AMOUNT_FIELD]);
+  assert!(fields[0].is_primary);
+
+  for (name, amount) in [("apple", "3"), ("banana", "12"), ("cherry", "7")] {
+    c.api_client
+      .insert_database_row(
+        &workspace_id,
+        &database_id,
+        InsertDatabaseRowParams {
+          cells: row_cells(name, amount),
+        },
+      )
+      .await
+      .unwrap();
+  }
+
+  // The rows are returned in the order of insertion
+  let rows = c
+    .api_client
+    .query_database_rows(
+      &workspace_id,
+      &database_id,
+      QueryDatabaseRowsParams::default(),
+    )
+    .await
+    .unwrap();
+  assert_eq!(rows.total, 3);
+  let names = rows
+    .rows
+    .iter()
+    .map(|row| row.cells[NAME_FIELD].as_str())
+    .collect::<Vec<_>>();
+  assert_eq!(names, vec!["apple", "banana", "cherry"]);
+
+  // Without filters and sorts, the page is taken from the row order
+  let rows = c
+    .api_client
+    .query_database_rows(
+      &workspace_id,
+      &database_id,
+      QueryDatabaseRowsParams {
+        offset: Some(2),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+  assert_eq!(rows.total, 3);
+  assert_eq!(rows.rows.len(), 1);
+  assert_eq!(rows.rows[0].cells[NAME_FIELD], "cherry");
+
+  // Without sorts, the page is taken from the filtered rows in the row order
+  let rows = c
+    .api_client
+    .query_database_rows(
+      &workspace_id,
+      &database_id,
+      QueryDatabaseRowsParams {
+        filters: vec![DatabaseRowFilter {
+          field_id: NAME_FIELD.to_string(),
+          condition: DatabaseRowFilterCondition::DoesNotContain,
+          value: "APPLE".to_string(),
+        }],
+        offset: Some(1),
+        limit: Some(1),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+  assert_eq!(rows.total, 2);
+  assert_eq!(rows.rows.len(), 1);
+  assert_eq!(rows.rows[0].cells[NAME_FIELD], "cherry");
+
+  // The numbers are sorted by value, and the page is taken after filtering and sorting
+  let rows = c
+    .api_client
+    .query_database_rows(
+      &workspace_id,
+      &database_id,
+      QueryDatabaseRowsParams {
+        filters: vec![DatabaseRowFilter {
+          field_id: NAME_FIELD.to_string(),
+          condition: DatabaseRowFilterCondition::DoesNotContain,
+          value: "APPLE".to_string(),
+        }],
+        sorts: vec![DatabaseRowSort {
+          field_id: AMOUNT_FIELD.to_string(),
+          descending: true,
+        }],
+        offset: Some(1),
+        limit: Some(1),
+      },
+    )
+    .await
+    .unwrap();
+  assert_eq!(rows.total, 2);
+  assert_eq!(rows.rows.len(), 1);
+  assert_eq!(rows.rows[0].cells[NAME_FIELD], "cherry");
+}
+
+#[tokio::test]
+async fn read_only_member_can_query_database_rows_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let c2 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+  c1.add_workspace_member(&workspace_id, &c2, AFRole::Member)
+    .await;
+  let database_id = create_database(&c1, &workspace_id).await;
+  c1.add_client_as_collab_member(&workspace_id, &database_id, &c2, AFAccessLevel::ReadOnly)
+    .await;
+  c1.api_client
+    .insert_database_row(
+      &workspace_id,
+      &database_id,
+      InsertDatabaseRowParams {
+        cells: row_cells("apple", "3"),
+      },
+    )
+    .await
+    .unwrap();
+
+  // The query is sent with POST, but it doesn't require the write access
+  let rows = c2
+    .api_client
+    .query_database_rows(
+      &workspace_id,
+      &database_id,
+      QueryDatabaseRowsParams::default(),
+    )
+    .await
+    .unwrap();
+  assert_eq!(rows.total, 1);
+}
+
+#[tokio::test]
+async fn update_database_row_test() {
+  let c = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c.workspace_id().await;
+  let database_id = create_database(&c, &workspace_id).await;
+  let row = c
+    .api_client
+    .insert_database_row(
+      &workspace_id,
+      &database_id,
+      InsertDatabaseRowParams {
+        cells: row_cells("apple", "3"),
+      },
+    )
+    .await
+    .unwrap();
+
+  let updated_row = c
+    .api_client
+    .update_database_row(
+      &workspace_id,
+      &database_id,
+      &row.row_id,
+      UpdateDatabaseRowParams {
+        cells: HashMap::from([(AMOUNT_FIELD.to_string(), "5".to_string())]),
+      },
+    )
+    .await
+    .unwrap();
+  assert_eq!(updated_row.cells, row_cells("apple", "5"));
+
+  let rows = c
+    .api_client
+    .query_database_rows(
+      &workspace_id,
+      &database_id,
+      QueryDatabaseRowsParams::default(),
+    )
+    .await
+    .unwrap();
+  assert_eq!(rows.rows, vec![updated_row]);
+
+  let error = c
+    .api_client
+    .update_database_row(
+      &workspace_id,
+      &database_id,
+      &row.row_id,
+      UpdateDatabaseRowParams {
+        cells: HashMap::from([("unknown_field".to_string(), "5".to_string())]),
+      },
+    )
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::InvalidRequest);
+}
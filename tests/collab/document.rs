@@ -0,0 +1,270 @@
+use crate::util::test_client::TestClient;
+use app_error::ErrorCode;
+use collab::core::collab::MutexCollab;
+use collab::core::origin::CollabOrigin;
+use collab_document::blocks::DocumentData;
+use collab_document::document::Document;
+use collab_entity::CollabType;
+use database_entity::dto::{
+  AFDocumentBlock, AFDocumentFormat, ImportDocumentParams, InsertCollabParams,
+  InsertDocumentBlocksParams,
+};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+use workspace_template::JsonToDocumentParser;
+
+async fn create_document(c: &TestClient, workspace_id: &str) -> String {
+  let object_id = Uuid::new_v4().to_string();
+  let json_str = json!({
+    "type": "page",
+    "children": [{ "type": "paragraph", "data": { "delta": [{ "insert": "first" }] } }]
+  })
+  .to_string();
+  let data = JsonToDocumentParser::json_str_to_document(&json_str).unwrap();
+  let collab = Arc::new(MutexCollab::new(CollabOrigin::Empty, &object_id, vec![]));
+  let document = Document::create_with_data(collab, data).unwrap();
+  let encoded_collab_v1 = document
+    .get_collab()
+    .encode_collab_v1()
+    .encode_to_bytes()
+    .unwrap();
+  c.api_client
+    .create_collab(InsertCollabParams::new(
+      &object_id,
+      CollabType::Document,
+      encoded_collab_v1,
+      workspace_id.to_string(),
+    ))
+    .await
+    .unwrap();
+  object_id
+}
+
+fn paragraph(text: &str, children: Vec<AFDocumentBlock>) -> AFDocumentBlock {
+  AFDocumentBlock {
+    ty: "paragraph".to_string(),
+    data: HashMap::from([("delta".to_string(), json!([{ "insert": text }]))]),
+    children,
+  }
+}
+
+fn children_of(data: &DocumentData, block_id: &str) -> Vec<String> {
+  let block = &data.blocks[block_id];
+  data.meta.children_map[&block.children].clone()
+}
+
+fn text_of(data: &DocumentData, block_id: &str) -> Value {
+  let text_id = data.blocks[block_id].external_id.as_ref().unwrap();
+  serde_json::from_str(&data.meta.text_map.as_ref().unwrap()[text_id]).unwrap()
+}
+
+#[tokio::test]
+async fn append_document_blocks_test() {
+  let c = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c.workspace_id().await;
+  let object_id = create_document(&c, &workspace_id).await;
+
+  let block_ids = c
+    .api_client
+    .insert_document_blocks(
+      &workspace_id,
+      &object_id,
+      InsertDocumentBlocksParams {
+        parent_id: None,
+        prev_id: None,
+        blocks: vec![
+          paragraph("second", vec![paragraph("nested", vec![])]),
+          paragraph("third", vec![]),
+        ],
+      },
+    )
+    .await
+    .unwrap();
+  assert_eq!(block_ids.len(), 2);
+
+  // The blocks are appended after the existing paragraph of the page
+  let data = c
+    .api_client
+    .get_document(&workspace_id, &object_id)
+    .await
+    .unwrap();
+  let page_children = children_of(&data, &data.page_id);
+  assert_eq!(page_children.len(), 3);
+  assert_eq!(
+    text_of(&data, &page_children[0]),
+    json!([{ "insert": "first" }])
+  );
+  assert_eq!(page_children[1..], block_ids[..]);
+  assert_eq!(
+    text_of(&data, &block_ids[0]),
+    json!([{ "insert": "second" }])
+  );
+  assert_eq!(
+    text_of(&data, &block_ids[1]),
+    json!([{ "insert": "third" }])
+  );
+
+  let nested_children = children_of(&data, &block_ids[0]);
+  assert_eq!(nested_children.len(), 1);
+  assert_eq!(data.blocks[&nested_children[0]].parent, block_ids[0]);
+  assert_eq!(
+    text_of(&data, &nested_children[0]),
+    json!([{ "insert": "nested" }])
+  );
+}
+
+#[tokio::test]
+async fn insert_document_blocks_under_unknown_parent_test() {
+  let c = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c.workspace_id().await;
+  let object_id = create_document(&c, &workspace_id).await;
+
+  let error = c
+    .api_client
+    .insert_document_blocks(
+      &workspace_id,
+      &object_id,
+      InsertDocumentBlocksParams {
+        parent_id: Some("unknown_block".to_string()),
+        prev_id: None,
+        blocks: vec![paragraph("hello", vec![])],
+      },
+    )
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::InvalidRequest);
+}
+
+const MARKDOWN: &str = r#"# Title
+
+Some **bold** and _italic_ text with [a link](https://appflowy.io)
+
+- first
+- second
+  - nested
+
+1. one
+2. two
+
+- [x] done
+- [ ] todo
+
+> quoted
+
+```rust
+fn main() {}
+```
+
+---
+"#;
+
+#[tokio::test]
+async fn import_and_export_document_test() {
+  let c = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c.workspace_id().await;
+  let object_id = c
+    .api_client
+    .import_document(
+      &workspace_id,
+      ImportDocumentParams {
+        format: AFDocumentFormat::Markdown,
+        content: MARKDOWN.to_string(),
+      },
+    )
+    .await
+    .unwrap()
+    .object_id;
+
+  let markdown = c
+    .api_client
+    .export_document(&workspace_id, &object_id, AFDocumentFormat::Markdown)
+    .await
+    .unwrap();
+  assert_eq!(markdown.content, MARKDOWN);
+
+  let html = c
+    .api_client
+    .export_document(&workspace_id, &object_id, AFDocumentFormat::Html)
+    .await
+    .unwrap()
+    .content;
+  for fragment in [
+    "<h1>Title</h1>",
+    "<strong>bold</strong>",
+    "<em>italic</em>",
+    r#"<a href="https://appflowy.io">a link</a>"#,
+    "<ul><li>first</li><li>second<ul><li>nested</li></ul>",
+    "<ol><li>one</li><li>two</li></ol>",
+    r#"<li><input type="checkbox" disabled checked>done</li>"#,
+    "<blockquote>quoted</blockquote>",
+    r#"<pre><code class="language-rust">fn main() {}</code></pre>"#,
+    "<hr>",
+  ] {
+    assert!(html.contains(fragment), "{} is not in {}", fragment, html);
+  }
+
+  // The HTML is imported as the same document
+  let object_id = c
+    .api_client
+    .import_document(
+      &workspace_id,
+      ImportDocumentParams {
+        format: AFDocumentFormat::Html,
+        content: html,
+      },
+    )
+    .await
+    .unwrap()
+    .object_id;
+  let markdown = c
+    .api_client
+    .export_document(&workspace_id, &object_id, AFDocumentFormat::Markdown)
+    .await
+    .unwrap();
+  assert_eq!(markdown.content, MARKDOWN);
+}
+
+#[tokio::test]
+async fn import_document_with_blob_image_test() {
+  let c = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c.workspace_id().await;
+  let blob_url = c.upload_blob("image", &mime::IMAGE_PNG).await;
+  let file_id = blob_url.rsplit('/').next().unwrap();
+
+  let object_id = c
+    .api_client
+    .import_document(
+      &workspace_id,
+      ImportDocumentParams {
+        format: AFDocumentFormat::Markdown,
+        content: format!("![]({})\n\n![](https://appflowy.io/logo.png)\n", file_id),
+      },
+    )
+    .await
+    .unwrap()
+    .object_id;
+
+  let data = c
+    .api_client
+    .get_document(&workspace_id, &object_id)
+    .await
+    .unwrap();
+  let urls = children_of(&data, &data.page_id)
+    .iter()
+    .map(|block_id| {
+      data.blocks[block_id].data["url"]
+        .as_str()
+        .unwrap()
+        .to_string()
+    })
+    .collect::<Vec<_>>();
+  assert_eq!(
+    urls,
+    vec![
+      format!("/api/file_storage/{}/blob/{}", workspace_id, file_id),
+      "https://appflowy.io/logo.png".to_string(),
+    ]
+  );
+}
@@ -0,0 +1,231 @@
+use crate::util::test_client::{assert_client_collab_include_value, TestClient};
+use app_error::ErrorCode;
+use collab_entity::CollabType;
+use database_entity::dto::{
+  AFAccessLevel, AFFolderView, AFRole, AFViewLayout, CreateFolderViewParams, MoveFolderViewParams,
+  UpdateFolderViewParams,
+};
+use serde_json::json;
+
+async fn create_view(
+  c: &TestClient,
+  workspace_id: &str,
+  parent_view_id: Option<&str>,
+  name: &str,
+) -> AFFolderView {
+  c.api_client
+    .create_folder_view(
+      workspace_id,
+      CreateFolderViewParams {
+        parent_view_id: parent_view_id.map(str::to_string),
+        prev_view_id: None,
+        name: name.to_string(),
+        layout: AFViewLayout::Document,
+        object_id: None,
+      },
+    )
+    .await
+    .unwrap()
+}
+
+fn view_names(views: &[AFFolderView]) -> Vec<&str> {
+  views.iter().map(|view| view.name.as_str()).collect()
+}
+
+#[tokio::test]
+async fn create_rename_move_and_delete_folder_views_test() {
+  let c = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c.workspace_id().await;
+  let parent = create_view(&c, &workspace_id, None, "Parent").await;
+  let child = create_view(&c, &workspace_id, Some(&parent.view_id), "Child").await;
+  assert_eq!(child.parent_view_id, parent.view_id);
+
+  // An empty document is created for each view
+  c.api_client
+    .get_document(&workspace_id, &child.view_id)
+    .await
+    .unwrap();
+  let views = c.api_client.get_folder_views(&workspace_id).await.unwrap();
+  assert_eq!(view_names(&views), vec!["Getting started", "Parent"]);
+  assert_eq!(view_names(&views[1].children), vec!["Child"]);
+
+  c.api_client
+    .update_folder_view(
+      &workspace_id,
+      &child.view_id,
+      UpdateFolderViewParams {
+        name: "Renamed".to_string(),
+      },
+    )
+    .await
+    .unwrap();
+  c.api_client
+    .move_folder_view(
+      &workspace_id,
+      &child.view_id,
+      MoveFolderViewParams {
+        parent_view_id: None,
+        prev_view_id: Some(views[0].view_id.clone()),
+      },
+    )
+    .await
+    .unwrap();
+  let views = c.api_client.get_folder_views(&workspace_id).await.unwrap();
+  assert_eq!(
+    view_names(&views),
+    vec!["Getting started", "Renamed", "Parent"]
+  );
+  assert!(views[2].children.is_empty());
+
+  // A view can't be moved under itself
+  let error = c
+    .api_client
+    .move_folder_view(
+      &workspace_id,
+      &parent.view_id,
+      MoveFolderViewParams {
+        parent_view_id: Some(parent.view_id.clone()),
+        prev_view_id: None,
+      },
+    )
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::InvalidRequest);
+
+  // The collabs of the deleted view and its child views are moved to the trash
+  let grandchild = create_view(&c, &workspace_id, Some(&parent.view_id), "Grandchild").await;
+  c.api_client
+    .delete_folder_view(&workspace_id, &parent.view_id)
+    .await
+    .unwrap();
+  let views = c.api_client.get_folder_views(&workspace_id).await.unwrap();
+  assert_eq!(view_names(&views), vec!["Getting started", "Renamed"]);
+  let mut trashed_ids = c
+    .api_client
+    .get_workspace_trash(&workspace_id)
+    .await
+    .unwrap()
+    .into_iter()
+    .map(|collab| collab.object_id)
+    .collect::<Vec<_>>();
+  trashed_ids.sort();
+  let mut expected_ids = vec![parent.view_id.clone(), grandchild.view_id.clone()];
+  expected_ids.sort();
+  assert_eq!(trashed_ids, expected_ids);
+
+  // The views are put back into the folder when their collabs are restored
+  c.api_client
+    .restore_collab_from_trash(&workspace_id, &parent.view_id)
+    .await
+    .unwrap();
+  let views = c.api_client.get_folder_views(&workspace_id).await.unwrap();
+  assert_eq!(
+    view_names(&views),
+    vec!["Getting started", "Renamed", "Parent"]
+  );
+  assert!(views[2].children.is_empty());
+  c.api_client
+    .restore_collab_from_trash(&workspace_id, &grandchild.view_id)
+    .await
+    .unwrap();
+  let views = c.api_client.get_folder_views(&workspace_id).await.unwrap();
+  assert_eq!(view_names(&views[2].children), vec!["Grandchild"]);
+}
+
+#[tokio::test]
+async fn member_can_not_edit_read_only_folder_view_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let c2 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+  c1.add_workspace_member(&workspace_id, &c2, AFRole::Member)
+    .await;
+  let view = create_view(&c1, &workspace_id, None, "Read only").await;
+  c1.add_client_as_collab_member(&workspace_id, &view.view_id, &c2, AFAccessLevel::ReadOnly)
+    .await;
+
+  let views = c2.api_client.get_folder_views(&workspace_id).await.unwrap();
+  assert!(views.iter().any(|v| v.view_id == view.view_id));
+
+  let error = c2
+    .api_client
+    .update_folder_view(
+      &workspace_id,
+      &view.view_id,
+      UpdateFolderViewParams {
+        name: "Renamed".to_string(),
+      },
+    )
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::NotEnoughPermissions);
+
+  let error = c2
+    .api_client
+    .create_folder_view(
+      &workspace_id,
+      CreateFolderViewParams {
+        parent_view_id: Some(view.view_id.clone()),
+        prev_view_id: None,
+        name: "Child".to_string(),
+        layout: AFViewLayout::Document,
+        object_id: None,
+      },
+    )
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::NotEnoughPermissions);
+
+  let error = c2
+    .api_client
+    .delete_folder_view(&workspace_id, &view.view_id)
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::NotEnoughPermissions);
+
+  // The read only view can't be moved, even among the views that the member can edit
+  let error = c2
+    .api_client
+    .move_folder_view(
+      &workspace_id,
+      &view.view_id,
+      MoveFolderViewParams {
+        parent_view_id: None,
+        prev_view_id: None,
+      },
+    )
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::NotEnoughPermissions);
+
+  // Moving a child view out of the read only view changes the read only view too
+  let child = create_view(&c1, &workspace_id, Some(&view.view_id), "Child").await;
+  let error = c2
+    .api_client
+    .move_folder_view(
+      &workspace_id,
+      &child.view_id,
+      MoveFolderViewParams {
+        parent_view_id: None,
+        prev_view_id: None,
+      },
+    )
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::NotEnoughPermissions);
+}
+
+#[tokio::test]
+async fn folder_view_is_synced_to_connected_client_test() {
+  let mut c = TestClient::new_user().await;
+  let workspace_id = c.workspace_id().await;
+  c.open_collab(&workspace_id, &workspace_id, CollabType::Folder)
+    .await;
+
+  let view = create_view(&c, &workspace_id, None, "Synced").await;
+  assert_client_collab_include_value(
+    &mut c,
+    &workspace_id,
+    json!({ "folder": { "views": { view.view_id: { "name": "Synced" } } } }),
+  )
+  .await;
+}
@@ -1,10 +1,17 @@
 use client_api::Client;
 
+mod comment;
+mod database;
+mod document;
 mod edit_permission;
+mod folder;
 mod member_crud;
 mod multi_devices_edit;
+mod share_link;
 mod single_device_edit;
+mod snapshot;
 mod storage_test;
+mod trash;
 mod workspace_collab;
 
 pub(crate) async fn workspace_id_from_client(c: &Client) -> String {
@@ -0,0 +1,159 @@
+use crate::localhost_client;
+use crate::util::test_client::TestClient;
+use app_error::ErrorCode;
+use chrono::{Duration, Utc};
+use collab::core::collab_plugin::EncodedCollabV1;
+use collab_entity::CollabType;
+use database_entity::dto::{
+  AFAccessLevel, AFRole, CreateCollabShareLinkParams, InsertCollabParams,
+};
+use uuid::Uuid;
+
+async fn create_document(c: &TestClient, workspace_id: &str) -> (String, Vec<u8>) {
+  let object_id = Uuid::new_v4().to_string();
+  let encoded_collab_v1 = EncodedCollabV1::new(vec![], b"hello world".to_vec())
+    .encode_to_bytes()
+    .unwrap();
+  c.api_client
+    .create_collab(InsertCollabParams::new(
+      &object_id,
+      CollabType::Document,
+      encoded_collab_v1.clone(),
+      workspace_id.to_string(),
+    ))
+    .await
+    .unwrap();
+  (object_id, encoded_collab_v1)
+}
+
+fn share_params(password: Option<&str>) -> CreateCollabShareLinkParams {
+  CreateCollabShareLinkParams {
+    collab_type: CollabType::Document,
+    access_level: AFAccessLevel::ReadOnly,
+    expires_at: None,
+    password: password.map(|password| password.to_string()),
+  }
+}
+
+#[tokio::test]
+async fn get_shared_collab_without_sign_in_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+  let (object_id, encoded_collab_v1) = create_document(&c1, &workspace_id).await;
+
+  let link = c1
+    .api_client
+    .create_collab_share_link(&workspace_id, &object_id, share_params(None))
+    .await
+    .unwrap();
+  assert!(!link.has_password);
+
+  let shared = localhost_client()
+    .get_shared_collab(&link.token, None)
+    .await
+    .unwrap();
+  assert_eq!(shared.object_id, object_id);
+  assert_eq!(shared.access_level, AFAccessLevel::ReadOnly);
+  assert_eq!(shared.encoded_collab_v1, encoded_collab_v1);
+
+  let links = c1
+    .api_client
+    .get_collab_share_links(&workspace_id, &object_id)
+    .await
+    .unwrap();
+  assert_eq!(links.len(), 1);
+  assert_eq!(links[0].token, link.token);
+}
+
+#[tokio::test]
+async fn shared_collab_with_password_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+  let (object_id, _) = create_document(&c1, &workspace_id).await;
+  let link = c1
+    .api_client
+    .create_collab_share_link(&workspace_id, &object_id, share_params(Some("secret")))
+    .await
+    .unwrap();
+  assert!(link.has_password);
+
+  let guest = localhost_client();
+  let error = guest
+    .get_shared_collab(&link.token, None)
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::NotEnoughPermissions);
+  let error = guest
+    .get_shared_collab(&link.token, Some("wrong"))
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::NotEnoughPermissions);
+  guest
+    .get_shared_collab(&link.token, Some("secret"))
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn deleted_share_link_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+  let (object_id, _) = create_document(&c1, &workspace_id).await;
+  let link = c1
+    .api_client
+    .create_collab_share_link(&workspace_id, &object_id, share_params(None))
+    .await
+    .unwrap();
+
+  c1.api_client
+    .delete_collab_share_link(&workspace_id, &object_id, &link.share_id)
+    .await
+    .unwrap();
+  let error = localhost_client()
+    .get_shared_collab(&link.token, None)
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::RecordNotFound);
+}
+
+#[tokio::test]
+async fn invalid_share_link_params_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+  let (object_id, _) = create_document(&c1, &workspace_id).await;
+
+  let mut params = share_params(None);
+  params.expires_at = Some(Utc::now() - Duration::minutes(1));
+  let error = c1
+    .api_client
+    .create_collab_share_link(&workspace_id, &object_id, params)
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::InvalidRequest);
+
+  let mut params = share_params(None);
+  params.access_level = AFAccessLevel::FullAccess;
+  let error = c1
+    .api_client
+    .create_collab_share_link(&workspace_id, &object_id, params)
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::InvalidRequest);
+}
+
+#[tokio::test]
+async fn member_can_not_create_share_link_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let c2 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+  c1.add_workspace_member(&workspace_id, &c2, AFRole::Member)
+    .await;
+  let (object_id, _) = create_document(&c1, &workspace_id).await;
+
+  let error = c2
+    .api_client
+    .create_collab_share_link(&workspace_id, &object_id, share_params(None))
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::NotEnoughPermissions);
+}
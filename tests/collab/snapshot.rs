@@ -0,0 +1,217 @@
+use crate::util::test_client::{assert_client_collab_include_value, TestClient};
+use app_error::ErrorCode;
+use collab_entity::CollabType;
+use database_entity::dto::{
+  AFAccessLevel, AFRole, AFViewLayout, CreateFolderViewParams, QuerySnapshotDiffParams,
+  RestoreSnapshotParams,
+};
+use serde_json::json;
+use std::time::Duration;
+
+/// Creates a document and opens it with the websocket connection of the client.
+async fn open_document(c: &mut TestClient, workspace_id: &str) -> String {
+  let object_id = c
+    .api_client
+    .create_folder_view(
+      workspace_id,
+      CreateFolderViewParams {
+        parent_view_id: None,
+        prev_view_id: None,
+        name: "Snapshot".to_string(),
+        layout: AFViewLayout::Document,
+        object_id: None,
+      },
+    )
+    .await
+    .unwrap()
+    .view_id;
+  c.open_collab(workspace_id, &object_id, CollabType::Document)
+    .await;
+  c.wait_object_sync_complete(&object_id).await;
+  object_id
+}
+
+fn edit_collab(c: &mut TestClient, object_id: &str, key: &str, value: &str) {
+  c.collab_by_object_id
+    .get_mut(object_id)
+    .unwrap()
+    .collab
+    .lock()
+    .insert(key, value);
+}
+
+/// Edits the collab and closes it, which creates a snapshot of the edited collab. Returns the
+/// id of the new snapshot.
+async fn edit_and_create_snapshot(
+  c: &mut TestClient,
+  object_id: &str,
+  key: &str,
+  value: &str,
+) -> i64 {
+  let snapshot_count = c
+    .api_client
+    .get_snapshot_list(object_id)
+    .await
+    .unwrap()
+    .0
+    .len();
+  edit_collab(c, object_id, key, value);
+  c.wait_object_sync_complete(object_id).await;
+
+  // The server creates a snapshot when the last subscriber of the collab leaves
+  c.disconnect().await;
+  let mut snapshot_id = None;
+  for _ in 0..30 {
+    let snapshots = c.api_client.get_snapshot_list(object_id).await.unwrap().0;
+    if snapshots.len() > snapshot_count {
+      snapshot_id = snapshots.iter().map(|snapshot| snapshot.snapshot_id).max();
+      break;
+    }
+    tokio::time::sleep(Duration::from_secs(1)).await;
+  }
+  c.reconnect().await;
+  c.wait_object_sync_complete(object_id).await;
+  snapshot_id.expect("the snapshot is not created")
+}
+
+#[tokio::test]
+async fn restore_snapshot_test() {
+  let mut c = TestClient::new_user().await;
+  let workspace_id = c.workspace_id().await;
+  let object_id = open_document(&mut c, &workspace_id).await;
+  let snapshot_id = edit_and_create_snapshot(&mut c, &object_id, "title", "first").await;
+  edit_collab(&mut c, &object_id, "title", "second");
+  c.wait_object_sync_complete(&object_id).await;
+
+  c.api_client
+    .restore_snapshot(
+      &workspace_id,
+      &object_id,
+      snapshot_id,
+      RestoreSnapshotParams {
+        collab_type: CollabType::Document,
+      },
+    )
+    .await
+    .unwrap();
+
+  // The restored content is synced to the connected client
+  assert_client_collab_include_value(&mut c, &object_id, json!({ "title": "first" })).await;
+}
+
+#[tokio::test]
+async fn read_only_member_can_not_restore_snapshot_test() {
+  let mut c1 = TestClient::new_user().await;
+  let c2 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+  c1.add_workspace_member(&workspace_id, &c2, AFRole::Member)
+    .await;
+  let object_id = open_document(&mut c1, &workspace_id).await;
+  c1.add_client_as_collab_member(&workspace_id, &object_id, &c2, AFAccessLevel::ReadOnly)
+    .await;
+  let snapshot_id = edit_and_create_snapshot(&mut c1, &object_id, "title", "first").await;
+
+  let error = c2
+    .api_client
+    .restore_snapshot(
+      &workspace_id,
+      &object_id,
+      snapshot_id,
+      RestoreSnapshotParams {
+        collab_type: CollabType::Document,
+      },
+    )
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::NotEnoughPermissions);
+}
+
+#[tokio::test]
+async fn snapshot_diff_test() {
+  let mut c = TestClient::new_user().await;
+  let workspace_id = c.workspace_id().await;
+  let object_id = open_document(&mut c, &workspace_id).await;
+  let first_snapshot_id = edit_and_create_snapshot(&mut c, &object_id, "title", "first").await;
+  let second_snapshot_id = edit_and_create_snapshot(&mut c, &object_id, "title", "second").await;
+
+  let diff = c
+    .api_client
+    .get_snapshot_diff(
+      &workspace_id,
+      &object_id,
+      QuerySnapshotDiffParams {
+        collab_type: CollabType::Document,
+        from_snapshot_id: first_snapshot_id,
+        to_snapshot_id: Some(second_snapshot_id),
+      },
+    )
+    .await
+    .unwrap();
+  assert!(!diff.update.is_empty());
+  assert!(diff.document_diff.is_some());
+
+  // Compare the snapshot with the current state of the collab
+  let diff = c
+    .api_client
+    .get_snapshot_diff(
+      &workspace_id,
+      &object_id,
+      QuerySnapshotDiffParams {
+        collab_type: CollabType::Document,
+        from_snapshot_id: second_snapshot_id,
+        to_snapshot_id: None,
+      },
+    )
+    .await
+    .unwrap();
+  assert!(diff.document_diff.is_some());
+}
+
+#[tokio::test]
+async fn snapshot_diff_of_other_collab_test() {
+  let mut c = TestClient::new_user().await;
+  let workspace_id = c.workspace_id().await;
+  let object_id = open_document(&mut c, &workspace_id).await;
+  let other_object_id = open_document(&mut c, &workspace_id).await;
+  let snapshot_id = edit_and_create_snapshot(&mut c, &object_id, "title", "first").await;
+  let other_snapshot_id =
+    edit_and_create_snapshot(&mut c, &other_object_id, "title", "other").await;
+
+  // Each snapshot must belong to the collab in the path
+  for (from_snapshot_id, to_snapshot_id) in [
+    (snapshot_id, None),
+    (other_snapshot_id, Some(snapshot_id)),
+  ] {
+    let error = c
+      .api_client
+      .get_snapshot_diff(
+        &workspace_id,
+        &other_object_id,
+        QuerySnapshotDiffParams {
+          collab_type: CollabType::Document,
+          from_snapshot_id,
+          to_snapshot_id,
+        },
+      )
+      .await
+      .unwrap_err();
+    assert_eq!(error.code, ErrorCode::InvalidRequest);
+  }
+
+  // The user outside of the workspace can't read the snapshots
+  let other_client = TestClient::new_user_without_ws_conn().await;
+  let error = other_client
+    .api_client
+    .get_snapshot_diff(
+      &workspace_id,
+      &object_id,
+      QuerySnapshotDiffParams {
+        collab_type: CollabType::Document,
+        from_snapshot_id: snapshot_id,
+        to_snapshot_id: None,
+      },
+    )
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::NotEnoughPermissions);
+}
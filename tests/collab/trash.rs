@@ -0,0 +1,98 @@
+use crate::util::test_client::TestClient;
+use app_error::ErrorCode;
+use collab::core::collab_plugin::EncodedCollabV1;
+use collab_entity::CollabType;
+use database_entity::dto::{AFRole, DeleteCollabParams, InsertCollabParams, QueryCollabParams};
+use uuid::Uuid;
+
+async fn create_and_delete_document(c: &TestClient, workspace_id: &str) -> String {
+  let object_id = Uuid::new_v4().to_string();
+  let encoded_collab_v1 = EncodedCollabV1::new(vec![], b"hello world".to_vec())
+    .encode_to_bytes()
+    .unwrap();
+  c.api_client
+    .create_collab(InsertCollabParams::new(
+      object_id.clone(),
+      CollabType::Document,
+      encoded_collab_v1,
+      workspace_id.to_string(),
+    ))
+    .await
+    .unwrap();
+  c.api_client
+    .delete_collab(DeleteCollabParams {
+      object_id: object_id.clone(),
+      workspace_id: workspace_id.to_string(),
+    })
+    .await
+    .unwrap();
+  object_id
+}
+
+#[tokio::test]
+async fn restore_deleted_collab_test() {
+  let c = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c.workspace_id().await;
+  let object_id = create_and_delete_document(&c, &workspace_id).await;
+
+  let trash = c
+    .api_client
+    .get_workspace_trash(&workspace_id)
+    .await
+    .unwrap();
+  assert_eq!(trash.len(), 1);
+  assert_eq!(trash[0].object_id, object_id);
+  assert_eq!(trash[0].collab_type, CollabType::Document);
+
+  c.api_client
+    .restore_collab_from_trash(&workspace_id, &object_id)
+    .await
+    .unwrap();
+  c.api_client
+    .get_collab(QueryCollabParams {
+      object_id: object_id.clone(),
+      workspace_id: workspace_id.clone(),
+      collab_type: CollabType::Document,
+    })
+    .await
+    .unwrap();
+  assert!(c
+    .api_client
+    .get_workspace_trash(&workspace_id)
+    .await
+    .unwrap()
+    .is_empty());
+
+  // The collab is not in the trash anymore
+  let error = c
+    .api_client
+    .restore_collab_from_trash(&workspace_id, &object_id)
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::RecordNotFound);
+}
+
+#[tokio::test]
+async fn guest_can_not_restore_deleted_collab_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let c2 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+  let object_id = create_and_delete_document(&c1, &workspace_id).await;
+
+  c1.add_workspace_member(&workspace_id, &c2, AFRole::Guest)
+    .await;
+  assert_eq!(
+    c2.api_client
+      .get_workspace_trash(&workspace_id)
+      .await
+      .unwrap()
+      .len(),
+    1
+  );
+  let error = c2
+    .api_client
+    .restore_collab_from_trash(&workspace_id, &object_id)
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::NotEnoughPermissions);
+}
@@ -115,8 +115,13 @@ impl TestClient {
   }
 
   pub(crate) async fn get_user_folder(&self) -> Folder {
-    let uid = self.uid().await;
     let workspace_id = self.workspace_id().await;
+    self.get_folder(&workspace_id).await
+  }
+
+  pub(crate) async fn get_folder(&self, workspace_id: &str) -> Folder {
+    let uid = self.uid().await;
+    let workspace_id = workspace_id.to_string();
     let data = self
       .api_client
       .get_collab(QueryCollabParams {
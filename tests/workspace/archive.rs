@@ -0,0 +1,213 @@
+use crate::util::test_client::{generate_temp_file_path, TestClient};
+use app_error::ErrorCode;
+use database_entity::dto::AFRole;
+use serde_json::{json, Value};
+use shared_entity::dto::workspace_dto::ImportWorkspaceParams;
+use std::io::Read;
+
+fn read_manifest(archive: &[u8]) -> Value {
+  let mut archive = tar::Archive::new(archive);
+  let mut entry = archive.entries().unwrap().next().unwrap().unwrap();
+  assert_eq!(entry.path().unwrap().to_str(), Some("manifest.json"));
+  let mut manifest = String::new();
+  entry.read_to_string(&mut manifest).unwrap();
+  serde_json::from_str(&manifest).unwrap()
+}
+
+#[tokio::test]
+async fn export_and_import_workspace_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let c2 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+  c1.add_workspace_member(&workspace_id, &c2, AFRole::Member)
+    .await;
+  let blob_url = c1.upload_blob("hello world", &mime::TEXT_PLAIN).await;
+  let view_id = c1.get_user_folder().await.get_workspace_views()[0]
+    .id
+    .clone();
+
+  let archive = c1.api_client.export_workspace(&workspace_id).await.unwrap();
+  let manifest = read_manifest(&archive);
+  assert_eq!(manifest["version"], 1);
+  assert_eq!(manifest["workspace_id"], workspace_id);
+  let object_ids = manifest["collabs"]
+    .as_array()
+    .unwrap()
+    .iter()
+    .map(|collab| collab["object_id"].as_str().unwrap())
+    .collect::<Vec<_>>();
+  assert!(object_ids.contains(&workspace_id.as_str()));
+  assert!(object_ids.contains(&view_id.as_str()));
+  assert_eq!(manifest["members"].as_array().unwrap().len(), 2);
+  assert_eq!(manifest["blobs"].as_array().unwrap().len(), 1);
+
+  // The workspace is recreated with new ids for the workspace and its collabs
+  let workspace = c1
+    .api_client
+    .import_workspace(
+      archive,
+      ImportWorkspaceParams {
+        invite_members: true,
+      },
+    )
+    .await
+    .unwrap();
+  let new_workspace_id = workspace.workspace_id.to_string();
+  assert_ne!(new_workspace_id, workspace_id);
+  assert_eq!(
+    workspace.workspace_name,
+    manifest["workspace_name"].as_str().unwrap()
+  );
+
+  let views = c1.get_folder(&new_workspace_id).await.get_workspace_views();
+  assert_eq!(views.len(), 1);
+  assert_ne!(views[0].id, view_id);
+  c1.api_client
+    .get_document(&new_workspace_id, &views[0].id)
+    .await
+    .unwrap();
+
+  let new_blob_url = blob_url.replace(&workspace_id, &new_workspace_id);
+  assert_eq!(c1.download_blob(&new_blob_url).await, b"hello world");
+
+  // The members are invited instead of being added
+  let members = c1.get_workspace_members(&new_workspace_id).await;
+  assert_eq!(members.len(), 1);
+  let invitations = c2.api_client.get_user_invitations().await.unwrap();
+  assert_eq!(invitations.len(), 1);
+  assert_eq!(invitations[0].workspace_id, workspace.workspace_id);
+  assert_eq!(invitations[0].role, AFRole::Member);
+  c2.api_client
+    .accept_workspace_invitation(&invitations[0].invite_id)
+    .await
+    .unwrap();
+  let members = c1.get_workspace_members(&new_workspace_id).await;
+  assert_eq!(members.len(), 2);
+}
+
+#[tokio::test]
+async fn import_workspace_without_inviting_members_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let c2 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+  c1.add_workspace_member(&workspace_id, &c2, AFRole::Member)
+    .await;
+
+  let archive = c1.api_client.export_workspace(&workspace_id).await.unwrap();
+  let workspace = c1
+    .api_client
+    .import_workspace(archive, ImportWorkspaceParams::default())
+    .await
+    .unwrap();
+  let members = c1
+    .get_workspace_members(&workspace.workspace_id.to_string())
+    .await;
+  assert_eq!(members.len(), 1);
+  let invitations = c2.api_client.get_user_invitations().await.unwrap();
+  assert!(invitations.is_empty());
+}
+
+#[tokio::test]
+async fn import_workspace_keeps_multipart_blob_id_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+  let data: Vec<u8> = (0..6 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+  let temp_file = generate_temp_file_path("archive.bin");
+  std::fs::write(&temp_file, &data).unwrap();
+  // The multipart uploads get a random id instead of the hash of the content
+  let blob_url = c1
+    .api_client
+    .put_blob_multipart(&workspace_id, temp_file.to_str().unwrap())
+    .await
+    .unwrap();
+
+  let archive = c1.api_client.export_workspace(&workspace_id).await.unwrap();
+  let workspace = c1
+    .api_client
+    .import_workspace(archive, ImportWorkspaceParams::default())
+    .await
+    .unwrap();
+  let new_workspace_id = workspace.workspace_id.to_string();
+
+  // The links to the blob only differ in the workspace id
+  let new_blob_url = blob_url.replace(&workspace_id, &new_workspace_id);
+  assert_eq!(c1.api_client.get_blob(&new_blob_url).await.unwrap(), data);
+
+  // The blob is kept in the imported workspace after it's deleted from the original workspace
+  c1.api_client.delete_blob(&blob_url).await.unwrap();
+  assert_eq!(c1.api_client.get_blob(&new_blob_url).await.unwrap(), data);
+}
+
+#[tokio::test]
+async fn import_workspace_with_mismatched_blob_hash_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+  c1.upload_blob("hello world", &mime::TEXT_PLAIN).await;
+  let archive = c1.api_client.export_workspace(&workspace_id).await.unwrap();
+
+  // Replace the content of the blob, which is stored under the hash of the original content
+  let mut builder = tar::Builder::new(Vec::new());
+  let mut entries = tar::Archive::new(archive.as_slice());
+  for entry in entries.entries().unwrap() {
+    let mut entry = entry.unwrap();
+    let path = entry.path().unwrap().to_str().unwrap().to_string();
+    let mut data = vec![];
+    entry.read_to_end(&mut data).unwrap();
+    if path.starts_with("blobs/") {
+      data = b"planted content".to_vec();
+    }
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    builder
+      .append_data(&mut header, &path, data.as_slice())
+      .unwrap();
+  }
+  let archive = builder.into_inner().unwrap();
+
+  let error = c1
+    .api_client
+    .import_workspace(archive, ImportWorkspaceParams::default())
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::InvalidRequest);
+}
+
+#[tokio::test]
+async fn only_owner_can_export_workspace_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let c2 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+  c1.add_workspace_member(&workspace_id, &c2, AFRole::Member)
+    .await;
+
+  let error = c2
+    .api_client
+    .export_workspace(&workspace_id)
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::NotEnoughPermissions);
+}
+
+#[tokio::test]
+async fn import_workspace_with_unsupported_version_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let manifest = json!({ "version": 2 }).to_string();
+  let mut header = tar::Header::new_gnu();
+  header.set_size(manifest.len() as u64);
+  header.set_mode(0o644);
+  let mut builder = tar::Builder::new(Vec::new());
+  builder
+    .append_data(&mut header, "manifest.json", manifest.as_bytes())
+    .unwrap();
+  let archive = builder.into_inner().unwrap();
+
+  let error = c1
+    .api_client
+    .import_workspace(archive, ImportWorkspaceParams::default())
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::InvalidRequest);
+  let workspaces = c1.api_client.get_workspaces().await.unwrap();
+  assert_eq!(workspaces.0.len(), 1);
+}
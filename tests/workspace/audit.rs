@@ -0,0 +1,106 @@
+use crate::util::test_client::TestClient;
+use app_error::ErrorCode;
+use database_entity::dto::{AFAuditAction, AFRole};
+use shared_entity::dto::workspace_dto::QueryAuditLogParams;
+
+#[tokio::test]
+async fn workspace_audit_log_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let c2 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+
+  c1.add_workspace_member(&workspace_id, &c2, AFRole::Member)
+    .await;
+  c1.try_update_workspace_member(&workspace_id, &c2, AFRole::Guest)
+    .await
+    .unwrap();
+  c1.try_remove_workspace_member(&workspace_id, &c2)
+    .await
+    .unwrap();
+
+  let logs = c1
+    .api_client
+    .get_workspace_audit_logs(&workspace_id, QueryAuditLogParams::default())
+    .await
+    .unwrap();
+  let actions = logs.iter().map(|log| log.action).collect::<Vec<_>>();
+  assert_eq!(
+    actions,
+    vec![
+      AFAuditAction::WorkspaceMemberRemove,
+      AFAuditAction::WorkspaceMemberRoleUpdate,
+      AFAuditAction::WorkspaceMemberAdd,
+    ]
+  );
+
+  let c1_uid = c1.uid().await;
+  let c2_email = c2.email().await;
+  for log in logs {
+    assert_eq!(log.actor_uid, c1_uid);
+    assert_eq!(log.target, c2_email);
+    assert_eq!(log.workspace_id.unwrap().to_string(), workspace_id);
+    assert!(!log.request_id.unwrap().is_empty());
+  }
+}
+
+#[tokio::test]
+async fn filter_workspace_audit_log_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let c2 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+
+  c1.add_workspace_member(&workspace_id, &c2, AFRole::Member)
+    .await;
+  for name in ["first", "second"] {
+    c1.api_client
+      .rename_workspace(&workspace_id, name)
+      .await
+      .unwrap();
+  }
+
+  let logs = c1
+    .api_client
+    .get_workspace_audit_logs(
+      &workspace_id,
+      QueryAuditLogParams {
+        action: Some(AFAuditAction::WorkspaceRename),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+  assert_eq!(logs.len(), 2);
+  assert_eq!(logs[0].metadata.as_ref().unwrap()["name"], "second");
+
+  let logs = c1
+    .api_client
+    .get_workspace_audit_logs(
+      &workspace_id,
+      QueryAuditLogParams {
+        offset: Some(1),
+        limit: Some(1),
+        ..Default::default()
+      },
+    )
+    .await
+    .unwrap();
+  assert_eq!(logs.len(), 1);
+  assert_eq!(logs[0].action, AFAuditAction::WorkspaceRename);
+  assert_eq!(logs[0].metadata.as_ref().unwrap()["name"], "first");
+}
+
+#[tokio::test]
+async fn member_cannot_read_workspace_audit_log_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let c2 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+
+  c1.add_workspace_member(&workspace_id, &c2, AFRole::Member)
+    .await;
+  let error = c2
+    .api_client
+    .get_workspace_audit_logs(&workspace_id, QueryAuditLogParams::default())
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::NotEnoughPermissions);
+}
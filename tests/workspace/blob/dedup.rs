@@ -0,0 +1,68 @@
+use crate::collab::workspace_id_from_client;
+use crate::user::utils::{admin_user_client, generate_unique_registered_user_client};
+use app_error::ErrorCode;
+use uuid::Uuid;
+
+#[tokio::test]
+async fn same_blob_in_two_workspaces_test() {
+  let (c1, _user1) = generate_unique_registered_user_client().await;
+  let (c2, _user2) = generate_unique_registered_user_client().await;
+  let workspace_id_1 = workspace_id_from_client(&c1).await;
+  let workspace_id_2 = workspace_id_from_client(&c2).await;
+  let mime = mime::TEXT_PLAIN_UTF_8;
+  let data = format!("shared content {}", Uuid::new_v4());
+
+  let url_1 = c1.put_blob(&workspace_id_1, &data, &mime).await.unwrap();
+  let url_2 = c2.put_blob(&workspace_id_2, &data, &mime).await.unwrap();
+  assert_eq!(
+    url_1.rsplit('/').next().unwrap(),
+    url_2.rsplit('/').next().unwrap()
+  );
+
+  // The blob is still referenced by the second workspace
+  c1.delete_blob(&url_1).await.unwrap();
+  let got_data = c2.get_blob(&url_2).await.unwrap();
+  assert_eq!(got_data, data.as_bytes());
+
+  c2.delete_blob(&url_2).await.unwrap();
+  let err = c2.get_blob(&url_2).await.unwrap_err();
+  assert_eq!(err.code, ErrorCode::RecordNotFound);
+}
+
+#[tokio::test]
+async fn put_same_blob_twice_in_workspace_test() {
+  let (c1, _user1) = generate_unique_registered_user_client().await;
+  let workspace_id = workspace_id_from_client(&c1).await;
+  let mime = mime::TEXT_PLAIN_UTF_8;
+  let data = format!("content {}", Uuid::new_v4());
+
+  let url_1 = c1.put_blob(&workspace_id, &data, &mime).await.unwrap();
+  let url_2 = c1.put_blob(&workspace_id, &data, &mime).await.unwrap();
+  assert_eq!(url_1, url_2);
+
+  // Each upload holds a reference of the blob
+  c1.delete_blob(&url_1).await.unwrap();
+  let got_data = c1.get_blob(&url_2).await.unwrap();
+  assert_eq!(got_data, data.as_bytes());
+  c1.delete_blob(&url_2).await.unwrap();
+}
+
+#[tokio::test]
+async fn admin_run_blob_gc_dry_run_test() {
+  let admin_client = admin_user_client().await;
+  let report = admin_client.run_blob_gc(true).await.unwrap();
+  assert!(report.dry_run);
+  assert!(report.started_at <= report.finished_at);
+
+  let last_report = admin_client.get_blob_gc_report().await.unwrap();
+  assert!(last_report.started_at >= report.started_at);
+}
+
+#[tokio::test]
+async fn run_blob_gc_without_admin_test() {
+  let (c1, _user1) = generate_unique_registered_user_client().await;
+  let err = c1.run_blob_gc(true).await.unwrap_err();
+  assert_eq!(err.code, ErrorCode::NotEnoughPermissions);
+  let err = c1.get_blob_gc_report().await.unwrap_err();
+  assert_eq!(err.code, ErrorCode::NotEnoughPermissions);
+}
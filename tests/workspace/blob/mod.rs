@@ -1,2 +1,7 @@
+mod dedup;
+mod multipart;
+mod presigned;
 mod put_and_get;
+mod range;
+mod thumbnail;
 mod usage;
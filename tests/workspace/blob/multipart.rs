@@ -0,0 +1,119 @@
+use crate::collab::workspace_id_from_client;
+use crate::user::utils::generate_unique_registered_user_client;
+use crate::util::test_client::generate_temp_file_path;
+use app_error::ErrorCode;
+use database_entity::dto::CreateUploadParams;
+
+#[tokio::test]
+async fn multipart_upload_file_larger_than_blob_limit() {
+  let (c1, _user1) = generate_unique_registered_user_client().await;
+  let workspace_id = workspace_id_from_client(&c1).await;
+  let data: Vec<u8> = (0..20 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+  let temp_file = generate_temp_file_path("large.bin");
+  std::fs::write(&temp_file, &data).unwrap();
+
+  let url = c1
+    .put_blob_multipart(&workspace_id, temp_file.to_str().unwrap())
+    .await
+    .unwrap();
+  let got_data = c1.get_blob(&url).await.unwrap();
+  assert_eq!(got_data.len(), data.len());
+  assert_eq!(got_data, data);
+
+  c1.delete_blob(&url).await.unwrap();
+}
+
+#[tokio::test]
+async fn resume_multipart_upload() {
+  let (c1, _user1) = generate_unique_registered_user_client().await;
+  let workspace_id = workspace_id_from_client(&c1).await;
+  let data: Vec<u8> = (0..10 * 1024 * 1024).map(|i| (i % 127) as u8).collect();
+  let temp_file = generate_temp_file_path("resume.bin");
+  std::fs::write(&temp_file, &data).unwrap();
+
+  let upload = c1
+    .create_upload(
+      &workspace_id,
+      CreateUploadParams {
+        file_type: "application/octet-stream".to_string(),
+        file_size: data.len() as i64,
+      },
+    )
+    .await
+    .unwrap();
+
+  // Upload the first part only, as if the upload was interrupted
+  c1.put_upload_part(
+    &workspace_id,
+    &upload.upload_id,
+    1,
+    data[..8 * 1024 * 1024].to_vec(),
+  )
+  .await
+  .unwrap();
+  let parts = c1
+    .get_upload_parts(&workspace_id, &upload.upload_id)
+    .await
+    .unwrap();
+  assert_eq!(parts.0.len(), 1);
+
+  let url = c1
+    .resume_blob_multipart(&workspace_id, temp_file.to_str().unwrap(), &upload)
+    .await
+    .unwrap();
+  let got_data = c1.get_blob(&url).await.unwrap();
+  assert_eq!(got_data, data);
+}
+
+#[tokio::test]
+async fn complete_upload_with_missing_part() {
+  let (c1, _user1) = generate_unique_registered_user_client().await;
+  let workspace_id = workspace_id_from_client(&c1).await;
+  let upload = c1
+    .create_upload(
+      &workspace_id,
+      CreateUploadParams {
+        file_type: "text/plain".to_string(),
+        file_size: 10,
+      },
+    )
+    .await
+    .unwrap();
+  c1.put_upload_part(&workspace_id, &upload.upload_id, 2, "hello")
+    .await
+    .unwrap();
+
+  let err = c1
+    .complete_upload(&workspace_id, &upload.upload_id)
+    .await
+    .unwrap_err();
+  assert_eq!(err.code, ErrorCode::InvalidRequest);
+}
+
+#[tokio::test]
+async fn abort_multipart_upload() {
+  let (c1, _user1) = generate_unique_registered_user_client().await;
+  let workspace_id = workspace_id_from_client(&c1).await;
+  let upload = c1
+    .create_upload(
+      &workspace_id,
+      CreateUploadParams {
+        file_type: "text/plain".to_string(),
+        file_size: 5,
+      },
+    )
+    .await
+    .unwrap();
+  c1.put_upload_part(&workspace_id, &upload.upload_id, 1, "hello")
+    .await
+    .unwrap();
+  c1.abort_upload(&workspace_id, &upload.upload_id)
+    .await
+    .unwrap();
+
+  let err = c1
+    .get_upload_parts(&workspace_id, &upload.upload_id)
+    .await
+    .unwrap_err();
+  assert_eq!(err.code, ErrorCode::RecordNotFound);
+}
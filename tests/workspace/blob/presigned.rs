@@ -0,0 +1,119 @@
+use crate::collab::workspace_id_from_client;
+use crate::user::utils::generate_unique_registered_user_client;
+use app_error::ErrorCode;
+use database_entity::dto::CreatePresignedUploadParams;
+use reqwest::Url;
+
+#[tokio::test]
+async fn presigned_put_and_get() {
+  let (c1, _user1) = generate_unique_registered_user_client().await;
+  let workspace_id = workspace_id_from_client(&c1).await;
+  let mime = mime::TEXT_PLAIN_UTF_8;
+  let data = "hello presigned url";
+  let file_url = c1
+    .put_blob_presigned(&workspace_id, data, &mime)
+    .await
+    .unwrap();
+
+  let got_data = c1.get_blob(&file_url).await.unwrap();
+  assert_eq!(got_data, data.as_bytes());
+
+  let url = Url::parse(&file_url).unwrap();
+  let file_id = url.path_segments().unwrap().last().unwrap();
+  let presigned_url = c1
+    .get_presigned_download_url(&workspace_id, file_id)
+    .await
+    .unwrap();
+  let got_data = reqwest::get(&presigned_url.url)
+    .await
+    .unwrap()
+    .bytes()
+    .await
+    .unwrap();
+  assert_eq!(got_data, data.as_bytes());
+}
+
+#[tokio::test]
+async fn complete_presigned_upload_without_uploading() {
+  let (c1, _user1) = generate_unique_registered_user_client().await;
+  let workspace_id = workspace_id_from_client(&c1).await;
+  let presigned_url = c1
+    .create_presigned_upload(
+      &workspace_id,
+      CreatePresignedUploadParams {
+        file_type: "text/plain".to_string(),
+        file_size: 10,
+      },
+    )
+    .await
+    .unwrap();
+
+  let err = c1
+    .complete_presigned_upload(&workspace_id, &presigned_url.file_id)
+    .await
+    .unwrap_err();
+  assert_eq!(err.code, ErrorCode::RecordNotFound);
+}
+
+#[tokio::test]
+async fn presigned_upload_exceeds_plan_limit() {
+  let (c1, _user1) = generate_unique_registered_user_client().await;
+  let workspace_id = workspace_id_from_client(&c1).await;
+  let err = c1
+    .create_presigned_upload(
+      &workspace_id,
+      CreatePresignedUploadParams {
+        file_type: "text/plain".to_string(),
+        file_size: 20 * 1024 * 1024 * 1024,
+      },
+    )
+    .await
+    .unwrap_err();
+  assert_eq!(err.code, ErrorCode::PayloadTooLarge);
+}
+
+async fn put_to_presigned_url(url: &str, data: &'static str) {
+  let resp = reqwest::Client::new()
+    .put(url)
+    .header(reqwest::header::CONTENT_LENGTH, data.len())
+    .body(data)
+    .send()
+    .await
+    .unwrap();
+  assert!(resp.status().is_success());
+}
+
+#[tokio::test]
+async fn presigned_put_after_completion_does_not_change_blob() {
+  let (c1, _user1) = generate_unique_registered_user_client().await;
+  let workspace_id = workspace_id_from_client(&c1).await;
+  let data = "hello presigned url";
+  let presigned_url = c1
+    .create_presigned_upload(
+      &workspace_id,
+      CreatePresignedUploadParams {
+        file_type: "text/plain".to_string(),
+        file_size: data.len() as i64,
+      },
+    )
+    .await
+    .unwrap();
+  put_to_presigned_url(&presigned_url.url, data).await;
+  c1.complete_presigned_upload(&workspace_id, &presigned_url.file_id)
+    .await
+    .unwrap();
+
+  // The url is still valid, but the write doesn't reach the completed blob
+  put_to_presigned_url(&presigned_url.url, "overwritten after completion").await;
+  let download_url = c1
+    .get_presigned_download_url(&workspace_id, &presigned_url.file_id)
+    .await
+    .unwrap();
+  let got_data = reqwest::get(&download_url.url)
+    .await
+    .unwrap()
+    .bytes()
+    .await
+    .unwrap();
+  assert_eq!(got_data, data.as_bytes());
+}
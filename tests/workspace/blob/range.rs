@@ -0,0 +1,33 @@
+use crate::collab::workspace_id_from_client;
+use crate::user::utils::generate_unique_registered_user_client;
+use app_error::ErrorCode;
+
+#[tokio::test]
+async fn get_blob_range() {
+  let (c1, _user1) = generate_unique_registered_user_client().await;
+  let workspace_id = workspace_id_from_client(&c1).await;
+  let mime = mime::TEXT_PLAIN_UTF_8;
+  let data = "hello range world";
+  let url = c1.put_blob(&workspace_id, data, &mime).await.unwrap();
+
+  let got_data = c1.get_blob_range(&url, 6, 10).await.unwrap();
+  assert_eq!(got_data, "range".as_bytes());
+
+  // The range is truncated to the end of the blob
+  let got_data = c1.get_blob_range(&url, 12, 100).await.unwrap();
+  assert_eq!(got_data, "world".as_bytes());
+}
+
+#[tokio::test]
+async fn get_blob_range_not_satisfiable() {
+  let (c1, _user1) = generate_unique_registered_user_client().await;
+  let workspace_id = workspace_id_from_client(&c1).await;
+  let mime = mime::TEXT_PLAIN_UTF_8;
+  let url = c1
+    .put_blob(&workspace_id, "range not satisfiable", &mime)
+    .await
+    .unwrap();
+
+  let err = c1.get_blob_range(&url, 100, 200).await.unwrap_err();
+  assert_eq!(err.code, ErrorCode::InvalidRequest);
+}
@@ -0,0 +1,72 @@
+use crate::collab::workspace_id_from_client;
+use crate::user::utils::generate_unique_registered_user_client;
+use image::{DynamicImage, GenericImageView, ImageBuffer, ImageOutputFormat, Rgba};
+use uuid::Uuid;
+
+/// Generates a png image with a unique color, so it's not shared with other tests.
+fn gen_png(width: u32, height: u32) -> Vec<u8> {
+  let seed = Uuid::new_v4();
+  let bytes = seed.as_bytes();
+  let image = ImageBuffer::from_pixel(width, height, Rgba([bytes[0], bytes[1], bytes[2], 255]));
+  let mut data = vec![];
+  DynamicImage::ImageRgba8(image)
+    .write_to(&mut data, ImageOutputFormat::Png)
+    .unwrap();
+  data
+}
+
+#[tokio::test]
+async fn get_image_thumbnail_test() {
+  let (c1, _user1) = generate_unique_registered_user_client().await;
+  let workspace_id = workspace_id_from_client(&c1).await;
+  let data = gen_png(800, 400);
+  let url = c1
+    .put_blob(&workspace_id, data.clone(), &mime::IMAGE_PNG)
+    .await
+    .unwrap();
+
+  let thumbnail = c1.get_blob_thumbnail(&url, 256).await.unwrap();
+  let thumbnail = image::load_from_memory(&thumbnail).unwrap();
+  assert_eq!(thumbnail.dimensions(), (256, 128));
+
+  // The smallest thumbnail that is not narrower than the requested width is returned
+  let thumbnail = c1.get_blob_thumbnail(&url, 300).await.unwrap();
+  let thumbnail = image::load_from_memory(&thumbnail).unwrap();
+  assert_eq!(thumbnail.dimensions(), (512, 256));
+
+  // There is no thumbnail wider than the original image
+  let original = c1.get_blob_thumbnail(&url, 1024).await.unwrap();
+  assert_eq!(original, data);
+
+  // The thumbnails don't count against the storage of the workspace
+  let usage = c1.get_workspace_usage(&workspace_id).await.unwrap();
+  assert_eq!(usage.consumed_capacity, data.len() as u64);
+}
+
+#[tokio::test]
+async fn get_thumbnail_of_invalid_image_test() {
+  let (c1, _user1) = generate_unique_registered_user_client().await;
+  let workspace_id = workspace_id_from_client(&c1).await;
+  let data = format!("not an image {}", Uuid::new_v4());
+  let url = c1
+    .put_blob(&workspace_id, data.clone(), &mime::IMAGE_PNG)
+    .await
+    .unwrap();
+
+  let got_data = c1.get_blob_thumbnail(&url, 256).await.unwrap();
+  assert_eq!(got_data, data.as_bytes());
+}
+
+#[tokio::test]
+async fn get_thumbnail_of_text_test() {
+  let (c1, _user1) = generate_unique_registered_user_client().await;
+  let workspace_id = workspace_id_from_client(&c1).await;
+  let data = format!("hello {}", Uuid::new_v4());
+  let url = c1
+    .put_blob(&workspace_id, data.clone(), &mime::TEXT_PLAIN_UTF_8)
+    .await
+    .unwrap();
+
+  let got_data = c1.get_blob_thumbnail(&url, 256).await.unwrap();
+  assert_eq!(got_data, data.as_bytes());
+}
@@ -0,0 +1,172 @@
+use crate::user::utils::generate_unique_registered_user;
+use crate::util::test_client::TestClient;
+use app_error::ErrorCode;
+use database_entity::dto::{AFRole, AFWorkspaceInvitationStatus};
+use shared_entity::dto::workspace_dto::WorkspaceMemberInvitation;
+use uuid::Uuid;
+
+async fn invite(c: &TestClient, workspace_id: &str, email: &str, role: AFRole) {
+  c.api_client
+    .invite_workspace_members(
+      workspace_id,
+      vec![WorkspaceMemberInvitation {
+        email: email.to_string(),
+        role,
+      }],
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn accept_workspace_invitation_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let c2 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+  invite(&c1, &workspace_id, &c2.email().await, AFRole::Member).await;
+
+  let invitations = c1
+    .api_client
+    .get_workspace_invitations(&workspace_id)
+    .await
+    .unwrap();
+  assert_eq!(invitations.len(), 1);
+  assert_eq!(invitations[0].status, AFWorkspaceInvitationStatus::Pending);
+
+  let invitations = c2.api_client.get_user_invitations().await.unwrap();
+  assert_eq!(invitations.len(), 1);
+  assert_eq!(invitations[0].workspace_id.to_string(), workspace_id);
+  assert_eq!(invitations[0].role, AFRole::Member);
+
+  c2.api_client
+    .accept_workspace_invitation(&invitations[0].invite_id)
+    .await
+    .unwrap();
+  let members = c1.get_workspace_members(&workspace_id).await;
+  assert_eq!(members.len(), 2);
+  assert!(c2
+    .api_client
+    .get_user_invitations()
+    .await
+    .unwrap()
+    .is_empty());
+
+  // The accepted invitation can't be accepted again
+  let error = c2
+    .api_client
+    .accept_workspace_invitation(&invitations[0].invite_id)
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::RecordNotFound);
+}
+
+#[tokio::test]
+async fn decline_workspace_invitation_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let c2 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+  invite(&c1, &workspace_id, &c2.email().await, AFRole::Member).await;
+
+  let invite_id = c2.api_client.get_user_invitations().await.unwrap()[0].invite_id;
+  c2.api_client
+    .decline_workspace_invitation(&invite_id)
+    .await
+    .unwrap();
+
+  let members = c1.get_workspace_members(&workspace_id).await;
+  assert_eq!(members.len(), 1);
+  assert!(c1
+    .api_client
+    .get_workspace_invitations(&workspace_id)
+    .await
+    .unwrap()
+    .is_empty());
+}
+
+#[tokio::test]
+async fn accept_invitation_of_other_user_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let c2 = TestClient::new_user_without_ws_conn().await;
+  let c3 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+  invite(&c1, &workspace_id, &c2.email().await, AFRole::Member).await;
+
+  let invite_id = c2.api_client.get_user_invitations().await.unwrap()[0].invite_id;
+  let error = c3
+    .api_client
+    .accept_workspace_invitation(&invite_id)
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::RecordNotFound);
+}
+
+#[tokio::test]
+async fn revoke_workspace_invitation_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let c2 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+  invite(&c1, &workspace_id, &c2.email().await, AFRole::Guest).await;
+
+  let invite_id = c1
+    .api_client
+    .get_workspace_invitations(&workspace_id)
+    .await
+    .unwrap()[0]
+    .invite_id;
+  c1.api_client
+    .revoke_workspace_invitation(&workspace_id, &invite_id)
+    .await
+    .unwrap();
+
+  let error = c2
+    .api_client
+    .accept_workspace_invitation(&invite_id)
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::RecordNotFound);
+}
+
+#[tokio::test]
+async fn invite_workspace_members_not_enough_permission_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let c2 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+  c1.add_workspace_member(&workspace_id, &c2, AFRole::Member)
+    .await;
+
+  let error = c2
+    .api_client
+    .invite_workspace_members(
+      &workspace_id,
+      vec![WorkspaceMemberInvitation {
+        email: format!("{}@appflowy.io", Uuid::new_v4()),
+        role: AFRole::Member,
+      }],
+    )
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::NotEnoughPermissions);
+}
+
+#[tokio::test]
+async fn join_invited_workspace_on_sign_up_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+
+  // The user is registered in gotrue, but hasn't signed in to the server yet
+  let registered_user = generate_unique_registered_user().await;
+  invite(&c1, &workspace_id, &registered_user.email, AFRole::Member).await;
+
+  let c2 = TestClient::new(Uuid::new_v4().to_string(), registered_user, false).await;
+  let info = c2.api_client.get_user_workspace_info().await.unwrap();
+  assert!(info
+    .workspaces
+    .iter()
+    .any(|workspace| workspace.workspace_id.to_string() == workspace_id));
+  assert!(c2
+    .api_client
+    .get_user_invitations()
+    .await
+    .unwrap()
+    .is_empty());
+}
@@ -1,3 +1,10 @@
+mod archive;
+mod audit;
 mod blob;
+mod invitation;
 mod member_crud;
+mod plan;
+mod role;
+mod search;
 mod template_test;
+mod workspace_crud;
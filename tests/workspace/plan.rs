@@ -0,0 +1,64 @@
+use crate::collab::workspace_id_from_client;
+use crate::user::utils::{admin_user_client, generate_unique_registered_user_client};
+use app_error::ErrorCode;
+
+#[tokio::test]
+async fn get_workspace_plans_test() {
+  let admin_client = admin_user_client().await;
+  let plans = admin_client.get_workspace_plans().await.unwrap();
+  assert!(plans.0.iter().any(|plan| plan.workspace_type == 0));
+
+  let (c1, _user1) = generate_unique_registered_user_client().await;
+  let err = c1.get_workspace_plans().await.unwrap_err();
+  assert_eq!(err.code, ErrorCode::NotEnoughPermissions);
+}
+
+#[tokio::test]
+async fn admin_update_workspace_plan_test() {
+  let (c1, _user1) = generate_unique_registered_user_client().await;
+  let workspace_id = workspace_id_from_client(&c1).await;
+  let usage = c1.get_workspace_usage(&workspace_id).await.unwrap();
+  assert_eq!(usage.workspace_type, 0);
+  assert_eq!(usage.member_count, 1);
+
+  let admin_client = admin_user_client().await;
+  admin_client
+    .update_workspace_plan(&workspace_id, 1)
+    .await
+    .unwrap();
+
+  let plans = admin_client.get_workspace_plans().await.unwrap();
+  let plan = plans
+    .0
+    .into_iter()
+    .find(|plan| plan.workspace_type == 1)
+    .unwrap();
+  let usage = c1.get_workspace_usage(&workspace_id).await.unwrap();
+  assert_eq!(usage.workspace_type, 1);
+  assert_eq!(usage.total_capacity, plan.storage_limit as u64);
+  assert_eq!(usage.max_members, plan.member_limit as u64);
+  assert_eq!(usage.max_blob_size, plan.blob_size_limit as u64);
+}
+
+#[tokio::test]
+async fn update_workspace_plan_without_admin_test() {
+  let (c1, _user1) = generate_unique_registered_user_client().await;
+  let workspace_id = workspace_id_from_client(&c1).await;
+  let err = c1
+    .update_workspace_plan(&workspace_id, 1)
+    .await
+    .unwrap_err();
+  assert_eq!(err.code, ErrorCode::NotEnoughPermissions);
+}
+
+#[tokio::test]
+async fn update_workspace_to_not_exist_plan_test() {
+  let (c1, _user1) = generate_unique_registered_user_client().await;
+  let workspace_id = workspace_id_from_client(&c1).await;
+  let admin_client = admin_user_client().await;
+  let err = admin_client
+    .update_workspace_plan(&workspace_id, 1000)
+    .await
+    .unwrap_err();
+  assert_eq!(err.code, ErrorCode::RecordNotFound);
+}
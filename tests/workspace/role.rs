@@ -0,0 +1,199 @@
+use crate::util::test_client::TestClient;
+use app_error::ErrorCode;
+use collab::core::collab_plugin::EncodedCollabV1;
+use collab_entity::CollabType;
+use database_entity::dto::{AFRole, AFWorkspacePermission, InsertCollabParams};
+use shared_entity::dto::workspace_dto::{CreateWorkspaceRoleParams, UpdateWorkspaceRoleParams};
+use uuid::Uuid;
+
+fn role_params(name: &str, permissions: Vec<AFWorkspacePermission>) -> CreateWorkspaceRoleParams {
+  CreateWorkspaceRoleParams {
+    name: name.to_string(),
+    permissions,
+  }
+}
+
+async fn create_document(c: &TestClient, workspace_id: &str) -> Result<(), ErrorCode> {
+  let encoded_collab_v1 = EncodedCollabV1::new(vec![], b"hello world".to_vec())
+    .encode_to_bytes()
+    .unwrap();
+  c.api_client
+    .create_collab(InsertCollabParams::new(
+      Uuid::new_v4().to_string(),
+      CollabType::Document,
+      encoded_collab_v1,
+      workspace_id.to_string(),
+    ))
+    .await
+    .map_err(|err| err.code)
+}
+
+#[tokio::test]
+async fn workspace_role_crud_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+
+  let role = c1
+    .api_client
+    .create_workspace_role(
+      &workspace_id,
+      role_params("Commenter", vec![AFWorkspacePermission::Comment]),
+    )
+    .await
+    .unwrap();
+  assert_eq!(role.name, "Commenter");
+  assert_eq!(role.permissions, vec![AFWorkspacePermission::Comment]);
+
+  // The name of the role is unique in the workspace
+  let error = c1
+    .api_client
+    .create_workspace_role(&workspace_id, role_params("Commenter", vec![]))
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::InvalidRequest);
+
+  let role = c1
+    .api_client
+    .update_workspace_role(
+      &workspace_id,
+      role.role_id,
+      UpdateWorkspaceRoleParams {
+        name: Some("Editor".to_string()),
+        permissions: Some(vec![
+          AFWorkspacePermission::CreateCollab,
+          AFWorkspacePermission::EditCollab,
+        ]),
+      },
+    )
+    .await
+    .unwrap();
+  assert_eq!(role.name, "Editor");
+  assert_eq!(
+    role.permissions,
+    vec![
+      AFWorkspacePermission::CreateCollab,
+      AFWorkspacePermission::EditCollab
+    ]
+  );
+
+  let roles = c1
+    .api_client
+    .get_workspace_roles(&workspace_id)
+    .await
+    .unwrap();
+  assert_eq!(roles.len(), 1);
+
+  c1.api_client
+    .delete_workspace_role(&workspace_id, role.role_id)
+    .await
+    .unwrap();
+  let roles = c1
+    .api_client
+    .get_workspace_roles(&workspace_id)
+    .await
+    .unwrap();
+  assert!(roles.is_empty());
+}
+
+#[tokio::test]
+async fn member_can_not_manage_workspace_role_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let c2 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+  c1.add_workspace_member(&workspace_id, &c2, AFRole::Member)
+    .await;
+
+  let error = c2
+    .api_client
+    .create_workspace_role(
+      &workspace_id,
+      role_params("Admin", AFWorkspacePermission::ALL.to_vec()),
+    )
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::NotEnoughPermissions);
+}
+
+#[tokio::test]
+async fn custom_role_restricts_collab_creation_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let c2 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+  c1.add_workspace_member(&workspace_id, &c2, AFRole::Member)
+    .await;
+  create_document(&c2, &workspace_id).await.unwrap();
+
+  let role = c1
+    .api_client
+    .create_workspace_role(
+      &workspace_id,
+      role_params("Commenter", vec![AFWorkspacePermission::Comment]),
+    )
+    .await
+    .unwrap();
+  c1.api_client
+    .assign_workspace_role(&workspace_id, &c2.email().await, Some(role.role_id))
+    .await
+    .unwrap();
+  assert_eq!(
+    create_document(&c2, &workspace_id).await.unwrap_err(),
+    ErrorCode::NotEnoughPermissions
+  );
+
+  // The member falls back to the default permissions of the built-in role
+  c1.api_client
+    .delete_workspace_role(&workspace_id, role.role_id)
+    .await
+    .unwrap();
+  create_document(&c2, &workspace_id).await.unwrap();
+}
+
+#[tokio::test]
+async fn custom_role_grants_invite_permission_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let c2 = TestClient::new_user_without_ws_conn().await;
+  let c3 = TestClient::new_user_without_ws_conn().await;
+  let c4 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+  c1.add_workspace_member(&workspace_id, &c2, AFRole::Member)
+    .await;
+
+  let role = c1
+    .api_client
+    .create_workspace_role(
+      &workspace_id,
+      role_params("Recruiter", vec![AFWorkspacePermission::InviteMember]),
+    )
+    .await
+    .unwrap();
+  c1.api_client
+    .assign_workspace_role(&workspace_id, &c2.email().await, Some(role.role_id))
+    .await
+    .unwrap();
+
+  c2.add_workspace_member(&workspace_id, &c3, AFRole::Member)
+    .await;
+  // Only the owner can grant the owner role
+  let error = c2
+    .try_add_workspace_member(&workspace_id, &c4, AFRole::Owner)
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::NotEnoughPermissions);
+}
+
+#[tokio::test]
+async fn owner_can_not_have_custom_role_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+  let role = c1
+    .api_client
+    .create_workspace_role(&workspace_id, role_params("Viewer", vec![]))
+    .await
+    .unwrap();
+  let error = c1
+    .api_client
+    .assign_workspace_role(&workspace_id, &c1.email().await, Some(role.role_id))
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::InvalidRequest);
+}
@@ -0,0 +1,161 @@
+use crate::collab::workspace_id_from_client;
+use crate::user::utils::generate_unique_registered_user_client;
+use app_error::ErrorCode;
+use collab::core::collab::MutexCollab;
+use collab::core::origin::CollabOrigin;
+use collab_document::blocks::{Block, DocumentData, DocumentMeta};
+use collab_document::document::Document;
+use collab_entity::CollabType;
+use database_entity::dto::{DeleteCollabParams, InsertCollabParams};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Creates a document with a paragraph for each of the texts.
+fn document_with_texts(object_id: &str, texts: &[&str]) -> Vec<u8> {
+  let page_id = "page".to_string();
+  let mut blocks = HashMap::new();
+  let mut children_map = HashMap::new();
+  let mut text_map = HashMap::new();
+  let mut page_children = vec![];
+  for (i, text) in texts.iter().enumerate() {
+    let block_id = format!("paragraph_{}", i);
+    let external_id = format!("text_{}", i);
+    blocks.insert(
+      block_id.clone(),
+      Block {
+        id: block_id.clone(),
+        ty: "paragraph".to_string(),
+        parent: page_id.clone(),
+        children: format!("{}_children", block_id),
+        external_id: Some(external_id.clone()),
+        external_type: Some("text".to_string()),
+        data: HashMap::new(),
+      },
+    );
+    children_map.insert(format!("{}_children", block_id), vec![]);
+    text_map.insert(external_id, json!([{ "insert": text }]).to_string());
+    page_children.push(block_id);
+  }
+  blocks.insert(
+    page_id.clone(),
+    Block {
+      id: page_id.clone(),
+      ty: "page".to_string(),
+      parent: "".to_string(),
+      children: "page_children".to_string(),
+      external_id: None,
+      external_type: None,
+      data: HashMap::new(),
+    },
+  );
+  children_map.insert("page_children".to_string(), page_children);
+
+  let data = DocumentData {
+    page_id,
+    blocks,
+    meta: DocumentMeta {
+      children_map,
+      text_map: Some(text_map),
+    },
+  };
+  let collab = Arc::new(MutexCollab::new(CollabOrigin::Empty, object_id, vec![]));
+  let document = Document::create_with_data(collab, data).unwrap();
+  let encoded_collab = document.get_collab().encode_collab_v1();
+  encoded_collab.encode_to_bytes().unwrap()
+}
+
+#[tokio::test]
+async fn search_document_test() {
+  let (c, _user) = generate_unique_registered_user_client().await;
+  let workspace_id = workspace_id_from_client(&c).await;
+  let object_id = Uuid::new_v4().to_string();
+  let keyword = format!("keyword{}", Uuid::new_v4().simple());
+  let text = format!("the quick brown fox {} jumps over the lazy dog", keyword);
+  c.create_collab(InsertCollabParams::new(
+    &object_id,
+    CollabType::Document,
+    document_with_texts(&object_id, &["first paragraph", &text]),
+    workspace_id.clone(),
+  ))
+  .await
+  .unwrap();
+
+  let results = c
+    .search_collab(&workspace_id, &keyword, None)
+    .await
+    .unwrap();
+  assert_eq!(results.len(), 1);
+  assert_eq!(results[0].object_id, object_id);
+  assert_eq!(results[0].collab_type, CollabType::Document);
+  assert!(results[0].snippet.contains(&format!("<b>{}</b>", keyword)));
+
+  let results = c
+    .search_collab(&workspace_id, &format!("{} -fox", keyword), None)
+    .await
+    .unwrap();
+  assert!(results.is_empty());
+
+  // The deleted document is left out of the results
+  c.delete_collab(DeleteCollabParams {
+    object_id: object_id.clone(),
+    workspace_id: workspace_id.clone(),
+  })
+  .await
+  .unwrap();
+  let results = c
+    .search_collab(&workspace_id, &keyword, None)
+    .await
+    .unwrap();
+  assert!(results.is_empty());
+
+  // The document is found again after it's restored from the trash
+  c.restore_collab_from_trash(&workspace_id, &object_id)
+    .await
+    .unwrap();
+  let results = c
+    .search_collab(&workspace_id, &keyword, None)
+    .await
+    .unwrap();
+  assert_eq!(results.len(), 1);
+  assert_eq!(results[0].object_id, object_id);
+}
+
+#[tokio::test]
+async fn search_updated_document_test() {
+  let (c, _user) = generate_unique_registered_user_client().await;
+  let workspace_id = workspace_id_from_client(&c).await;
+  let object_id = Uuid::new_v4().to_string();
+  let old_keyword = format!("old{}", Uuid::new_v4().simple());
+  let new_keyword = format!("new{}", Uuid::new_v4().simple());
+  for keyword in [&old_keyword, &new_keyword] {
+    c.create_collab(InsertCollabParams::new(
+      &object_id,
+      CollabType::Document,
+      document_with_texts(&object_id, &[keyword]),
+      workspace_id.clone(),
+    ))
+    .await
+    .unwrap();
+  }
+
+  let results = c
+    .search_collab(&workspace_id, &old_keyword, None)
+    .await
+    .unwrap();
+  assert!(results.is_empty());
+  let results = c
+    .search_collab(&workspace_id, &new_keyword, None)
+    .await
+    .unwrap();
+  assert_eq!(results.len(), 1);
+}
+
+#[tokio::test]
+async fn search_with_empty_query_test() {
+  let (c, _user) = generate_unique_registered_user_client().await;
+  let workspace_id = workspace_id_from_client(&c).await;
+  let err = c.search_collab(&workspace_id, " ", None).await.unwrap_err();
+  assert_eq!(err.code, ErrorCode::InvalidRequest);
+}
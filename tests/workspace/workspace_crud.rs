@@ -0,0 +1,187 @@
+use crate::util::test_client::TestClient;
+use app_error::ErrorCode;
+use collab_entity::CollabType;
+use database_entity::dto::{AFRole, QueryCollabParams};
+
+#[tokio::test]
+async fn create_workspace_with_templates_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let workspace = c1
+    .api_client
+    .create_workspace(Some("Team".to_string()))
+    .await
+    .unwrap();
+  assert_eq!(workspace.workspace_name, "Team");
+
+  let workspaces = c1.api_client.get_workspaces().await.unwrap();
+  assert_eq!(workspaces.0.len(), 2);
+
+  // The new workspace is seeded with the folder of the default workspace
+  let workspace_id = workspace.workspace_id.to_string();
+  c1.api_client
+    .get_collab(QueryCollabParams {
+      object_id: workspace_id.clone(),
+      workspace_id,
+      collab_type: CollabType::Folder,
+    })
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn create_workspace_with_empty_name_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let error = c1
+    .api_client
+    .create_workspace(Some("  ".to_string()))
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::InvalidRequest);
+}
+
+#[tokio::test]
+async fn rename_workspace_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let c2 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+  c1.add_workspace_member(&workspace_id, &c2, AFRole::Member)
+    .await;
+
+  c1.api_client
+    .rename_workspace(&workspace_id, "Renamed")
+    .await
+    .unwrap();
+  let workspace = c1.api_client.open_workspace(&workspace_id).await.unwrap();
+  assert_eq!(workspace.workspace_name, "Renamed");
+
+  // Only the owner can rename the workspace
+  let error = c2
+    .api_client
+    .rename_workspace(&workspace_id, "Hacked")
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::NotEnoughPermissions);
+}
+
+#[tokio::test]
+async fn delete_workspace_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let default_workspace_id = c1.workspace_id().await;
+  let workspace = c1.api_client.create_workspace(None).await.unwrap();
+  let workspace_id = workspace.workspace_id.to_string();
+  c1.api_client.open_workspace(&workspace_id).await.unwrap();
+
+  c1.api_client.delete_workspace(&workspace_id).await.unwrap();
+  let workspaces = c1.api_client.get_workspaces().await.unwrap();
+  assert_eq!(workspaces.0.len(), 1);
+  let error = c1
+    .api_client
+    .open_workspace(&workspace_id)
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::RecordNotFound);
+
+  // The user falls back to the remaining workspace after the visiting workspace is deleted
+  let info = c1.api_client.get_user_workspace_info().await.unwrap();
+  assert_eq!(
+    info.visiting_workspace.workspace_id.to_string(),
+    default_workspace_id
+  );
+
+  // The last workspace can't be deleted
+  let error = c1
+    .api_client
+    .delete_workspace(&default_workspace_id)
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::InvalidRequest);
+}
+
+#[tokio::test]
+async fn delete_workspace_with_sole_workspace_member_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let c2 = TestClient::new_user_without_ws_conn().await;
+  let workspace = c1.api_client.create_workspace(None).await.unwrap();
+  let workspace_id = workspace.workspace_id.to_string();
+  c1.add_workspace_member(&workspace_id, &c2, AFRole::Member)
+    .await;
+
+  // The workspace of c1 becomes the only workspace of c2
+  let c2_default_workspace_id = c2.workspace_id().await;
+  c2.api_client
+    .delete_workspace(&c2_default_workspace_id)
+    .await
+    .unwrap();
+
+  c1.api_client.delete_workspace(&workspace_id).await.unwrap();
+
+  // c2 gets a new default workspace
+  let info = c2.api_client.get_user_workspace_info().await.unwrap();
+  assert_eq!(info.workspaces.len(), 1);
+  assert_ne!(info.visiting_workspace.workspace_id, workspace.workspace_id);
+
+  // The collabs of the deleted workspace are no longer readable by its members
+  for client in [&c1, &c2] {
+    let error = client
+      .api_client
+      .get_collab(QueryCollabParams {
+        object_id: workspace_id.clone(),
+        workspace_id: workspace_id.clone(),
+        collab_type: CollabType::Folder,
+      })
+      .await
+      .unwrap_err();
+    assert_eq!(error.code, ErrorCode::NotEnoughPermissions);
+  }
+}
+
+#[tokio::test]
+async fn transfer_workspace_ownership_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let c2 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+  c1.add_workspace_member(&workspace_id, &c2, AFRole::Member)
+    .await;
+
+  c1.api_client
+    .transfer_workspace_ownership(&workspace_id, &c2.email().await)
+    .await
+    .unwrap();
+
+  let c1_email = c1.email().await;
+  let c2_email = c2.email().await;
+  let members = c2.get_workspace_members(&workspace_id).await;
+  for member in members {
+    if member.email == c1_email {
+      assert_eq!(member.role, AFRole::Member);
+    } else if member.email == c2_email {
+      assert_eq!(member.role, AFRole::Owner);
+    }
+  }
+
+  // The previous owner is no longer allowed to rename the workspace
+  let error = c1
+    .api_client
+    .rename_workspace(&workspace_id, "Renamed")
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::NotEnoughPermissions);
+  c2.api_client
+    .rename_workspace(&workspace_id, "Renamed")
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn transfer_workspace_ownership_to_non_member_test() {
+  let c1 = TestClient::new_user_without_ws_conn().await;
+  let c2 = TestClient::new_user_without_ws_conn().await;
+  let workspace_id = c1.workspace_id().await;
+
+  let error = c1
+    .api_client
+    .transfer_workspace_ownership(&workspace_id, &c2.email().await)
+    .await
+    .unwrap_err();
+  assert_eq!(error.code, ErrorCode::InvalidRequest);
+}